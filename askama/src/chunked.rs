@@ -0,0 +1,320 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{DynTemplate, Error, NO_VALUES, Result, Values};
+
+/// An [`fmt::Write`] sink that hands the rendered output out in bounded byte chunks
+///
+/// Every time `chunk_size` bytes were written, the chunk is passed to the callback, so the first
+/// part of a template can e.g. be sent to a client while the rest of it is still being rendered.
+/// Call [`ChunkedWriter::finish()`] once the rendering is done to emit the last, possibly shorter,
+/// chunk. Chunks are never empty and never longer than `chunk_size`, but a chunk boundary may fall
+/// inside a multi-byte UTF-8 character.
+///
+/// You don't need to use this type directly in most cases, see [`Template::render_chunked()`].
+///
+/// ```
+/// # use core::fmt::Write;
+/// # use askama::ChunkedWriter;
+/// let mut chunks = vec![];
+/// let mut writer = ChunkedWriter::new(4, |chunk: &[u8]| {
+///     chunks.push(chunk.to_vec());
+///     Ok(())
+/// });
+/// writer.write_str("hello ").unwrap();
+/// writer.write_str("world").unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(chunks, [&b"hell"[..], b"o wo", b"rld"]);
+/// ```
+///
+/// [`Template::render_chunked()`]: crate::Template::render_chunked
+pub struct ChunkedWriter<F: FnMut(&[u8]) -> Result<()>> {
+    buf: Vec<u8>,
+    chunk_size: usize,
+    on_chunk: F,
+    err: Option<Error>,
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> ChunkedWriter<F> {
+    /// Creates a new writer that calls `on_chunk` for every `chunk_size` bytes of output
+    ///
+    /// A `chunk_size` of `0` is treated as `1`.
+    pub fn new(chunk_size: usize, on_chunk: F) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            buf: Vec::new(),
+            chunk_size,
+            on_chunk,
+            err: None,
+        }
+    }
+
+    /// Emits the remaining buffered output, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the callback, or the error that made a previous write fail.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(err) = self.err.take() {
+            return Err(err);
+        }
+        if !self.buf.is_empty() {
+            (self.on_chunk)(&self.buf)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the error that made a previous write fail, if the callback returned one
+    ///
+    /// [`fmt::Write`] can only return a [`fmt::Error`], so the actual error of the callback is
+    /// stored in the writer.
+    #[inline]
+    pub fn take_err(&mut self) -> Option<Error> {
+        self.err.take()
+    }
+
+    fn write_bytes(&mut self, mut bytes: &[u8]) -> Result<()> {
+        loop {
+            if self.buf.is_empty() {
+                // Emit full chunks directly from the input, without copying them.
+                while let Some((chunk, rest)) = bytes.split_at_checked(self.chunk_size) {
+                    (self.on_chunk)(chunk)?;
+                    bytes = rest;
+                }
+            }
+
+            let room = self.chunk_size - self.buf.len();
+            if bytes.len() < room {
+                self.buf.extend_from_slice(bytes);
+                return Ok(());
+            }
+
+            let (head, rest) = bytes.split_at(room);
+            if self.buf.capacity() == 0 {
+                let _ = self.buf.try_reserve_exact(self.chunk_size);
+            }
+            self.buf.extend_from_slice(head);
+            (self.on_chunk)(&self.buf)?;
+            self.buf.clear();
+            bytes = rest;
+        }
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> fmt::Write for ChunkedWriter<F> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.err.is_some() {
+            return Err(fmt::Error);
+        }
+        self.write_bytes(s.as_bytes()).map_err(|err| {
+            self.err = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> fmt::Debug for ChunkedWriter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedWriter")
+            .field("buffered", &self.buf.len())
+            .field("chunk_size", &self.chunk_size)
+            .field("err", &self.err)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An [`Iterator`] over the rendered output of a template, in bounded byte chunks
+///
+/// The template is rendered on the thread that calls [`next()`](Iterator::next), and only one
+/// chunk is buffered at a time. A template cannot be paused in the middle of its rendering, so
+/// every call renders the template from the start again, skips the output that was already
+/// returned, and stops as soon as the next chunk is full. The rendering must therefore produce the
+/// same output every time, and a template that is rendered in `n` chunks is run `n` times: choose
+/// a `chunk_size` that splits your pages into a handful of chunks. If you can hand out the chunks
+/// from a callback, [`Template::render_chunked()`] renders the template only once.
+///
+/// If the rendering fails, the output up to the error is returned first, and the error is the
+/// last item.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{% for i in 0..5 %}{{ i }},{% endfor %}", ext = "txt")]
+/// struct Counter;
+///
+/// let chunks = Counter.render_chunks(4).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(chunks, [&b"0,1,"[..], b"2,3,", b"4,"]);
+/// ```
+///
+/// To render a [`DynTemplate`], use [`RenderChunks::new()`].
+///
+/// [`Template::render_chunked()`]: crate::Template::render_chunked
+pub struct RenderChunks<'a> {
+    template: &'a dyn DynTemplate,
+    values: &'a dyn Values,
+    chunk_size: usize,
+    /// The number of bytes that were returned already
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> RenderChunks<'a> {
+    /// Creates an iterator over the output of `template` in chunks of at most `chunk_size` bytes
+    ///
+    /// A `chunk_size` of `0` is treated as `1`.
+    #[inline]
+    pub fn new(template: &'a dyn DynTemplate, chunk_size: usize) -> Self {
+        Self::with_values(template, chunk_size, NO_VALUES)
+    }
+
+    /// Creates an iterator over the output of `template` in chunks of at most `chunk_size` bytes
+    /// with provided [`Values`]
+    ///
+    /// A `chunk_size` of `0` is treated as `1`.
+    pub fn with_values(
+        template: &'a dyn DynTemplate,
+        chunk_size: usize,
+        values: &'a dyn Values,
+    ) -> Self {
+        Self {
+            template,
+            values,
+            chunk_size: chunk_size.max(1),
+            offset: 0,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for RenderChunks<'_> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut writer = ChunkSlicer {
+            skip: self.offset,
+            chunk: Vec::new(),
+            chunk_size: self.chunk_size,
+            full: false,
+        };
+        let result = self
+            .template
+            .dyn_render_into_with_values(&mut writer, self.values);
+        let ChunkSlicer { chunk, full, .. } = writer;
+        match result {
+            // The writer stopped the rendering because the chunk is complete.
+            Err(_) if full => {}
+            // The output up to the error is returned first, the error is returned by the next
+            // call, which stops at the same place with an empty chunk.
+            Err(err) if chunk.is_empty() => {
+                self.done = true;
+                return Some(Err(err));
+            }
+            Err(_) => {}
+            Ok(()) => self.done = true,
+        }
+        if chunk.is_empty() {
+            return None;
+        }
+        self.offset += chunk.len();
+        Some(Ok(chunk))
+    }
+}
+
+impl fmt::Debug for RenderChunks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderChunks")
+            .field("chunk_size", &self.chunk_size)
+            .field("offset", &self.offset)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Collects the `chunk_size` bytes of output after the first `skip` bytes, then aborts
+struct ChunkSlicer {
+    skip: usize,
+    chunk: Vec<u8>,
+    chunk_size: usize,
+    full: bool,
+}
+
+impl fmt::Write for ChunkSlicer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        if self.skip > 0 {
+            let skipped = self.skip.min(bytes.len());
+            self.skip -= skipped;
+            bytes = &bytes[skipped..];
+        }
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        if self.chunk.capacity() == 0 {
+            let _ = self.chunk.try_reserve_exact(self.chunk_size);
+        }
+        let room = self.chunk_size - self.chunk.len();
+        if bytes.len() > room {
+            // Only stop once there is more output, so the end of the template is not mistaken
+            // for a full chunk.
+            self.chunk.extend_from_slice(&bytes[..room]);
+            self.full = true;
+            return Err(fmt::Error);
+        }
+        self.chunk.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::fmt::Write;
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn collect(chunk_size: usize, parts: &[&str]) -> Vec<Vec<u8>> {
+        let mut chunks = vec![];
+        let mut writer = ChunkedWriter::new(chunk_size, |chunk: &[u8]| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        });
+        for part in parts {
+            writer.write_str(part).unwrap();
+        }
+        writer.finish().unwrap();
+        chunks
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        assert_eq!(collect(3, &[]), Vec::<Vec<u8>>::new());
+        assert_eq!(collect(3, &["", ""]), Vec::<Vec<u8>>::new());
+        assert_eq!(collect(3, &["ab"]), [b"ab"]);
+        assert_eq!(collect(3, &["abc"]), [b"abc"]);
+        assert_eq!(collect(3, &["a", "b", "c", "d"]), [&b"abc"[..], b"d"]);
+        assert_eq!(collect(3, &["ab", "cdefgh", "i"]), [b"abc", b"def", b"ghi"]);
+        assert_eq!(collect(0, &["ab"]), [b"a", b"b"]);
+        assert_eq!(collect(100, &["ab", "cd"]), [b"abcd"]);
+    }
+
+    #[test]
+    fn test_callback_error() {
+        let mut calls = 0;
+        let mut writer = ChunkedWriter::new(2, |_: &[u8]| {
+            calls += 1;
+            Err(Error::ValueMissing)
+        });
+        assert!(writer.write_str("abc").is_err());
+        assert!(writer.write_str("def").is_err());
+        assert_matches!(writer.finish(), Err(Error::ValueMissing));
+        assert_eq!(calls, 1);
+    }
+}
@@ -23,12 +23,24 @@ pub enum Error {
     ValueMissing,
     /// Incompatible value type for key in [`Values`][crate::Values]
     ValueType,
+    /// Block not present in a [`BlockTemplate`][crate::BlockTemplate]
+    BlockMissing,
     /// An error raised by using `?` in a template
     #[cfg(feature = "alloc")]
     Custom(Box<dyn StdError + Send + Sync>),
     /// JSON conversion error
     #[cfg(feature = "serde_json")]
     Json(serde_json::Error),
+    /// An error raised by the [`io::Write`] or other byte sink the template was written into
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// An error that was raised at a known [`Location`] in a template
+    ///
+    /// The [`Display`][fmt::Display] output and the [`source`][StdError::source] are the ones of
+    /// the contained error. Use [`Error::location()`] to get the location, and
+    /// [`Error::without_location()`] or [`Error::inner()`] to get the contained error.
+    #[cfg(feature = "alloc")]
+    Located(Box<Error>, &'static Location),
 }
 
 impl Error {
@@ -39,6 +51,60 @@ impl Error {
         Self::Custom(err.into())
     }
 
+    /// Attach the template [`Location`] where this error was raised
+    ///
+    /// If the error already has a location, the location is not replaced, so an error that is
+    /// passed through multiple templates keeps the location where it was raised first.
+    #[cfg(feature = "alloc")]
+    pub fn with_location(self, location: &'static Location) -> Self {
+        match self {
+            Error::Located(..) => self,
+            err => Error::Located(Box::new(err), location),
+        }
+    }
+
+    /// The template file, line and column where this error was raised, if known
+    ///
+    /// The location is only known for templates that were loaded from a file with `path = "…"`.
+    #[inline]
+    pub fn location(&self) -> Option<&'static Location> {
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Located(_, location) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The actual error, without its [`Location`]
+    ///
+    /// Use this method to match on the variant of an error that may have a location:
+    ///
+    /// ```
+    /// # let err = askama::Error::ValueMissing;
+    /// match err.inner() {
+    ///     askama::Error::ValueMissing => {}
+    ///     err => panic!("unexpected error: {err}"),
+    /// }
+    /// ```
+    #[inline]
+    pub fn inner(&self) -> &Self {
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Located(err, _) => err,
+            err => err,
+        }
+    }
+
+    /// Strip the [`Location`] of this error, if any, and return the actual error
+    #[inline]
+    pub fn without_location(self) -> Self {
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Located(err, _) => *err,
+            err => err,
+        }
+    }
+
     /// Convert this [`Error`] into a
     /// <code>[Box]&lt;dyn [StdError] + [Send] + [Sync]&gt;</code>
     #[cfg(feature = "alloc")]
@@ -47,9 +113,13 @@ impl Error {
             Error::Fmt => fmt::Error.into(),
             Error::ValueMissing => Box::new(Error::ValueMissing),
             Error::ValueType => Box::new(Error::ValueType),
+            Error::BlockMissing => Box::new(Error::BlockMissing),
             Error::Custom(err) => err,
             #[cfg(feature = "serde_json")]
             Error::Json(err) => err.into(),
+            #[cfg(feature = "std")]
+            Error::Io(err) => err.into(),
+            Error::Located(..) => Box::new(self),
         }
     }
 
@@ -59,6 +129,7 @@ impl Error {
     #[cfg(feature = "std")]
     pub fn into_io_error(self) -> io::Error {
         io::Error::other(match self {
+            Error::Io(err) => return err,
             Error::Custom(err) => match err.downcast() {
                 Ok(err) => return *err,
                 Err(err) => err,
@@ -74,10 +145,15 @@ impl StdError for Error {
             Error::Fmt => Some(&fmt::Error),
             Error::ValueMissing => None,
             Error::ValueType => None,
+            Error::BlockMissing => None,
             #[cfg(feature = "alloc")]
             Error::Custom(err) => Some(err.as_ref()),
             #[cfg(feature = "serde_json")]
             Error::Json(err) => Some(err),
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            #[cfg(feature = "alloc")]
+            Error::Located(err, _) => err.source(),
         }
     }
 }
@@ -88,14 +164,62 @@ impl fmt::Display for Error {
             Error::Fmt => fmt::Error.fmt(f),
             Error::ValueMissing => f.write_str("key missing in values"),
             Error::ValueType => f.write_str("value has wrong type"),
+            Error::BlockMissing => f.write_str("block not found in template"),
             #[cfg(feature = "alloc")]
             Error::Custom(err) => err.fmt(f),
             #[cfg(feature = "serde_json")]
             Error::Json(err) => err.fmt(f),
+            #[cfg(feature = "std")]
+            Error::Io(err) => err.fmt(f),
+            #[cfg(feature = "alloc")]
+            Error::Located(err, _) => err.fmt(f),
         }
     }
 }
 
+/// A position in a template file, cf. [`Error::location()`]
+///
+/// Displayed as `file:line:column`, e.g. `base.html:42:7`. Lines and columns are counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl Location {
+    /// Create a new location, used by the generated code
+    #[inline]
+    pub const fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Self { file, line, column }
+    }
+
+    /// The path of the template, relative to the template directory it was found in
+    #[inline]
+    pub const fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// The line number, starting at 1
+    #[inline]
+    pub const fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// The column in characters, starting at 1
+    #[inline]
+    pub const fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl fmt::Display for Location {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 impl From<Error> for fmt::Error {
     #[inline]
     fn from(_: Error) -> Self {
@@ -169,7 +293,7 @@ fn error_from_stderror(err: Box<dyn StdError + Send + Sync>, unwraps: usize) ->
 #[cfg(feature = "std")]
 fn error_from_io_error(err: io::Error, unwraps: usize) -> Error {
     let Some(inner) = err.get_ref() else {
-        return Error::custom(err);
+        return Error::Io(err);
     };
     let Some(unwraps) = unwraps.checked_sub(1) else {
         return match err.into_inner() {
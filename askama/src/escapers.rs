@@ -0,0 +1,222 @@
+// The file is shared across many crates, not all have this feature.
+// If they don't then the tests won't be compiled in, but that's OK, because they are executed at
+// least in the crate `askama`. There's no need to run the test multiple times.
+#![allow(unexpected_cfgs)]
+
+use core::fmt;
+
+/// Escapes the content of a JavaScript string literal, template literal, regular expression or
+/// comment.
+#[allow(unused)]
+pub(crate) fn write_js_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| {
+            matches!(
+                c,
+                '\0'..='\x1f'
+                    | '\x7f'
+                    | '"'
+                    | '$'
+                    | '&'
+                    | '\''
+                    | '/'
+                    | '<'
+                    | '>'
+                    | '\\'
+                    | '`'
+                    | '{'
+                    | '\u{2028}'
+                    | '\u{2029}'
+            )
+        },
+        |dest, c| match c {
+            '\\' => dest.write_str(r"\\"),
+            '/' => dest.write_str(r"\/"),
+            '\n' => dest.write_str(r"\n"),
+            '\r' => dest.write_str(r"\r"),
+            '\t' => dest.write_str(r"\t"),
+            c => write!(dest, r"\u{:04X}", c as u32),
+        },
+    )
+}
+
+/// Escapes the content of a CSS string or identifier.
+#[allow(unused)]
+pub(crate) fn write_css_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| !(c.is_ascii_alphanumeric() || matches!(c, ' ' | '#' | '%' | ',' | '-' | '.' | '_')),
+        |dest, c| write!(dest, r"\{:x} ", c as u32),
+    )
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986, so the output can be used
+/// as a component of a URL, e.g. a path segment or a query parameter.
+#[allow(unused)]
+pub(crate) fn write_url_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')),
+        |dest, c| {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                write!(dest, "%{byte:02X}")?;
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Escapes XML texts and attribute values.
+///
+/// Tabs and line breaks are escaped, too, so they are retained in attribute values. Characters
+/// that must not appear in an XML document are replaced with U+FFFD.
+#[allow(unused)]
+pub(crate) fn write_xml_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| {
+            matches!(
+                c,
+                '\0'..='\x1f' | '"' | '&' | '\'' | '<' | '>' | '\u{fffe}' | '\u{ffff}'
+            )
+        },
+        |dest, c| match c {
+            '\t' | '\n' | '\r' | '"' | '&' | '\'' | '<' | '>' => write!(dest, "&#{};", c as u32),
+            _ => dest.write_char(char::REPLACEMENT_CHARACTER),
+        },
+    )
+}
+
+/// Escapes the content of a JSON string.
+///
+/// Like the `|json` filter, the output does not contain HTML special characters.
+#[allow(unused)]
+pub(crate) fn write_json_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| matches!(c, '\0'..='\x1f' | '"' | '&' | '\'' | '<' | '>' | '\\'),
+        |dest, c| match c {
+            '"' => dest.write_str(r#"\""#),
+            '\\' => dest.write_str(r"\\"),
+            '\n' => dest.write_str(r"\n"),
+            '\r' => dest.write_str(r"\r"),
+            '\t' => dest.write_str(r"\t"),
+            '\x08' => dest.write_str(r"\b"),
+            '\x0c' => dest.write_str(r"\f"),
+            c => write!(dest, r"\u{:04x}", c as u32),
+        },
+    )
+}
+
+/// Escapes (La)TeX texts.
+#[allow(unused)]
+pub(crate) fn write_tex_escaped(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    write_escaped_with(
+        dest,
+        src,
+        |c| {
+            matches!(
+                c,
+                '#' | '$' | '%' | '&' | '<' | '>' | '\\' | '^' | '_' | '{' | '}' | '~'
+            )
+        },
+        |dest, c| match c {
+            '\\' => dest.write_str(r"\textbackslash{}"),
+            '^' => dest.write_str(r"\textasciicircum{}"),
+            '~' => dest.write_str(r"\textasciitilde{}"),
+            '<' => dest.write_str(r"\textless{}"),
+            '>' => dest.write_str(r"\textgreater{}"),
+            c => {
+                dest.write_char('\\')?;
+                dest.write_char(c)
+            }
+        },
+    )
+}
+
+/// Copies `src` into `dest`, but every character for which `needs_escaping` returns `true` is
+/// written with `write_escaped` instead.
+#[inline]
+fn write_escaped_with<W: fmt::Write>(
+    mut dest: W,
+    src: &str,
+    needs_escaping: impl Fn(char) -> bool,
+    write_escaped: impl Fn(&mut W, char) -> fmt::Result,
+) -> fmt::Result {
+    let mut last = 0;
+    for (index, c) in src.char_indices() {
+        if needs_escaping(c) {
+            write_str_if_nonempty(&mut dest, &src[last..index])?;
+            write_escaped(&mut dest, c)?;
+            last = index + c.len_utf8();
+        }
+    }
+    write_str_if_nonempty(&mut dest, &src[last..])
+}
+
+#[inline(always)]
+fn write_str_if_nonempty(output: &mut impl fmt::Write, input: &str) -> fmt::Result {
+    if !input.is_empty() {
+        output.write_str(input)
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_escapers() {
+    extern crate alloc;
+
+    fn escape(
+        f: fn(&mut alloc::string::String, &str) -> fmt::Result,
+        src: &str,
+    ) -> alloc::string::String {
+        let mut buf = alloc::string::String::new();
+        f(&mut buf, src).unwrap();
+        buf
+    }
+
+    assert_eq!(escape(|d, s| write_js_escaped(d, s), "plain"), "plain");
+    assert_eq!(
+        escape(|d, s| write_js_escaped(d, s), "</script>\n'${x}'\u{2028}"),
+        r"\u003C\/script\u003E\n\u0027\u0024\u007Bx}\u0027\u2028",
+    );
+
+    assert_eq!(
+        escape(|d, s| write_css_escaped(d, s), "a;b}c\"ä"),
+        r"a\3b b\7d c\22 \e4 ",
+    );
+
+    assert_eq!(
+        escape(|d, s| write_url_escaped(d, s), "a b/c?d=e&f~g.ä"),
+        "a%20b%2Fc%3Fd%3De%26f~g.%C3%A4",
+    );
+
+    assert_eq!(
+        escape(|d, s| write_xml_escaped(d, s), "<a b=\"c\">\td\0'&\r\n"),
+        "&#60;a b=&#34;c&#34;&#62;&#9;d\u{fffd}&#39;&#38;&#13;&#10;",
+    );
+
+    assert_eq!(
+        escape(
+            |d, s| write_json_escaped(d, s),
+            "\"a\\b\"\n\x08\x0c\x01</b>&'"
+        ),
+        r#"\"a\\b\"\n\b\f\u0001\u003c/b\u003e\u0026\u0027"#,
+    );
+
+    assert_eq!(
+        escape(
+            |d, s| write_tex_escaped(d, s),
+            r"50% of $x_1^2 & {y} \in #~<>"
+        ),
+        r"50\% of \$x\_1\textasciicircum{}2 \& \{y\} \textbackslash{}in \#\textasciitilde{}\textless{}\textgreater{}",
+    );
+}
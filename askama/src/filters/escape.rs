@@ -3,6 +3,7 @@ use core::fmt::{self, Formatter, Write};
 use core::pin::Pin;
 use core::str;
 
+use crate::helpers::TemplateWriter;
 use crate::{FastWritable, Values};
 
 /// Marks a string (or other `Display` type) as safe
@@ -86,7 +87,7 @@ impl<T: FastWritable, E: Escaper> FastWritable for EscapeDisplay<T, E> {
     }
 }
 
-struct EscapeWriter<W, E>(W, E);
+pub(crate) struct EscapeWriter<W, E>(pub(crate) W, pub(crate) E);
 
 impl<W: Write, E: Escaper> Write for EscapeWriter<W, E> {
     #[inline]
@@ -163,6 +164,280 @@ impl Escaper for Text {
     }
 }
 
+/// Escape characters in a safe way for the content of JavaScript strings, template literals,
+/// regular expressions and comments
+///
+/// * `\` => `\\`
+/// * `/` => `\/`
+/// * line feeds, carriage returns and tabs => `\n`, `\r` and `\t`
+/// * other control characters, `"`, `'`, `` ` ``, `<`, `>`, `&`, `$` and `{` => `\u00XX`
+/// * U+2028 and U+2029 => `\u2028` and `\u2029`
+///
+/// The output does not contain any HTML special characters, so it can be used in event handler
+/// attributes like `onclick`, too.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Js;
+
+impl Escaper for Js {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_js_escaped(dest, string)
+    }
+}
+
+/// Escape values to be used as a JavaScript expression
+///
+/// The output of the expression is written as a JavaScript string literal, which is escaped
+/// with [`Js`]. Values that implement [`JsSafe`], e.g. numbers, booleans or the output of the
+/// [`|json`](super::json) filter, are written verbatim.
+///
+/// This type is used by askama for expressions in a `<script>` element, and cannot be used as
+/// an [`Escaper`] on its own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsValue;
+
+/// Quote values as a JavaScript string
+impl<'a, T: fmt::Display + ?Sized> AutoEscape for &&AutoEscaper<'a, T, JsValue> {
+    type Escaped = JsString<&'a T>;
+    type Error = Infallible;
+
+    #[inline]
+    fn askama_auto_escape(&self) -> Result<Self::Escaped, Self::Error> {
+        Ok(JsString(self.text))
+    }
+}
+
+/// Don't quote JavaScript safe types
+impl<'a, T: JsSafe + ?Sized> AutoEscape for &AutoEscaper<'a, T, JsValue> {
+    type Escaped = &'a T;
+    type Error = Infallible;
+
+    #[inline]
+    fn askama_auto_escape(&self) -> Result<Self::Escaped, Self::Error> {
+        Ok(self.text)
+    }
+}
+
+/// A value written as a quoted and escaped JavaScript string, see [`JsValue`]
+pub struct JsString<T>(T);
+
+impl<T: fmt::Display> fmt::Display for JsString<T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        write!(EscapeWriter(&mut *f, Js), "{}", self.0)?;
+        f.write_char('"')
+    }
+}
+
+/// Types that implement this marker trait are valid JavaScript expressions
+///
+/// Their output is not quoted if they are used as a value in a `<script>` element, see
+/// [`JsValue`].
+///
+/// If you are unsure if your type generates JavaScript safe output in all cases, then DON'T mark
+/// it. Better safe than sorry!
+pub trait JsSafe: fmt::Display {}
+
+/// Escape characters in a safe way for CSS
+///
+/// All characters but ASCII letters and digits, ` `, `#`, `%`, `,`, `-`, `.` and `_` are written
+/// as hexadecimal escape sequences, e.g. `;` => `\3b `.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Css;
+
+impl Escaper for Css {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_css_escaped(dest, string)
+    }
+}
+
+/// Percent-encode characters in a safe way for URL components, e.g. path segments or query
+/// parameters
+///
+/// All characters but ASCII letters and digits, `-`, `.`, `_` and `~` are percent-encoded as
+/// UTF-8, e.g. `/` => `%2F` and `ä` => `%C3%A4`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Url;
+
+impl Escaper for Url {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_url_escaped(dest, string)
+    }
+}
+
+/// Escape characters in a safe way for XML texts and attributes
+///
+/// * `"`, `&`, `'`, `<` and `>` => `&#34;`, `&#38;`, `&#39;`, `&#60;` and `&#62;`
+/// * tabs, line feeds and carriage returns => `&#9;`, `&#10;` and `&#13;`
+/// * other control characters, U+FFFE and U+FFFF, which must not appear in XML documents,
+///   => U+FFFD
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xml;
+
+impl Escaper for Xml {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_xml_escaped(dest, string)
+    }
+}
+
+/// Escape characters in a safe way for the content of JSON strings
+///
+/// * `"` => `\"`
+/// * `\` => `\\`
+/// * line feeds, carriage returns, tabs, backspaces and form feeds => `\n`, `\r`, `\t`, `\b`
+///   and `\f`
+/// * other control characters, `&`, `'`, `<` and `>` => `\u00xx`
+///
+/// Like the output of the [`|json`](super::json) filter, the output does not contain HTML
+/// special characters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Escaper for Json {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_json_escaped(dest, string)
+    }
+}
+
+/// Escape characters in a safe way for (La)TeX texts
+///
+/// * `#`, `$`, `%`, `&`, `_`, `{` and `}` => `\#`, `\$`, `\%`, `\&`, `\_`, `\{` and `\}`
+/// * `\` => `\textbackslash{}`
+/// * `^` and `~` => `\textasciicircum{}` and `\textasciitilde{}`
+/// * `<` and `>` => `\textless{}` and `\textgreater{}`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tex;
+
+impl Escaper for Tex {
+    #[inline]
+    fn write_escaped_str<W: Write>(&self, dest: W, string: &str) -> fmt::Result {
+        crate::escapers::write_tex_escaped(dest, string)
+    }
+}
+
+/// Reject URLs with an unsafe scheme
+///
+/// If the output of an expression starts with a URL scheme other than `http:`, `https:` or
+/// `mailto:`, e.g. `javascript:`, then `#askama-unsafe-url` is written instead.
+/// Relative URLs are written as is.
+///
+/// This type is used by askama for expressions at the start of URL attributes like `href` or
+/// `src`, and cannot be used as an [`Escaper`] on its own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckedUrl;
+
+impl<'a, T: fmt::Display + ?Sized> AutoEscape for &&AutoEscaper<'a, T, CheckedUrl> {
+    type Escaped = CheckedUrlDisplay<&'a T>;
+    type Error = Infallible;
+
+    #[inline]
+    fn askama_auto_escape(&self) -> Result<Self::Escaped, Self::Error> {
+        Ok(CheckedUrlDisplay(self.text))
+    }
+}
+
+/// A value written as a URL with a safe scheme, see [`CheckedUrl`]
+pub struct CheckedUrlDisplay<T>(pub(crate) T);
+
+impl<T: fmt::Display> fmt::Display for CheckedUrlDisplay<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut writer = CheckedUrlWriter {
+            dest: &mut *f,
+            state: CheckedUrlState::Undecided,
+            scheme: [0; MAX_SCHEME_LEN],
+            scheme_len: 0,
+        };
+        write!(writer, "{}", self.0)?;
+        match writer.state {
+            // no `:` was found, so this is a relative URL
+            CheckedUrlState::Undecided => {
+                let (scheme, len) = (writer.scheme, writer.scheme_len);
+                f.write_str(scheme_str(&scheme, len))
+            }
+            CheckedUrlState::Safe | CheckedUrlState::Unsafe => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckedUrlState {
+    Undecided,
+    Safe,
+    Unsafe,
+}
+
+struct CheckedUrlWriter<W> {
+    dest: W,
+    state: CheckedUrlState,
+    scheme: [u8; MAX_SCHEME_LEN],
+    scheme_len: usize,
+}
+
+const MAX_SCHEME_LEN: usize = 16;
+const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+fn scheme_str(scheme: &[u8; MAX_SCHEME_LEN], len: usize) -> &str {
+    // only ASCII characters are ever stored in `scheme`
+    str::from_utf8(&scheme[..len]).unwrap_or_default()
+}
+
+impl<W: Write> Write for CheckedUrlWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s;
+        while self.state == CheckedUrlState::Undecided {
+            let Some(c) = rest.chars().next() else {
+                return Ok(());
+            };
+            rest = &rest[c.len_utf8()..];
+            match c {
+                // Browsers ignore leading whitespace and control characters, and strip tabs and
+                // newlines anywhere in the URL.
+                '\0'..=' ' if self.scheme_len == 0 => {}
+                '\t' | '\n' | '\r' => {}
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '+' | '-' | '.' => {
+                    if self.scheme_len == self.scheme.len() {
+                        // too long for any scheme we accept
+                        self.state = CheckedUrlState::Unsafe;
+                        return self.dest.write_str("#askama-unsafe-url");
+                    }
+                    self.scheme[self.scheme_len] = c as u8;
+                    self.scheme_len += 1;
+                }
+                ':' => {
+                    let scheme = scheme_str(&self.scheme, self.scheme_len);
+                    if SAFE_SCHEMES
+                        .iter()
+                        .any(|safe| safe.eq_ignore_ascii_case(scheme))
+                    {
+                        self.state = CheckedUrlState::Safe;
+                        self.dest.write_str(scheme)?;
+                        self.dest.write_char(':')?;
+                    } else {
+                        self.state = CheckedUrlState::Unsafe;
+                        return self.dest.write_str("#askama-unsafe-url");
+                    }
+                }
+                _ => {
+                    // any other character ends a possible scheme, so this is a relative URL
+                    self.state = CheckedUrlState::Safe;
+                    self.dest
+                        .write_str(scheme_str(&self.scheme, self.scheme_len))?;
+                    self.dest.write_char(c)?;
+                }
+            }
+        }
+        match self.state {
+            CheckedUrlState::Safe => self.dest.write_str(rest),
+            CheckedUrlState::Undecided | CheckedUrlState::Unsafe => Ok(()),
+        }
+    }
+}
+
 /// Escapers are used to make generated text safe for printing in some context.
 ///
 /// E.g. in an [`Html`] context, any and all generated text can be used in HTML/XML text nodes and
@@ -490,13 +765,39 @@ crate::impl_for_ref! {
 
 impl<T: HtmlSafe> HtmlSafe for Pin<T> {}
 
+macro_rules! mark_js_safe {
+    ($($ty:ty),* $(,)?) => {$(
+        impl JsSafe for $ty {}
+    )*};
+}
+
+mark_js_safe! {
+    bool,
+    f32, f64,
+    i8, i16, i32, i64, i128, isize,
+    u8, u16, u32, u64, u128, usize,
+    core::num::NonZeroI8, core::num::NonZeroI16, core::num::NonZeroI32,
+    core::num::NonZeroI64, core::num::NonZeroI128, core::num::NonZeroIsize,
+    core::num::NonZeroU8, core::num::NonZeroU16, core::num::NonZeroU32,
+    core::num::NonZeroU64, core::num::NonZeroU128, core::num::NonZeroUsize,
+}
+
+impl<T: JsSafe> JsSafe for core::num::Wrapping<T> {}
+
+crate::impl_for_ref! {
+    impl JsSafe for T {}
+}
+
+impl<T: JsSafe> JsSafe for Pin<T> {}
+
 /// Used internally by askama to select the appropriate [`write!()`] mechanism
 pub struct Writable<'a, S: ?Sized>(pub &'a S);
 
 /// Used internally by askama to select the appropriate [`write!()`] mechanism
 pub trait WriteWritable {
     /// Used internally by askama to select the appropriate [`write!()`] mechanism
-    fn askama_write(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()>;
+    fn askama_write(&self, dest: &mut dyn TemplateWriter, values: &dyn Values)
+    -> crate::Result<()>;
 }
 
 #[test]
@@ -643,3 +944,134 @@ fn test_html_safe_marker() {
         "<script>",
     );
 }
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_escape_js() {
+    use alloc::string::ToString;
+
+    assert_eq!(escape("", Js).unwrap().to_string(), "");
+    assert_eq!(
+        escape("Hello, world!", Js).unwrap().to_string(),
+        "Hello, world!"
+    );
+    assert_eq!(
+        escape(r#"</script><a href="x" onclick='y'>"#, Js)
+            .unwrap()
+            .to_string(),
+        r"\u003C\/script\u003E\u003Ca href=\u0022x\u0022 onclick=\u0027y\u0027\u003E",
+    );
+    assert_eq!(
+        escape("a\\b\nc\r\td\0", Js).unwrap().to_string(),
+        r"a\\b\nc\r\td\u0000",
+    );
+    assert_eq!(
+        escape("`${x}` & \u{2028}\u{2029}", Js).unwrap().to_string(),
+        r"\u0060\u0024\u007Bx}\u0060 \u0026 \u2028\u2029",
+    );
+    assert_eq!(escape("äöü €", Js).unwrap().to_string(), "äöü €");
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_js_value() {
+    use alloc::string::ToString;
+
+    struct Script;
+
+    impl fmt::Display for Script {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str("alert(1)")
+        }
+    }
+
+    assert_eq!(
+        (&&AutoEscaper::new(&"it's \"quoted\"", JsValue))
+            .askama_auto_escape()
+            .unwrap()
+            .to_string(),
+        r#""it\u0027s \u0022quoted\u0022""#,
+    );
+    assert_eq!(
+        (&&AutoEscaper::new(&Script, JsValue))
+            .askama_auto_escape()
+            .unwrap()
+            .to_string(),
+        r#""alert(1)""#,
+    );
+    assert_eq!(
+        (&&AutoEscaper::new(&Safe(Script), JsValue))
+            .askama_auto_escape()
+            .unwrap()
+            .to_string(),
+        "alert(1)",
+    );
+    assert_eq!(
+        (&&AutoEscaper::new(&-42, JsValue))
+            .askama_auto_escape()
+            .unwrap()
+            .to_string(),
+        "-42",
+    );
+    assert_eq!(
+        (&&AutoEscaper::new(&&true, JsValue))
+            .askama_auto_escape()
+            .unwrap()
+            .to_string(),
+        "true",
+    );
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_escape_css() {
+    use alloc::string::ToString;
+
+    assert_eq!(escape("", Css).unwrap().to_string(), "");
+    assert_eq!(
+        escape("#fff, 10.5% -x_y", Css).unwrap().to_string(),
+        "#fff, 10.5% -x_y"
+    );
+    assert_eq!(
+        escape("red;}</style>", Css).unwrap().to_string(),
+        r"red\3b \7d \3c \2f style\3e ",
+    );
+    assert_eq!(
+        escape("url('x')\n", Css).unwrap().to_string(),
+        r"url\28 \27 x\27 \29 \a ",
+    );
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_checked_url() {
+    use alloc::string::ToString;
+
+    #[track_caller]
+    fn check(url: &str, expected: &str) {
+        assert_eq!(
+            (&&AutoEscaper::new(url, CheckedUrl))
+                .askama_auto_escape()
+                .unwrap()
+                .to_string(),
+            expected,
+        );
+    }
+
+    check("", "");
+    check("https://example.com/?a=b", "https://example.com/?a=b");
+    check("HTTP://example.com", "HTTP://example.com");
+    check("mailto:ferris@example.com", "mailto:ferris@example.com");
+    check("/path/to:file", "/path/to:file");
+    check("relative", "relative");
+    check("?q=javascript:x", "?q=javascript:x");
+    check("#anchor", "#anchor");
+    check("  https://x", "https://x");
+
+    check("javascript:alert(1)", "#askama-unsafe-url");
+    check("JavaScript:alert(1)", "#askama-unsafe-url");
+    check(" \x01java\nscript:alert(1)", "#askama-unsafe-url");
+    check("data:text/html,<script>", "#askama-unsafe-url");
+    check("vbscript:x", "#askama-unsafe-url");
+    check("aaaaaaaaaaaaaaaaaaaaaaaaaaaa:x", "#askama-unsafe-url");
+}
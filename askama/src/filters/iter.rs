@@ -0,0 +1,594 @@
+#[cfg(feature = "alloc")]
+use alloc::borrow::ToOwned;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::ops::Add;
+
+/// Returns an iterator that applies `f` to every item.
+///
+/// In templates, the filter is called with the name of a field, like `map(attribute = "name")`.
+/// The name can contain multiple fields, e.g. `"address.city"`.
+///
+/// ```
+/// # use askama::Template;
+/// struct User<'a> {
+///     name: &'a str,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(ext = "html", source = r#"{{ users|map(attribute = "name")|join(", ") }}"#)]
+/// struct Example<'a> {
+///     users: Vec<User<'a>>,
+/// }
+///
+/// assert_eq!(
+///     Example { users: vec![User { name: "Ferris" }, User { name: "Corro" }] }.to_string(),
+///     "Ferris, Corro"
+/// );
+/// ```
+#[inline]
+pub fn map<T, U>(
+    it: impl IntoIterator<Item = T>,
+    f: impl FnMut(T) -> U,
+) -> Result<impl Iterator<Item = U>, Infallible> {
+    Ok(it.into_iter().map(f))
+}
+
+/// Returns an iterator with only the items for which `predicate` returns `true`.
+///
+/// In templates, the filter is called with the name of a `bool` field, like
+/// `selectattr("is_active")`. If a [test](crate::tests) is given as second argument, then the
+/// field is tested instead, e.g. `selectattr("age", "odd")` or `selectattr("name", "in", names)`.
+///
+/// ```
+/// # use askama::Template;
+/// struct User<'a> {
+///     name: &'a str,
+///     is_admin: bool,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"{% for user in users|selectattr("is_admin") %}{{ user.name }} {% endfor %}"#,
+/// )]
+/// struct Example<'a> {
+///     users: Vec<User<'a>>,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         users: vec![
+///             User { name: "Ferris", is_admin: true },
+///             User { name: "Corro", is_admin: false },
+///         ],
+///     }
+///     .to_string(),
+///     "Ferris "
+/// );
+/// ```
+#[inline]
+pub fn selectattr<T>(
+    it: impl IntoIterator<Item = T>,
+    predicate: impl FnMut(&T) -> bool,
+) -> Result<impl Iterator<Item = T>, Infallible> {
+    Ok(it.into_iter().filter(predicate))
+}
+
+/// Returns the first item, or `None` if there are no items.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{% if let Some(name) = names|first %}{{ name }}{% else %}nobody{% endif %}",
+/// )]
+/// struct Example<'a> {
+///     names: &'a [&'a str],
+/// }
+///
+/// assert_eq!(Example { names: &["Ferris", "Corro"] }.to_string(), "Ferris");
+/// assert_eq!(Example { names: &[] }.to_string(), "nobody");
+/// ```
+#[inline]
+pub fn first<T>(it: impl IntoIterator<Item = T>) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().next())
+}
+
+/// Returns the last item, or `None` if there are no items.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{% if let Some(name) = names|last %}{{ name }}{% else %}nobody{% endif %}",
+/// )]
+/// struct Example<'a> {
+///     names: &'a [&'a str],
+/// }
+///
+/// assert_eq!(Example { names: &["Ferris", "Corro"] }.to_string(), "Corro");
+/// assert_eq!(Example { names: &[] }.to_string(), "nobody");
+/// ```
+#[inline]
+pub fn last<T>(it: impl IntoIterator<Item = T>) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().last())
+}
+
+/// Adds all items to `start`.
+///
+/// In templates, `start` is `0` by default. Use e.g. `sum(start = 0.0)` to add floats.
+/// Use the argument `attribute` to add a field of the items instead.
+///
+/// ```
+/// # use askama::Template;
+/// struct Item {
+///     price: u32,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"{{ numbers|sum }} {{ items|sum(attribute = "price", start = 100) }}"#,
+/// )]
+/// struct Example {
+///     numbers: Vec<u32>,
+///     items: Vec<Item>,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         numbers: vec![1, 2, 3],
+///         items: vec![Item { price: 10 }, Item { price: 20 }],
+///     }
+///     .to_string(),
+///     "6 130"
+/// );
+/// ```
+#[inline]
+pub fn sum<T, S: Add<T, Output = S>>(
+    it: impl IntoIterator<Item = T>,
+    start: S,
+) -> Result<S, Infallible> {
+    Ok(it.into_iter().fold(start, |sum, item| sum + item))
+}
+
+/// Returns the smallest item, or `None` if there are no items.
+///
+/// If multiple items are equally small, the first one is returned.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = "{{ numbers|min|assigned_or(\"-\") }}")]
+/// struct Example<'a> {
+///     numbers: &'a [i32],
+/// }
+///
+/// assert_eq!(Example { numbers: &[3, -1, 2] }.to_string(), "-1");
+/// assert_eq!(Example { numbers: &[] }.to_string(), "-");
+/// ```
+#[inline]
+pub fn min<T: Ord>(it: impl IntoIterator<Item = T>) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().min())
+}
+
+/// Returns the item with the smallest key, or `None` if there are no items.
+///
+/// If multiple items are equally small, the first one is returned.
+/// In templates, the filter is called with the name of a field, like `min(attribute = "age")`.
+#[inline]
+pub fn min_by_key<T, K: Ord + ?Sized>(
+    it: impl IntoIterator<Item = T>,
+    mut key: impl FnMut(&T) -> &K,
+) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().min_by(|a, b| key(a).cmp(key(b))))
+}
+
+/// Returns the largest item, or `None` if there are no items.
+///
+/// If multiple items are equally large, the last one is returned.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = "{{ numbers|max|assigned_or(\"-\") }}")]
+/// struct Example<'a> {
+///     numbers: &'a [i32],
+/// }
+///
+/// assert_eq!(Example { numbers: &[3, -1, 2] }.to_string(), "3");
+/// assert_eq!(Example { numbers: &[] }.to_string(), "-");
+/// ```
+#[inline]
+pub fn max<T: Ord>(it: impl IntoIterator<Item = T>) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().max())
+}
+
+/// Returns the item with the largest key, or `None` if there are no items.
+///
+/// If multiple items are equally large, the last one is returned.
+/// In templates, the filter is called with the name of a field, like `max(attribute = "age")`.
+#[inline]
+pub fn max_by_key<T, K: Ord + ?Sized>(
+    it: impl IntoIterator<Item = T>,
+    mut key: impl FnMut(&T) -> &K,
+) -> Result<Option<T>, Infallible> {
+    Ok(it.into_iter().max_by(|a, b| key(a).cmp(key(b))))
+}
+
+/// Returns an iterator over the sorted items.
+///
+/// The sort is stable, also if `reverse` is `true`: equal items keep their order.
+/// Use the argument `attribute` to sort the items by one of their fields.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{{ names|sort|join(\", \") }} / {{ names|sort(reverse = true)|join(\", \") }}",
+/// )]
+/// struct Example<'a> {
+///     names: Vec<&'a str>,
+/// }
+///
+/// assert_eq!(
+///     Example { names: vec!["b", "c", "a"] }.to_string(),
+///     "a, b, c / c, b, a"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn sort<T: Ord>(
+    it: impl IntoIterator<Item = T>,
+    reverse: bool,
+) -> Result<impl DoubleEndedIterator<Item = T> + ExactSizeIterator, Infallible> {
+    sort_by_key(it, |item| item, reverse)
+}
+
+/// Returns an iterator over the items, sorted by their keys.
+///
+/// The sort is stable, also if `reverse` is `true`: items with equal keys keep their order.
+/// In templates, the filter is called with the name of a field, like `sort(attribute = "age")`.
+///
+/// ```
+/// # use askama::Template;
+/// struct User<'a> {
+///     name: &'a str,
+///     age: u32,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"{% for user in users|sort(attribute = "age") %}{{ user.name }} {% endfor %}"#,
+/// )]
+/// struct Example<'a> {
+///     users: Vec<User<'a>>,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         users: vec![User { name: "Ferris", age: 12 }, User { name: "Corro", age: 7 }],
+///     }
+///     .to_string(),
+///     "Corro Ferris "
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn sort_by_key<T, K: Ord + ?Sized>(
+    it: impl IntoIterator<Item = T>,
+    mut key: impl FnMut(&T) -> &K,
+    reverse: bool,
+) -> Result<impl DoubleEndedIterator<Item = T> + ExactSizeIterator, Infallible> {
+    let mut items = it.into_iter().collect::<Vec<_>>();
+    match reverse {
+        false => items.sort_by(|a, b| key(a).cmp(key(b))),
+        true => items.sort_by(|a, b| key(b).cmp(key(a))),
+    }
+    Ok(items.into_iter())
+}
+
+/// Returns an iterator over the items, grouped by their keys.
+///
+/// The groups are sorted by their key. Every [`Group`] contains the key as
+/// [`grouper`](Group::grouper), and its items as [`list`](Group::list).
+/// In templates, the filter is called with the name of a field, like `groupby("city")`.
+///
+/// ```
+/// # use askama::Template;
+/// struct User<'a> {
+///     name: &'a str,
+///     city: &'a str,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"
+///         {%- for group in users|groupby("city") -%}
+///             {{ group.grouper }}: {{ group.list|map(attribute = "name")|join(", ") }};
+///         {%~ endfor -%}
+///     "#,
+/// )]
+/// struct Example<'a> {
+///     users: Vec<User<'a>>,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         users: vec![
+///             User { name: "Ferris", city: "Paris" },
+///             User { name: "Corro", city: "Berlin" },
+///             User { name: "Rusty", city: "Paris" },
+///         ],
+///     }
+///     .to_string(),
+///     "Berlin: Corro;\nParis: Ferris, Rusty;\n"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn groupby<T, K: Ord + ToOwned + ?Sized>(
+    it: impl IntoIterator<Item = T>,
+    mut key: impl FnMut(&T) -> &K,
+) -> Result<impl Iterator<Item = Group<K::Owned, T>>, Infallible> {
+    let mut items = it.into_iter().collect::<Vec<_>>();
+    items.sort_by(|a, b| key(a).cmp(key(b)));
+    let mut items = items.into_iter().peekable();
+    Ok(core::iter::from_fn(move || {
+        let first = items.next()?;
+        let grouper = key(&first).to_owned();
+        let mut list = Vec::from([first]);
+        while let Some(item) = items.next_if(|item| key(item) == key(&list[0])) {
+            list.push(item);
+        }
+        Some(Group { grouper, list })
+    }))
+}
+
+/// A group of items returned by [`groupby()`]
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group<K, T> {
+    /// The key that all items of the group have
+    pub grouper: K,
+    /// The items of the group
+    pub list: Vec<T>,
+}
+
+/// Returns an iterator over lists of `linecount` items.
+///
+/// The last list can be shorter, unless `fill_with` is given: then it is filled up with clones of
+/// `fill_with`. A `linecount` of zero is an error.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"
+///         {%- for row in cells|batch(3, "-") -%}
+///             {% for cell in row %}{{ cell }}{% endfor %};
+///         {%- endfor -%}
+///     "#,
+/// )]
+/// struct Example<'a> {
+///     cells: Vec<&'a str>,
+/// }
+///
+/// assert_eq!(
+///     Example { cells: vec!["a", "b", "c", "d"] }.to_string(),
+///     "abc;d--;"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn batch<'a, T: Clone + 'a>(
+    it: impl IntoIterator<Item = T> + 'a,
+    linecount: usize,
+    fill_with: Option<&'a T>,
+) -> crate::Result<impl Iterator<Item = Vec<T>> + 'a> {
+    if linecount == 0 {
+        return Err(crate::Error::custom(
+            "the `linecount` of a batch must not be zero",
+        ));
+    }
+
+    let mut items = it.into_iter();
+    Ok(core::iter::from_fn(move || {
+        let first = items.next()?;
+        let mut batch = Vec::with_capacity(linecount);
+        batch.push(first);
+        batch.extend(items.by_ref().take(linecount - 1));
+        if let Some(fill_with) = fill_with {
+            batch.resize(linecount, fill_with.clone());
+        }
+        Some(batch)
+    }))
+}
+
+/// Returns an iterator over `slices` lists, that contain all items, e.g. to render columns.
+///
+/// The first lists can contain one item more than the last lists, unless `fill_with` is given:
+/// then a clone of `fill_with` is added to the shorter lists. A number of `slices` of zero is an
+/// error.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"
+///         {%- for column in cells|slice(3) -%}
+///             {% for cell in column %}{{ cell }}{% endfor %};
+///         {%- endfor -%}
+///     "#,
+/// )]
+/// struct Example {
+///     cells: Vec<u32>,
+/// }
+///
+/// assert_eq!(
+///     Example { cells: (1..=7).collect() }.to_string(),
+///     "123;45;67;"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn slice<'a, T: Clone + 'a>(
+    it: impl IntoIterator<Item = T>,
+    slices: usize,
+    fill_with: Option<&'a T>,
+) -> crate::Result<impl Iterator<Item = Vec<T>> + 'a> {
+    if slices == 0 {
+        return Err(crate::Error::custom(
+            "the number of slices must not be zero",
+        ));
+    }
+
+    let mut items = it.into_iter().collect::<Vec<_>>().into_iter();
+    let per_slice = items.len() / slices;
+    let with_extra = items.len() % slices;
+    Ok((0..slices).map(move |index| {
+        let len = per_slice + usize::from(index < with_extra);
+        let mut slice = items.by_ref().take(len).collect::<Vec<_>>();
+        if let Some(fill_with) = fill_with
+            && index >= with_extra
+            && with_extra > 0
+        {
+            slice.push(fill_with.clone());
+        }
+        slice
+    }))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec; // It's the macro, not the module.
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_lazy() {
+        // only the needed items are taken from the iterator
+        let mut it = [1, 2, 3].into_iter();
+        assert_eq!(first(&mut it).unwrap(), Some(1));
+        assert_eq!(it.next(), Some(2));
+
+        let mut taken = 0;
+        let it = map(0.., |i| {
+            taken += 1;
+            i * 2
+        })
+        .unwrap();
+        assert_eq!(
+            selectattr(it, |i| i % 3 == 0)
+                .unwrap()
+                .take(2)
+                .collect::<Vec<_>>(),
+            vec![0, 6]
+        );
+        assert_eq!(taken, 4);
+    }
+
+    #[test]
+    fn test_aggregates() {
+        assert_eq!(last([1, 2, 3]).unwrap(), Some(3));
+        assert_eq!(last(None::<i32>).unwrap(), None);
+        assert_eq!(sum([1, 2, 3], 0).unwrap(), 6);
+        assert_eq!(sum([0.5, 1.0], 0.0).unwrap(), 1.5);
+        assert_eq!(sum(None::<u8>, 7).unwrap(), 7);
+        assert_eq!(min(["b", "a", "c"]).unwrap(), Some("a"));
+        assert_eq!(max(["b", "a", "c"]).unwrap(), Some("c"));
+        assert_eq!(max(None::<i32>).unwrap(), None);
+
+        let pairs = [(1, 'a'), (0, 'b'), (1, 'c'), (0, 'd')];
+        assert_eq!(min_by_key(pairs, |p| &p.0).unwrap(), Some((0, 'b')));
+        assert_eq!(max_by_key(pairs, |p| &p.0).unwrap(), Some((1, 'c')));
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(
+            sort([3, 1, 2], false).unwrap().collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            sort([3, 1, 2], true).unwrap().collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+
+        // the sort is stable in both directions
+        let pairs = [(1, 'a'), (0, 'b'), (1, 'c'), (0, 'd')];
+        assert_eq!(
+            sort_by_key(pairs, |p| &p.0, false)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![(0, 'b'), (0, 'd'), (1, 'a'), (1, 'c')]
+        );
+        assert_eq!(
+            sort_by_key(pairs, |p| &p.0, true)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![(1, 'a'), (1, 'c'), (0, 'b'), (0, 'd')]
+        );
+    }
+
+    #[test]
+    fn test_groupby() {
+        let pairs = [("b", 1), ("a", 2), ("b", 3)];
+        assert_eq!(
+            groupby(&pairs, |p| &p.0).unwrap().collect::<Vec<_>>(),
+            vec![
+                Group {
+                    grouper: "a",
+                    list: vec![&("a", 2)],
+                },
+                Group {
+                    grouper: "b",
+                    list: vec![&("b", 1), &("b", 3)],
+                },
+            ]
+        );
+        assert_eq!(groupby(None::<(u8, u8)>, |p| &p.0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_batch() {
+        assert_eq!(
+            batch(1..=5, 2, None).unwrap().collect::<Vec<_>>(),
+            vec![vec![1, 2], vec![3, 4], vec![5]]
+        );
+        assert_eq!(
+            batch(1..=5, 3, Some(&0)).unwrap().collect::<Vec<_>>(),
+            vec![vec![1, 2, 3], vec![4, 5, 0]]
+        );
+        assert_eq!(batch(1..=4, 2, Some(&0)).unwrap().count(), 2);
+        assert_eq!(batch(0..0, 2, None).unwrap().count(), 0);
+        assert!(batch(0..1, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_eq!(
+            slice(1..=7, 3, None).unwrap().collect::<Vec<_>>(),
+            vec![vec![1, 2, 3], vec![4, 5], vec![6, 7]]
+        );
+        assert_eq!(
+            slice(1..=7, 3, Some(&0)).unwrap().collect::<Vec<_>>(),
+            vec![vec![1, 2, 3], vec![4, 5, 0], vec![6, 7, 0]]
+        );
+        assert_eq!(
+            slice(1..=6, 3, Some(&0)).unwrap().collect::<Vec<_>>(),
+            vec![vec![1, 2], vec![3, 4], vec![5, 6]]
+        );
+        assert_eq!(
+            slice(1..=2, 3, None).unwrap().collect::<Vec<_>>(),
+            vec![vec![1], vec![2], vec![]]
+        );
+        assert!(slice(0..1, 0, None).is_err());
+    }
+}
@@ -4,18 +4,18 @@ use std::{fmt, io, str};
 use serde::Serialize;
 use serde_json::ser::{CompactFormatter, PrettyFormatter, Serializer};
 
-use super::AsIndent;
+use super::{AsIndent, JsSafe};
 use crate::ascii_str::{AsciiChar, AsciiStr};
 use crate::{FastWritable, NO_VALUES, Values};
 
 /// Serialize to JSON (requires `json` feature)
 ///
 /// The generated string does not contain ampersands `&`, chevrons `< >`, or apostrophes `'`.
-/// To use it in a `<script>` you can combine it with the safe filter:
+/// The output is a valid JavaScript expression, so in a `<script>` you can use it as is:
 ///
 /// ``` html
 /// <script>
-/// var data = {{data|json|safe}};
+/// var data = {{data|json}};
 /// </script>
 /// ```
 ///
@@ -43,7 +43,7 @@ use crate::{FastWritable, NO_VALUES, Values};
 /// # }
 /// ```
 #[inline]
-pub fn json(value: impl Serialize) -> Result<impl fmt::Display, Infallible> {
+pub fn json(value: impl Serialize) -> Result<impl JsSafe, Infallible> {
     Ok(ToJson { value })
 }
 
@@ -85,7 +85,7 @@ pub fn json(value: impl Serialize) -> Result<impl fmt::Display, Infallible> {
 pub fn json_pretty(
     value: impl Serialize,
     indent: impl AsIndent,
-) -> Result<impl fmt::Display, Infallible> {
+) -> Result<impl JsSafe, Infallible> {
     Ok(ToJsonPretty { value, indent })
 }
 
@@ -100,6 +100,8 @@ struct ToJsonPretty<S, I> {
     indent: I,
 }
 
+impl<S: Serialize> JsSafe for ToJson<S> {}
+
 impl<S: Serialize> FastWritable for ToJson<S> {
     #[inline]
     fn write_into(&self, f: &mut dyn fmt::Write, _: &dyn Values) -> crate::Result<()> {
@@ -114,6 +116,8 @@ impl<S: Serialize> fmt::Display for ToJson<S> {
     }
 }
 
+impl<S: Serialize, I: AsIndent> JsSafe for ToJsonPretty<S, I> {}
+
 impl<S: Serialize, I: AsIndent> FastWritable for ToJsonPretty<S, I> {
     #[inline]
     fn write_into(&self, f: &mut dyn fmt::Write, _: &dyn Values) -> crate::Result<()> {
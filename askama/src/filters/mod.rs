@@ -17,8 +17,11 @@ mod default;
 mod escape;
 mod humansize;
 mod indent;
+mod iter;
 #[cfg(feature = "serde_json")]
 mod json;
+#[cfg(feature = "alloc")]
+mod sanitize;
 #[cfg(feature = "std")]
 mod std;
 #[cfg(feature = "urlencode")]
@@ -34,13 +37,19 @@ pub use self::core::{
 };
 pub use self::default::{DefaultFilterable, assigned_or};
 pub use self::escape::{
-    AutoEscape, AutoEscaper, Escaper, Html, HtmlSafe, HtmlSafeOutput, MaybeSafe, Safe, Text,
-    Unsafe, Writable, WriteWritable, e, escape, safe,
+    AutoEscape, AutoEscaper, CheckedUrl, CheckedUrlDisplay, Css, Escaper, Html, HtmlSafe,
+    HtmlSafeOutput, Js, JsSafe, JsString, JsValue, Json, MaybeSafe, Safe, Tex, Text, Unsafe, Url,
+    Writable, WriteWritable, Xml, e, escape, safe,
 };
 pub use self::humansize::filesizeformat;
 pub use self::indent::{AsIndent, indent};
+#[cfg(feature = "alloc")]
+pub use self::iter::{Group, batch, groupby, slice, sort, sort_by_key};
+pub use self::iter::{first, last, map, max, max_by_key, min, min_by_key, selectattr, sum};
 #[cfg(feature = "serde_json")]
 pub use self::json::{json, json_pretty};
+#[cfg(feature = "alloc")]
+pub use self::sanitize::{DEFAULT_SANITIZE_ATTRIBUTES, DEFAULT_SANITIZE_TAGS, sanitize};
 #[cfg(feature = "std")]
 pub use self::std::unique;
 #[cfg(feature = "urlencode")]
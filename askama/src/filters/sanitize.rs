@@ -0,0 +1,507 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt::{self, Write};
+
+use super::escape::{CheckedUrlDisplay, EscapeWriter};
+use super::{Html, Safe};
+use crate::unescape::write_unescaped_str;
+use crate::{FastWritable, Values};
+
+/// The tags that [`sanitize`] keeps if no list was given
+pub const DEFAULT_SANITIZE_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "dd",
+    "del",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// The attributes that [`sanitize`] keeps if no list was given
+pub const DEFAULT_SANITIZE_ATTRIBUTES: &[&str] = &[
+    "alt", "cite", "colspan", "href", "lang", "rowspan", "src", "title",
+];
+
+/// Only keep allowed HTML tags and attributes
+///
+/// The filter is meant to render HTML fragments from untrusted sources, e.g. the output of a
+/// rich-text editor, without having to mark them as [`|safe`](super::safe).
+/// Without arguments, [`DEFAULT_SANITIZE_TAGS`] and [`DEFAULT_SANITIZE_ATTRIBUTES`] are allowed.
+/// Use the arguments `tags` and `attributes` to set your own lists.
+///
+/// * Tags that are not allowed are removed, but their content is kept.
+/// * `<script>` and `<style>` elements are removed with their content, even if they are allowed.
+/// * Comments, doctypes and processing instructions are removed.
+/// * Attributes that are not allowed are removed. Event handlers like `onclick` are always
+///   removed.
+/// * URLs in attributes like `href` or `src` with a scheme other than `http`, `https` or `mailto`
+///   are replaced with `#askama-unsafe-url`.
+/// * Texts and attribute values are escaped again, and unclosed tags are closed at the end.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// <div>{{ comment|sanitize }}</div>
+/// /// <div>{{ comment|sanitize(tags = ["a"], attributes = ["href"]) }}</div>
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "html", in_doc = true)]
+/// struct Example<'a> {
+///     comment: &'a str,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         comment: r#"<p onclick="steal()">Hi <a href="javascript:steal()" title=x>you</a>"#,
+///     }
+///     .to_string(),
+///     "<div><p>Hi <a href=\"#askama-unsafe-url\" title=\"x\">you</a></p></div>\n\
+///      <div>Hi <a href=\"#askama-unsafe-url\">you</a></div>"
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn sanitize<'a, S, Tags, T, Attrs, A>(
+    source: S,
+    tags: &'a Tags,
+    attributes: &'a Attrs,
+) -> Result<Safe<Sanitize<'a, S, T, A>>, Infallible>
+where
+    S: fmt::Display,
+    Tags: AsRef<[T]> + ?Sized,
+    T: AsRef<str> + 'a,
+    Attrs: AsRef<[A]> + ?Sized,
+    A: AsRef<str> + 'a,
+{
+    Ok(Safe(Sanitize {
+        source,
+        tags: tags.as_ref(),
+        attributes: attributes.as_ref(),
+    }))
+}
+
+pub struct Sanitize<'a, S, T, A> {
+    source: S,
+    tags: &'a [T],
+    attributes: &'a [A],
+}
+
+impl<S: fmt::Display, T: AsRef<str>, A: AsRef<str>> fmt::Display for Sanitize<'_, S, T, A> {
+    fn fmt(&self, dest: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut source = String::new();
+        write!(source, "{}", self.source)?;
+        self.write_sanitized(dest, &source)
+    }
+}
+
+impl<S: FastWritable, T: AsRef<str>, A: AsRef<str>> FastWritable for Sanitize<'_, S, T, A> {
+    fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()> {
+        let mut source = String::new();
+        self.source.write_into(&mut source, values)?;
+        Ok(self.write_sanitized(dest, &source)?)
+    }
+}
+
+impl<'a, S, T: AsRef<str>, A: AsRef<str>> Sanitize<'a, S, T, A> {
+    fn write_sanitized(&self, dest: &mut (impl fmt::Write + ?Sized), src: &str) -> fmt::Result {
+        // the allowed tags that are currently open
+        let mut open = Vec::new();
+        let mut last = 0;
+        let mut index = 0;
+        while let Some(offset) = src[index..].find('<') {
+            let start = index + offset;
+            let Some((tag, end)) = parse_tag(src, start) else {
+                // not a tag, so the `<` is text
+                index = start + 1;
+                continue;
+            };
+            write_text(dest, &src[last..start])?;
+            last = end;
+            index = end;
+
+            match tag {
+                Tag::Start(name, _)
+                    if ALWAYS_REMOVED.iter().any(|t| t.eq_ignore_ascii_case(name)) =>
+                {
+                    // skip the content, too
+                    index = find_end_tag(src, end, name);
+                    last = index;
+                }
+                Tag::Start(name, attrs) => {
+                    let Some(name) = self.allowed_tag(name) else {
+                        continue;
+                    };
+                    dest.write_char('<')?;
+                    dest.write_str(name)?;
+                    self.write_attributes(dest, attrs)?;
+                    dest.write_char('>')?;
+                    if !VOID_ELEMENTS.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+                        open.push(name);
+                    }
+                }
+                Tag::End(name) => {
+                    let Some(pos) = open.iter().rposition(|t| t.eq_ignore_ascii_case(name)) else {
+                        continue;
+                    };
+                    for name in open.drain(pos..).rev() {
+                        write!(dest, "</{name}>")?;
+                    }
+                }
+                Tag::Other => {}
+            }
+        }
+        write_text(dest, &src[last..])?;
+        for name in open.into_iter().rev() {
+            write!(dest, "</{name}>")?;
+        }
+        Ok(())
+    }
+
+    fn allowed_tag(&self, name: &str) -> Option<&'a str> {
+        self.tags
+            .iter()
+            .map(|tag| tag.as_ref())
+            .find(|tag| tag.eq_ignore_ascii_case(name))
+    }
+
+    fn write_attributes(&self, dest: &mut (impl fmt::Write + ?Sized), attrs: &str) -> fmt::Result {
+        let mut written: Vec<&str> = Vec::new();
+        for (name, value) in Attributes(attrs) {
+            let Some(name) = self
+                .attributes
+                .iter()
+                .map(|attr| attr.as_ref())
+                .find(|attr| attr.eq_ignore_ascii_case(name))
+            else {
+                continue;
+            };
+            // browsers only use the first attribute with the same name
+            if written.iter().any(|attr| attr.eq_ignore_ascii_case(name))
+                || name
+                    .get(..2)
+                    .is_some_and(|on| on.eq_ignore_ascii_case("on"))
+            {
+                continue;
+            }
+            written.push(name);
+
+            write!(dest, " {name}")?;
+            let Some(value) = value else {
+                continue;
+            };
+            dest.write_str("=\"")?;
+            if URL_ATTRIBUTES
+                .iter()
+                .any(|attr| attr.eq_ignore_ascii_case(name))
+            {
+                let mut url = String::new();
+                write_unescaped_str(&mut url, value)?;
+                write!(EscapeWriter(&mut *dest, Html), "{}", CheckedUrlDisplay(url))?;
+            } else {
+                write_unescaped_str(EscapeWriter(&mut *dest, Html), value)?;
+            }
+            dest.write_char('"')?;
+        }
+        Ok(())
+    }
+}
+
+/// Elements that are removed with their content
+const ALWAYS_REMOVED: &[&str] = &["script", "style"];
+
+/// Elements that have no end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Attributes that contain a URL
+const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "formaction",
+    "href",
+    "longdesc",
+    "poster",
+    "src",
+    "usemap",
+    "xlink:href",
+];
+
+fn write_text(dest: &mut (impl fmt::Write + ?Sized), text: &str) -> fmt::Result {
+    write_unescaped_str(EscapeWriter(dest, Html), text)
+}
+
+enum Tag<'a> {
+    /// The name, and the unparsed attributes
+    Start(&'a str, &'a str),
+    End(&'a str),
+    /// Comments, doctypes and processing instructions
+    Other,
+}
+
+/// Parses the tag that starts with the `<` at `src[start]`, and returns it and the index after
+/// its end. Returns `None` if the `<` does not start a tag.
+fn parse_tag(src: &str, start: usize) -> Option<(Tag<'_>, usize)> {
+    let rest = &src[start + 1..];
+    let end_of = |offset: usize, pat: &str| {
+        rest[offset..]
+            .find(pat)
+            .map_or(src.len(), |pos| start + 1 + offset + pos + pat.len())
+    };
+    match rest.as_bytes().first()? {
+        b'!' if rest.starts_with("!--") => Some((Tag::Other, end_of(3, "-->"))),
+        b'!' | b'?' => Some((Tag::Other, end_of(1, ">"))),
+        b'/' if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) => {
+            let name = tag_name(&rest[1..]);
+            Some((Tag::End(name), end_of(1 + name.len(), ">")))
+        }
+        c if c.is_ascii_alphabetic() => {
+            let name = tag_name(rest);
+            let attrs_start = start + 1 + name.len();
+            let attrs_end = find_tag_end(&src[attrs_start..]).map(|pos| attrs_start + pos);
+            match attrs_end {
+                Some(attrs_end) => Some((
+                    Tag::Start(name, &src[attrs_start..attrs_end]),
+                    attrs_end + 1,
+                )),
+                // browsers drop an unfinished tag at the end of the input
+                None => Some((Tag::Other, src.len())),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn tag_name(src: &str) -> &str {
+    let len = src
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(src.len());
+    &src[..len]
+}
+
+/// Finds the `>` that ends a start tag, skipping quoted attribute values.
+fn find_tag_end(attrs: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in attrs.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(index),
+            (None, '"' | '\'') if attrs[..index].trim_end().ends_with('=') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds the end of the end tag `</name>` after `index`, or the end of the input.
+fn find_end_tag(src: &str, mut index: usize, name: &str) -> usize {
+    while let Some(offset) = src[index..].find("</") {
+        let start = index + offset;
+        let candidate = tag_name(&src[start + 2..]);
+        if candidate.eq_ignore_ascii_case(name) {
+            return src[start..]
+                .find('>')
+                .map_or(src.len(), |pos| start + pos + 1);
+        }
+        index = start + 2;
+    }
+    src.len()
+}
+
+/// Iterates over the attributes in a start tag, returning their names and raw values
+struct Attributes<'a>(&'a str);
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let src = self
+            .0
+            .trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if src.is_empty() {
+            self.0 = src;
+            return None;
+        }
+
+        // the first character is part of the name, even if it is a `=`
+        let name_len = src[1..]
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '='))
+            .map_or(src.len(), |len| len + 1);
+        let name = &src[..name_len];
+        let rest = src[name_len..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let Some(rest) = rest.strip_prefix('=') else {
+            self.0 = rest;
+            return Some((name, None));
+        };
+
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let (value, rest) = match rest.as_bytes().first() {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let rest = &rest[1..];
+                let len = rest.find(quote as char).unwrap_or(rest.len());
+                (&rest[..len], rest.get(len + 1..).unwrap_or_default())
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+                (&rest[..len], &rest[len..])
+            }
+        };
+        self.0 = rest;
+        Some((name, Some(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    fn sanitize_default(src: &str) -> String {
+        sanitize(src, DEFAULT_SANITIZE_TAGS, DEFAULT_SANITIZE_ATTRIBUTES)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_sanitize_tags() {
+        assert_eq!(sanitize_default(""), "");
+        assert_eq!(sanitize_default("plain text"), "plain text");
+        assert_eq!(
+            sanitize_default("<p>Hello <b>world</b>!</p>"),
+            "<p>Hello <b>world</b>!</p>"
+        );
+        assert_eq!(
+            sanitize_default("<P>Hello <Blink>world</BLINK>!</p>"),
+            "<p>Hello world!</p>"
+        );
+        assert_eq!(
+            sanitize_default("a<script>alert('<b>')</script>b<STYLE>*{}</style>c"),
+            "abc"
+        );
+        assert_eq!(sanitize_default("a<script>alert(1)"), "a");
+        assert_eq!(
+            sanitize_default("a<!-- <b> -->b<!DOCTYPE html>c<?xml?>d"),
+            "abcd"
+        );
+        assert_eq!(
+            sanitize_default("1 < 2 <3 <> 4"),
+            "1 &#60; 2 &#60;3 &#60;&#62; 4"
+        );
+        assert_eq!(sanitize_default("a<b"), "a");
+        assert_eq!(sanitize_default("a</b>c"), "ac");
+    }
+
+    #[test]
+    fn test_sanitize_nesting() {
+        assert_eq!(
+            sanitize_default("<ul><li><b>one<li>two</ul>"),
+            "<ul><li><b>one<li>two</li></b></li></ul>"
+        );
+        assert_eq!(
+            sanitize_default("<p>a<br>b<br/>c<hr />"),
+            "<p>a<br>b<br>c<hr></p>"
+        );
+        assert_eq!(sanitize_default("<i>a</b>b</i></i>"), "<i>ab</i>");
+    }
+
+    #[test]
+    fn test_sanitize_attributes() {
+        assert_eq!(
+            sanitize_default(r#"<a href="https://askama.rs/" target=_blank TITLE='a "b"'>x</a>"#),
+            r#"<a href="https://askama.rs/" title="a &#34;b&#34;">x</a>"#
+        );
+        assert_eq!(
+            sanitize_default(r#"<img src="javascript:alert(1)" alt=x onerror="alert(1)">"#),
+            r##"<img src="#askama-unsafe-url" alt="x">"##
+        );
+        assert_eq!(
+            sanitize_default(r#"<a href=" &#106;avascript:alert(1)">x</a>"#),
+            r##"<a href="#askama-unsafe-url">x</a>"##
+        );
+        assert_eq!(
+            sanitize_default(r#"<a href="/a?b=1&amp;c=2" title="1" title="2" lang>x</a>"#),
+            r#"<a href="/a?b=1&#38;c=2" title="1" lang>x</a>"#
+        );
+        assert_eq!(
+            sanitize_default(r#"<p title="a > b">c</p>"#),
+            r#"<p title="a &#62; b">c</p>"#
+        );
+    }
+
+    #[test]
+    fn test_sanitize_custom_lists() {
+        assert_eq!(
+            sanitize(
+                r#"<p onclick="x()" class="c" id="d"><em>a</em></p>"#,
+                &["P"],
+                &["class", "onclick"],
+            )
+            .unwrap()
+            .to_string(),
+            r#"<P class="c">a</P>"#
+        );
+        assert_eq!(
+            sanitize(
+                "<b>a</b><script>b</script>",
+                &["b", "script"],
+                &[] as &[&str]
+            )
+            .unwrap()
+            .to_string(),
+            "<b>a</b>"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_text() {
+        assert_eq!(
+            sanitize_default("Tom &amp; Jerry &lt;3 & &quot;you&quot; &eacute;"),
+            "Tom &#38; Jerry &#60;3 &#38; &#34;you&#34; é"
+        );
+    }
+}
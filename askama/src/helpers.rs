@@ -6,15 +6,101 @@ pub extern crate core;
 #[cfg(feature = "std")]
 pub extern crate std;
 
+use core::any::Any;
 use core::cell::Cell;
 use core::fmt;
 use core::iter::{Enumerate, Peekable};
+use core::marker::PhantomData;
 use core::ops::Deref;
 use core::pin::Pin;
 
 pub use crate::error::{ErrorMarker, ResultConverter};
+use crate::sink::SinkWriter;
 pub use crate::values::get_value;
-use crate::{FastWritable, Values};
+use crate::{ByteSink, FastWritable, Location, Template, Values};
+
+/// Attaches the template `location` to the error of a fallible expression or filter
+#[inline]
+pub fn located<E: Into<crate::Error>>(
+    location: &'static Location,
+) -> impl FnOnce(E) -> crate::Error {
+    move |err| {
+        #[cfg(feature = "alloc")]
+        return err.into().with_location(location);
+        #[cfg(not(feature = "alloc"))]
+        {
+            let _ = location;
+            err.into()
+        }
+    }
+}
+
+/// The type of a value declared with `#[template(values(...))]`, used with [`DeclaredValue`]
+pub struct ValueMarker<T>(PhantomData<T>);
+
+impl<T> ValueMarker<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for ValueMarker<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Looks up a value declared with `#[template(values(...))]`
+///
+/// `(&&ValueMarker::<T>::new()).askama_get_value(..)` looks up a value of type `T`, unless `T`
+/// is an [`Option<U>`]: then the value is looked up as `U`, and may be missing.
+pub trait DeclaredValue {
+    /// The type of the looked up value
+    type Value: Any;
+
+    /// Looks up the value `key`
+    fn askama_get_value<'a>(
+        self,
+        values: &'a dyn Values,
+        key: &str,
+    ) -> crate::Result<&'a Self::Value>;
+
+    /// Checks the value `key` before the template is rendered
+    fn askama_check_value(self, values: &dyn Values, key: &str) -> crate::Result<()>;
+}
+
+impl<T: Any> DeclaredValue for &ValueMarker<T> {
+    type Value = T;
+
+    #[inline]
+    fn askama_get_value<'a>(self, values: &'a dyn Values, key: &str) -> crate::Result<&'a T> {
+        get_value(values, key)
+    }
+
+    #[inline]
+    fn askama_check_value(self, values: &dyn Values, key: &str) -> crate::Result<()> {
+        get_value::<T>(values, key).map(|_| ())
+    }
+}
+
+impl<T: Any> DeclaredValue for &&ValueMarker<Option<T>> {
+    type Value = T;
+
+    #[inline]
+    fn askama_get_value<'a>(self, values: &'a dyn Values, key: &str) -> crate::Result<&'a T> {
+        get_value(values, key)
+    }
+
+    #[inline]
+    fn askama_check_value(self, values: &dyn Values, key: &str) -> crate::Result<()> {
+        match get_value::<T>(values, key) {
+            Ok(_) | Err(crate::Error::ValueMissing) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
 
 pub struct TemplateLoop<I>
 where
@@ -82,6 +168,12 @@ where
     pub fn take_err(&self) -> crate::Result<()> {
         Err(self.err.take().unwrap_or(crate::Error::Fmt))
     }
+
+    /// Like [`take_err()`][Self::take_err], but falls back to the error of the writer.
+    #[inline]
+    pub fn take_err_or(&self, err: impl Into<crate::Error>) -> crate::Result<()> {
+        Err(self.err.take().unwrap_or_else(|| err.into()))
+    }
 }
 
 impl<F> fmt::Display for FmtCell<F>
@@ -260,6 +352,31 @@ impl fmt::Write for Empty {
     }
 }
 
+/// The value of an inline `a if cond` without an `else` branch: nothing is written if the
+/// condition was `false`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OrEmpty<T>(pub Option<T>);
+
+impl<T: fmt::Display> fmt::Display for OrEmpty<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: FastWritable> FastWritable for OrEmpty<T> {
+    #[inline]
+    fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()> {
+        match &self.0 {
+            Some(value) => value.write_into(dest, values),
+            None => Ok(()),
+        }
+    }
+}
+
 #[inline]
 pub fn as_bool<T: PrimitiveType<Value = bool>>(value: T) -> bool {
     value.get()
@@ -281,12 +398,111 @@ impl<L: FastWritable, R: FastWritable> FastWritable for Concat<L, R> {
         self.0.write_into(dest, values)?;
         self.1.write_into(dest, values)
     }
+
+    #[inline]
+    fn write_bytes_into(&self, dest: &mut dyn ByteSink, values: &dyn Values) -> crate::Result<()> {
+        self.0.write_bytes_into(dest, values)?;
+        self.1.write_bytes_into(dest, values)
+    }
 }
 
-pub trait EnumVariantTemplate {
-    fn render_into_with_values(
+/// The body of a template, rendered into a [`TemplateWriter`]
+///
+/// Implemented by the generated code of structs and enum variants. The writer is a trait object,
+/// so the body is only compiled once, no matter if it is rendered into a [`fmt::Write`] or into
+/// any kind of [`ByteSink`].
+pub trait TemplateBody {
+    fn render_body(
         &self,
-        writer: &mut dyn fmt::Write,
-        values: &dyn crate::Values,
+        writer: &mut dyn TemplateWriter,
+        values: &dyn Values,
     ) -> crate::Result<()>;
 }
+
+/// The output of the generated code: a [`fmt::Write`] or a [`ByteSink`]
+pub trait TemplateWriter {
+    fn write_str(&mut self, s: &str) -> crate::Result<()>;
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> crate::Result<()>;
+
+    /// The underlying output, so values and templates can be written into it without an adapter
+    fn output(&mut self) -> TemplateOutput<'_>;
+}
+
+/// The underlying output of a [`TemplateWriter`]
+pub enum TemplateOutput<'a> {
+    Fmt(&'a mut dyn fmt::Write),
+    Bytes(&'a mut dyn ByteSink),
+}
+
+impl dyn TemplateWriter + '_ {
+    #[inline]
+    pub fn write_writable<T: FastWritable + ?Sized>(
+        &mut self,
+        value: &T,
+        values: &dyn Values,
+    ) -> crate::Result<()> {
+        match self.output() {
+            TemplateOutput::Fmt(dest) => value.write_into(dest, values),
+            TemplateOutput::Bytes(dest) => value.write_bytes_into(dest, values),
+        }
+    }
+
+    #[inline]
+    pub fn write_template<T: Template + ?Sized>(
+        &mut self,
+        tmpl: &T,
+        values: &dyn Values,
+    ) -> crate::Result<()> {
+        match self.output() {
+            TemplateOutput::Fmt(dest) => tmpl.render_into_with_values(dest, values),
+            TemplateOutput::Bytes(dest) => tmpl.write_bytes_with_values(dest, values),
+        }
+    }
+}
+
+macro_rules! impl_fmt_template_writer {
+    ($($ty:ty)*) => { $(
+        impl TemplateWriter for $ty {
+            #[inline]
+            fn write_str(&mut self, s: &str) -> crate::Result<()> {
+                Ok(fmt::Write::write_str(self, s)?)
+            }
+
+            #[inline]
+            fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> crate::Result<()> {
+                Ok(fmt::Write::write_fmt(self, args)?)
+            }
+
+            #[inline]
+            fn output(&mut self) -> TemplateOutput<'_> {
+                TemplateOutput::Fmt(self)
+            }
+        }
+    )* };
+}
+
+impl_fmt_template_writer!(&mut (dyn fmt::Write + '_) fmt::Formatter<'_> Empty);
+
+impl<S: ByteSink> TemplateWriter for S {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> crate::Result<()> {
+        self.write_bytes(s.as_bytes())
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> crate::Result<()> {
+        if let Some(s) = args.as_str() {
+            return self.write_bytes(s.as_bytes());
+        }
+        let mut writer = SinkWriter::new(self);
+        match fmt::Write::write_fmt(&mut writer, args) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(writer.take_err().unwrap_or(err.into())),
+        }
+    }
+
+    #[inline]
+    fn output(&mut self) -> TemplateOutput<'_> {
+        TemplateOutput::Bytes(self)
+    }
+}
@@ -9,20 +9,16 @@ use crate::ascii_str::{AsciiChar, AsciiStr};
 
 #[allow(unused)]
 pub(crate) fn write_escaped_str(mut dest: impl fmt::Write, src: &str) -> fmt::Result {
-    // This implementation reads one byte after another.
-    // It's not very fast, but should work well enough until portable SIMD gets stabilized.
-
     let mut escaped_buf = ESCAPED_BUF_INIT;
     let mut last = 0;
 
-    for (index, byte) in src.bytes().enumerate() {
-        if let Some(escaped) = get_escaped(byte) {
-            [escaped_buf[2], escaped_buf[3]] = escaped;
-            write_str_if_nonempty(&mut dest, &src[last..index])?;
-            dest.write_str(AsciiStr::from_slice(&escaped_buf[..ESCAPED_BUF_LEN]))?;
-            last = index + 1;
-        }
-    }
+    simd::for_each_escaped(src.as_bytes(), 0, &mut |index, escaped| {
+        [escaped_buf[2], escaped_buf[3]] = escaped;
+        write_str_if_nonempty(&mut dest, &src[last..index])?;
+        dest.write_str(AsciiStr::from_slice(&escaped_buf[..ESCAPED_BUF_LEN]))?;
+        last = index + 1;
+        Ok(())
+    })?;
     write_str_if_nonempty(&mut dest, &src[last..])
 }
 
@@ -51,6 +47,179 @@ fn get_escaped(byte: u8) -> Option<[AsciiChar; 2]> {
     }
 }
 
+/// Calls `f` with the index and escaped value of every byte in `bytes` that needs HTML escaping.
+/// `offset` is added to the index.
+///
+/// This implementation reads one byte after another. It is used if no SIMD implementation is
+/// available for the target, and for the tail of the input that is shorter than a vector.
+#[inline]
+fn for_each_escaped_scalar(
+    bytes: &[u8],
+    offset: usize,
+    f: &mut impl FnMut(usize, [AsciiChar; 2]) -> fmt::Result,
+) -> fmt::Result {
+    for (index, &byte) in bytes.iter().enumerate() {
+        if let Some(escaped) = get_escaped(byte) {
+            f(offset + index, escaped)?;
+        }
+    }
+    Ok(())
+}
+
+/// Calls `f` for every byte of a vector that is marked in `mask` as needing escaping.
+/// Every byte is represented by `BITS` bits in `mask`.
+#[inline(always)]
+fn for_each_in_mask<const BITS: u32>(
+    chunk: &[u8],
+    mut mask: u64,
+    offset: usize,
+    f: &mut impl FnMut(usize, [AsciiChar; 2]) -> fmt::Result,
+) -> fmt::Result {
+    while mask != 0 {
+        let index = (mask.trailing_zeros() / BITS) as usize;
+        if let Some(escaped) = get_escaped(chunk[index]) {
+            f(offset + index, escaped)?;
+        }
+        mask &= !(((1 << BITS) - 1) << (index as u32 * BITS));
+    }
+    Ok(())
+}
+
+/// Tests 16 (SSE2) or 64 bytes (AVX2) at once. AVX2 is only used if the library was compiled
+/// with the feature `std`, which is needed to detect support for it at runtime.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+mod simd {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+    use core::fmt;
+
+    use super::{AsciiChar, CHARS, for_each_escaped_scalar, for_each_in_mask};
+
+    #[inline]
+    pub(super) fn for_each_escaped(
+        bytes: &[u8],
+        mut offset: usize,
+        f: &mut impl FnMut(usize, [AsciiChar; 2]) -> fmt::Result,
+    ) -> fmt::Result {
+        #[cfg(feature = "std")]
+        let bytes = if bytes.len() >= 64 && std::is_x86_feature_detected!("avx2") {
+            let (chunks, tail) = bytes.as_chunks::<64>();
+            for chunk in chunks {
+                // SAFETY: We just tested that the CPU supports AVX2.
+                let mask = unsafe { mask_avx2(chunk) };
+                for_each_in_mask::<1>(chunk, mask, offset, f)?;
+                offset += 64;
+            }
+            tail
+        } else {
+            bytes
+        };
+
+        let (chunks, tail) = bytes.as_chunks::<16>();
+        for chunk in chunks {
+            // SAFETY: The module is only compiled if SSE2 is enabled for the target.
+            let mask = unsafe { mask_sse2(chunk) };
+            for_each_in_mask::<1>(chunk, mask, offset, f)?;
+            offset += 16;
+        }
+        for_each_escaped_scalar(tail, offset, f)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn mask_sse2(chunk: &[u8; 16]) -> u64 {
+        // SAFETY: `chunk` is 16 bytes long, and `loadu` does not need any alignment.
+        let vector = unsafe { _mm_loadu_si128(chunk.as_ptr().cast()) };
+        let mut found = _mm_setzero_si128();
+        for &c in CHARS {
+            found = _mm_or_si128(found, _mm_cmpeq_epi8(vector, _mm_set1_epi8(c as i8)));
+        }
+        _mm_movemask_epi8(found) as u32 as u64
+    }
+
+    // Only the mask is calculated in here: calling `f` from a function that uses AVX2 is slow.
+    #[cfg(feature = "std")]
+    #[target_feature(enable = "avx2")]
+    fn mask_avx2(chunk: &[u8; 64]) -> u64 {
+        let mut mask = 0;
+        for half in 0..2 {
+            // SAFETY: `chunk` is 64 bytes long, and `loadu` does not need any alignment.
+            let vector = unsafe { _mm256_loadu_si256(chunk.as_ptr().add(32 * half).cast()) };
+            let mut found = _mm256_setzero_si256();
+            for &c in CHARS {
+                found =
+                    _mm256_or_si256(found, _mm256_cmpeq_epi8(vector, _mm256_set1_epi8(c as i8)));
+            }
+            mask |= (_mm256_movemask_epi8(found) as u32 as u64) << (32 * half);
+        }
+        mask
+    }
+}
+
+/// Tests 16 bytes at once. NEON is always available on AArch64.
+#[cfg(all(
+    target_arch = "aarch64",
+    target_feature = "neon",
+    target_endian = "little",
+))]
+mod simd {
+    use core::arch::aarch64::*;
+    use core::fmt;
+
+    use super::{AsciiChar, CHARS, for_each_escaped_scalar, for_each_in_mask};
+
+    #[inline]
+    pub(super) fn for_each_escaped(
+        bytes: &[u8],
+        mut offset: usize,
+        f: &mut impl FnMut(usize, [AsciiChar; 2]) -> fmt::Result,
+    ) -> fmt::Result {
+        let (chunks, tail) = bytes.as_chunks::<16>();
+        for chunk in chunks {
+            // SAFETY: The module is only compiled if NEON is enabled for the target.
+            let mask = unsafe { mask_neon(chunk) };
+            for_each_in_mask::<4>(chunk, mask, offset, f)?;
+            offset += 16;
+        }
+        for_each_escaped_scalar(tail, offset, f)
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    fn mask_neon(chunk: &[u8; 16]) -> u64 {
+        // SAFETY: `chunk` is 16 bytes long, and `vld1q_u8` does not need any alignment.
+        let vector = unsafe { vld1q_u8(chunk.as_ptr()) };
+        let mut found = vdupq_n_u8(0);
+        for &c in CHARS {
+            found = vorrq_u8(found, vceqq_u8(vector, vdupq_n_u8(c)));
+        }
+        // There is no `movemask` in NEON: shifting every 16 bit lane right by 4 and
+        // narrowing it to 8 bits leaves 4 bits for every input byte.
+        let mask = vshrn_n_u16::<4>(vreinterpretq_u16_u8(found));
+        vget_lane_u64::<0>(vreinterpret_u64_u8(mask))
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    ),
+    all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        target_endian = "little",
+    ),
+)))]
+mod simd {
+    pub(super) use super::for_each_escaped_scalar as for_each_escaped;
+}
+
 #[inline(always)]
 fn write_str_if_nonempty(output: &mut impl fmt::Write, input: &str) -> fmt::Result {
     if !input.is_empty() {
@@ -132,3 +301,50 @@ fn test_simple_html_string_escaping() {
     write_escaped_str(&mut buf, "s<cripcripcripcripcripcripcripcripcripcrip>t").unwrap();
     assert_eq!(buf, "s&#60;cripcripcripcripcripcripcripcripcripcrip&#62;t");
 }
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_for_each_escaped() {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+
+    fn escaped(bytes: &[u8], simd: bool) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let mut f = |index, _| {
+            indices.push(index);
+            Ok(())
+        };
+        if simd {
+            simd::for_each_escaped(bytes, 0, &mut f).unwrap();
+        } else {
+            for_each_escaped_scalar(bytes, 0, &mut f).unwrap();
+        }
+        indices
+    }
+
+    // Test every position in inputs that span multiple vectors and a scalar tail.
+    for len in 0..100 {
+        let mut bytes = alloc::vec![b'a'; len];
+        assert!(escaped(&bytes, true).is_empty());
+        for pos in 0..len {
+            for &c in CHARS {
+                bytes[pos] = c;
+                assert_eq!(escaped(&bytes, true), [pos]);
+                assert_eq!(escaped(&bytes, false), [pos]);
+            }
+            // same low bits as `&`, but must not be matched
+            bytes[pos] = 0xa6;
+            assert!(escaped(&bytes, true).is_empty());
+            bytes[pos] = b'a';
+        }
+    }
+
+    let bytes = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+    for start in 0..bytes.len() {
+        assert_eq!(
+            escaped(&bytes[start..], true),
+            escaped(&bytes[start..], false),
+        );
+    }
+}
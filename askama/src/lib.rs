@@ -66,11 +66,19 @@ extern crate alloc;
 extern crate std;
 
 mod ascii_str;
+#[cfg(feature = "alloc")]
+mod chunked;
 mod error;
+mod escapers;
 pub mod filters;
 #[doc(hidden)]
 pub mod helpers;
 mod html;
+#[cfg(feature = "reload")]
+pub mod reload;
+mod sink;
+pub mod tests;
+mod unescape;
 mod values;
 
 #[cfg(feature = "alloc")]
@@ -83,11 +91,18 @@ use std::io;
 #[cfg(feature = "derive")]
 pub use askama_macros::Template;
 #[cfg(feature = "derive")]
+pub use askama_macros::Values;
+#[cfg(feature = "derive")]
 pub use askama_macros::filter_fn;
+#[cfg(feature = "derive")]
+pub use askama_macros::test_fn;
 
-pub use crate::error::{Error, Result};
+#[cfg(feature = "alloc")]
+pub use crate::chunked::{ChunkedWriter, RenderChunks};
+pub use crate::error::{Error, Location, Result};
 pub use crate::helpers::PrimitiveType;
-pub use crate::values::{NO_VALUES, Value, Values, get_value};
+pub use crate::sink::ByteSink;
+pub use crate::values::{Key, Layered, NO_VALUES, Value, ValueKey, Values, get_value};
 
 /// Main `Template` trait; implementations are generally derived
 ///
@@ -100,8 +115,10 @@ pub use crate::values::{NO_VALUES, Value, Values, get_value};
 /// * [`.render()`][Template::render] (to render the content into a new string),
 /// * [`.render_into()`][Template::render_into] (to render the content into an [`fmt::Write`]
 ///   object, e.g. [`String`]) or
-/// * [`.write_into()`][Template::write_into] (to render the content into an [`io::Write`] object,
-///   e.g. [`Vec<u8>`][alloc::vec::Vec])
+/// * [`.write_into()`][Template::write_into] (to render the content into an [`io::Write`] object)
+///   or
+/// * [`.write_bytes()`][Template::write_bytes] (to render the content into a [`ByteSink`], e.g.
+///   [`Vec<u8>`][alloc::vec::Vec])
 ///
 /// over [`.to_string()`][std::string::ToString::to_string] or [`format!()`][alloc::format].
 /// While `.to_string()` and `format!()` give you the same result, they generally perform much worse
@@ -191,41 +208,127 @@ pub trait Template: fmt::Display + FastWritable {
         writer: &mut dyn io::Write,
         values: &dyn Values,
     ) -> io::Result<()> {
-        struct Wrapped<W: io::Write> {
-            writer: W,
-            err: Option<io::Error>,
-        }
+        self.write_bytes_with_values(writer, values)
+            .map_err(Error::into_io_error)
+    }
 
-        impl<W: io::Write> fmt::Write for Wrapped<W> {
-            #[inline]
-            fn write_str(&mut self, s: &str) -> fmt::Result {
-                if let Err(err) = self.writer.write_all(s.as_bytes()) {
-                    self.err = Some(err);
-                    Err(fmt::Error)
-                } else {
-                    Ok(())
-                }
-            }
+    /// Renders the template into the given byte `sink`, e.g. a [`Vec<u8>`][alloc::vec::Vec].
+    ///
+    /// # Errors
+    ///
+    /// If the sink fails, its error is returned, e.g. [`Error::Io`] for an [`io::Write`] sink.
+    /// For other potential errors, please take a look at the [`Error`] enum variants
+    /// documentation.
+    #[inline]
+    fn write_bytes<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<()> {
+        self.write_bytes_with_values(sink, NO_VALUES)
+    }
+
+    /// Renders the template into the given byte `sink` with provided [`Values`].
+    ///
+    /// # Errors
+    ///
+    /// If the sink fails, its error is returned, e.g. [`Error::Io`] for an [`io::Write`] sink.
+    /// For other potential errors, please take a look at the [`Error`] enum variants
+    /// documentation.
+    ///
+    /// The code generated by `#[derive(Template)]` writes into the sink directly. The default
+    /// implementation forwards the output of [`render_into_with_values()`][Self::render_into_with_values].
+    fn write_bytes_with_values<S: ByteSink + ?Sized>(
+        &self,
+        sink: &mut S,
+        values: &dyn Values,
+    ) -> Result<()> {
+        sink.reserve(Self::SIZE_HINT);
+        let mut writer = sink::SinkWriter::new(sink);
+        match self.render_into_with_values(&mut writer, values) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(writer.take_err().unwrap_or(err)),
         }
+    }
 
-        let mut wrapped = Wrapped { writer, err: None };
-        if self.render_into_with_values(&mut wrapped, values).is_ok() {
-            Ok(())
-        } else {
-            let err = wrapped.err.take();
-            Err(err.unwrap_or_else(|| io::Error::other(fmt::Error)))
+    /// Renders the template in chunks of at most `chunk_size` bytes, passing each one to
+    /// `on_chunk` as soon as it is complete.
+    ///
+    /// This lets you e.g. send the start of a large page to a client while the rest of the
+    /// template is still being rendered. The chunks are never empty, but a chunk boundary may fall
+    /// inside a multi-byte UTF-8 character. See [`ChunkedWriter`] if you need more control.
+    ///
+    /// # Errors
+    ///
+    /// If `on_chunk` returns an error, the rendering is aborted and its error is returned. For
+    /// other potential errors, please take a look at the [`Error`] enum variants documentation.
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_chunked(
+        &self,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        self.render_chunked_with_values(chunk_size, NO_VALUES, on_chunk)
+    }
+
+    /// Renders the template in chunks of at most `chunk_size` bytes with provided [`Values`],
+    /// passing each one to `on_chunk` as soon as it is complete.
+    ///
+    /// # Errors
+    ///
+    /// If `on_chunk` returns an error, the rendering is aborted and its error is returned. For
+    /// other potential errors, please take a look at the [`Error`] enum variants documentation.
+    #[cfg(feature = "alloc")]
+    fn render_chunked_with_values(
+        &self,
+        chunk_size: usize,
+        values: &dyn Values,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let mut writer = ChunkedWriter::new(chunk_size, on_chunk);
+        if let Err(err) = self.render_into_with_values(&mut writer, values) {
+            return Err(writer.take_err().unwrap_or(err));
         }
+        writer.finish()
+    }
+
+    /// Returns an [`Iterator`] over the rendered output, in chunks of at most `chunk_size` bytes.
+    ///
+    /// Unlike [`render_chunked()`](Template::render_chunked), the consumer pulls the chunks: the
+    /// template is rendered again for every chunk, see [`RenderChunks`] for the details. If the
+    /// rendering fails, the error is the last item of the iterator.
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_chunks(&self, chunk_size: usize) -> RenderChunks<'_>
+    where
+        Self: Sized,
+    {
+        RenderChunks::new(self, chunk_size)
+    }
+
+    /// Returns an [`Iterator`] over the rendered output with provided [`Values`], in chunks of at
+    /// most `chunk_size` bytes.
+    ///
+    /// See [`render_chunks()`](Template::render_chunks).
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_chunks_with_values<'a>(
+        &'a self,
+        chunk_size: usize,
+        values: &'a dyn Values,
+    ) -> RenderChunks<'a>
+    where
+        Self: Sized,
+    {
+        RenderChunks::with_values(self, chunk_size, values)
     }
 
     /// Provides a rough estimate of the expanded length of the rendered template. Larger
     /// values result in higher memory usage but fewer reallocations. Smaller values result in the
-    /// opposite. This value only affects [`render`]. It does not take effect when calling
-    /// [`render_into`], [`write_into`], the [`fmt::Display`] implementation, or the blanket
+    /// opposite. This value only affects [`render`] and [`write_bytes`]. It does not take effect
+    /// when calling [`render_into`], the [`fmt::Display`] implementation, or the blanket
     /// [`ToString::to_string`] implementation.
     ///
     /// [`render`]: Template::render
     /// [`render_into`]: Template::render_into
-    /// [`write_into`]: Template::write_into
+    /// [`write_bytes`]: Template::write_bytes
     /// [`ToString::to_string`]: alloc::string::ToString::to_string
     const SIZE_HINT: usize;
 }
@@ -273,9 +376,171 @@ impl<T: Template + ?Sized> Template for &T {
         <T as Template>::write_into_with_values(self, writer, values)
     }
 
+    #[inline]
+    fn write_bytes<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<()> {
+        <T as Template>::write_bytes(self, sink)
+    }
+
+    #[inline]
+    fn write_bytes_with_values<S: ByteSink + ?Sized>(
+        &self,
+        sink: &mut S,
+        values: &dyn Values,
+    ) -> Result<()> {
+        <T as Template>::write_bytes_with_values(self, sink, values)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_chunked(
+        &self,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        <T as Template>::render_chunked(self, chunk_size, on_chunk)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_chunked_with_values(
+        &self,
+        chunk_size: usize,
+        values: &dyn Values,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        <T as Template>::render_chunked_with_values(self, chunk_size, values, on_chunk)
+    }
+
     const SIZE_HINT: usize = T::SIZE_HINT;
 }
 
+/// Render single blocks of a template, selected by their name at runtime
+///
+/// Implemented by `#[derive(Template)]` if the `render_blocks = true` argument is used.
+/// Blocks of the templates a template extends are resolved the same way as if the full
+/// template was rendered.
+///
+/// ```
+/// # use askama::{BlockTemplate, Template};
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "<h1>{% block title %}{{ title }}{% endblock %}</h1>",
+///     render_blocks = true,
+/// )]
+/// struct Page<'a> {
+///     title: &'a str,
+/// }
+///
+/// let page = Page { title: "Hello" };
+/// assert_eq!(Page::BLOCK_NAMES, ["title"]);
+/// assert_eq!(page.render_block("title").unwrap(), "Hello");
+/// assert!(matches!(page.render_block("body"), Err(askama::Error::BlockMissing)));
+/// ```
+pub trait BlockTemplate: Template {
+    /// The names of all blocks that can be rendered, in alphabetical order
+    const BLOCK_NAMES: &'static [&'static str];
+
+    /// Helper method which allocates a new `String` and renders the block `name` into it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BlockMissing`] if the template has no block `name`. For other potential
+    /// errors, please take a look at the [`Error`] enum variants documentation.
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_block(&self, name: &str) -> Result<String> {
+        self.render_block_with_values(name, NO_VALUES)
+    }
+
+    /// Helper method which allocates a new `String` and renders the block `name` into it with
+    /// provided [`Values`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BlockMissing`] if the template has no block `name`. For other potential
+    /// errors, please take a look at the [`Error`] enum variants documentation.
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn render_block_with_values(&self, name: &str, values: &dyn Values) -> Result<String> {
+        let mut buf = String::new();
+        self.render_block_into_with_values(name, &mut buf, values)?;
+        Ok(buf)
+    }
+
+    /// Renders the block `name` to the given `writer` fmt buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BlockMissing`] if the template has no block `name`. For other potential
+    /// errors, please take a look at the [`Error`] enum variants documentation.
+    #[inline]
+    fn render_block_into(&self, name: &str, writer: &mut dyn fmt::Write) -> Result<()> {
+        self.render_block_into_with_values(name, writer, NO_VALUES)
+    }
+
+    /// Renders the block `name` to the given `writer` fmt buffer with provided [`Values`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BlockMissing`] if the template has no block `name`. For other potential
+    /// errors, please take a look at the [`Error`] enum variants documentation.
+    fn render_block_into_with_values(
+        &self,
+        name: &str,
+        writer: &mut dyn fmt::Write,
+        values: &dyn Values,
+    ) -> Result<()>;
+
+    /// Renders the block `name` to the given `writer` io buffer.
+    ///
+    /// # Errors
+    ///
+    /// An unknown block `name` is reported as an [`io::Error`] that contains
+    /// [`Error::BlockMissing`]. It internally uses the [`std::io::Write`] trait so it can fail and
+    /// return `Err` for the same reasons.
+    #[inline]
+    #[cfg(feature = "std")]
+    fn write_block(&self, name: &str, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.write_block_with_values(name, writer, NO_VALUES)
+    }
+
+    /// Renders the block `name` to the given `writer` io buffer with provided [`Values`].
+    ///
+    /// # Errors
+    ///
+    /// An unknown block `name` is reported as an [`io::Error`] that contains
+    /// [`Error::BlockMissing`]. It internally uses the [`std::io::Write`] trait so it can fail and
+    /// return `Err` for the same reasons.
+    #[cfg(feature = "std")]
+    fn write_block_with_values(
+        &self,
+        name: &str,
+        writer: &mut dyn io::Write,
+        values: &dyn Values,
+    ) -> io::Result<()> {
+        let mut writer = sink::SinkWriter::new(writer);
+        match self.render_block_into_with_values(name, &mut writer, values) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(writer.take_err().unwrap_or(err).into_io_error()),
+        }
+    }
+}
+
+impl<T: BlockTemplate + ?Sized> BlockTemplate for &T {
+    const BLOCK_NAMES: &'static [&'static str] = T::BLOCK_NAMES;
+
+    #[inline]
+    fn render_block_into_with_values(
+        &self,
+        name: &str,
+        writer: &mut dyn fmt::Write,
+        values: &dyn Values,
+    ) -> Result<()> {
+        <T as BlockTemplate>::render_block_into_with_values(self, name, writer, values)
+    }
+}
+
 /// [`dyn`-compatible] wrapper trait around [`Template`] implementers
 ///
 /// This trades reduced performance (mostly due to writing into `dyn Write`) for dyn-compatibility.
@@ -312,6 +577,53 @@ pub trait DynTemplate {
         values: &dyn Values,
     ) -> io::Result<()>;
 
+    /// Renders the template into the given byte `sink`.
+    #[inline]
+    fn dyn_write_bytes(&self, sink: &mut dyn ByteSink) -> Result<()> {
+        self.dyn_write_bytes_with_values(sink, NO_VALUES)
+    }
+
+    /// Renders the template into the given byte `sink` with provided [`Values`].
+    fn dyn_write_bytes_with_values(
+        &self,
+        sink: &mut dyn ByteSink,
+        values: &dyn Values,
+    ) -> Result<()> {
+        sink.reserve(self.size_hint());
+        let mut writer = sink::SinkWriter::new(sink);
+        match self.dyn_render_into_with_values(&mut writer, values) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(writer.take_err().unwrap_or(err)),
+        }
+    }
+
+    /// Renders the template in chunks of at most `chunk_size` bytes, passing each one to
+    /// `on_chunk` as soon as it is complete.
+    #[cfg(feature = "alloc")]
+    fn dyn_render_chunked(
+        &self,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        self.dyn_render_chunked_with_values(chunk_size, NO_VALUES, on_chunk)
+    }
+
+    /// Renders the template in chunks of at most `chunk_size` bytes with provided [`Values`],
+    /// passing each one to `on_chunk` as soon as it is complete.
+    #[cfg(feature = "alloc")]
+    fn dyn_render_chunked_with_values(
+        &self,
+        chunk_size: usize,
+        values: &dyn Values,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let mut writer = ChunkedWriter::new(chunk_size, on_chunk);
+        if let Err(err) = self.dyn_render_into_with_values(&mut writer, values) {
+            return Err(writer.take_err().unwrap_or(err));
+        }
+        writer.finish()
+    }
+
     /// Provides a conservative estimate of the expanded length of the rendered template.
     fn size_hint(&self) -> usize;
 }
@@ -359,6 +671,41 @@ impl<T: Template> DynTemplate for T {
         <Self as Template>::write_into_with_values(self, writer, values)
     }
 
+    #[inline]
+    fn dyn_write_bytes(&self, sink: &mut dyn ByteSink) -> Result<()> {
+        <Self as Template>::write_bytes(self, sink)
+    }
+
+    #[inline]
+    fn dyn_write_bytes_with_values(
+        &self,
+        sink: &mut dyn ByteSink,
+        values: &dyn Values,
+    ) -> Result<()> {
+        <Self as Template>::write_bytes_with_values(self, sink, values)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn dyn_render_chunked(
+        &self,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        <Self as Template>::render_chunked(self, chunk_size, on_chunk)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    fn dyn_render_chunked_with_values(
+        &self,
+        chunk_size: usize,
+        values: &dyn Values,
+        on_chunk: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        <Self as Template>::render_chunked_with_values(self, chunk_size, values, on_chunk)
+    }
+
     #[inline]
     fn size_hint(&self) -> usize {
         <Self as Template>::SIZE_HINT
@@ -415,6 +762,18 @@ macro_rules! impl_for_ref {
 pub trait FastWritable {
     /// Used internally by askama to speed up writing some types.
     fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()>;
+
+    /// Used internally by askama to write the value into a [`ByteSink`].
+    ///
+    /// The default implementation forwards the output of [`write_into()`][Self::write_into].
+    #[inline]
+    fn write_bytes_into(&self, dest: &mut dyn ByteSink, values: &dyn Values) -> crate::Result<()> {
+        let mut writer = sink::SinkWriter::new(dest);
+        match self.write_into(&mut writer, values) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(writer.take_err().unwrap_or(err)),
+        }
+    }
 }
 
 const _: () = {
@@ -428,6 +787,15 @@ const _: () = {
             ) -> crate::Result<()> {
                 <T>::write_into(self, dest, values)
             }
+
+            #[inline]
+            fn write_bytes_into(
+                &self,
+                dest: &mut dyn ByteSink,
+                values: &dyn Values,
+            ) -> crate::Result<()> {
+                <T>::write_bytes_into(self, dest, values)
+            }
         }
     }
 
@@ -440,6 +808,15 @@ const _: () = {
         fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()> {
             self.as_ref().get_ref().write_into(dest, values)
         }
+
+        #[inline]
+        fn write_bytes_into(
+            &self,
+            dest: &mut dyn ByteSink,
+            values: &dyn Values,
+        ) -> crate::Result<()> {
+            self.as_ref().get_ref().write_bytes_into(dest, values)
+        }
     }
 
     #[cfg(feature = "alloc")]
@@ -448,6 +825,15 @@ const _: () = {
         fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()> {
             T::write_into(self.as_ref(), dest, values)
         }
+
+        #[inline]
+        fn write_bytes_into(
+            &self,
+            dest: &mut dyn ByteSink,
+            values: &dyn Values,
+        ) -> crate::Result<()> {
+            T::write_bytes_into(self.as_ref(), dest, values)
+        }
     }
 
     // implement FastWritable for a list of types
@@ -462,6 +848,15 @@ const _: () = {
                 ) -> crate::Result<()> {
                     itoa::Buffer::new().format(*self).write_into(dest, values)
                 }
+
+                #[inline]
+                fn write_bytes_into(
+                    &self,
+                    dest: &mut dyn ByteSink,
+                    _: &dyn Values,
+                ) -> crate::Result<()> {
+                    dest.write_bytes(itoa::Buffer::new().format(*self).as_bytes())
+                }
             }
         )* };
     }
@@ -483,6 +878,15 @@ const _: () = {
                 ) -> crate::Result<()> {
                     self.get().write_into(dest, values)
                 }
+
+                #[inline]
+                fn write_bytes_into(
+                    &self,
+                    dest: &mut dyn ByteSink,
+                    values: &dyn Values,
+                ) -> crate::Result<()> {
+                    self.get().write_bytes_into(dest, values)
+                }
             }
         )* };
     }
@@ -497,6 +901,11 @@ const _: () = {
         fn write_into(&self, dest: &mut dyn fmt::Write, _: &dyn Values) -> crate::Result<()> {
             Ok(dest.write_str(self)?)
         }
+
+        #[inline]
+        fn write_bytes_into(&self, dest: &mut dyn ByteSink, _: &dyn Values) -> crate::Result<()> {
+            dest.write_bytes(self.as_bytes())
+        }
     }
 
     #[cfg(feature = "alloc")]
@@ -505,6 +914,11 @@ const _: () = {
         fn write_into(&self, dest: &mut dyn fmt::Write, values: &dyn Values) -> crate::Result<()> {
             self.as_str().write_into(dest, values)
         }
+
+        #[inline]
+        fn write_bytes_into(&self, dest: &mut dyn ByteSink, _: &dyn Values) -> crate::Result<()> {
+            dest.write_bytes(self.as_bytes())
+        }
     }
 
     impl FastWritable for bool {
@@ -515,6 +929,14 @@ const _: () = {
                 false => "false",
             })?)
         }
+
+        #[inline]
+        fn write_bytes_into(&self, dest: &mut dyn ByteSink, _: &dyn Values) -> crate::Result<()> {
+            dest.write_bytes(match self {
+                true => b"true",
+                false => b"false",
+            })
+        }
     }
 
     impl FastWritable for char {
@@ -522,6 +944,11 @@ const _: () = {
         fn write_into(&self, dest: &mut dyn fmt::Write, _: &dyn Values) -> crate::Result<()> {
             Ok(dest.write_char(*self)?)
         }
+
+        #[inline]
+        fn write_bytes_into(&self, dest: &mut dyn ByteSink, _: &dyn Values) -> crate::Result<()> {
+            dest.write_bytes(self.encode_utf8(&mut [0; 4]).as_bytes())
+        }
     }
 
     impl FastWritable for fmt::Arguments<'_> {
@@ -532,16 +959,28 @@ const _: () = {
                 None => dest.write_fmt(*self),
             }?)
         }
+
+        #[inline]
+        fn write_bytes_into(
+            &self,
+            mut dest: &mut dyn ByteSink,
+            _: &dyn Values,
+        ) -> crate::Result<()> {
+            match self.as_str() {
+                Some(s) => dest.write_bytes(s.as_bytes()),
+                None => helpers::TemplateWriter::write_fmt(&mut dest, *self),
+            }
+        }
     }
 
     impl<S: crate::Template + ?Sized> filters::WriteWritable for &filters::Writable<'_, S> {
         #[inline]
         fn askama_write(
             &self,
-            dest: &mut dyn fmt::Write,
+            dest: &mut dyn helpers::TemplateWriter,
             values: &dyn Values,
         ) -> crate::Result<()> {
-            self.0.render_into_with_values(dest, values)
+            dest.write_template(self.0, values)
         }
     }
 
@@ -549,17 +988,21 @@ const _: () = {
         #[inline]
         fn askama_write(
             &self,
-            dest: &mut dyn fmt::Write,
+            dest: &mut dyn helpers::TemplateWriter,
             values: &dyn Values,
         ) -> crate::Result<()> {
-            self.0.write_into(dest, values)
+            dest.write_writable(self.0, values)
         }
     }
 
     impl<S: fmt::Display + ?Sized> filters::WriteWritable for &&&filters::Writable<'_, S> {
         #[inline]
-        fn askama_write(&self, dest: &mut dyn fmt::Write, _: &dyn Values) -> crate::Result<()> {
-            Ok(write!(dest, "{}", self.0)?)
+        fn askama_write(
+            &self,
+            dest: &mut dyn helpers::TemplateWriter,
+            _: &dyn Values,
+        ) -> crate::Result<()> {
+            dest.write_fmt(format_args!("{}", self.0))
         }
     }
 };
@@ -567,7 +1010,7 @@ const _: () = {
 pub(crate) use impl_for_ref;
 
 #[cfg(all(test, feature = "alloc"))]
-mod tests {
+mod test {
     use std::fmt;
 
     use super::*;
@@ -620,5 +1063,17 @@ mod tests {
         let mut vec = alloc::vec![];
         test.dyn_write_into(&mut vec).unwrap();
         assert_eq!(vec, alloc::vec![b't', b'e', b's', b't']);
+
+        let mut vec = alloc::vec![b'>'];
+        test.dyn_write_bytes(&mut vec).unwrap();
+        assert_eq!(vec, b">test");
+
+        let mut chunks = alloc::vec![];
+        test.dyn_render_chunked(3, &mut |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(chunks, [&b"tes"[..], b"t"]);
     }
 }
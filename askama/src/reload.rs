@@ -0,0 +1,165 @@
+//! Development hot-reload support for `#[derive(Template)]`
+//!
+//! A template that is derived with `#[template(path = "…", reload = true)]` calls [`render()`]
+//! in debug builds before it runs its compiled code. As long as none of the template files was
+//! modified since the first render, the compiled code is used. Once a file changed, the template
+//! is rendered by the installed [`Renderer`] instead, so edits show up without rebuilding your
+//! program.
+//!
+//! Askama itself cannot interpret templates. Call e.g. `askama_interpreter::reload::install()`
+//! at the start of your program to install the runtime interpreter of the
+//! [`askama_interpreter`](https://docs.rs/askama_interpreter) crate. Without a renderer, the
+//! compiled code is always used.
+//!
+//! The fields of the template struct are passed to the renderer as JSON, so every field must
+//! implement [`Serialize`]; `#[derive(Template)]` reports fields that don't. The runtime
+//! [`Values`](crate::Values) are passed on as they are.
+//!
+//! Release builds don't contain any reloading code.
+
+use core::fmt;
+use std::borrow::ToOwned;
+use std::collections::HashMap;
+use std::fs;
+use std::string::String;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
+use std::vec::Vec;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The compile time information about a reloadable template
+#[derive(Debug)]
+pub struct TemplateFile {
+    /// The absolute path of the template
+    pub path: &'static str,
+    /// The absolute paths of all files the template uses, including `path`
+    pub files: &'static [&'static str],
+    /// The template directories of the configuration
+    pub dirs: &'static [&'static str],
+    /// The extension that selects the escaper
+    pub ext: &'static str,
+    /// `block_start`, `block_end`, `expr_start`, `expr_end`, `comment_start` and `comment_end`
+    pub syntax: [&'static str; 6],
+    /// The default whitespace handling
+    pub whitespace: Whitespace,
+}
+
+/// The default whitespace handling of a [`TemplateFile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Whitespace {
+    /// Keep all whitespace characters
+    Preserve,
+    /// Remove all whitespace characters around the tags
+    Suppress,
+    /// Replace whitespace characters around the tags by a single space or newline
+    Minimize,
+}
+
+/// Collects the fields of a template struct
+#[derive(Debug, Default)]
+pub struct Fields(Map<String, Value>);
+
+impl Fields {
+    /// Creates an empty set of fields
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the field `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` could not be serialized.
+    pub fn field<T: ReloadField + ?Sized>(
+        mut self,
+        name: &str,
+        value: &T,
+    ) -> serde_json::Result<Self> {
+        self.0.insert(name.to_owned(), serde_json::to_value(value)?);
+        Ok(self)
+    }
+
+    /// Returns the collected fields as object
+    #[inline]
+    pub fn finish(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+/// A field of a template struct that is derived with `reload = true`
+///
+/// Implemented for all types that implement [`Serialize`].
+#[diagnostic::on_unimplemented(
+    message = "the field type `{Self}` does not implement `Serialize`",
+    label = "the fields of a template with `reload = true` must implement `serde::Serialize`",
+    note = "the fields are serialized for the interpreter once a template file changed"
+)]
+pub trait ReloadField: Serialize {}
+
+impl<T: Serialize + ?Sized> ReloadField for T {}
+
+/// Renders a [`TemplateFile`] with the serialized fields of the template struct, and the runtime
+/// [`Values`](crate::Values) it was rendered with
+pub type Renderer =
+    fn(&'static TemplateFile, &Value, &dyn crate::Values, &mut dyn fmt::Write) -> crate::Result<()>;
+
+static RENDERER: OnceLock<Renderer> = OnceLock::new();
+
+/// Installs the [`Renderer`] that is used once a template file changed
+///
+/// Returns `false` if a renderer was installed already.
+pub fn set_renderer(renderer: Renderer) -> bool {
+    RENDERER.set(renderer).is_ok()
+}
+
+/// Renders `tmpl` with the installed [`Renderer`] if one of its files changed since the first
+/// render
+///
+/// Returns `None` if the compiled template should be used.
+pub fn render(
+    tmpl: &'static TemplateFile,
+    fields: impl FnOnce() -> serde_json::Result<Value>,
+    values: &dyn crate::Values,
+    dest: &mut dyn fmt::Write,
+) -> Option<crate::Result<()>> {
+    static STATES: Mutex<Option<HashMap<&'static str, State>>> = Mutex::new(None);
+
+    let renderer = RENDERER.get()?;
+    let modified = tmpl
+        .files
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect::<Vec<_>>();
+    {
+        let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = states
+            .get_or_insert_default()
+            .entry(tmpl.path)
+            .or_insert_with(|| State {
+                modified: modified.clone(),
+                changed: false,
+            });
+        if !state.changed {
+            state.changed = state.modified != modified;
+            if !state.changed {
+                return None;
+            }
+        }
+    }
+
+    Some(
+        fields()
+            .map_err(crate::Error::from)
+            .and_then(|fields| renderer(tmpl, &fields, values, dest)),
+    )
+}
+
+/// The modification times of the files of a template when it was rendered first
+struct State {
+    modified: Vec<Option<SystemTime>>,
+    /// Once a file changed, the template is always interpreted
+    changed: bool,
+}
@@ -0,0 +1,182 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{Error, Result};
+
+/// A byte-oriented output target for [`Template::write_bytes()`][crate::Template::write_bytes]
+///
+/// Implemented for <code>[Vec]&lt;u8&gt;</code>, for
+/// [`bytes::BytesMut`](https://docs.rs/bytes/1/bytes/struct.BytesMut.html) if the feature
+/// `"bytes"` is enabled, and for <code>dyn [io::Write]</code> if the feature `"std"` is enabled.
+///
+/// [Vec]: alloc::vec::Vec
+pub trait ByteSink {
+    /// Appends `bytes` to the sink.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes could not be written, e.g. [`Error::Io`] for [`io::Write`].
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Reserves capacity for at least `additional` more bytes, if the sink supports it.
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+}
+
+impl<T: ByteSink + ?Sized> ByteSink for &mut T {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        T::write_bytes(self, bytes)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        T::reserve(self, additional);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ByteSink + ?Sized> ByteSink for alloc::boxed::Box<T> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        T::write_bytes(self, bytes)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        T::reserve(self, additional);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSink for alloc::vec::Vec<u8> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        let _ = self.try_reserve(additional);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl ByteSink for bytes::BytesMut {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        bytes::BytesMut::reserve(self, additional);
+    }
+}
+
+#[cfg(feature = "std")]
+impl ByteSink for dyn io::Write + '_ {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_all(bytes).map_err(Error::Io)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ByteSink for dyn io::Write + Send + '_ {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_all(bytes).map_err(Error::Io)
+    }
+}
+
+/// Adapter to write values that only implement [`fmt::Display`] into a [`ByteSink`]
+///
+/// The generated code writes into the sink directly, but e.g. [`fmt::Display`] and manual
+/// implementations of [`Template`][crate::Template] can only target a [`fmt::Write`].
+/// [`fmt::Write`] can only return a [`fmt::Error`], so the actual error of the sink is stored
+/// until the rendering is done.
+pub(crate) struct SinkWriter<'a, S: ByteSink + ?Sized> {
+    sink: &'a mut S,
+    err: Option<Error>,
+}
+
+impl<'a, S: ByteSink + ?Sized> SinkWriter<'a, S> {
+    #[inline]
+    pub(crate) fn new(sink: &'a mut S) -> Self {
+        Self { sink, err: None }
+    }
+
+    #[inline]
+    pub(crate) fn take_err(&mut self) -> Option<Error> {
+        self.err.take()
+    }
+}
+
+impl<S: ByteSink + ?Sized> fmt::Write for SinkWriter<'_, S> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.sink.write_bytes(s.as_bytes()).map_err(|err| {
+            self.err = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::fmt::Write;
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn test_vec_sink() {
+        let mut buf = alloc::vec::Vec::new();
+        let mut writer = SinkWriter::new(&mut buf);
+        writer.write_str("hello ").unwrap();
+        writer.write_char('w').unwrap();
+        write!(writer, "{}ld", "or".len()).unwrap();
+        assert!(writer.take_err().is_none());
+        assert_eq!(buf, b"hello w2ld");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_mut_sink() {
+        let mut buf = bytes::BytesMut::new();
+        let mut writer = SinkWriter::new(&mut buf);
+        writer.write_str("hello").unwrap();
+        assert_eq!(&buf[..], b"hello");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_sink_error() {
+        struct Broken;
+
+        impl io::Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let sink: &mut dyn io::Write = &mut Broken;
+        let mut writer = SinkWriter::new(sink);
+        assert!(writer.write_str("hello").is_err());
+        assert_matches!(
+            writer.take_err(),
+            Some(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Module for built-in test functions
+//!
+//! Contains all the built-in tests for use in templates, e.g. `{% if x is divisibleby(3) %}`.
+//! A test is negated with `is not`, e.g. `{% if x is not none %}`.
+//!
+//! You can define your own tests with [`#[askama::test_fn]`](crate::test_fn). A test that is not
+//! built-in is looked up in a module named `template_tests` in the scope of the template struct,
+//! like custom filters are looked up in a module named `filters`.
+//!
+//! The traits of this module are implemented for the types of the standard library, and for
+//! references to them. You can implement them for your own types, too.
+
+use core::ops::{Range, RangeInclusive};
+
+use crate::impl_for_ref;
+
+/// `value is even`: returns `true` if the integer `value` is even.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{% for i in 1..=4 %}{% if i is even %}{{ i }} {% endif %}{% endfor %}"
+/// )]
+/// struct Example;
+///
+/// assert_eq!(Example.to_string(), "2 4 ");
+/// ```
+#[inline]
+pub fn even<T: Integer + ?Sized>(value: &T) -> bool {
+    value.is_even()
+}
+
+/// `value is odd`: returns `true` if the integer `value` is odd.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{% for i in 1..=4 %}{% if i is odd %}{{ i }} {% endif %}{% endfor %}"
+/// )]
+/// struct Example;
+///
+/// assert_eq!(Example.to_string(), "1 3 ");
+/// ```
+#[inline]
+pub fn odd<T: Integer + ?Sized>(value: &T) -> bool {
+    !value.is_even()
+}
+
+/// `value is divisibleby(num)`: returns `true` if the integer `value` is a multiple of `num`.
+///
+/// Like [`u32::is_multiple_of()`], only `0` is a multiple of `0`.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = "{{ count is divisibleby(3) }}")]
+/// struct Example {
+///     count: u8,
+/// }
+///
+/// assert_eq!(Example { count: 9 }.to_string(), "true");
+/// assert_eq!(Example { count: 10 }.to_string(), "false");
+/// ```
+#[inline]
+pub fn divisibleby<T: Integer + ?Sized>(value: &T, num: T::Primitive) -> bool {
+    value.is_multiple_of(num)
+}
+
+/// `value is none`: returns `true` if `value` is [`None`].
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = "{% if user is none %}anonymous{% endif %}")]
+/// struct Example<'a> {
+///     user: Option<&'a str>,
+/// }
+///
+/// assert_eq!(Example { user: None }.to_string(), "anonymous");
+/// assert_eq!(Example { user: Some("Ferris") }.to_string(), "");
+/// ```
+#[inline]
+pub fn none<T: Optional + ?Sized>(value: &T) -> bool {
+    !value.is_some()
+}
+
+/// `value is some`: returns `true` if `value` is [`Some`].
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = "{% if user is some %}{{ user.unwrap() }}{% endif %}")]
+/// struct Example<'a> {
+///     user: Option<&'a str>,
+/// }
+///
+/// assert_eq!(Example { user: None }.to_string(), "");
+/// assert_eq!(Example { user: Some("Ferris") }.to_string(), "Ferris");
+/// ```
+#[inline]
+pub fn some<T: Optional + ?Sized>(value: &T) -> bool {
+    value.is_some()
+}
+
+/// `value is empty`: returns `true` if the string or collection `value` is empty.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = "{% if users is empty %}no users{% else %}{{ users.len() }} users{% endif %}"
+/// )]
+/// struct Example<'a> {
+///     users: &'a [&'a str],
+/// }
+///
+/// assert_eq!(Example { users: &[] }.to_string(), "no users");
+/// assert_eq!(Example { users: &["Ferris"] }.to_string(), "1 users");
+/// ```
+#[inline]
+pub fn empty<T: Collection + ?Sized>(value: &T) -> bool {
+    value.is_empty()
+}
+
+/// `value is startingwith(prefix)`: returns `true` if the string `value` starts with `prefix`.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(ext = "html", source = r#"{{ url is startingwith("https://") }}"#)]
+/// struct Example<'a> {
+///     url: &'a str,
+/// }
+///
+/// assert_eq!(Example { url: "https://example.com" }.to_string(), "true");
+/// assert_eq!(Example { url: "ftp://example.com" }.to_string(), "false");
+/// ```
+#[inline]
+pub fn startingwith<T, P>(value: &T, prefix: &P) -> bool
+where
+    T: AsRef<str> + ?Sized,
+    P: AsRef<str> + ?Sized,
+{
+    value.as_ref().starts_with(prefix.as_ref())
+}
+
+/// `value is in(container)`: returns `true` if `container` contains `value`, see [`Container`].
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     ext = "html",
+///     source = r#"{{ role is in(admins) }} {{ "ell" is in("hello") }} {{ 7 is in(1..5) }}"#
+/// )]
+/// struct Example<'a> {
+///     role: &'a str,
+///     admins: &'a [&'a str],
+/// }
+///
+/// assert_eq!(
+///     Example { role: "root", admins: &["root", "admin"] }.to_string(),
+///     "true true false",
+/// );
+/// ```
+#[inline]
+pub fn r#in<T: ?Sized, C: Container<T> + ?Sized>(value: &T, container: &C) -> bool {
+    container.contains(value)
+}
+
+/// An integer type (or a reference to it) that can be used in [`is even`](even),
+/// [`is odd`](odd) and [`is divisibleby`](divisibleby).
+#[diagnostic::on_unimplemented(
+    label = "`{Self}` is not an integer",
+    message = "`{Self}` cannot be used in the `even`, `odd` or `divisibleby` tests"
+)]
+pub trait Integer {
+    /// The underlying primitive integer type.
+    type Primitive: Copy;
+
+    /// Returns `true` if the integer is even.
+    fn is_even(&self) -> bool;
+
+    /// Returns `true` if the integer is a multiple of `num`.
+    fn is_multiple_of(&self, num: Self::Primitive) -> bool;
+}
+
+impl_for_ref! {
+    impl Integer for T {
+        type Primitive = T::Primitive;
+
+        #[inline]
+        fn is_even(&self) -> bool {
+            <T>::is_even(self)
+        }
+
+        #[inline]
+        fn is_multiple_of(&self, num: Self::Primitive) -> bool {
+            <T>::is_multiple_of(self, num)
+        }
+    }
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty)*) => { $(
+        impl Integer for $ty {
+            type Primitive = $ty;
+
+            #[inline]
+            fn is_even(&self) -> bool {
+                *self % 2 == 0
+            }
+
+            #[inline]
+            fn is_multiple_of(&self, num: $ty) -> bool {
+                match self.checked_rem(num) {
+                    Some(rem) => rem == 0,
+                    // Either `num` is `0`, or the division overflowed, i.e. `MIN / -1`.
+                    None => num != 0 || *self == 0,
+                }
+            }
+        }
+    )* };
+}
+
+impl_integer!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+/// A type (or a reference to it) that can be used in [`is none`](none) and [`is some`](some).
+#[diagnostic::on_unimplemented(
+    label = "`{Self}` is not optional",
+    message = "`{Self}` cannot be used in the `none` or `some` tests"
+)]
+pub trait Optional {
+    /// Returns `true` if the value is present.
+    fn is_some(&self) -> bool;
+}
+
+impl_for_ref! {
+    impl Optional for T {
+        #[inline]
+        fn is_some(&self) -> bool {
+            <T>::is_some(self)
+        }
+    }
+}
+
+impl<T> Optional for Option<T> {
+    #[inline]
+    fn is_some(&self) -> bool {
+        Option::is_some(self)
+    }
+}
+
+/// A string or collection (or a reference to it) that can be used in [`is empty`](empty).
+#[diagnostic::on_unimplemented(
+    label = "`{Self}` is not a collection",
+    message = "`{Self}` cannot be used in the `empty` test"
+)]
+pub trait Collection {
+    /// Returns `true` if the collection contains no elements.
+    fn is_empty(&self) -> bool;
+}
+
+impl_for_ref! {
+    impl Collection for T {
+        #[inline]
+        fn is_empty(&self) -> bool {
+            <T>::is_empty(self)
+        }
+    }
+}
+
+impl Collection for str {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        str::is_empty(self)
+    }
+}
+
+impl<T> Collection for [T] {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[T]>::is_empty(self)
+    }
+}
+
+impl<T, const N: usize> Collection for [T; N] {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        N == 0
+    }
+}
+
+impl<T> Collection for Option<T> {
+    /// [`None`] is empty.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.is_none()
+    }
+}
+
+macro_rules! impl_collection {
+    ($($(#[$meta:meta])* [$($generics:tt)*] $ty:ty;)*) => { $(
+        $(#[$meta])*
+        impl<$($generics)*> Collection for $ty {
+            #[inline]
+            fn is_empty(&self) -> bool {
+                <$ty>::is_empty(self)
+            }
+        }
+    )* };
+}
+
+impl_collection! {
+    #[cfg(feature = "alloc")] [] alloc::string::String;
+    #[cfg(feature = "alloc")] [T] alloc::vec::Vec<T>;
+    #[cfg(feature = "alloc")] [T] alloc::collections::VecDeque<T>;
+    #[cfg(feature = "alloc")] [K, V] alloc::collections::BTreeMap<K, V>;
+    #[cfg(feature = "alloc")] [T] alloc::collections::BTreeSet<T>;
+    #[cfg(feature = "std")] [K, V, S] std::collections::HashMap<K, V, S>;
+    #[cfg(feature = "std")] [T, S] std::collections::HashSet<T, S>;
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Collection + alloc::borrow::ToOwned + ?Sized> Collection for alloc::borrow::Cow<'_, T> {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.as_ref().is_empty()
+    }
+}
+
+/// A string, collection or range (or a reference to it) that can be used in [`is in`](r#in).
+///
+/// A string contains all its substrings. A collection contains a value if any of its elements,
+/// or for maps any of its keys, is equal to the value. A range contains all values between its
+/// bounds.
+#[diagnostic::on_unimplemented(
+    label = "`{Self}` cannot contain `{T}`",
+    message = "`{Self}` cannot be used in the `in` test with a value of type `{T}`"
+)]
+pub trait Container<T: ?Sized> {
+    /// Returns `true` if `value` is contained.
+    fn contains(&self, value: &T) -> bool;
+}
+
+impl<T: ?Sized, C: Container<T> + ?Sized> Container<T> for &C {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        C::contains(self, value)
+    }
+}
+
+impl<T: ?Sized, C: Container<T> + ?Sized> Container<T> for &mut C {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        C::contains(self, value)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ?Sized, C: Container<T> + ?Sized> Container<T> for alloc::boxed::Box<C> {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        C::contains(self, value)
+    }
+}
+
+impl<T: AsRef<str> + ?Sized> Container<T> for str {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        str::contains(self, value.as_ref())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: AsRef<str> + ?Sized> Container<T> for alloc::string::String {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        str::contains(self, value.as_ref())
+    }
+}
+
+impl<T: PartialEq<U> + ?Sized, U> Container<T> for [U] {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        self.iter().any(|elem| value == elem)
+    }
+}
+
+impl<T: PartialEq<U> + ?Sized, U, const N: usize> Container<T> for [U; N] {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        self.iter().any(|elem| value == elem)
+    }
+}
+
+impl<T: PartialOrd<U> + ?Sized, U: PartialOrd<T>> Container<T> for Range<U> {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        *value >= self.start && *value < self.end
+    }
+}
+
+impl<T: PartialOrd<U> + ?Sized, U: PartialOrd<T>> Container<T> for RangeInclusive<U> {
+    #[inline]
+    fn contains(&self, value: &T) -> bool {
+        *value >= *self.start() && *value <= *self.end()
+    }
+}
+
+macro_rules! impl_container {
+    ($($(#[$meta:meta])* [$($generics:tt)*] $ty:ty => $iter:ident;)*) => { $(
+        $(#[$meta])*
+        impl<T: PartialEq<U> + ?Sized, U, $($generics)*> Container<T> for $ty {
+            #[inline]
+            fn contains(&self, value: &T) -> bool {
+                self.$iter().any(|elem| value == elem)
+            }
+        }
+    )* };
+}
+
+impl_container! {
+    #[cfg(feature = "alloc")] [] alloc::vec::Vec<U> => iter;
+    #[cfg(feature = "alloc")] [] alloc::collections::VecDeque<U> => iter;
+    #[cfg(feature = "alloc")] [] alloc::collections::BTreeSet<U> => iter;
+    #[cfg(feature = "alloc")] [V] alloc::collections::BTreeMap<U, V> => keys;
+    #[cfg(feature = "std")] [S] std::collections::HashSet<U, S> => iter;
+    #[cfg(feature = "std")] [V, S] std::collections::HashMap<U, V, S> => keys;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integer() {
+        assert!(even(&0_u8));
+        assert!(even(&-2_i32));
+        assert!(odd(&&7_usize));
+        assert!(!odd(&i64::MIN));
+
+        assert!(divisibleby(&9_u8, 3));
+        assert!(!divisibleby(&10_u8, 3));
+        assert!(divisibleby(&0_i32, 0));
+        assert!(!divisibleby(&5_i32, 0));
+        assert!(divisibleby(&i32::MIN, -1));
+        assert!(divisibleby(&&-12_i16, -4));
+    }
+
+    #[test]
+    fn test_optional() {
+        assert!(none(&None::<u8>));
+        assert!(!none(&Some(1)));
+        assert!(some(&&Some("a")));
+    }
+
+    #[test]
+    fn test_collection() {
+        assert!(empty(""));
+        assert!(!empty(&"a"));
+        assert!(empty::<[u8; 0]>(&[]));
+        assert!(!empty(&[1, 2][..]));
+        assert!(empty(&None::<u8>));
+    }
+
+    #[test]
+    fn test_startingwith() {
+        assert!(startingwith("hello", "he"));
+        assert!(!startingwith(&"hello", &"lo"));
+        assert!(startingwith("hello", ""));
+    }
+
+    #[test]
+    fn test_in() {
+        assert!(r#in("ell", "hello"));
+        assert!(!r#in(&"xyz", &"hello"));
+        assert!(r#in(&"b", &["a", "b"]));
+        assert!(!r#in(&3, &[1, 2][..]));
+        assert!(r#in(&4, &(1..5)));
+        assert!(!r#in(&5, &(1..5)));
+        assert!(r#in(&5, &(1..=5)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_in_std() {
+        use std::collections::{HashMap, HashSet};
+        use std::string::String;
+        use std::vec;
+
+        let names: vec::Vec<String> = vec!["a".into(), "b".into()];
+        assert!(r#in(&"a", &names));
+        assert!(r#in(&"b", &&names));
+        assert!(!r#in(&"c", &names));
+
+        let set: HashSet<u32> = [1, 2].into_iter().collect();
+        assert!(r#in(&1, &set));
+
+        let map: HashMap<&str, u32> = [("a", 1)].into_iter().collect();
+        assert!(r#in(&"a", &map));
+        assert!(!r#in(&"b", &map));
+    }
+}
@@ -1,5 +1,7 @@
 use core::any::Any;
 use core::borrow::Borrow;
+use core::fmt;
+use core::marker::PhantomData;
 
 use crate::Error;
 
@@ -7,14 +9,116 @@ use crate::Error;
 pub const NO_VALUES: &dyn Values = &();
 
 /// Try to find `key` in `values` and then to convert it to `T`.
+///
+/// `key` is either a string, or a typed [`Key`]. If a [`Key<T>`] is used, `T` is known to the
+/// compiler, so it does not need to be specified, and cannot be mismatched.
 #[inline]
-pub fn get_value<T: Any>(values: &dyn Values, key: impl AsRef<str>) -> Result<&T, Error> {
+pub fn get_value<T: Any>(values: &dyn Values, key: impl ValueKey<T>) -> Result<&T, Error> {
     values
-        .get_value(key.as_ref())
+        .get_value(key.name())
         .ok_or(Error::ValueMissing)
         .and_then(convert_value)
 }
 
+/// The name of a value in [`Values`], and the type of the value
+///
+/// Typed keys are generated by [`#[derive(Values)]`][macro@crate::Values] for each field, but
+/// they can be defined manually, too:
+///
+/// ```
+/// # use std::any::Any;
+/// # use std::collections::HashMap;
+/// use askama::{Key, get_value};
+///
+/// const USER_ID: Key<u32> = Key::new("user_id");
+///
+/// let mut values: HashMap<&str, Box<dyn Any>> = HashMap::new();
+/// values.insert(USER_ID.name(), Box::new(42u32));
+/// assert_eq!(get_value(&values, USER_ID).unwrap(), &42);
+/// ```
+///
+/// A mismatched type is a compile time error:
+///
+/// ```compile_fail
+/// # use askama::{Key, NO_VALUES, get_value};
+/// const USER_ID: Key<u32> = Key::new("user_id");
+/// let _ = get_value::<String>(NO_VALUES, USER_ID);
+/// ```
+pub struct Key<T> {
+    name: &'static str,
+    _ty: PhantomData<fn() -> T>,
+}
+
+impl<T> Key<T> {
+    /// Create a typed key for the value `name`
+    #[inline]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _ty: PhantomData,
+        }
+    }
+
+    /// The name of the value
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T: Any> Key<T> {
+    /// Try to find this key in `values`, cf. [`get_value()`]
+    #[inline]
+    pub fn get<'a>(&self, values: &'a dyn Values) -> Result<&'a T, Error> {
+        get_value(values, self)
+    }
+}
+
+// Implemented manually, so `T` does not need to implement the traits.
+impl<T> Clone for Key<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Key<T> {}
+
+impl<T> fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.name).finish()
+    }
+}
+
+/// A key that can be used to look up a value of type `T` in [`Values`]
+///
+/// Implemented for strings, which can name a value of any type, and for [`Key<T>`].
+pub trait ValueKey<T> {
+    /// The name of the value
+    fn name(&self) -> &str;
+}
+
+impl<T, S: AsRef<str>> ValueKey<T> for S {
+    #[inline]
+    fn name(&self) -> &str {
+        self.as_ref()
+    }
+}
+
+impl<T> ValueKey<T> for Key<T> {
+    #[inline]
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+impl<T> ValueKey<T> for &Key<T> {
+    #[inline]
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
 fn convert_value<T: Any>(src: &dyn Any) -> Result<&T, Error> {
     if let Some(value) = src.downcast_ref::<T>() {
         return Ok(value);
@@ -49,6 +153,64 @@ crate::impl_for_ref! {
     }
 }
 
+/// Two [`Values`] stores layered on top of each other
+///
+/// A key is searched in the `top` store first, and then in the `fallback` store. Layers can be
+/// nested to compose any number of stores, e.g. an app-wide store, a per-request store, and a
+/// per-render override:
+///
+/// ```
+/// # use std::any::Any;
+/// # use std::collections::HashMap;
+/// use askama::{Layered, get_value};
+///
+/// let mut app: HashMap<&str, Box<dyn Any>> = HashMap::new();
+/// app.insert("site", Box::new("askama.rs"));
+/// app.insert("theme", Box::new("light"));
+/// let request: (&str, &dyn Any) = ("user", &"Ferris");
+/// let overrides: (&str, &dyn Any) = ("theme", &"dark");
+///
+/// let values = Layered::new(overrides, Layered::new(request, &app));
+/// assert_eq!(get_value::<&str>(&values, "theme").unwrap(), &"dark");
+/// assert_eq!(get_value::<&str>(&values, "user").unwrap(), &"Ferris");
+/// assert_eq!(get_value::<&str>(&values, "site").unwrap(), &"askama.rs");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layered<T, U> {
+    top: T,
+    fallback: U,
+}
+
+impl<T: Values, U: Values> Layered<T, U> {
+    /// Layers `top` on top of `fallback`.
+    #[inline]
+    pub const fn new(top: T, fallback: U) -> Self {
+        Self { top, fallback }
+    }
+
+    /// Returns the top layer.
+    #[inline]
+    pub const fn top(&self) -> &T {
+        &self.top
+    }
+
+    /// Returns the fallback layer.
+    #[inline]
+    pub const fn fallback(&self) -> &U {
+        &self.fallback
+    }
+}
+
+impl<T: Values, U: Values> Values for Layered<T, U> {
+    #[inline]
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+        match self.top.get_value(key) {
+            Some(value) => Some(value),
+            None => self.fallback.get_value(key),
+        }
+    }
+}
+
 impl Values for () {
     #[inline]
     fn get_value<'a>(&'a self, _: &str) -> Option<&'a dyn Any> {
@@ -300,6 +462,34 @@ mod tests {
         assert_a_10_c_blam(&list);
     }
 
+    #[test]
+    fn values_with_typed_key() {
+        const A: Key<u32> = Key::new("a");
+        const C: Key<&str> = Key::new("c");
+        const D: Key<u8> = Key::new("d");
+
+        let slice: &[(&str, &dyn Any)] = &[("a", &12u32), ("c", &"blam")];
+        assert_matches!(get_value(&slice, A), Ok(12u32));
+        assert_matches!(A.get(&slice), Ok(12u32));
+        let c: &Key<&str> = &C;
+        assert_matches!(get_value(&slice, c), Ok(&"blam"));
+        assert_matches!(D.get(&slice), Err(Error::ValueMissing));
+        assert_matches!(Key::<u8>::new("a").get(&slice), Err(Error::ValueType));
+    }
+
+    #[test]
+    fn values_layered() {
+        let top: [(&str, &dyn Any); 2] = [("a", &10u32), ("b", &None::<u8>)];
+        let fallback: [(&str, &dyn Any); 3] = [("a", &12u32), ("b", &1u8), ("c", &"blam")];
+        let values = Layered::new(&top, &fallback);
+        assert_a_10_c_blam(&values);
+        // `top` contains `b`, even if it's the wrong type
+        assert_matches!(get_value::<u8>(&values, "b"), Err(Error::ValueType));
+
+        let values = Layered::new((), Layered::new(None::<()>, &fallback));
+        assert_a_12_c_blam(&values);
+    }
+
     #[test]
     fn values_on_tuple() {
         let tuple: (&str, &dyn Any) = ("a", &10u32);
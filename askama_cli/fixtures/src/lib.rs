@@ -0,0 +1,20 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "page.html")]
+struct Page;
+
+#[derive(Template)]
+#[template(
+    ext = "html",
+    source = r#"{% import "macros.html" as m %}{% call m::card("a") %}{% endcall %}"#
+)]
+struct Card;
+
+#[derive(Template)]
+#[template(path = "angle.html", syntax = "angle", whitespace = "suppress")]
+struct Angle;
+
+#[derive(Template)]
+#[template(path = "partial.html", whitespace = "minimize")]
+struct Partial;
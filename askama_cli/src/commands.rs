@@ -0,0 +1,271 @@
+//! The subcommands. Each command writes its report to `out`, and returns `false` if it found
+//! problems.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use askama_parser::cst::{FormatOptions, format};
+use askama_parser::{Ast, ParseError};
+
+use crate::project::{Project, Summary, Template};
+use crate::rust::TemplateRef;
+
+/// Parses every template, and reports syntax errors and templates that cannot be found.
+pub(crate) fn check(project: &Project, out: &mut dyn Write) -> io::Result<bool> {
+    let mut errors = 0;
+    for template in &project.templates {
+        let ast = match template.parse() {
+            Ok(ast) => ast,
+            Err(errs) => {
+                for err in &errs {
+                    report_parse_error(template, err, out)?;
+                }
+                errors += errs.len();
+                continue;
+            }
+        };
+        for dep in project.dependencies(template, &ast) {
+            if let Err(err) = dep.target {
+                let offset = offset_in(&template.source, dep.name);
+                writeln!(out, "{}: error: {err}", template.location(offset))?;
+                errors += 1;
+            }
+        }
+    }
+    writeln!(
+        out,
+        "checked {} template{}, found {errors} error{}",
+        project.templates.len(),
+        plural(project.templates.len()),
+        plural(errors),
+    )?;
+    Ok(errors == 0)
+}
+
+/// Prints the `extends`, `include` and `import` relations between the templates, either as a
+/// list, or in the DOT format of graphviz.
+pub(crate) fn graph(project: &Project, dot: bool, out: &mut dyn Write) -> io::Result<bool> {
+    if dot {
+        writeln!(out, "digraph templates {{")?;
+    }
+    for template in &project.templates {
+        let Ok(ast) = template.parse() else {
+            continue;
+        };
+        let deps = project.dependencies(template, &ast);
+        if !dot {
+            writeln!(out, "{}", template.name)?;
+        } else if deps.is_empty() {
+            writeln!(out, "    {:?};", template.name)?;
+        }
+        for dep in deps {
+            let target = match &dep.target {
+                Ok(path) => project.name_of(path),
+                Err(_) => format!("{} (not found)", dep.name),
+            };
+            if dot {
+                writeln!(
+                    out,
+                    "    {:?} -> {target:?} [label={:?}];",
+                    template.name, dep.kind,
+                )?;
+            } else {
+                writeln!(out, "    {} {target}", dep.kind)?;
+            }
+        }
+    }
+    if dot {
+        writeln!(out, "}}")?;
+    }
+    Ok(true)
+}
+
+/// Lists the templates that are neither used by a `#[template(…)]` attribute nor by another used
+/// template, and the blocks that cannot be rendered because no template they extend defines a
+/// block of the same name.
+pub(crate) fn unused(project: &Project, out: &mut dyn Write) -> io::Result<bool> {
+    let parsed = project
+        .templates
+        .iter()
+        .filter_map(|template| Some((template, template.parse().ok()?)))
+        .collect::<Vec<_>>();
+    let summaries = parsed
+        .iter()
+        .map(|(template, ast)| (Arc::clone(&template.path), (*template, Summary::new(ast))))
+        .collect::<BTreeMap<_, _>>();
+
+    // Templates used by the Rust code, and then the templates they use.
+    let mut queue = vec![];
+    for attr in &project.attrs {
+        match &attr.template {
+            TemplateRef::Path(name) => queue.extend(project.resolve(name, None).ok()),
+            TemplateRef::Source(source) => {
+                if let Ok(ast) = Ast::from_str(source, None, &project.syntax_of(attr)) {
+                    queue.extend(
+                        Summary::new(&ast)
+                            .dependencies
+                            .iter()
+                            .filter_map(|(_, name)| project.resolve(name, None).ok()),
+                    );
+                }
+            }
+        }
+    }
+    let used = reachable(project, &summaries, queue, |_| true);
+
+    let mut found = 0;
+    for template in &project.templates {
+        if !used.contains(&template.path) {
+            writeln!(out, "{}: unused template", template.name)?;
+            found += 1;
+        }
+    }
+
+    for (template, summary) in summaries.values() {
+        if summary.extends.is_none() {
+            continue;
+        }
+        let inherited = inherited_blocks(project, &summaries, template, summary);
+        for block in &summary.blocks {
+            if !block.nested && !inherited.contains(block.name) {
+                writeln!(
+                    out,
+                    "{}: unused block `{}`, no extended template defines it",
+                    template.location(block.offset),
+                    block.name,
+                )?;
+                found += 1;
+            }
+        }
+    }
+    Ok(found == 0)
+}
+
+type Summaries<'a> = BTreeMap<Arc<Path>, (&'a Template, Summary<'a>)>;
+
+/// The templates in `queue`, and the templates they use with a dependency of a kind accepted by
+/// `follow`.
+fn reachable(
+    project: &Project,
+    summaries: &Summaries<'_>,
+    mut queue: Vec<Arc<Path>>,
+    follow: impl Fn(&str) -> bool,
+) -> BTreeSet<Arc<Path>> {
+    let mut reached = BTreeSet::new();
+    while let Some(path) = queue.pop() {
+        if !reached.insert(Arc::clone(&path)) {
+            continue;
+        }
+        if let Some((template, summary)) = summaries.get(&path) {
+            queue.extend(
+                summary
+                    .dependencies
+                    .iter()
+                    .filter(|(kind, _)| follow(kind))
+                    .filter_map(|(_, name)| project.resolve(name, Some(&template.path)).ok()),
+            );
+        }
+    }
+    reached
+}
+
+/// The names of the blocks defined by the templates `template` extends, and by the templates
+/// they include.
+fn inherited_blocks<'a>(
+    project: &Project,
+    summaries: &'a Summaries<'_>,
+    template: &Template,
+    summary: &Summary<'_>,
+) -> BTreeSet<&'a str> {
+    let mut ancestors = vec![];
+    let mut parent = summary
+        .extends
+        .and_then(|name| project.resolve(name, Some(&template.path)).ok());
+    while let Some(path) = parent.take() {
+        if ancestors.contains(&path) {
+            break;
+        }
+        ancestors.push(Arc::clone(&path));
+        parent = summaries.get(&path).and_then(|(template, summary)| {
+            let name = summary.extends?;
+            project.resolve(name, Some(&template.path)).ok()
+        });
+    }
+
+    reachable(project, summaries, ancestors, |kind| kind == "include")
+        .iter()
+        .filter_map(|path| summaries.get(path))
+        .flat_map(|(_, summary)| summary.blocks.iter().map(|block| block.name))
+        .collect()
+}
+
+/// Formats every template. With `check`, the templates are not modified, and the command fails
+/// if any of them is not formatted.
+pub(crate) fn fmt(project: &Project, check: bool, out: &mut dyn Write) -> io::Result<bool> {
+    let mut unformatted = 0;
+    for template in &project.templates {
+        let options = FormatOptions {
+            whitespace: template.whitespace,
+            ..FormatOptions::default()
+        };
+        let formatted = format(&template.source, &template.syntax, &options);
+        if formatted == template.source {
+            continue;
+        }
+        if check {
+            writeln!(out, "{}: not formatted", template.name)?;
+            unformatted += 1;
+        } else {
+            fs::write(&template.path, formatted)?;
+            writeln!(out, "{}: formatted", template.name)?;
+        }
+    }
+    Ok(unformatted == 0)
+}
+
+/// Prints the AST of a template.
+pub(crate) fn ast(project: &Project, name: &str, out: &mut dyn Write) -> io::Result<bool> {
+    let Some(template) = project.find(name) else {
+        writeln!(out, "error: template {name:?} not found")?;
+        return Ok(false);
+    };
+    match template.parse() {
+        Ok(ast) => {
+            writeln!(out, "{:#?}", ast.nodes())?;
+            Ok(true)
+        }
+        Err(errs) => {
+            for err in &errs {
+                report_parse_error(template, err, out)?;
+            }
+            Ok(false)
+        }
+    }
+}
+
+fn report_parse_error(
+    template: &Template,
+    err: &ParseError,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let message = err
+        .message
+        .as_deref()
+        .unwrap_or("failed to parse template source");
+    writeln!(out, "{}: error: {message}", template.location(err.offset))
+}
+
+/// The offset of `part`, a subslice of `text`.
+fn offset_in(text: &str, part: &str) -> usize {
+    (part.as_ptr() as usize)
+        .checked_sub(text.as_ptr() as usize)
+        .filter(|&offset| offset <= text.len())
+        .unwrap_or_default()
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
@@ -0,0 +1,174 @@
+//! The `askama` command-line tool, to check, format and inspect the templates of a crate without
+//! compiling it.
+//!
+//! The templates and the syntax are found using the `askama.toml` file of the crate, i.e. of the
+//! nearest ancestor directory of the current directory that contains an `askama.toml` or
+//! `Cargo.toml` file.
+
+mod commands;
+mod project;
+mod rust;
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::project::Project;
+
+const USAGE: &str = "\
+Usage: askama [--root <dir>] [--syntax <name>] <command>
+
+Commands:
+    check             Parse every template and report syntax errors and missing templates
+    graph [--dot]     Print the `extends`, `include` and `import` graph of the templates
+    unused            List unused templates and blocks
+    fmt [--check]     Format every template, or only check that they are formatted
+    ast <template>    Print the AST of a template, given by name or by path
+
+Options:
+    --root <dir>      The directory of the crate containing `askama.toml`
+    --syntax <name>   The syntax to use for every template, instead of the syntax given in the
+                      `#[template(...)]` attributes, or the default syntax of the configuration
+";
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    root: Option<PathBuf>,
+    syntax: Option<String>,
+    command: Command,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Check,
+    Graph { dot: bool },
+    Unused,
+    Fmt { check: bool },
+    Ast { template: String },
+    Help,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if args.command == Command::Help {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let root = match args.root {
+        Some(root) => root,
+        None => match std::env::current_dir() {
+            Ok(dir) => find_root(&dir).to_path_buf(),
+            Err(err) => {
+                eprintln!("error: could not get the current directory: {err}");
+                return ExitCode::from(2);
+            }
+        },
+    };
+    let project = match std::path::absolute(&root)
+        .map_err(|err| format!("invalid root {}: {err}", root.display()))
+        .and_then(|root| Project::load(&root, args.syntax.as_deref()))
+    {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&project, &args.command, &mut io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(project: &Project, command: &Command, out: &mut dyn Write) -> io::Result<bool> {
+    match command {
+        Command::Check => commands::check(project, out),
+        Command::Graph { dot } => commands::graph(project, *dot, out),
+        Command::Unused => commands::unused(project, out),
+        Command::Fmt { check } => commands::fmt(project, *check, out),
+        Command::Ast { template } => commands::ast(project, template, out),
+        Command::Help => Ok(true),
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut root = None;
+    let mut syntax = None;
+    let mut command = None;
+    let mut flags = vec![];
+    let mut positional = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" | "help" if command.is_none() => return Ok(help()),
+            "--root" => root = Some(args.next().ok_or("missing value for `--root`")?.into()),
+            "--syntax" => syntax = Some(args.next().ok_or("missing value for `--syntax`")?),
+            _ if arg.starts_with('-') => flags.push(arg),
+            _ if command.is_none() => command = Some(arg),
+            _ => positional.push(arg),
+        }
+    }
+
+    let command = command.ok_or("missing command")?;
+    let mut flag = |name: &str| match flags.iter().position(|flag| flag == name) {
+        Some(index) => {
+            flags.remove(index);
+            true
+        }
+        None => false,
+    };
+    let command = match command.as_str() {
+        "check" => Command::Check,
+        "graph" => Command::Graph { dot: flag("--dot") },
+        "unused" => Command::Unused,
+        "fmt" => Command::Fmt {
+            check: flag("--check"),
+        },
+        "ast" => match positional.pop() {
+            Some(template) => Command::Ast { template },
+            None => return Err("missing template for `ast`".into()),
+        },
+        _ => return Err(format!("unknown command `{command}`")),
+    };
+    if let Some(flag) = flags.first() {
+        return Err(format!("unknown option `{flag}`"));
+    }
+    if let Some(arg) = positional.first() {
+        return Err(format!("unexpected argument `{arg}`"));
+    }
+    Ok(Args {
+        root,
+        syntax,
+        command,
+    })
+}
+
+fn help() -> Args {
+    Args {
+        root: None,
+        syntax: None,
+        command: Command::Help,
+    }
+}
+
+/// The nearest ancestor of `dir` that contains an `askama.toml` or `Cargo.toml` file.
+fn find_root(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|dir| dir.join("askama.toml").is_file() || dir.join("Cargo.toml").is_file())
+        .unwrap_or(dir)
+}
@@ -0,0 +1,340 @@
+//! Loading the templates of a crate, and the relations between them.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use askama_derive::tooling::Config;
+use askama_parser::node::{BlockDef, Extends, Import, Include, Whitespace};
+use askama_parser::visit::{Visit, walk_block_def};
+use askama_parser::{Ast, ParseError, Syntax, WithSpan};
+
+use crate::rust::{TemplateAttr, TemplateRef, template_attrs};
+
+pub(crate) struct Project {
+    pub(crate) config: Config,
+    /// The syntax given with `--syntax`, if any.
+    forced_syntax: Option<Syntax<'static>>,
+    /// The templates of the `#[template(…)]` attributes of the crate.
+    pub(crate) attrs: BTreeSet<TemplateAttr>,
+    /// All files in the template directories, sorted by name.
+    pub(crate) templates: Vec<Template>,
+}
+
+pub(crate) struct Template {
+    pub(crate) path: Arc<Path>,
+    /// The path relative to its template directory, as used in `#[template(path = "…")]`.
+    pub(crate) name: String,
+    pub(crate) source: String,
+    /// The syntax of the `#[template(…)]` attribute using the template, directly or through
+    /// other templates, or else the default syntax.
+    pub(crate) syntax: Syntax<'static>,
+    /// The whitespace handling of the configuration, or of the `#[template(…)]` attributes using
+    /// the template, directly or through other templates.
+    pub(crate) whitespace: Whitespace,
+}
+
+impl Project {
+    pub(crate) fn load(root: &Path, syntax: Option<&str>) -> Result<Self, String> {
+        let config = Config::load(root)?;
+        let forced_syntax =
+            match syntax {
+                Some(name) => Some(config.syntax(Some(name)).ok_or_else(|| {
+                    format!("syntax {name:?} is not defined in the configuration")
+                })?),
+                None => None,
+            };
+        let default_syntax =
+            forced_syntax.unwrap_or_else(|| config.syntax(None).unwrap_or_default());
+
+        let mut seen = BTreeSet::new();
+        let mut templates = vec![];
+        for dir in config.dirs() {
+            for path in files(dir, &|_| true) {
+                if !seen.insert(path.clone()) {
+                    continue;
+                }
+                let source = match fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    // Not a template.
+                    Err(err) if err.kind() == ErrorKind::InvalidData => continue,
+                    Err(err) => return Err(format!("unable to read {}: {err}", path.display())),
+                };
+                templates.push(Template {
+                    name: relative_name(dir, &path),
+                    path: path.into(),
+                    source,
+                    syntax: default_syntax,
+                    whitespace: config.whitespace(),
+                });
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut project = Self {
+            config,
+            forced_syntax,
+            attrs: template_attrs(root),
+            templates,
+        };
+        if project.forced_syntax.is_none() {
+            project.apply_attr_syntaxes();
+        }
+        project.apply_attr_whitespaces();
+        Ok(project)
+    }
+
+    /// The syntax `attr` is parsed with.
+    pub(crate) fn syntax_of(&self, attr: &TemplateAttr) -> Syntax<'static> {
+        self.forced_syntax
+            .or_else(|| self.config.syntax(attr.syntax.as_deref()))
+            .unwrap_or_default()
+    }
+
+    /// Like `#[derive(Template)]`, uses the syntax of a `#[template(…)]` attribute for its
+    /// template, and for all templates it uses.
+    fn apply_attr_syntaxes(&mut self) {
+        for attr in self.attrs.iter().filter(|attr| attr.syntax.is_some()) {
+            let syntax = self.syntax_of(attr);
+            for index in self.used_templates(attr, syntax) {
+                self.templates[index].syntax = syntax;
+            }
+        }
+    }
+
+    /// A `whitespace = "…"` argument applies to the template of the attribute, and to all
+    /// templates it uses. Templates used with different whitespace handlings are formatted as if
+    /// whitespace was preserved, so formatting them does not change the output of any of them.
+    fn apply_attr_whitespaces(&mut self) {
+        let default = self.config.whitespace();
+        let whitespace_of = |attr: &TemplateAttr| {
+            attr.whitespace
+                .as_deref()
+                .and_then(|ws| ws.parse().ok())
+                .unwrap_or(default)
+        };
+        if self.attrs.iter().all(|attr| whitespace_of(attr) == default) {
+            return;
+        }
+
+        let mut whitespaces = vec![None; self.templates.len()];
+        for attr in &self.attrs {
+            let whitespace = whitespace_of(attr);
+            for index in self.used_templates(attr, self.syntax_of(attr)) {
+                whitespaces[index] = match whitespaces[index] {
+                    Some(ws) if ws != whitespace => Some(Whitespace::Preserve),
+                    _ => Some(whitespace),
+                };
+            }
+        }
+        for (template, whitespace) in self.templates.iter_mut().zip(whitespaces) {
+            template.whitespace = whitespace.unwrap_or(default);
+        }
+    }
+
+    /// The indices of the templates used by `attr`, directly or through other templates.
+    fn used_templates(&self, attr: &TemplateAttr, syntax: Syntax<'_>) -> Vec<usize> {
+        let mut queue = match &attr.template {
+            TemplateRef::Path(name) => self.resolve(name, None).into_iter().collect(),
+            TemplateRef::Source(source) => match Ast::from_str(source, None, &syntax) {
+                Ok(ast) => Summary::new(&ast)
+                    .dependencies
+                    .iter()
+                    .filter_map(|(_, name)| self.resolve(name, None).ok())
+                    .collect(),
+                Err(_) => vec![],
+            },
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut used = vec![];
+        while let Some(path) = queue.pop() {
+            if !seen.insert(Arc::clone(&path)) {
+                continue;
+            }
+            let Some(index) = self.templates.iter().position(|t| t.path == path) else {
+                continue;
+            };
+            let template = &self.templates[index];
+            if let Ok(ast) = Ast::from_str(&template.source, None, &syntax) {
+                queue.extend(
+                    self.dependencies(template, &ast)
+                        .into_iter()
+                        .filter_map(|dep| dep.target.ok()),
+                );
+            }
+            used.push(index);
+        }
+        used
+    }
+
+    /// Finds a template by its name, or by its path.
+    pub(crate) fn find(&self, name: &str) -> Option<&Template> {
+        self.templates.iter().find(|t| t.name == name).or_else(|| {
+            let path = fs::canonicalize(name).ok()?;
+            self.templates
+                .iter()
+                .find(|t| fs::canonicalize(&t.path).is_ok_and(|p| p == path))
+        })
+    }
+
+    /// The name of the template at `path`, or its path if it is not in a template directory.
+    pub(crate) fn name_of(&self, path: &Path) -> String {
+        match self.templates.iter().find(|t| *t.path == *path) {
+            Some(template) => template.name.clone(),
+            None => path.display().to_string(),
+        }
+    }
+
+    /// The templates used by `template`.
+    pub(crate) fn dependencies<'a>(
+        &self,
+        template: &Template,
+        ast: &Ast<'a>,
+    ) -> Vec<Dependency<'a>> {
+        Summary::new(ast)
+            .dependencies
+            .into_iter()
+            .map(|(kind, name)| Dependency {
+                kind,
+                name,
+                target: self.resolve(name, Some(&template.path)),
+            })
+            .collect()
+    }
+
+    /// Resolves a template path used in the template at `from`, like `#[derive(Template)]` does.
+    pub(crate) fn resolve(&self, name: &str, from: Option<&Path>) -> Result<Arc<Path>, String> {
+        let path = self.config.find_template(name, from)?;
+        // Remove the `..` of paths relative to `from`, to get the same path as in `templates`.
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                Component::CurDir => {}
+                component => normalized.push(component),
+            }
+        }
+        Ok(normalized.into())
+    }
+}
+
+impl Template {
+    pub(crate) fn parse(&self) -> Result<Ast<'_>, Vec<ParseError>> {
+        Ast::from_str_with_recovery(&self.source, Some(Arc::clone(&self.path)), &self.syntax)
+    }
+
+    /// The 1-based line and column of `offset`.
+    pub(crate) fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.source[..offset.min(self.source.len())];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    /// Formats `offset` as `name:line:column`.
+    pub(crate) fn location(&self, offset: usize) -> String {
+        let (line, col) = self.line_col(offset);
+        format!("{}:{line}:{col}", self.name)
+    }
+}
+
+pub(crate) struct Dependency<'a> {
+    /// `extends`, `include` or `import`.
+    pub(crate) kind: &'static str,
+    /// The path as written in the template.
+    pub(crate) name: &'a str,
+    pub(crate) target: Result<Arc<Path>, String>,
+}
+
+/// The blocks defined by a template, and the templates it uses.
+#[derive(Default)]
+pub(crate) struct Summary<'a> {
+    pub(crate) dependencies: Vec<(&'static str, &'a str)>,
+    pub(crate) extends: Option<&'a str>,
+    pub(crate) blocks: Vec<Block<'a>>,
+    depth: usize,
+}
+
+pub(crate) struct Block<'a> {
+    pub(crate) name: &'a str,
+    /// The offset of the name of the block.
+    pub(crate) offset: usize,
+    /// Whether the block is defined inside of another block.
+    pub(crate) nested: bool,
+}
+
+impl<'a> Summary<'a> {
+    pub(crate) fn new(ast: &Ast<'a>) -> Self {
+        let mut summary = Self::default();
+        summary.visit_nodes(ast.nodes());
+        summary
+    }
+}
+
+impl<'a> Visit<'a> for Summary<'a> {
+    fn visit_extends(&mut self, extends: &WithSpan<Extends<'a>>) {
+        self.extends = Some(extends.path);
+        self.dependencies.push(("extends", extends.path));
+    }
+
+    fn visit_include(&mut self, include: &WithSpan<Include<'a>>) {
+        self.dependencies.push(("include", include.path));
+    }
+
+    fn visit_import(&mut self, import: &WithSpan<Import<'a>>) {
+        self.dependencies.push(("import", import.path));
+    }
+
+    fn visit_block_def(&mut self, block: &WithSpan<BlockDef<'a>>) {
+        let span = block.name.span().byte_range().unwrap_or_default();
+        self.blocks.push(Block {
+            name: *block.name,
+            offset: span.start,
+            nested: self.depth > 0,
+        });
+        self.depth += 1;
+        walk_block_def(self, block);
+        self.depth -= 1;
+    }
+}
+
+/// Returns the files in `dir` and its subdirectories for which `filter` returns `true`, sorted
+/// by path. Hidden entries are skipped.
+pub(crate) fn files(dir: &Path, filter: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if filter(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn relative_name(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
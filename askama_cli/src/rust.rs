@@ -0,0 +1,126 @@
+//! Finding the templates used by the Rust code of a crate, without compiling it.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use syn::punctuated::Punctuated;
+use syn::visit::Visit;
+use syn::{Attribute, Expr, ExprLit, Lit, Meta, Token};
+
+use crate::project::files;
+
+/// The directories of a crate that can contain `#[derive(Template)]` items.
+const SOURCE_DIRS: &[&str] = &["src", "tests", "examples", "benches"];
+
+/// A template given in a `#[template(…)]` attribute.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum TemplateRef {
+    /// `path = "…"`
+    Path(String),
+    /// `source = "…"`
+    Source(String),
+}
+
+/// A template given in a `#[template(…)]` attribute, with the syntax it is parsed with and its
+/// whitespace handling.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TemplateAttr {
+    pub(crate) template: TemplateRef,
+    /// `syntax = "…"`
+    pub(crate) syntax: Option<String>,
+    /// `whitespace = "…"`
+    pub(crate) whitespace: Option<String>,
+}
+
+/// Returns the templates of all `#[template(…)]` attributes in the Rust files of the crate at
+/// `root`.
+pub(crate) fn template_attrs(root: &Path) -> BTreeSet<TemplateAttr> {
+    let is_rust = |path: &Path| path.extension().is_some_and(|ext| ext == "rs");
+    SOURCE_DIRS
+        .iter()
+        .flat_map(|dir| files(&root.join(dir), &is_rust))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|source| attribute_templates(&source))
+        .collect()
+}
+
+/// Returns the templates of all `#[template(…)]` attributes in the Rust `source`, including the
+/// ones in `#[cfg_attr(…, template(…))]`.
+///
+/// Files that cannot be parsed as Rust contain no templates.
+pub(crate) fn attribute_templates(source: &str) -> Vec<TemplateAttr> {
+    let Ok(file) = syn::parse_file(source) else {
+        return vec![];
+    };
+    let mut visitor = AttrVisitor::default();
+    visitor.visit_file(&file);
+    visitor.attrs
+}
+
+#[derive(Default)]
+struct AttrVisitor {
+    attrs: Vec<TemplateAttr>,
+}
+
+impl<'ast> Visit<'ast> for AttrVisitor {
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        self.visit_meta_args(&attr.meta);
+    }
+}
+
+impl AttrVisitor {
+    /// Collects the templates of `template(…)`, or of the attributes in `cfg_attr(…)`.
+    fn visit_meta_args(&mut self, meta: &Meta) {
+        let Meta::List(list) = meta else {
+            return;
+        };
+        let Ok(args) = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated) else {
+            return;
+        };
+        if list.path.is_ident("cfg_attr") {
+            // The first argument is the condition.
+            for meta in args.iter().skip(1) {
+                self.visit_meta_args(meta);
+            }
+        } else if list.path.is_ident("template") {
+            self.add_template(&args);
+        }
+    }
+
+    fn add_template(&mut self, args: &Punctuated<Meta, Token![,]>) {
+        let mut refs = vec![];
+        let mut syntax = None;
+        let mut whitespace = None;
+        for arg in args {
+            let Meta::NameValue(pair) = arg else {
+                continue;
+            };
+            let Expr::Lit(ExprLit {
+                lit: Lit::Str(value),
+                ..
+            }) = &pair.value
+            else {
+                continue;
+            };
+            let Some(name) = pair.path.get_ident() else {
+                continue;
+            };
+            if name == "path" {
+                refs.push(TemplateRef::Path(value.value()));
+            } else if name == "source" {
+                refs.push(TemplateRef::Source(value.value()));
+            } else if name == "syntax" {
+                syntax = Some(value.value());
+            } else if name == "whitespace" {
+                whitespace = Some(value.value());
+            }
+        }
+        self.attrs
+            .extend(refs.into_iter().map(|template| TemplateAttr {
+                template,
+                syntax: syntax.clone(),
+                whitespace: whitespace.clone(),
+            }));
+    }
+}
@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use askama_parser::node::Whitespace;
+
+use crate::project::Project;
+use crate::rust::{TemplateAttr, TemplateRef, attribute_templates};
+use crate::{Args, Command, parse_args, run};
+
+fn project() -> Project {
+    Project::load(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures"),
+        None,
+    )
+    .unwrap()
+}
+
+/// Runs `command` on the fixtures, and returns its success and its output.
+fn output(command: Command) -> (bool, String) {
+    let mut out = vec![];
+    let success = run(&project(), &command, &mut out).unwrap();
+    (success, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_parse_args() {
+    let parse = |args: &[&str]| parse_args(args.iter().map(|arg| (*arg).to_owned()));
+
+    assert_eq!(
+        parse(&["check"]),
+        Ok(Args {
+            root: None,
+            syntax: None,
+            command: Command::Check,
+        }),
+    );
+    assert_eq!(
+        parse(&["--root", "crate", "graph", "--dot", "--syntax", "mine"]),
+        Ok(Args {
+            root: Some("crate".into()),
+            syntax: Some("mine".into()),
+            command: Command::Graph { dot: true },
+        }),
+    );
+    assert_eq!(
+        parse(&["ast", "index.html"]).map(|args| args.command),
+        Ok(Command::Ast {
+            template: "index.html".into(),
+        }),
+    );
+    assert_eq!(
+        parse(&["fmt", "--check"]).map(|args| args.command),
+        Ok(Command::Fmt { check: true }),
+    );
+    assert_eq!(
+        parse(&["--help"]).map(|args| args.command),
+        Ok(Command::Help)
+    );
+
+    assert_eq!(parse(&[]), Err("missing command".into()));
+    assert_eq!(parse(&["ast"]), Err("missing template for `ast`".into()));
+    assert_eq!(parse(&["lint"]), Err("unknown command `lint`".into()));
+    assert_eq!(
+        parse(&["check", "--dot"]),
+        Err("unknown option `--dot`".into())
+    );
+    assert_eq!(
+        parse(&["check", "a"]),
+        Err("unexpected argument `a`".into())
+    );
+    assert_eq!(
+        parse(&["check", "--root"]),
+        Err("missing value for `--root`".into())
+    );
+}
+
+#[test]
+fn test_attribute_templates() {
+    let source = r####"
+        #[derive(Template)]
+        #[template(path = "a.html", print = "all")]
+        struct A;
+
+        #[derive(Template)]
+        #[template (
+            ext = "html",
+            source = "{% include \"b.html\" %}\n\
+                      c\u{e9}\u{1_F600}\x41",
+        )]
+        #[template(name = "raw", source = r##"{{ "#" }}"##, path = "wrong", syntax = "s", whitespace = "~")]
+        struct B;
+
+        // #[template(path = "commented.html")]
+        /// #[template(path = "doc.html")]
+        #[derive(Template)]
+        #[cfg_attr(feature = "x", template(path = "c.html"), cfg_attr(test, template(path = "d.html")))]
+        struct C {
+            s: &'static str,
+        }
+
+        const S: &str = "#[template(path = \"string.html\")]";
+        const R: &str = r"\";
+        const Q: char = '"';
+    "####;
+    let attr = |template, syntax: Option<&str>, whitespace: Option<&str>| TemplateAttr {
+        template,
+        syntax: syntax.map(Into::into),
+        whitespace: whitespace.map(Into::into),
+    };
+    assert_eq!(
+        attribute_templates(source),
+        [
+            attr(TemplateRef::Path("a.html".into()), None, None),
+            attr(
+                TemplateRef::Source("{% include \"b.html\" %}\nc\u{e9}\u{1F600}A".into()),
+                None,
+                None,
+            ),
+            attr(
+                TemplateRef::Source(r##"{{ "#" }}"##.into()),
+                Some("s"),
+                Some("~")
+            ),
+            attr(TemplateRef::Path("wrong".into()), Some("s"), Some("~")),
+            attr(TemplateRef::Path("c.html".into()), None, None),
+            attr(TemplateRef::Path("d.html".into()), None, None),
+        ],
+    );
+}
+
+#[test]
+fn test_whitespace() {
+    // `partial.html` is used with `whitespace = "minimize"`, and by `page.html` which preserves
+    // whitespace.
+    let project = project();
+    let whitespace = |name| project.find(name).unwrap().whitespace;
+    assert_eq!(whitespace("angle.html"), Whitespace::Suppress);
+    assert_eq!(whitespace("angle-part.html"), Whitespace::Suppress);
+    assert_eq!(whitespace("partial.html"), Whitespace::Preserve);
+    assert_eq!(whitespace("orphan.html"), Whitespace::Preserve);
+}
+
+#[test]
+fn test_check() {
+    let (success, out) = output(Command::Check);
+    assert!(!success);
+    let lines = out.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..2],
+        [
+            "broken.html:1:7: error: the token `%` was not expected at this point in the expression",
+            "broken.html:3:8: error: unexpected character `}`",
+        ],
+    );
+    assert!(lines[2].starts_with(
+        "dangling.html:1:12: error: template \"missing.html\" not found in directories"
+    ));
+    assert_eq!(lines[3..], ["checked 9 templates, found 3 errors"]);
+}
+
+#[test]
+fn test_graph() {
+    let (success, out) = output(Command::Graph { dot: false });
+    assert!(success);
+    assert_eq!(
+        out,
+        "\
+angle-part.html
+angle.html
+    include angle-part.html
+base.html
+    include partial.html
+dangling.html
+    include missing.html (not found)
+macros.html
+orphan.html
+page.html
+    extends base.html
+partial.html
+"
+    );
+
+    let (success, out) = output(Command::Graph { dot: true });
+    assert!(success);
+    assert_eq!(
+        out,
+        r#"digraph templates {
+    "angle-part.html";
+    "angle.html" -> "angle-part.html" [label="include"];
+    "base.html" -> "partial.html" [label="include"];
+    "dangling.html" -> "missing.html (not found)" [label="include"];
+    "macros.html";
+    "orphan.html";
+    "page.html" -> "base.html" [label="extends"];
+    "partial.html";
+}
+"#
+    );
+}
+
+#[test]
+fn test_unused() {
+    // `macros.html` is used by the `source` of a template, and the `footer` block of `page.html`
+    // is defined in a template included by `base.html`.
+    let (success, out) = output(Command::Unused);
+    assert!(!success);
+    assert_eq!(
+        out,
+        "\
+broken.html: unused template
+dangling.html: unused template
+orphan.html: unused template
+page.html:4:10: unused block `sidebar`, no extended template defines it
+"
+    );
+}
+
+#[test]
+fn test_fmt() {
+    // `angle-part.html` would not be formatted in the default syntax, but it is included by a
+    // template that uses the `angle` syntax.
+    let (success, out) = output(Command::Fmt { check: true });
+    assert!(!success);
+    assert_eq!(out, "dangling.html: not formatted\n");
+}
+
+#[test]
+fn test_ast() {
+    let (success, out) = output(Command::Ast {
+        template: "orphan.html".into(),
+    });
+    assert!(success);
+    assert!(out.starts_with("[\n    Lit(\n"));
+    assert!(out.contains("val: \"Hello\""));
+
+    let (success, out) = output(Command::Ast {
+        template: "broken.html".into(),
+    });
+    assert!(!success);
+    assert_eq!(out.lines().count(), 2);
+
+    let (success, out) = output(Command::Ast {
+        template: "nope.html".into(),
+    });
+    assert!(!success);
+    assert_eq!(out, "error: template \"nope.html\" not found\n");
+}
@@ -75,12 +75,32 @@ impl Config {
         template_whitespace: Option<Whitespace>,
         config_span: Option<Span>,
         full_config_path: Option<PathBuf>,
+    ) -> Result<&'static Config, CompileError> {
+        Self::new_in(
+            manifest_root(),
+            source,
+            config_path,
+            template_whitespace,
+            config_span,
+            full_config_path,
+        )
+    }
+
+    /// Like [`Config::new`], but with template directories relative to `root` instead of the
+    /// manifest directory of the crate being compiled.
+    pub(crate) fn new_in(
+        root: PathBuf,
+        source: &str,
+        config_path: Option<&str>,
+        template_whitespace: Option<Whitespace>,
+        config_span: Option<Span>,
+        full_config_path: Option<PathBuf>,
     ) -> Result<&'static Config, CompileError> {
         static CACHE: ManuallyDrop<OnceLock<OnceMap<OwnedConfigKey, &'static Config>>> =
             ManuallyDrop::new(OnceLock::new());
         CACHE.get_or_init(OnceMap::default).get_or_try_insert(
             &ConfigKey {
-                root: Cow::Owned(manifest_root()),
+                root: Cow::Owned(root),
                 source: source.into(),
                 config_path: config_path.map(Cow::Borrowed),
                 template_whitespace,
@@ -274,7 +294,7 @@ impl SyntaxAndCache {
         &self,
         source: Arc<str>,
         source_path: Option<Arc<Path>>,
-    ) -> Result<Arc<Parsed>, ParseError> {
+    ) -> Result<Arc<Parsed>, Vec<ParseError>> {
         self.cache.get_or_try_insert(
             &SyntaxAndCacheKey {
                 source: Cow::Owned(source),
@@ -288,7 +308,7 @@ impl SyntaxAndCache {
                         .as_deref()
                         .map(|v| Cow::Owned(Arc::clone(v))),
                 });
-                let parsed = Parsed::new(
+                let parsed = Parsed::new_with_recovery(
                     Arc::clone(key.source.as_ref()),
                     key.source_path.as_deref().map(Arc::clone),
                     &self.syntax,
@@ -345,7 +365,14 @@ pub(crate) fn read_config_file(
     config_path: Option<&str>,
     span: Option<Span>,
 ) -> Result<(String, Option<PathBuf>), CompileError> {
-    let root = manifest_root();
+    read_config_file_in(&manifest_root(), config_path, span)
+}
+
+pub(crate) fn read_config_file_in(
+    root: &Path,
+    config_path: Option<&str>,
+    span: Option<Span>,
+) -> Result<(String, Option<PathBuf>), CompileError> {
     let filename = match config_path {
         Some(config_path) => root.join(config_path),
         None => root.join(CONFIG_FILE_NAME),
@@ -382,10 +409,16 @@ static DEFAULT_SYNTAX_NAME: &str = "default";
 static DEFAULT_ESCAPERS: &[(&[&str], &str)] = &[
     (
         &[
-            "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg", "xml",
+            "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg",
         ],
         "Html",
     ),
+    (&["xml"], "Xml"),
+    (&["js", "mjs"], "Js"),
+    (&["json"], "Json"),
+    (&["css"], "Css"),
+    (&["url"], "Url"),
+    (&["latex", "tex"], "Tex"),
     (&["md", "none", "txt", "yml", ""], "Text"),
 ];
 
@@ -665,10 +698,16 @@ mod tests {
                 (str_set(&["js"]), "::my_filters::Js".into()),
                 (
                     str_set(&[
-                        "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg", "xml"
+                        "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg"
                     ]),
                     "askama::filters::Html".into()
                 ),
+                (str_set(&["xml"]), "askama::filters::Xml".into()),
+                (str_set(&["js", "mjs"]), "askama::filters::Js".into()),
+                (str_set(&["json"]), "askama::filters::Json".into()),
+                (str_set(&["css"]), "askama::filters::Css".into()),
+                (str_set(&["url"]), "askama::filters::Url".into()),
+                (str_set(&["latex", "tex"]), "askama::filters::Tex".into()),
                 (
                     str_set(&["md", "none", "txt", "yml", ""]),
                     "askama::filters::Text".into()
@@ -2,6 +2,9 @@
 //! This parses an annotated freestanding function annotated with the `filter_fn` attribute
 //! into an internal intermediate representation (the `FilterSignature` struct).
 //! Then, the output code is generated from said struct.
+//!
+//! Functions annotated with `askama::test_fn` are template tests, e.g. `x is my_test(y)`. They
+//! are generated the same way, as they are invoked the same way as filters.
 
 use std::ops::ControlFlow;
 
@@ -21,14 +24,31 @@ pub fn derive_filter_fn(
     attr: TokenStream,
     item: TokenStream,
     import_askama: fn() -> TokenStream,
+) -> TokenStream {
+    derive_fn(attr, item, import_askama, filter_fn_impl)
+}
+
+pub fn derive_test_fn(
+    attr: TokenStream,
+    item: TokenStream,
+    import_askama: fn() -> TokenStream,
+) -> TokenStream {
+    derive_fn(attr, item, import_askama, test_fn_impl)
+}
+
+fn derive_fn(
+    attr: TokenStream,
+    item: TokenStream,
+    import_askama: fn() -> TokenStream,
+    fn_impl: fn(TokenStream, &ItemFn) -> Result<TokenStream, CompileError>,
 ) -> TokenStream {
     let ffn: ItemFn = match parse_ts_or_compile_error(item, import_askama) {
         ControlFlow::Continue(ffn) => ffn,
         ControlFlow::Break(err) => return err,
     };
-    match filter_fn_impl(attr, &ffn) {
+    match fn_impl(attr, &ffn) {
         Ok(tt) => tt,
-        Err(CompileError { msg, span }) => {
+        Err(CompileError { msg, span, .. }) => {
             let import_askama = import_askama();
             quote_spanned! {
                 span.unwrap_or_else(|| ffn.sig.ident.span()) =>
@@ -715,7 +735,18 @@ fn filter_fn_impl(attr: TokenStream, ffn: &ItemFn) -> Result<TokenStream, Compil
         attr.is_empty(),
         attr.span() => "`#[askama::filter_fn]` does not expect any attributes"
     )?;
+    gen_fn(ffn)
+}
+
+fn test_fn_impl(attr: TokenStream, ffn: &ItemFn) -> Result<TokenStream, CompileError> {
+    p_assert!(
+        attr.is_empty(),
+        attr.span() => "`#[askama::test_fn]` does not expect any attributes"
+    )?;
+    gen_fn(ffn)
+}
 
+fn gen_fn(ffn: &ItemFn) -> Result<TokenStream, CompileError> {
     let fsig = FilterSignature::try_from_signature(&ffn.sig)?;
 
     for gp in &ffn.sig.generics.params {
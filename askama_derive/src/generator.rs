@@ -1,11 +1,15 @@
 mod expr;
 mod filter;
 mod helpers;
+mod html_context;
 mod node;
+mod test;
 
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str;
 use std::sync::Arc;
 
@@ -16,6 +20,7 @@ use quote::{ToTokens, quote_spanned};
 use syn::Token;
 
 use crate::generator::helpers::{clean_path, diff_paths};
+use crate::generator::html_context::{Escaping, HtmlContext};
 use crate::heritage::{Context, Heritage};
 use crate::html::write_escaped_str;
 use crate::input::{Source, TemplateInput};
@@ -55,9 +60,9 @@ pub(crate) fn template_to_string(
 pub(crate) enum TmplKind<'a> {
     /// [`askama::Template`]
     Struct,
-    /// [`askama::helpers::EnumVariantTemplate`]
+    /// [`askama::helpers::TemplateBody`]
     Variant,
-    /// Used in `blocks` implementation
+    /// Used in `blocks` implementation and for named templates
     Block(&'a str),
 }
 
@@ -152,6 +157,7 @@ impl<'a, 'h> Generator<'a, 'h> {
             super_block: None,
             buf_writable: WritableBuffer {
                 discard: buf_writable_discard,
+                html: (input.escaper == "askama::filters::Html").then(HtmlContext::default),
                 ..Default::default()
             },
             is_in_block,
@@ -203,11 +209,6 @@ impl<'a, 'h> Generator<'a, 'h> {
         let ctx = &self.contexts[&self.input.path];
 
         let span = Span::call_site();
-        let target = match tmpl_kind {
-            TmplKind::Struct => quote_spanned!(span=> askama::Template),
-            TmplKind::Variant => quote_spanned!(span=> askama::helpers::EnumVariantTemplate),
-            TmplKind::Block(trait_name) => field_new(trait_name, span),
-        };
 
         let mut paths_ts = TokenStream::new();
 
@@ -253,44 +254,301 @@ impl<'a, 'h> Generator<'a, 'h> {
             _ctx.resolve_path(&path);
         }
 
+        let reload_ts = match tmpl_kind {
+            TmplKind::Struct if self.input.reload => self.impl_reload()?,
+            _ => TokenStream::new(),
+        };
+
+        let mut values_ts = TokenStream::new();
+        if let Some(decls) = self.input.values {
+            let var_values = crate::var_values();
+            let mut checks = TokenStream::new();
+            for decl in decls {
+                let name = decl.key();
+                let ty = &decl.ty;
+                checks.extend(quote_spanned!(span=>
+                    (&&askama::helpers::ValueMarker::<#ty>::new())
+                        .askama_check_value(#var_values, #name)?;
+                ));
+            }
+            values_ts = quote_spanned!(span=> {
+                use askama::helpers::DeclaredValue as _;
+                #checks
+            });
+        }
+
         let mut content = Buffer::new();
         let size_hint = self.impl_template_inner(ctx, &mut content)?;
         let content = content.into_token_stream();
 
-        let mut size_hint_s = TokenStream::new();
+        let var_writer = crate::var_writer();
+        let var_values = crate::var_values();
+        let body = quote_spanned!(span=>
+            #[allow(unused_imports)]
+            use askama::{
+                filters::{AutoEscape as _, WriteWritable as _},
+                helpers::{ResultConverter as _, TemplateWriter as _},
+            };
+
+            #paths_ts
+            #values_ts
+            #content
+            askama::Result::Ok(())
+        );
+
+        if let TmplKind::Block(trait_name) = tmpl_kind {
+            // The block traits are only rendered into a `dyn Write`.
+            write_header(self.input.ast, buf, field_new(trait_name, span));
+            quote_into!(buf, span, { {
+                fn render_into_with_values(
+                    &self,
+                    mut #var_writer: &mut dyn askama::helpers::core::fmt::Write,
+                    #var_values: &dyn askama::Values,
+                ) -> askama::Result<()> {
+                    let #var_writer: &mut dyn askama::helpers::TemplateWriter = &mut #var_writer;
+                    #body
+                }
+            } });
+        } else {
+            // The body takes a `dyn TemplateWriter`, so it is compiled only once, but can be
+            // rendered into a `fmt::Write` and into a `ByteSink` alike.
+            write_header(
+                self.input.ast,
+                buf,
+                quote_spanned!(span=> askama::helpers::TemplateBody),
+            );
+            quote_into!(buf, span, { {
+                fn render_body(
+                    &self,
+                    #var_writer: &mut dyn askama::helpers::TemplateWriter,
+                    #var_values: &dyn askama::Values,
+                ) -> askama::Result<()> {
+                    #body
+                }
+            } });
+        }
+
         if tmpl_kind == TmplKind::Struct {
-            size_hint_s = quote_spanned!(span=>
+            // The interpreter only renders into a `fmt::Write`, so let the byte sink use the
+            // default implementation of `write_bytes_with_values()` if the template is reloaded.
+            let reload_cfg = match self.input.reload {
+                true => quote_spanned!(span=> #[cfg(not(debug_assertions))]),
+                false => TokenStream::new(),
+            };
+            write_header(self.input.ast, buf, quote_spanned!(span=> askama::Template));
+            quote_into!(buf, span, { {
+                #[inline]
+                fn render_into_with_values(
+                    &self,
+                    mut #var_writer: &mut dyn askama::helpers::core::fmt::Write,
+                    #var_values: &dyn askama::Values,
+                ) -> askama::Result<()> {
+                    #reload_ts
+                    <Self as askama::helpers::TemplateBody>::render_body(
+                        self,
+                        &mut #var_writer,
+                        #var_values,
+                    )
+                }
+
+                #reload_cfg
+                #[inline]
+                fn write_bytes_with_values<__AskamaSink: askama::ByteSink + ?askama::helpers::core::marker::Sized>(
+                    &self,
+                    mut #var_writer: &mut __AskamaSink,
+                    #var_values: &dyn askama::Values,
+                ) -> askama::Result<()> {
+                    askama::ByteSink::reserve(#var_writer, #size_hint);
+                    <Self as askama::helpers::TemplateBody>::render_body(
+                        self,
+                        &mut #var_writer,
+                        #var_values,
+                    )
+                }
+
                 const SIZE_HINT: askama::helpers::core::primitive::usize = #size_hint;
-            );
+            } });
+        }
+
+        for block in self.input.blocks {
+            self.impl_block(buf, block)?;
+        }
+        if tmpl_kind == TmplKind::Struct && self.input.render_blocks {
+            self.impl_block_template(buf)?;
+        }
+
+        Ok(size_hint)
+    }
+
+    // In debug builds, let the interpreter render the template once its files were modified.
+    fn impl_reload(&self) -> Result<TokenStream, CompileError> {
+        let span = Span::call_site();
+        let ext = match self.input.escaper {
+            "askama::filters::Html" => "html",
+            "askama::filters::Xml" => "xml",
+            "askama::filters::Js" => "js",
+            "askama::filters::Json" => "json",
+            "askama::filters::Css" => "css",
+            "askama::filters::Url" => "url",
+            "askama::filters::Tex" => "tex",
+            "askama::filters::Text" => "txt",
+            _ => {
+                return Err(CompileError::no_file_info(
+                    "template attribute `reload` only supports the built-in escapers",
+                    None,
+                ));
+            }
+        };
+
+        let path = self.input.path.display().to_string();
+        let mut files = self
+            .contexts
+            .keys()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        files.sort();
+        let dirs = self
+            .input
+            .config
+            .dirs
+            .iter()
+            .map(|dir| dir.display().to_string());
+        let syntax = [
+            self.input.syntax.block_start,
+            self.input.syntax.block_end,
+            self.input.syntax.expr_start,
+            self.input.syntax.expr_end,
+            self.input.syntax.comment_start,
+            self.input.syntax.comment_end,
+        ];
+        let whitespace = match self.input.config.whitespace {
+            Whitespace::Preserve => quote_spanned!(span=> Preserve),
+            Whitespace::Suppress => quote_spanned!(span=> Suppress),
+            Whitespace::Minimize => quote_spanned!(span=> Minimize),
+        };
+
+        // The calls are spanned at the fields, so an unserializable field is reported there.
+        let mut fields = TokenStream::new();
+        if let syn::Data::Struct(data) = &self.input.ast.data {
+            for field in &data.fields {
+                let Some(ident) = &field.ident else {
+                    continue;
+                };
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name);
+                let field_span = ident.span();
+                fields.extend(quote_spanned!(field_span=> .field(#name, &self.#ident)?));
+            }
         }
 
-        write_header(self.input.ast, buf, target);
         let var_writer = crate::var_writer();
         let var_values = crate::var_values();
-        quote_into!(buf, span, { {
-            fn render_into_with_values(
-                &self,
-                #var_writer: &mut dyn askama::helpers::core::fmt::Write,
-                #var_values: &dyn askama::Values,
-            ) -> askama::Result<()> {
-                #[allow(unused_imports)]
-                use askama::{
-                    filters::{AutoEscape as _, WriteWritable as _},
-                    helpers::{ResultConverter as _, core::fmt::Write as _},
+        Ok(quote_spanned!(span=>
+            #[cfg(debug_assertions)]
+            {
+                static TEMPLATE: askama::reload::TemplateFile =
+                    askama::reload::TemplateFile {
+                        path: #path,
+                        files: &[#(#files),*],
+                        dirs: &[#(#dirs),*],
+                        ext: #ext,
+                        syntax: [#(#syntax),*],
+                        whitespace: askama::reload::Whitespace::#whitespace,
+                    };
+                let fields = || {
+                    askama::helpers::core::result::Result::Ok(
+                        askama::reload::Fields::new()
+                            #fields
+                            .finish()
+                    )
                 };
-
-                #paths_ts
-                #content
-                askama::Result::Ok(())
+                if let askama::helpers::core::option::Option::Some(result) =
+                    askama::reload::render(&TEMPLATE, fields, #var_values, #var_writer)
+                {
+                    return result;
+                }
             }
-            #size_hint_s
-        } });
+        ))
+    }
 
-        for block in self.input.blocks {
-            self.impl_block(buf, block)?;
+    // Implements `askama::BlockTemplate`, so every block can be rendered by its name.
+    fn impl_block_template(&self, buf: &mut Buffer) -> Result<(), CompileError> {
+        use syn::Ident;
+
+        let span = Span::call_site();
+        let ident = &self.input.ast.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.ast.generics.split_for_impl();
+
+        let mut names = match self.heritage {
+            Some(heritage) => heritage.blocks.keys().copied().collect::<Vec<_>>(),
+            None => vec![],
+        };
+        names.sort_unstable();
+
+        let mut impls = TokenStream::new();
+        let mut arms = TokenStream::new();
+        for name in &names {
+            let trait_name = format!("__Askama__{ident}__render__{name}");
+            let trait_id = Ident::new(&trait_name, span);
+
+            let input = TemplateInput {
+                block: Some((name, span)),
+                blocks: &[],
+                reload: false,
+                render_blocks: false,
+                ..self.input.clone()
+            };
+            let mut template_buf = Buffer::new();
+            // The blocks are rendered into a `dyn Write`, so their size hints are not used.
+            let _: SizeHint = template_to_string(
+                &mut template_buf,
+                &input,
+                self.contexts,
+                self.heritage,
+                TmplKind::Block(&trait_name),
+            )?;
+            let template_buf = template_buf.into_token_stream();
+
+            impls.extend(quote_spanned!(span=>
+                #template_buf
+
+                pub trait #trait_id {
+                    fn render_into_with_values(
+                        &self,
+                        writer: &mut dyn askama::helpers::core::fmt::Write,
+                        values: &dyn askama::Values,
+                    ) -> askama::Result<()>;
+                }
+            ));
+            arms.extend(quote_spanned!(span=>
+                #name => <Self as #trait_id>::render_into_with_values(self, writer, values),
+            ));
         }
 
-        Ok(size_hint)
+        quote_into!(buf, span, {
+            #[allow(missing_docs, non_camel_case_types, non_snake_case, unreachable_pub)]
+            const _: () = {
+                #impls
+
+                impl #impl_generics askama::BlockTemplate for #ident #ty_generics #where_clause {
+                    const BLOCK_NAMES: &'static [&'static askama::helpers::core::primitive::str] =
+                        &[#(#names),*];
+
+                    fn render_block_into_with_values(
+                        &self,
+                        name: &askama::helpers::core::primitive::str,
+                        writer: &mut dyn askama::helpers::core::fmt::Write,
+                        values: &dyn askama::Values,
+                    ) -> askama::Result<()> {
+                        match name {
+                            #arms
+                            _ => askama::Result::Err(askama::Error::BlockMissing),
+                        }
+                    }
+                }
+            };
+        });
+        Ok(())
     }
 
     fn impl_block(
@@ -298,14 +556,6 @@ impl<'a, 'h> Generator<'a, 'h> {
         buf: &mut Buffer,
         block: &crate::input::Block,
     ) -> Result<(), CompileError> {
-        // RATIONALE: `*self` must be the input type, implementation details should not leak:
-        // - impl Self { fn as_block(self) } ->
-        // - struct __Askama__Self__as__block__Wrapper { this: self } ->
-        // - impl Template for __Askama__Self__as__block__Wrapper { fn render_into_with_values() } ->
-        // - impl __Askama__Self__as__block for Self { render_into_with_values() }
-
-        use syn::{GenericParam, Ident, Lifetime, LifetimeParam, Token};
-
         let span = Span::call_site();
         let ident = &self.input.ast.ident;
 
@@ -313,30 +563,7 @@ impl<'a, 'h> Generator<'a, 'h> {
             "A sub-template that renders only the block `{}` of [`{ident}`].",
             block.name
         );
-        let method_name = format!("as_{}", block.name);
         let trait_name = format!("__Askama__{ident}__as__{}", block.name);
-        let wrapper_name = format!("__Askama__{ident}__as__{}__Wrapper", block.name);
-        let self_lt_name = format!("'__Askama__{ident}__as__{}__self", block.name);
-
-        let method_id = Ident::new(&method_name, span);
-        let trait_id = Ident::new(&trait_name, span);
-        let wrapper_id = Ident::new(&wrapper_name, span);
-        let self_lt = Lifetime::new(&self_lt_name, span);
-
-        // generics of the input with an additional lifetime to capture `self`
-        let mut wrapper_generics = self.input.ast.generics.clone();
-        if wrapper_generics.lt_token.is_none() {
-            wrapper_generics.lt_token = Some(Token![<](span));
-            wrapper_generics.gt_token = Some(Token![>](span));
-        }
-        wrapper_generics.params.insert(
-            0,
-            GenericParam::Lifetime(LifetimeParam::new(self_lt.clone())),
-        );
-
-        let (impl_generics, ty_generics, where_clause) = self.input.ast.generics.split_for_impl();
-        let (wrapper_impl_generics, wrapper_ty_generics, wrapper_where_clause) =
-            wrapper_generics.split_for_impl();
 
         let input = TemplateInput {
             block: Some((&block.name, span)),
@@ -352,80 +579,15 @@ impl<'a, 'h> Generator<'a, 'h> {
             TmplKind::Block(&trait_name),
         )?;
 
-        let template_buf = template_buf.into_token_stream();
-        quote_into!(buf, span, {
-            #[allow(missing_docs, non_camel_case_types, non_snake_case, unreachable_pub)]
-            const _: () = {
-                #template_buf
-
-                pub trait #trait_id {
-                    fn render_into_with_values(
-                        &self,
-                        writer: &mut dyn askama::helpers::core::fmt::Write,
-                        values: &dyn askama::Values,
-                    ) -> askama::Result<()>;
-                }
-
-                impl #impl_generics #ident #ty_generics #where_clause {
-                    #[inline]
-                    #[doc = #doc]
-                    pub fn #method_id(&self) -> impl askama::Template + '_ {
-                        #wrapper_id {
-                            this: self,
-                        }
-                    }
-                }
-
-                #[askama::helpers::core::prelude::rust_2021::derive(
-                    askama::helpers::core::prelude::rust_2021::Clone,
-                    askama::helpers::core::prelude::rust_2021::Copy
-                )]
-                pub struct #wrapper_id #wrapper_generics #wrapper_where_clause {
-                    this: &#self_lt #ident #ty_generics,
-                }
-
-                impl #wrapper_impl_generics askama::Template
-                for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
-                    #[inline]
-                    fn render_into_with_values(
-                        &self,
-                        writer: &mut dyn askama::helpers::core::fmt::Write,
-                        values: &dyn askama::Values
-                    ) -> askama::Result<()> {
-                        <_ as #trait_id>::render_into_with_values(self.this, writer, values)
-                    }
-
-                    const SIZE_HINT: askama::helpers::core::primitive::usize = #size_hint;
-                }
-
-                // cannot use `crate::integrations::impl_fast_writable()` w/o cloning the struct
-                impl #wrapper_impl_generics askama::FastWritable
-                for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
-                    #[inline]
-                    fn write_into(
-                        &self,
-                        dest: &mut dyn askama::helpers::core::fmt::Write,
-                        values: &dyn askama::Values,
-                    ) -> askama::Result<()> {
-                        <_ as askama::Template>::render_into_with_values(self, dest, values)
-                    }
-                }
-
-                // cannot use `crate::integrations::impl_display()` w/o cloning the struct
-                impl #wrapper_impl_generics askama::helpers::core::fmt::Display
-                for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
-                    #[inline]
-                    fn fmt(
-                        &self,
-                        f: &mut askama::helpers::core::fmt::Formatter<'_>
-                    ) -> askama::helpers::core::fmt::Result {
-                        <_ as askama::Template>::render_into(self, f)
-                            .map_err(|_| askama::helpers::core::fmt::Error)
-                    }
-                }
-            };
-        });
-
+        impl_sub_template(
+            buf,
+            self.input.ast,
+            &block.name,
+            &trait_name,
+            &doc,
+            template_buf.into_token_stream(),
+            size_hint,
+        );
         Ok(())
     }
 
@@ -441,6 +603,43 @@ impl<'a, 'h> Generator<'a, 'h> {
             self.input.fields.iter().any(|f| f == var_name)
         }
     }
+
+    /// A `.map_err()` call that attaches the template location of `node` to an error, so it
+    /// can be reported with `askama::Error::location()`.
+    fn located(&self, ctx: &Context<'_>, node: parser::Span) -> TokenStream {
+        let (Some(path), Some(range)) = (ctx.path, node.byte_range()) else {
+            return TokenStream::new();
+        };
+        // Templates defined in rust source only have a fake path.
+        if matches!(self.input.source, Source::Source(_)) && *path == *self.input.path {
+            return TokenStream::new();
+        }
+        let file = self
+            .input
+            .config
+            .dirs
+            .iter()
+            .find_map(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        let before = &ctx.parsed.source()[..range.start];
+        let line = before.matches('\n').count() as u32 + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count() as u32
+            + 1;
+
+        let span = ctx.span_for_node(node);
+        quote_spanned!(span=>
+            .map_err(askama::helpers::located(
+                const { &askama::Location::new(#file, #line, #column) }
+            ))
+        )
+    }
 }
 
 #[cfg(target_pointer_width = "16")]
@@ -601,6 +800,8 @@ fn compile_time_escape<'a>(expr: &WithSpan<Box<Expr<'a>>>, escaper: &str) -> Opt
 struct LocalVariableMeta {
     refs: Option<String>,
     initialized: bool,
+    /// For variables declared with `{% let %}`: set once the variable is read.
+    used: Option<Rc<Cell<bool>>>,
 }
 
 #[derive(Clone)]
@@ -637,6 +838,7 @@ impl<'a> LocalMeta<'a> {
         Self::Variable(LocalVariableMeta {
             refs: None,
             initialized: false,
+            used: None,
         })
     }
 
@@ -645,6 +847,7 @@ impl<'a> LocalMeta<'a> {
         Self::Variable(LocalVariableMeta {
             refs: None,
             initialized: true,
+            used: None,
         })
     }
 
@@ -653,6 +856,7 @@ impl<'a> LocalMeta<'a> {
         Self::Variable(LocalVariableMeta {
             refs: Some(refs),
             initialized: true,
+            used: None,
         })
     }
 
@@ -680,7 +884,10 @@ impl<'a> MapChain<'a> {
     fn get_any<'b>(&'b self, key: &str) -> Option<&'b LocalMeta<'a>> {
         match self.scopes.iter().rev().find_map(|set| set.get(key)) {
             Some(LocalMeta::Negative) => None,
-            Some(local) => Some(local),
+            Some(local) => {
+                self.mark_used(key);
+                Some(local)
+            }
             _ => None,
         }
     }
@@ -727,7 +934,23 @@ impl<'a> MapChain<'a> {
         self.insert(key, LocalMeta::var_decl());
     }
 
+    /// Marks the variable `name` as read, for the `unused_variables` lint.
+    fn mark_used(&self, name: &str) {
+        if let Some(used) = self.get(name).and_then(|meta| meta.used.as_ref()) {
+            used.set(true);
+        }
+    }
+
+    /// Tracks the variable `name`, which was just declared with `{% let %}`, for the
+    /// `unused_variables` lint.
+    fn track(&mut self, name: &str, used: Rc<Cell<bool>>) {
+        if let Some(LocalMeta::Variable(meta)) = self.scopes.last_mut().unwrap().get_mut(name) {
+            meta.used = Some(used);
+        }
+    }
+
     fn resolve(&self, name: &str) -> Option<String> {
+        self.mark_used(name);
         self.get(&Cow::Borrowed(name)).map(|meta| match &meta.refs {
             Some(expr) => expr.clone(),
             None => name.to_string(),
@@ -808,14 +1031,28 @@ enum DisplayWrap {
 struct WritableBuffer<'a> {
     buf: Vec<Writable<'a>>,
     discard: bool,
+    /// The HTML context at the end of the buffer, if the template is HTML escaped
+    html: Option<HtmlContext>,
 }
 
 impl<'a> WritableBuffer<'a> {
     fn push(&mut self, writable: Writable<'a>) {
+        if let Some(html) = &mut self.html {
+            match &writable {
+                Writable::Lit(lit) => html.feed(lit),
+                Writable::Expr(..) => html.skip_expr(),
+            }
+        }
         if !self.discard {
             self.buf.push(writable);
         }
     }
+
+    /// How an expression at the end of the buffer needs to be escaped, if the template is HTML
+    /// escaped
+    fn escaping(&self) -> Option<Escaping> {
+        self.html.as_ref().map(HtmlContext::escaping)
+    }
 }
 
 impl<'a> Deref for WritableBuffer<'a> {
@@ -830,7 +1067,8 @@ impl<'a> Deref for WritableBuffer<'a> {
 #[derive(Debug)]
 enum Writable<'a> {
     Lit(WithSpan<Cow<'a, str>>),
-    Expr(&'a WithSpan<Box<Expr<'a>>>),
+    /// An expression, and how it needs to be escaped in its HTML context
+    Expr(&'a WithSpan<Box<Expr<'a>>>, Option<Escaping>),
 }
 
 macro_rules! make_token_match {
@@ -869,3 +1107,122 @@ fn binary_op(op: &str, span: proc_macro2::Span) -> TokenStream {
         = += -= *= /= %= &= |= ^= <<= >>=
     )
 }
+
+/// Generates the method `as_<name>()` that returns a sub-template implemented by `trait_name`
+///
+/// RATIONALE: `*self` must be the input type, implementation details should not leak:
+/// - impl Self { fn as_name(self) } ->
+/// - struct __Askama__Self__as__name__Wrapper { this: self } ->
+/// - impl Template for __Askama__Self__as__name__Wrapper { fn render_into_with_values() } ->
+/// - impl __Askama__Self__as__name for Self { render_into_with_values() }
+pub(crate) fn impl_sub_template(
+    buf: &mut Buffer,
+    ast: &syn::DeriveInput,
+    name: &str,
+    trait_name: &str,
+    doc: &str,
+    template_buf: TokenStream,
+    size_hint: SizeHint,
+) {
+    use syn::{GenericParam, Ident, Lifetime, LifetimeParam, Token};
+
+    let span = Span::call_site();
+    let ident = &ast.ident;
+
+    let method_name = format!("as_{name}");
+    let wrapper_name = format!("{trait_name}__Wrapper");
+    let self_lt_name = format!("'{trait_name}__self");
+
+    let method_id = Ident::new(&method_name, span);
+    let trait_id = Ident::new(trait_name, span);
+    let wrapper_id = Ident::new(&wrapper_name, span);
+    let self_lt = Lifetime::new(&self_lt_name, span);
+
+    // generics of the input with an additional lifetime to capture `self`
+    let mut wrapper_generics = ast.generics.clone();
+    if wrapper_generics.lt_token.is_none() {
+        wrapper_generics.lt_token = Some(Token![<](span));
+        wrapper_generics.gt_token = Some(Token![>](span));
+    }
+    wrapper_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(self_lt.clone())),
+    );
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let (wrapper_impl_generics, wrapper_ty_generics, wrapper_where_clause) =
+        wrapper_generics.split_for_impl();
+
+    quote_into!(buf, span, {
+        #[allow(missing_docs, non_camel_case_types, non_snake_case, unreachable_pub)]
+        const _: () = {
+            #template_buf
+
+            pub trait #trait_id {
+                fn render_into_with_values(
+                    &self,
+                    writer: &mut dyn askama::helpers::core::fmt::Write,
+                    values: &dyn askama::Values,
+                ) -> askama::Result<()>;
+            }
+
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #[inline]
+                #[doc = #doc]
+                pub fn #method_id(&self) -> impl askama::Template + '_ {
+                    #wrapper_id {
+                        this: self,
+                    }
+                }
+            }
+
+            #[askama::helpers::core::prelude::rust_2021::derive(
+                askama::helpers::core::prelude::rust_2021::Clone,
+                askama::helpers::core::prelude::rust_2021::Copy
+            )]
+            pub struct #wrapper_id #wrapper_generics #wrapper_where_clause {
+                this: &#self_lt #ident #ty_generics,
+            }
+
+            impl #wrapper_impl_generics askama::Template
+            for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
+                #[inline]
+                fn render_into_with_values(
+                    &self,
+                    writer: &mut dyn askama::helpers::core::fmt::Write,
+                    values: &dyn askama::Values
+                ) -> askama::Result<()> {
+                    <_ as #trait_id>::render_into_with_values(self.this, writer, values)
+                }
+
+                const SIZE_HINT: askama::helpers::core::primitive::usize = #size_hint;
+            }
+
+            // cannot use `crate::integrations::impl_fast_writable()` w/o cloning the struct
+            impl #wrapper_impl_generics askama::FastWritable
+            for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
+                #[inline]
+                fn write_into(
+                    &self,
+                    dest: &mut dyn askama::helpers::core::fmt::Write,
+                    values: &dyn askama::Values,
+                ) -> askama::Result<()> {
+                    <_ as askama::Template>::render_into_with_values(self, dest, values)
+                }
+            }
+
+            // cannot use `crate::integrations::impl_display()` w/o cloning the struct
+            impl #wrapper_impl_generics askama::helpers::core::fmt::Display
+            for #wrapper_id #wrapper_ty_generics #wrapper_where_clause {
+                #[inline]
+                fn fmt(
+                    &self,
+                    f: &mut askama::helpers::core::fmt::Formatter<'_>
+                ) -> askama::helpers::core::fmt::Result {
+                    <_ as askama::Template>::render_into(self, f)
+                        .map_err(|_| askama::helpers::core::fmt::Error)
+                }
+            }
+        };
+    });
+}
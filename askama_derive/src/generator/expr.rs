@@ -3,8 +3,8 @@ use std::str::FromStr;
 
 use parser::node::CondTest;
 use parser::{
-    AssociatedItem, CharLit, CharPrefix, Expr, PathComponent, Span, StrLit, StrPrefix, Target,
-    TyGenerics, TyGenericsKind, WithSpan,
+    AssociatedItem, CharLit, CharPrefix, Conditional, Expr, PathComponent, Span, StrLit, StrPrefix,
+    Target, TyGenerics, TyGenericsKind, WithSpan,
 };
 use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
 use quote::quote_spanned;
@@ -82,6 +82,8 @@ impl<'a> Generator<'a, '_> {
             Expr::Filter(ref v) => {
                 self.visit_filter(ctx, buf, &v.name, &v.arguments, expr.span())?
             }
+            Expr::Test(ref v) => self.visit_test(ctx, buf, v, expr.span())?,
+            Expr::Conditional(ref v) => self.visit_conditional(ctx, buf, v, expr.span())?,
             Expr::Unary(op, ref inner) => self.visit_unary(ctx, buf, op, inner, expr.span())?,
             Expr::BinOp(ref v) => self.visit_binop(ctx, buf, v.op, &v.lhs, &v.rhs, expr.span())?,
             Expr::Range(ref v) => {
@@ -198,6 +200,48 @@ impl<'a> Generator<'a, '_> {
         Ok(())
     }
 
+    /// An inline `a if cond else b` is written as a Rust `if`-`else` expression, so both branches
+    /// need to have the same type, like the arms of a `match`. Without an `else` branch, nothing is
+    /// rendered if the condition is `false`.
+    fn visit_conditional(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        expr: &Conditional<'a>,
+        span: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        let span = ctx.span_for_node(span);
+
+        let mut cond = Buffer::new();
+        self.visit_condition(ctx, &mut cond, &expr.cond)?;
+        let cond = cond.into_token_stream();
+
+        let mut then = Buffer::new();
+        let then_wrap = self.visit_expr(ctx, &mut then, &expr.then)?;
+        let then = then.into_token_stream();
+
+        let Some(otherwise) = &expr.otherwise else {
+            quote_into!(buf, span, {
+                askama::helpers::OrEmpty(if #cond {
+                    askama::helpers::core::option::Option::Some(#then)
+                } else {
+                    askama::helpers::core::option::Option::None
+                })
+            });
+            return Ok(then_wrap);
+        };
+
+        let mut tmp = Buffer::new();
+        let otherwise_wrap = self.visit_expr(ctx, &mut tmp, otherwise)?;
+        let otherwise = tmp.into_token_stream();
+
+        quote_into!(buf, span, { (if #cond { #then } else { #otherwise }) });
+        Ok(match (then_wrap, otherwise_wrap) {
+            (DisplayWrap::Wrapped, DisplayWrap::Wrapped) => DisplayWrap::Wrapped,
+            _ => DisplayWrap::Unwrapped,
+        })
+    }
+
     fn visit_is_defined(
         &mut self,
         ctx: &Context<'_>,
@@ -280,10 +324,11 @@ impl<'a> Generator<'a, '_> {
         let span = ctx.span_for_node(expr.span());
         self.visit_expr(ctx, &mut tmp, expr)?;
         let tmp = tmp.into_token_stream();
+        let located = self.located(ctx, expr.span());
 
         quote_into!(buf, span, {
             match (#tmp) {
-                res => (&&askama::helpers::ErrorMarker::of(&res)).askama_conv_result(res)?
+                res => (&&askama::helpers::ErrorMarker::of(&res)).askama_conv_result(res) #located?
             }
         });
         Ok(DisplayWrap::Unwrapped)
@@ -312,12 +357,34 @@ impl<'a> Generator<'a, '_> {
             TokenStream::from_str(*args).unwrap(),
             ctx.span_for_node(args.span()),
         );
+        // The arguments are copied verbatim, but they can still read local variables.
+        self.mark_idents_used(args.clone());
 
         quote_into!(buf, span, { #name !(#args) });
 
         DisplayWrap::Unwrapped
     }
 
+    fn mark_idents_used(&self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => self.locals.mark_used(&ident.to_string()),
+                TokenTree::Group(group) => self.mark_idents_used(group.stream()),
+                // Variables captured by a format string, like `format!("{name}")`.
+                TokenTree::Literal(lit) => {
+                    let lit = lit.to_string();
+                    for arg in lit.split('{').skip(1) {
+                        let end = arg
+                            .find(|c: char| !c.is_alphanumeric() && c != '_')
+                            .unwrap_or(arg.len());
+                        self.locals.mark_used(&arg[..end]);
+                    }
+                }
+                TokenTree::Punct(_) => {}
+            }
+        }
+    }
+
     pub(super) fn visit_value(
         &mut self,
         ctx: &Context<'_>,
@@ -334,10 +401,49 @@ impl<'a> Generator<'a, '_> {
             ));
         };
 
+        // With declared values, string literal keys are checked against the declaration.
+        let decl = match (self.input.values, &***key) {
+            (Some(decls), Expr::StrLit(name)) => {
+                let name = name.content;
+                match decls.iter().find(|decl| decl.key() == name) {
+                    Some(decl) => Some(decl),
+                    None => {
+                        return Err(ctx.generate_error(
+                            format_args!(
+                                "value `{name}` is not declared in `#[template(values(...))]`"
+                            ),
+                            node,
+                        ));
+                    }
+                }
+            }
+            _ => None,
+        };
+
         let Some(generics) = generics else {
-            return Err(
-                ctx.generate_error(format_args!("{kind} expects one generic, found none"), node)
-            );
+            let span = ctx.span_for_node(node);
+            let var_values = crate::var_values();
+            if let Some(decl) = decl {
+                // The type is taken from the declaration.
+                let ty = &decl.ty;
+                let name = decl.key();
+                quote_into!(buf, span, {{
+                    use askama::helpers::DeclaredValue as _;
+                    (&&askama::helpers::ValueMarker::<#ty>::new())
+                        .askama_get_value(#var_values, #name)
+                }});
+                return Ok(DisplayWrap::Unwrapped);
+            }
+            // Without generic, the type is inferred from the typed `askama::Key`.
+            if matches!(***key, Expr::StrLit(_)) {
+                return Err(ctx
+                    .generate_error(format_args!("{kind} expects one generic, found none"), node));
+            }
+            let args = self.visit_arg(ctx, key, span)?;
+            quote_into!(buf, span, {
+                askama::helpers::get_value(&#var_values, &(#args))
+            });
+            return Ok(DisplayWrap::Unwrapped);
         };
         let [r#gen] = generics.as_slice() else {
             return Err(ctx.generate_error(
@@ -357,6 +463,20 @@ impl<'a> Generator<'a, '_> {
         let args = self.visit_arg(ctx, key, span)?;
 
         let ty_generics = ty_generics.into_token_stream();
+        if let Some(decl) = decl {
+            // Let the compiler check that the generic matches the declared type.
+            let ty = &decl.ty;
+            let name = decl.key();
+            let var_values = crate::var_values();
+            quote_into!(buf, span, {{
+                use askama::helpers::DeclaredValue as _;
+                let __askama_value: askama::Result<&#ty_generics> =
+                    (&&askama::helpers::ValueMarker::<#ty>::new())
+                        .askama_get_value(#var_values, #name);
+                __askama_value
+            }});
+            return Ok(DisplayWrap::Unwrapped);
+        }
         let var_values = crate::var_values();
         quote_into!(buf, span, {
             askama::helpers::get_value::<#ty_generics>(&#var_values, &(#args))
@@ -589,6 +709,13 @@ impl<'a> Generator<'a, '_> {
         left: &WithSpan<Box<Expr<'a>>>,
         args: &[WithSpan<Box<Expr<'a>>>],
     ) -> Result<DisplayWrap, CompileError> {
+        if ***left == Expr::Var("cfg") && !self.is_var_defined("cfg") {
+            return Err(ctx.generate_error(
+                "`cfg(…)` can only be used as the whole condition of an `if` or `elif` block",
+                left.span(),
+            ));
+        }
+
         // ensure that no named args are used in normal rust call expressions
         if let Some(arg) = args
             .iter()
@@ -5,14 +5,14 @@ use std::ptr;
 use std::str::FromStr;
 
 use parser::{
-    Expr, IntKind, Num, PathComponent, PathOrIdentifier, Span, StrLit, StrPrefix, TyGenerics,
-    WithSpan,
+    AssociatedItem, Expr, IntKind, Num, PathComponent, PathOrIdentifier, Span, StrLit, StrPrefix,
+    Test, TyGenerics, WithSpan,
 };
 use proc_macro2::TokenStream;
 use quote::{ToTokens, format_ident, quote_spanned};
 use syn::Token;
 
-use super::{DisplayWrap, Generator, TargetIsize, TargetUsize};
+use super::{DisplayWrap, Generator, LocalMeta, TargetIsize, TargetUsize};
 use crate::heritage::Context;
 use crate::integration::Buffer;
 use crate::{CompileError, MsgValidEscapers, field_new, fmt_left, fmt_right, quote_into};
@@ -35,24 +35,36 @@ impl<'a> Generator<'a, '_> {
         };
         let filter = match *name {
             "assigned_or" => Self::visit_assigned_or,
+            "batch" => Self::visit_batch_filter,
             "center" => Self::visit_center_filter,
             "default" => Self::visit_default_filter,
             "defined_or" => Self::visit_defined_or,
             "deref" => Self::visit_deref_filter,
             "escape" | "e" => Self::visit_escape_filter,
             "filesizeformat" => Self::visit_humansize,
+            "first" => Self::visit_first_filter,
             "fmt" => Self::visit_fmt_filter,
             "format" => Self::visit_format_filter,
+            "groupby" => Self::visit_groupby_filter,
             "indent" => Self::visit_indent_filter,
             "join" => Self::visit_join_filter,
             "json" | "tojson" => Self::visit_json_filter,
+            "last" => Self::visit_last_filter,
             "linebreaks" => Self::visit_linebreaks_filter,
             "linebreaksbr" => Self::visit_linebreaksbr_filter,
+            "map" => Self::visit_map_filter,
+            "max" => Self::visit_max_filter,
+            "min" => Self::visit_min_filter,
             "paragraphbreaks" => Self::visit_paragraphbreaks_filter,
             "pluralize" => Self::visit_pluralize_filter,
             "ref" => Self::visit_ref_filter,
             "reject" => Self::visit_reject_filter,
             "safe" => Self::visit_safe_filter,
+            "sanitize" => Self::visit_sanitize_filter,
+            "selectattr" => Self::visit_selectattr_filter,
+            "slice" => Self::visit_slice_filter,
+            "sort" => Self::visit_sort_filter,
+            "sum" => Self::visit_sum_filter,
             "truncate" => Self::visit_truncate_filter,
             "urlencode" => Self::visit_urlencode_filter,
             "urlencode_strict" => Self::visit_urlencode_strict_filter,
@@ -73,7 +85,7 @@ impl<'a> Generator<'a, '_> {
         filter(self, ctx, buf, args, node)
     }
 
-    fn visit_custom_filter_with_path(
+    pub(super) fn visit_custom_filter_with_path(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
@@ -131,13 +143,14 @@ impl<'a> Generator<'a, '_> {
         // call execute() on filter invocation builder - pass in input and askama runtime args
         let input_expr = self.visit_arg(ctx, &args[0], ctx.span_for_node(args[0].span()))?;
         let var_values = crate::var_values();
+        let located = self.located(ctx, node);
 
         quote_into!(buf, span, {{
             #assertion_block
             askama::filters::ValidFilterInvocation::wrap(
                 #filter_path::default()
                     #arg_setter_invocations
-            ).execute(#input_expr, #var_values)?
+            ).execute(#input_expr, #var_values) #located?
         }});
 
         Ok(DisplayWrap::Unwrapped)
@@ -210,7 +223,8 @@ impl<'a> Generator<'a, '_> {
             self.visit_call_generics(ctx, buf, generics);
         }
         let arg = self.visit_arg(ctx, arg, span)?;
-        quote_into!(buf, span, { (#arg)? });
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { (#arg) #located? });
         Ok(DisplayWrap::Unwrapped)
     }
 
@@ -254,9 +268,10 @@ impl<'a> Generator<'a, '_> {
         let arg = self.visit_arg(ctx, arg, span)?;
 
         let name = quote::format_ident!("{name}");
+        let located = self.located(ctx, node);
         // Both filters return HTML-safe strings.
         quote_into!(buf, span, {
-            askama::filters::HtmlSafeOutput(askama::filters::#name(#arg)?)
+            askama::filters::HtmlSafeOutput(askama::filters::#name(#arg) #located?)
         });
         Ok(DisplayWrap::Unwrapped)
     }
@@ -311,13 +326,14 @@ impl<'a> Generator<'a, '_> {
         let source = self.visit_arg(ctx, source, ctx.span_for_node(source.span()))?;
         let precision = self.visit_arg(ctx, precision, ctx.span_for_node(precision.span()))?;
 
+        let located = self.located(ctx, node);
         // All filters return numbers, and any default formatted number is HTML safe.
         quote_into!(buf, ctx.span_for_node(node), {
             askama::filters::HtmlSafeOutput(
                 askama::filters::filesizeformat(
                     askama::helpers::get_primitive_value(&(#source)) as askama::helpers::core::primitive::u128,
                     askama::helpers::get_primitive_value(&(#precision)) as askama::helpers::core::primitive::u8
-                )?
+                ) #located?
             )
         });
         Ok(DisplayWrap::Unwrapped)
@@ -346,21 +362,449 @@ impl<'a> Generator<'a, '_> {
             let arg = self.visit_arg(ctx, filter, ctx.span_for_node(filter.span()))?;
 
             let tmp = tmp.into_token_stream();
-            quote_into!(buf, span, { askama::filters::reject_with(#tmp, #arg)? });
+            let located = self.located(ctx, node);
+            quote_into!(buf, span, { askama::filters::reject_with(#tmp, #arg) #located? });
         } else {
             self.visit_loop_iter(ctx, &mut tmp, input)?;
             let arg = self.visit_arg(ctx, filter, ctx.span_for_node(filter.span()))?;
 
             let tmp = tmp.into_token_stream();
+            let located = self.located(ctx, node);
             quote_into!(buf, span, {
                 // coerce [T, &T, &&T...] to &T
-                askama::filters::reject(#tmp, (&&&(#arg)) as &_)?
+                askama::filters::reject(#tmp, (&&&(#arg)) as &_) #located?
             });
         }
 
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn visit_first_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.visit_first_last_filter(ctx, buf, "first", args, node)
+    }
+
+    fn visit_last_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.visit_first_last_filter(ctx, buf, "last", args, node)
+    }
+
+    fn visit_first_last_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        let input = no_arguments(ctx, name, args)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let span = ctx.span_for_node(node);
+        let name = field_new(name, span);
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::#name(#iter) #located? });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_min_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.visit_min_max_filter(ctx, buf, "min", args, node)
+    }
+
+    fn visit_max_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.visit_min_max_filter(ctx, buf, "max", args, node)
+    }
+
+    fn visit_min_max_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 2] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "attribute",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+
+        let [input, attribute] = collect_filter_args(ctx, name, node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let span = ctx.span_for_node(node);
+        let located = self.located(ctx, node);
+        if is_argument_placeholder(attribute) {
+            let name = field_new(name, span);
+            quote_into!(buf, span, { askama::filters::#name(#iter) #located? });
+        } else {
+            let key = self.visit_attribute_closure(ctx, name, attribute)?;
+            let name = format_ident!("{name}_by_key", span = span);
+            quote_into!(buf, span, { askama::filters::#name(#iter, #key) #located? });
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_sum_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 3] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "attribute",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+            &FilterArgument {
+                name: "start",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+
+        let [input, attribute, start] = collect_filter_args(ctx, "sum", node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let mut iter = iter.into_token_stream();
+        let span = ctx.span_for_node(node);
+        if !is_argument_placeholder(attribute) {
+            let map = self.visit_attribute_closure(ctx, "sum", attribute)?;
+            iter = quote_spanned!(span => askama::helpers::core::iter::Iterator::map(#iter, #map));
+        }
+        let start = match is_argument_placeholder(start) {
+            true => quote_spanned!(span => 0),
+            false => self.visit_expr_to_tokens(ctx, start)?,
+        };
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::sum(#iter, #start) #located? });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_map_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 2] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "attribute",
+                default_value: None,
+            },
+        ];
+
+        let [input, attribute] = collect_filter_args(ctx, "map", node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let map = self.visit_attribute_closure(ctx, "map", attribute)?;
+        let span = ctx.span_for_node(node);
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::map(#iter, #map) #located? });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_selectattr_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 4] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "attribute",
+                default_value: None,
+            },
+            &FilterArgument {
+                name: "test",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+            &FilterArgument {
+                name: "value",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+
+        let [input, attribute, test, value] =
+            collect_filter_args(ctx, "selectattr", node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let span = ctx.span_for_node(node);
+        let field = attribute_access(ctx, "selectattr", attribute)?;
+        let predicate = if is_argument_placeholder(test) {
+            if !is_argument_placeholder(value) {
+                return Err(ctx.generate_error(
+                    "`selectattr` filter needs a `test` to compare the `value` with",
+                    value.span(),
+                ));
+            }
+            let field = self.visit_elem_expr(ctx, &field)?;
+            quote_spanned!(span => askama::helpers::as_bool(&(#field)))
+        } else {
+            let Expr::StrLit(StrLit {
+                prefix: None,
+                content,
+                ..
+            }) = ***test
+            else {
+                return Err(ctx.generate_error(
+                    "`selectattr` filter expects the name of a test as string literal, \
+                     like `\"odd\"`",
+                    test.span(),
+                ));
+            };
+            if !is_identifier(content) {
+                return Err(ctx.generate_error(
+                    format_args!("invalid test name {content:?} for `selectattr` filter"),
+                    test.span(),
+                ));
+            }
+            let mut arguments = vec![field];
+            if !is_argument_placeholder(value) {
+                arguments.push(value.clone());
+            }
+            let test = Test {
+                name: PathOrIdentifier::Identifier(WithSpan::new(content, test.span())),
+                arguments,
+                negated: false,
+            };
+            let mut tmp = Buffer::new();
+            self.push_locals(|this| {
+                this.locals
+                    .insert(Cow::Borrowed(VAR_ELEM), LocalMeta::var_def());
+                this.visit_test(ctx, &mut tmp, &test, node)
+            })?;
+            tmp.into_token_stream()
+        };
+
+        let elem = crate::var_elem();
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, {
+            askama::filters::selectattr(#iter, |#elem| #predicate) #located?
+        });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_sort_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 3] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "reverse",
+                default_value: Some(FALSE),
+            },
+            &FilterArgument {
+                name: "attribute",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+
+        ensure_filter_has_feature_alloc(ctx, "sort", node)?;
+        let [input, reverse, attribute] = collect_filter_args(ctx, "sort", node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let reverse = self.visit_arg(ctx, reverse, ctx.span_for_node(reverse.span()))?;
+        let span = ctx.span_for_node(node);
+        let located = self.located(ctx, node);
+        if is_argument_placeholder(attribute) {
+            quote_into!(buf, span, {
+                askama::filters::sort(#iter, askama::helpers::as_bool(&(#reverse))) #located?
+            });
+        } else {
+            let key = self.visit_attribute_closure(ctx, "sort", attribute)?;
+            quote_into!(buf, span, {
+                askama::filters::sort_by_key(
+                    #iter,
+                    #key,
+                    askama::helpers::as_bool(&(#reverse))
+                ) #located?
+            });
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_groupby_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 2] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "attribute",
+                default_value: None,
+            },
+        ];
+
+        ensure_filter_has_feature_alloc(ctx, "groupby", node)?;
+        let [input, attribute] = collect_filter_args(ctx, "groupby", node, args, ARGUMENTS)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let key = self.visit_attribute_closure(ctx, "groupby", attribute)?;
+        let span = ctx.span_for_node(node);
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::groupby(#iter, #key) #located? });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_batch_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 3] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "linecount",
+                default_value: None,
+            },
+            &FilterArgument {
+                name: "fill_with",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+        self.visit_batch_slice_filter(ctx, buf, "batch", ARGUMENTS, args, node)
+    }
+
+    fn visit_slice_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 3] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "slices",
+                default_value: None,
+            },
+            &FilterArgument {
+                name: "fill_with",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+        self.visit_batch_slice_filter(ctx, buf, "slice", ARGUMENTS, args, node)
+    }
+
+    fn visit_batch_slice_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        arguments: &'static [&'static FilterArgument; 3],
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        ensure_filter_has_feature_alloc(ctx, name, node)?;
+        let [input, count, fill_with] = collect_filter_args(ctx, name, node, args, arguments)?;
+        let mut iter = Buffer::new();
+        self.visit_loop_iter(ctx, &mut iter, input)?;
+
+        let iter = iter.into_token_stream();
+        let count = self.visit_arg(ctx, count, ctx.span_for_node(count.span()))?;
+        let span = ctx.span_for_node(node);
+        let fill_with = if is_argument_placeholder(fill_with) {
+            quote_spanned!(span => askama::helpers::core::option::Option::None)
+        } else {
+            let fill_with = self.visit_expr_to_tokens(ctx, fill_with)?;
+            quote_spanned!(span =>
+                // coerce [T, &T, &&T...] to &T; unlike an `as` cast, the argument coercion
+                // happens before the type of an integer literal falls back to `i32`
+                askama::helpers::core::option::Option::Some(&&&(#fill_with))
+            )
+        };
+        let name = field_new(name, span);
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::#name(
+                #iter,
+                askama::helpers::core::primitive::usize::try_from(
+                    askama::helpers::get_primitive_value(&(#count))
+                ).map_err(|_| askama::Error::Fmt) #located?,
+                #fill_with
+            ) #located? });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    /// Returns the closure `|item| &item.attribute`, that is used by filters like
+    /// `map(attribute = "name")`.
+    fn visit_attribute_closure(
+        &mut self,
+        ctx: &Context<'_>,
+        name: &str,
+        attribute: &WithSpan<Box<Expr<'a>>>,
+    ) -> Result<TokenStream, CompileError> {
+        let field = attribute_access(ctx, name, attribute)?;
+        let field = self.visit_elem_expr(ctx, &field)?;
+        let span = ctx.span_for_node(attribute.span());
+        let elem = crate::var_elem();
+        Ok(quote_spanned!(span => |#elem| &#field))
+    }
+
+    /// Visits an expression returned by [`attribute_access()`].
+    fn visit_elem_expr(
+        &mut self,
+        ctx: &Context<'_>,
+        expr: &WithSpan<Box<Expr<'a>>>,
+    ) -> Result<TokenStream, CompileError> {
+        self.push_locals(|this| {
+            this.locals
+                .insert(Cow::Borrowed(VAR_ELEM), LocalMeta::var_def());
+            this.visit_expr_to_tokens(ctx, expr)
+        })
+    }
+
     fn visit_pluralize_filter(
         &mut self,
         ctx: &Context<'_>,
@@ -410,7 +854,8 @@ impl<'a> Generator<'a, '_> {
             self.visit_auto_escaped_arg(ctx, &mut pl_buf, pl)?;
             let sg = sg_buf.into_token_stream();
             let pl = pl_buf.into_token_stream();
-            quote_into!(buf, span, { askama::filters::pluralize(#arg, #sg, #pl)? });
+            let located = self.located(ctx, node);
+            quote_into!(buf, span, { askama::filters::pluralize(#arg, #sg, #pl) #located? });
         }
         Ok(DisplayWrap::Wrapped)
     }
@@ -458,11 +903,12 @@ impl<'a> Generator<'a, '_> {
         let span = ctx.span_for_node(node);
 
         let name = quote::format_ident!("{name}");
+        let located = self.located(ctx, node);
         quote_into!(buf, span, { askama::filters::#name(
            &(&&askama::filters::AutoEscaper::new(&(
                #arg
            // The input is always HTML escaped, regardless of the selected escaper:
-           ), askama::filters::Html)).askama_auto_escape()?)?
+           ), askama::filters::Html)).askama_auto_escape()?) #located?
         });
         // The output is marked as HTML safe, not safe in all contexts:
         Ok(DisplayWrap::Unwrapped)
@@ -518,13 +964,14 @@ impl<'a> Generator<'a, '_> {
 
         let [value, indent] = collect_filter_args(ctx, "json", node, args, ARGUMENTS)?;
         let span = ctx.span_for_node(node);
+        let located = self.located(ctx, node);
         if is_argument_placeholder(indent) {
             let arg = self.visit_arg(ctx, value, ctx.span_for_node(value.span()))?;
-            quote_into!(buf, span, { askama::filters::json(#arg)? });
+            quote_into!(buf, span, { askama::filters::json(#arg) #located? });
         } else {
             let value = self.visit_arg(ctx, value, ctx.span_for_node(value.span()))?;
             let indent = self.visit_arg(ctx, indent, ctx.span_for_node(indent.span()))?;
-            quote_into!(buf, span, { askama::filters::json_pretty(#value, #indent)? });
+            quote_into!(buf, span, { askama::filters::json_pretty(#value, #indent) #located? });
         }
         Ok(DisplayWrap::Unwrapped)
     }
@@ -559,6 +1006,7 @@ impl<'a> Generator<'a, '_> {
         let indent = self.visit_arg(ctx, indent, ctx.span_for_node(indent.span()))?;
         let first = self.visit_arg(ctx, first, ctx.span_for_node(first.span()))?;
         let blank = self.visit_arg(ctx, blank, ctx.span_for_node(blank.span()))?;
+        let located = self.located(ctx, node);
 
         quote_into!(buf, ctx.span_for_node(node), {
             askama::filters::indent(
@@ -566,7 +1014,7 @@ impl<'a> Generator<'a, '_> {
                 #indent,
                 askama::helpers::as_bool(&(#first)),
                 askama::helpers::as_bool(&(#blank))
-            )?
+            ) #located?
         });
         Ok(DisplayWrap::Unwrapped)
     }
@@ -583,7 +1031,53 @@ impl<'a> Generator<'a, '_> {
 
         let span = ctx.span_for_node(node);
         let escaper = TokenStream::from_str(self.input.escaper).unwrap();
-        quote_into!(buf, span, { askama::filters::safe(#arg, #escaper)? });
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::safe(#arg, #escaper) #located? });
+        Ok(DisplayWrap::Wrapped)
+    }
+
+    fn visit_sanitize_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        const ARGUMENTS: &[&FilterArgument; 3] = &[
+            FILTER_SOURCE,
+            &FilterArgument {
+                name: "tags",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+            &FilterArgument {
+                name: "attributes",
+                default_value: Some(ARGUMENT_PLACEHOLDER),
+            },
+        ];
+
+        ensure_filter_has_feature_alloc(ctx, "sanitize", node)?;
+        let [source, tags, attributes] =
+            collect_filter_args(ctx, "sanitize", node, args, ARGUMENTS)?;
+        let source = self.visit_arg(ctx, source, ctx.span_for_node(source.span()))?;
+        let span = ctx.span_for_node(node);
+        let tags = if is_argument_placeholder(tags) {
+            quote_spanned!(span => askama::filters::DEFAULT_SANITIZE_TAGS)
+        } else {
+            let tags = self.visit_arg(ctx, tags, ctx.span_for_node(tags.span()))?;
+            quote_spanned!(span => &(#tags))
+        };
+        let attributes = if is_argument_placeholder(attributes) {
+            quote_spanned!(span => askama::filters::DEFAULT_SANITIZE_ATTRIBUTES)
+        } else {
+            let attributes =
+                self.visit_arg(ctx, attributes, ctx.span_for_node(attributes.span()))?;
+            quote_spanned!(span => &(#attributes))
+        };
+        let located = self.located(ctx, node);
+
+        quote_into!(buf, span, {
+            askama::filters::sanitize(#source, #tags, #attributes) #located?
+        });
         Ok(DisplayWrap::Wrapped)
     }
 
@@ -654,7 +1148,8 @@ impl<'a> Generator<'a, '_> {
         let source = self.visit_arg(ctx, source, ctx.span_for_node(source.span()))?;
         let span = ctx.span_for_node(node);
         let escaper = TokenStream::from_str(escaper).unwrap();
-        quote_into!(buf, span, { askama::filters::escape(#source, #escaper)? });
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::escape(#source, #escaper) #located? });
         Ok(DisplayWrap::Wrapped)
     }
 
@@ -735,13 +1230,18 @@ impl<'a> Generator<'a, '_> {
         ];
 
         let [iterable, separator] = collect_filter_args(ctx, "join", node, args, ARGUMENTS)?;
+        // The output of another filter, e.g. `users|map(attribute = "name")`, can be an iterator,
+        // so it cannot be borrowed.
+        let is_filter = matches!(***iterable, Expr::Filter(_));
         let iterable = self.visit_arg(ctx, iterable, ctx.span_for_node(iterable.span()))?;
         let separator = self.visit_arg(ctx, separator, ctx.span_for_node(separator.span()))?;
         let span = ctx.span_for_node(node);
-        quote_into!(buf, span, { askama::filters::join(
-                (&(#iterable)).into_iter(),
-                #separator
-            )? });
+        let iterable = match is_filter {
+            true => quote_spanned!(span => (#iterable).into_iter()),
+            false => quote_spanned!(span => (&(#iterable)).into_iter()),
+        };
+        let located = self.located(ctx, node);
+        quote_into!(buf, span, { askama::filters::join(#iterable, #separator) #located? });
         Ok(DisplayWrap::Unwrapped)
     }
 
@@ -787,12 +1287,13 @@ impl<'a> Generator<'a, '_> {
         let length = self.visit_arg(ctx, length, ctx.span_for_node(length.span()))?;
         let span = ctx.span_for_node(node);
         let name = quote::format_ident!("{name}");
+        let located = self.located(ctx, node);
         quote_into!(buf, span, { askama::filters::#name(
                 #arg,
                 askama::helpers::core::primitive::usize::try_from(
                     askama::helpers::get_primitive_value(&(#length))
-                ).map_err(|_| askama::Error::Fmt)?
-            )? });
+                ).map_err(|_| askama::Error::Fmt) #located?
+            ) #located? });
         Ok(DisplayWrap::Unwrapped)
     }
 
@@ -876,8 +1377,9 @@ impl<'a> Generator<'a, '_> {
         )?);
         let fallback_buf = fallback_buf.into_token_stream();
 
+        let located = self.located(ctx, node);
         quote_into!(buf, ctx.span_for_node(node), {
-            askama::filters::assigned_or(&(#value_buf), #fallback_buf)?
+            askama::filters::assigned_or(&(#value_buf), #fallback_buf) #located?
         });
 
         Ok(DisplayWrap::Unwrapped)
@@ -1073,6 +1575,55 @@ fn is_argument_placeholder(arg: &WithSpan<Box<Expr<'_>>>) -> bool {
     matches!(***arg, Expr::ArgumentPlaceholder)
 }
 
+/// The name of the closure argument in e.g. `map(attribute = "name")`, see [`crate::var_elem()`]
+const VAR_ELEM: &str = "__askama_elem";
+
+/// Parses an `attribute` argument like `"address.city"` into the field access
+/// `__askama_elem.address.city`.
+fn attribute_access<'a>(
+    ctx: &Context<'_>,
+    name: &str,
+    attribute: &WithSpan<Box<Expr<'a>>>,
+) -> Result<WithSpan<Box<Expr<'a>>>, CompileError> {
+    let span = attribute.span();
+    let Expr::StrLit(StrLit {
+        prefix: None,
+        content,
+        ..
+    }) = ***attribute
+    else {
+        return Err(ctx.generate_error(
+            format_args!(
+                "`{name}` filter expects the name of a field as string literal, \
+                 like `\"name\"` or `\"address.city\"`"
+            ),
+            span,
+        ));
+    };
+
+    let mut expr = WithSpan::new(Box::new(Expr::Var(VAR_ELEM)), span);
+    for field in content.split('.') {
+        let is_index = !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit());
+        if !is_identifier(field) && !is_index {
+            return Err(ctx.generate_error(
+                format_args!("invalid field name {content:?} for `{name}` filter"),
+                span,
+            ));
+        }
+        let item = AssociatedItem {
+            name: WithSpan::new(field, span),
+            generics: None,
+        };
+        expr = WithSpan::new(Box::new(Expr::AssociatedItem(expr, item)), span);
+    }
+    Ok(expr)
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
 fn no_arguments<'a, 'b>(
     ctx: &Context<'_>,
     name: &str,
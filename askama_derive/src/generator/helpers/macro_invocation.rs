@@ -34,6 +34,7 @@ impl<'a, 'b> MacroInvocation<'a, 'b> {
         generator: &mut Generator<'a, 'h>,
         render_for: RenderFor,
     ) -> Result<SizeHint, CompileError> {
+        generator.input.lints.call_macro(self.macro_def);
         if generator
             .seen_callers
             .iter()
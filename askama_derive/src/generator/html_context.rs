@@ -0,0 +1,805 @@
+//! Tracks the HTML context of the expressions in a template, so that they can be escaped
+//! accordingly, e.g. as JavaScript inside of a `<script>` element, or rejected if no escaping
+//! could make them safe, e.g. as the name of an attribute.
+//!
+//! The tracker only ever sees the literal text of the template. The output of expressions is
+//! assumed not to change the context, which is true for all escaped output.
+
+use std::mem;
+
+/// How an expression needs to be escaped in the context it is used in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Escaping {
+    /// HTML text, comments, and normal attribute values
+    Html,
+    /// Inside of a JavaScript string, template literal, regular expression or comment
+    Js,
+    /// Where a JavaScript expression is expected
+    JsValue { in_attr: bool },
+    /// Inside of a `<style>` element or a `style` attribute
+    Css,
+    /// At the start of a URL attribute like `href`, where the scheme must be checked
+    Url,
+    /// No escaping can make the output safe in this context
+    Unsafe(&'static str),
+}
+
+const IN_TAG: &str = "expressions cannot be used inside an HTML tag outside of a quoted attribute \
+    value; use the `|safe` filter if this is intended";
+const UNQUOTED_ATTR: &str = "expressions cannot be used in unquoted HTML attribute values; \
+    put the value in quotation marks, e.g. `name=\"{{ value }}\"`";
+const AMBIGUOUS: &str = "the HTML context of this expression is ambiguous, because the \
+    preceding branches of an `if`, `match` or `for` block end in different contexts";
+
+/// The HTML context at the current position of a template
+///
+/// After a block like `{% if %}` or `{% match %}` whose branches end in different contexts, the
+/// tracker has to follow all of them until they converge again. Expressions are only accepted
+/// if all possible contexts need the same escaping.
+#[derive(Debug, Clone)]
+pub(crate) struct HtmlContext {
+    states: Vec<State>,
+}
+
+impl Default for HtmlContext {
+    fn default() -> Self {
+        Self {
+            states: vec![State::Text],
+        }
+    }
+}
+
+impl PartialEq for HtmlContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.states.len() == other.states.len()
+            && self.states.iter().all(|state| other.states.contains(state))
+    }
+}
+
+impl HtmlContext {
+    /// Advances the context over the literal text `text`
+    pub(crate) fn feed(&mut self, text: &str) {
+        for state in &mut self.states {
+            for c in text.chars() {
+                state.feed(c);
+            }
+        }
+        self.dedup();
+    }
+
+    /// Returns how an expression needs to be escaped at the current position
+    pub(crate) fn escaping(&self) -> Escaping {
+        let mut states = self.states.iter().map(State::escaping);
+        let escaping = states.next().unwrap_or(Escaping::Html);
+        match states.all(|other| other == escaping) {
+            true => escaping,
+            false => Escaping::Unsafe(AMBIGUOUS),
+        }
+    }
+
+    /// Advances the context over the (escaped) output of an expression
+    pub(crate) fn skip_expr(&mut self) {
+        for state in &mut self.states {
+            state.skip_expr();
+        }
+        self.dedup();
+    }
+
+    /// Adds the possible states of `other`, e.g. for the end of another branch of an `if` block
+    pub(crate) fn merge(&mut self, other: Self) {
+        self.states.extend(other.states);
+        self.dedup();
+    }
+
+    fn dedup(&mut self) {
+        let mut i = 1;
+        while i < self.states.len() {
+            if self.states[..i].contains(&self.states[i]) {
+                self.states.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Merges the contexts at the end of two branches, if the context is tracked at all
+pub(crate) fn merge(a: Option<HtmlContext>, b: Option<HtmlContext>) -> Option<HtmlContext> {
+    match (a, b) {
+        (Some(mut a), Some(b)) => {
+            a.merge(b);
+            Some(a)
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+/// A simplified version of the states of the [HTML tokenizer].
+///
+/// [HTML tokenizer]: https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After `<`
+    TagOpen,
+    /// After `</`
+    EndTagOpen,
+    /// After `<!`, with the number of `-` read so far
+    MarkupDeclaration(u8),
+    /// Inside of `<!-- … -->`, with the number of trailing `-` read so far
+    Comment(u8),
+    /// End tags, `<!DOCTYPE …>`, `<?…>`: everything up to the next `>` is ignored
+    Bogus,
+    TagName(String),
+    BeforeAttrName(Tag),
+    AttrName(Tag, String),
+    AfterAttrName(Tag, String),
+    BeforeAttrValue(Tag, Attr),
+    AttrValue(Tag, Attr, Quote),
+    /// After `/` in a start tag
+    SelfClosingStartTag(Tag),
+    /// The content of an element like `<script>` or `<textarea>` that can only be ended by its
+    /// end tag, with the number of characters of `</name` matched so far
+    RawText(RawText, usize),
+}
+
+impl State {
+    fn feed(&mut self, c: char) {
+        *self = match mem::replace(self, State::Text) {
+            State::Text => match c {
+                '<' => State::TagOpen,
+                _ => State::Text,
+            },
+            State::TagOpen => match c {
+                'a'..='z' | 'A'..='Z' => State::TagName(c.to_ascii_lowercase().into()),
+                '/' => State::EndTagOpen,
+                '!' => State::MarkupDeclaration(0),
+                '?' => State::Bogus,
+                '<' => State::TagOpen,
+                _ => State::Text,
+            },
+            State::EndTagOpen => match c {
+                '>' => State::Text,
+                _ => State::Bogus,
+            },
+            State::MarkupDeclaration(0) if c == '-' => State::MarkupDeclaration(1),
+            State::MarkupDeclaration(_) if c == '-' => State::Comment(0),
+            State::MarkupDeclaration(_) | State::Bogus => match c {
+                '>' => State::Text,
+                _ => State::Bogus,
+            },
+            State::Comment(dashes) => match c {
+                '-' => State::Comment((dashes + 1).min(2)),
+                '>' if dashes == 2 => State::Text,
+                _ => State::Comment(0),
+            },
+            State::TagName(mut name) => match c {
+                '/' => State::SelfClosingStartTag(Tag::new(&name)),
+                '>' => Tag::new(&name).content(),
+                c if is_html_whitespace(c) => State::BeforeAttrName(Tag::new(&name)),
+                c => {
+                    name.push(c.to_ascii_lowercase());
+                    State::TagName(name)
+                }
+            },
+            State::BeforeAttrName(tag) => match c {
+                '/' => State::SelfClosingStartTag(tag),
+                '>' => tag.content(),
+                c if is_html_whitespace(c) => State::BeforeAttrName(tag),
+                c => State::AttrName(tag, c.to_ascii_lowercase().into()),
+            },
+            State::AttrName(tag, mut name) => match c {
+                '/' => State::SelfClosingStartTag(tag),
+                '>' => tag.content(),
+                '=' => {
+                    let attr = Attr::new(&tag, &name);
+                    State::BeforeAttrValue(tag, attr)
+                }
+                c if is_html_whitespace(c) => State::AfterAttrName(tag, name),
+                c => {
+                    name.push(c.to_ascii_lowercase());
+                    State::AttrName(tag, name)
+                }
+            },
+            State::AfterAttrName(tag, name) => match c {
+                '/' => State::SelfClosingStartTag(tag),
+                '>' => tag.content(),
+                '=' => {
+                    let attr = Attr::new(&tag, &name);
+                    State::BeforeAttrValue(tag, attr)
+                }
+                c if is_html_whitespace(c) => State::AfterAttrName(tag, name),
+                c => State::AttrName(tag, c.to_ascii_lowercase().into()),
+            },
+            State::BeforeAttrValue(tag, attr) => match c {
+                '"' => State::AttrValue(tag, attr, Quote::Double),
+                '\'' => State::AttrValue(tag, attr, Quote::Single),
+                '>' => tag.content(),
+                c if is_html_whitespace(c) => State::BeforeAttrValue(tag, attr),
+                c => {
+                    let mut state = State::AttrValue(tag, attr, Quote::None);
+                    state.feed(c);
+                    state
+                }
+            },
+            State::AttrValue(tag, mut attr, quote) => match (c, quote) {
+                ('"', Quote::Double) | ('\'', Quote::Single) => {
+                    State::BeforeAttrName(attr.finish(tag))
+                }
+                (c, Quote::None) if is_html_whitespace(c) => {
+                    State::BeforeAttrName(attr.finish(tag))
+                }
+                ('>', Quote::None) => attr.finish(tag).content(),
+                (c, quote) => {
+                    attr.feed(c);
+                    State::AttrValue(tag, attr, quote)
+                }
+            },
+            State::SelfClosingStartTag(tag) => match c {
+                // A self-closing `<script />` is only valid in SVG, where it has no content.
+                '>' => State::Text,
+                c => {
+                    let mut state = State::BeforeAttrName(tag);
+                    state.feed(c);
+                    state
+                }
+            },
+            State::RawText(mut raw, matched) => {
+                let end_tag = raw.end_tag();
+                match end_tag.get(matched..matched + 1) {
+                    Some(expected) if expected.eq_ignore_ascii_case(c.encode_utf8(&mut [0; 4])) => {
+                        raw.feed(c);
+                        State::RawText(raw, matched + 1)
+                    }
+                    None if c == '>' => State::Text,
+                    None if c == '/' || is_html_whitespace(c) => State::Bogus,
+                    _ => {
+                        raw.feed(c);
+                        State::RawText(raw, usize::from(c == '<'))
+                    }
+                }
+            }
+        };
+    }
+
+    fn escaping(&self) -> Escaping {
+        match self {
+            State::Text | State::Comment(_) => Escaping::Html,
+            State::TagOpen
+            | State::EndTagOpen
+            | State::MarkupDeclaration(_)
+            | State::Bogus
+            | State::TagName(_)
+            | State::BeforeAttrName(_)
+            | State::AttrName(..)
+            | State::AfterAttrName(..)
+            | State::SelfClosingStartTag(_) => Escaping::Unsafe(IN_TAG),
+            State::BeforeAttrValue(..) | State::AttrValue(_, _, Quote::None) => {
+                Escaping::Unsafe(UNQUOTED_ATTR)
+            }
+            State::AttrValue(_, attr, _) => match attr {
+                Attr::Normal | Attr::ScriptType(_) | Attr::Url(Url::Rest) => Escaping::Html,
+                Attr::Url(Url::Start | Url::Scheme) => Escaping::Url,
+                Attr::Js(js) => js.escaping(true),
+                Attr::Css => Escaping::Css,
+            },
+            State::RawText(raw, _) => match raw {
+                RawText::Script(js) => js.escaping(false),
+                RawText::Style => Escaping::Css,
+                RawText::RcData(_) | RawText::Other(_) => Escaping::Html,
+            },
+        }
+    }
+
+    fn skip_expr(&mut self) {
+        match self {
+            State::AttrValue(_, attr, _) => attr.skip_expr(),
+            State::RawText(RawText::Script(js), matched) => {
+                js.skip_expr();
+                *matched = 0;
+            }
+            State::RawText(_, matched) => *matched = 0,
+            _ => {}
+        }
+    }
+}
+
+/// A start tag, as far as it matters for its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tag {
+    Normal,
+    Script {
+        is_js: bool,
+    },
+    Style,
+    /// `<textarea>` and `<title>`, whose content can contain character references
+    RcData(&'static str),
+    /// Other elements whose content is not parsed as HTML, e.g. `<xmp>`
+    RawText(&'static str),
+}
+
+impl Tag {
+    fn new(name: &str) -> Self {
+        match name {
+            "script" => Tag::Script { is_js: true },
+            "style" => Tag::Style,
+            "textarea" => Tag::RcData("textarea"),
+            "title" => Tag::RcData("title"),
+            "iframe" => Tag::RawText("iframe"),
+            "noembed" => Tag::RawText("noembed"),
+            "noframes" => Tag::RawText("noframes"),
+            "xmp" => Tag::RawText("xmp"),
+            _ => Tag::Normal,
+        }
+    }
+
+    /// The state after the `>` of the start tag
+    fn content(self) -> State {
+        let raw = match self {
+            Tag::Normal => return State::Text,
+            Tag::Script { is_js: true } => RawText::Script(Js::default()),
+            Tag::Script { is_js: false } => RawText::Other("script"),
+            Tag::Style => RawText::Style,
+            Tag::RcData(name) => RawText::RcData(name),
+            Tag::RawText(name) => RawText::Other(name),
+        };
+        State::RawText(raw, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RawText {
+    Script(Js),
+    Style,
+    RcData(&'static str),
+    Other(&'static str),
+}
+
+impl RawText {
+    fn end_tag(&self) -> &'static str {
+        match self {
+            RawText::Script(_) => "</script",
+            RawText::Style => "</style",
+            RawText::RcData(name) | RawText::Other(name) => match *name {
+                "textarea" => "</textarea",
+                "title" => "</title",
+                "iframe" => "</iframe",
+                "noembed" => "</noembed",
+                "noframes" => "</noframes",
+                "xmp" => "</xmp",
+                _ => "</script",
+            },
+        }
+    }
+
+    fn feed(&mut self, c: char) {
+        if let RawText::Script(js) = self {
+            js.feed(c);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+/// The kind of an attribute, as far as it matters for its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Attr {
+    Normal,
+    /// An attribute that contains a URL like `href` or `src`
+    Url(Url),
+    /// An event handler like `onclick`
+    Js(Js),
+    /// A `style` attribute
+    Css,
+    /// The `type` of a `<script>`, which is `None` if it contains an expression
+    ScriptType(Option<String>),
+}
+
+impl Attr {
+    fn new(tag: &Tag, name: &str) -> Self {
+        match name {
+            "style" => Attr::Css,
+            "type" if matches!(tag, Tag::Script { .. }) => Attr::ScriptType(Some(String::new())),
+            "action" | "archive" | "background" | "cite" | "classid" | "codebase" | "data"
+            | "formaction" | "href" | "icon" | "longdesc" | "manifest" | "ping" | "poster"
+            | "profile" | "src" | "srcset" | "usemap" | "xlink:href" => Attr::Url(Url::Start),
+            _ if name.len() > 2 && name.starts_with("on") => Attr::Js(Js::default()),
+            _ => Attr::Normal,
+        }
+    }
+
+    fn feed(&mut self, c: char) {
+        match self {
+            Attr::Url(url) => url.feed(c),
+            Attr::Js(js) => js.feed(c),
+            Attr::ScriptType(Some(value)) => value.push(c),
+            Attr::Normal | Attr::Css | Attr::ScriptType(None) => {}
+        }
+    }
+
+    fn skip_expr(&mut self) {
+        match self {
+            Attr::Url(url @ Url::Start) => *url = Url::Scheme,
+            Attr::Js(js) => js.skip_expr(),
+            Attr::ScriptType(value) => *value = None,
+            Attr::Normal | Attr::Url(_) | Attr::Css => {}
+        }
+    }
+
+    /// Applies the attribute to its tag after the end of its value
+    fn finish(self, tag: Tag) -> Tag {
+        match (self, tag) {
+            (Attr::ScriptType(value), Tag::Script { .. }) => Tag::Script {
+                // An unknown type is treated like JavaScript, which is the safer choice.
+                is_js: value.is_none_or(|value| is_js_type(&value)),
+            },
+            (_, tag) => tag,
+        }
+    }
+}
+
+/// The position inside of a URL attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Url {
+    /// Nothing but whitespace was written yet
+    Start,
+    /// Only characters that could be part of a scheme were written yet
+    Scheme,
+    /// The scheme, if any, is part of the template
+    Rest,
+}
+
+impl Url {
+    fn feed(&mut self, c: char) {
+        *self = match (*self, c) {
+            (Url::Start, c) if is_html_whitespace(c) => Url::Start,
+            (Url::Start | Url::Scheme, 'a'..='z' | 'A'..='Z' | '0'..='9' | '+' | '-' | '.') => {
+                Url::Scheme
+            }
+            _ => Url::Rest,
+        };
+    }
+}
+
+/// The state of the JavaScript tokenizer in a `<script>` element or an event handler attribute
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Js {
+    state: JsState,
+    /// Whether a `/` at this position would start a regular expression instead of a division
+    slash_is_regex: bool,
+    /// The identifier, keyword or number that is currently read
+    word: String,
+    /// For every `${` in a template literal, the number of open `{` inside of it
+    braces: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum JsState {
+    #[default]
+    Code,
+    /// After a `/` in code, which could start a comment, a regex or be a division
+    Slash,
+    Str(char),
+    StrEscape(char),
+    Template,
+    TemplateEscape,
+    /// After a `$` in a template literal
+    TemplateDollar,
+    Regex,
+    RegexClass,
+    RegexEscape {
+        in_class: bool,
+    },
+    LineComment,
+    BlockComment,
+    BlockCommentStar,
+}
+
+impl Js {
+    fn feed(&mut self, c: char) {
+        self.state = match (self.state, c) {
+            (JsState::Code, c) => return self.feed_code(c),
+            (JsState::Slash, '/') => JsState::LineComment,
+            (JsState::Slash, '*') => JsState::BlockComment,
+            (JsState::Slash, c) if self.slash_is_regex => {
+                self.state = JsState::Regex;
+                return self.feed(c);
+            }
+            (JsState::Slash, c) => {
+                // a division
+                self.state = JsState::Code;
+                self.slash_is_regex = true;
+                return self.feed_code(c);
+            }
+            (JsState::Str(quote), '\\') => JsState::StrEscape(quote),
+            (JsState::Str(quote), c) if c == quote || c == '\n' => self.end_value(),
+            (JsState::StrEscape(quote), _) => JsState::Str(quote),
+            (JsState::Template, '\\') => JsState::TemplateEscape,
+            (JsState::Template, '`') => self.end_value(),
+            (JsState::Template, '$') => JsState::TemplateDollar,
+            (JsState::TemplateEscape, _) => JsState::Template,
+            (JsState::TemplateDollar, '{') => {
+                self.braces.push(0);
+                self.slash_is_regex = true;
+                JsState::Code
+            }
+            (JsState::TemplateDollar, c) => {
+                self.state = JsState::Template;
+                return self.feed(c);
+            }
+            (JsState::Regex, '\\') => JsState::RegexEscape { in_class: false },
+            (JsState::Regex, '[') => JsState::RegexClass,
+            (JsState::Regex, '/' | '\n') => self.end_value(),
+            (JsState::RegexClass, '\\') => JsState::RegexEscape { in_class: true },
+            (JsState::RegexClass, ']') => JsState::Regex,
+            (JsState::RegexEscape { in_class: true }, _) => JsState::RegexClass,
+            (JsState::RegexEscape { in_class: false }, _) => JsState::Regex,
+            (JsState::LineComment, '\n' | '\r' | '\u{2028}' | '\u{2029}') => JsState::Code,
+            (JsState::BlockComment | JsState::BlockCommentStar, '*') => JsState::BlockCommentStar,
+            (JsState::BlockCommentStar, '/') => JsState::Code,
+            (JsState::BlockCommentStar, _) => JsState::BlockComment,
+            (state, _) => state,
+        };
+    }
+
+    fn feed_code(&mut self, c: char) {
+        if c.is_alphanumeric() || matches!(c, '_' | '$' | '.') {
+            self.word.push(c);
+            return;
+        }
+        if !self.word.is_empty() {
+            self.slash_is_regex = is_keyword_before_expr(&self.word);
+            self.word.clear();
+        }
+        match c {
+            '"' | '\'' => self.state = JsState::Str(c),
+            '`' => self.state = JsState::Template,
+            '/' => self.state = JsState::Slash,
+            '}' => match self.braces.last_mut() {
+                Some(0) => {
+                    self.braces.pop();
+                    self.state = JsState::Template;
+                }
+                Some(open) => {
+                    *open -= 1;
+                    self.slash_is_regex = true;
+                }
+                None => self.slash_is_regex = true,
+            },
+            '{' => {
+                if let Some(open) = self.braces.last_mut() {
+                    *open += 1;
+                }
+                self.slash_is_regex = true;
+            }
+            ')' | ']' => self.slash_is_regex = false,
+            c if c.is_whitespace() => {}
+            _ => self.slash_is_regex = true,
+        }
+    }
+
+    /// The end of a string, template literal or regular expression: a `/` after it is a division
+    fn end_value(&mut self) -> JsState {
+        self.slash_is_regex = false;
+        JsState::Code
+    }
+
+    fn escaping(&self, in_attr: bool) -> Escaping {
+        match self.state {
+            JsState::Code | JsState::Slash => Escaping::JsValue { in_attr },
+            _ => Escaping::Js,
+        }
+    }
+
+    fn skip_expr(&mut self) {
+        self.state = match self.state {
+            JsState::Code | JsState::Slash => {
+                self.word.clear();
+                self.end_value()
+            }
+            JsState::StrEscape(quote) => JsState::Str(quote),
+            JsState::TemplateEscape | JsState::TemplateDollar => JsState::Template,
+            JsState::RegexEscape { in_class: true } => JsState::RegexClass,
+            JsState::RegexEscape { in_class: false } => JsState::Regex,
+            state => state,
+        };
+    }
+}
+
+fn is_keyword_before_expr(word: &str) -> bool {
+    matches!(
+        word,
+        "await"
+            | "case"
+            | "delete"
+            | "do"
+            | "else"
+            | "in"
+            | "instanceof"
+            | "new"
+            | "of"
+            | "return"
+            | "throw"
+            | "typeof"
+            | "void"
+            | "yield"
+    )
+}
+
+fn is_js_type(value: &str) -> bool {
+    let value = value.split(';').next().unwrap_or_default().trim();
+    [
+        "",
+        "module",
+        "application/ecmascript",
+        "application/javascript",
+        "application/json",
+        "application/ld+json",
+        "application/x-ecmascript",
+        "application/x-javascript",
+        "text/ecmascript",
+        "text/javascript",
+        "text/jscript",
+        "text/x-ecmascript",
+        "text/x-javascript",
+    ]
+    .iter()
+    .any(|ty| ty.eq_ignore_ascii_case(value))
+}
+
+fn is_html_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\x0c' | '\r')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn escaping_after(parts: &[&str]) -> Escaping {
+        let mut ctx = HtmlContext::default();
+        for (idx, part) in parts.iter().enumerate() {
+            if idx != 0 {
+                ctx.skip_expr();
+            }
+            ctx.feed(part);
+        }
+        ctx.escaping()
+    }
+
+    #[test]
+    fn test_text_and_attributes() {
+        assert_eq!(escaping_after(&[""]), Escaping::Html);
+        assert_eq!(escaping_after(&["<p>"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<p class=\""]), Escaping::Html);
+        assert_eq!(escaping_after(&["<p class='a "]), Escaping::Html);
+        assert_eq!(escaping_after(&["<p class=\"a\">"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<!-- <p class=\"a\" -->"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<!-- <p class='"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<textarea><p class='"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<title>a</title> <p>"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<br/>"]), Escaping::Html);
+
+        assert_eq!(escaping_after(&["<"]), Escaping::Unsafe(IN_TAG));
+        assert_eq!(escaping_after(&["</"]), Escaping::Unsafe(IN_TAG));
+        assert_eq!(escaping_after(&["<p "]), Escaping::Unsafe(IN_TAG));
+        assert_eq!(escaping_after(&["<p data-"]), Escaping::Unsafe(IN_TAG));
+        assert_eq!(
+            escaping_after(&["<p class=\"a\" "]),
+            Escaping::Unsafe(IN_TAG)
+        );
+        assert_eq!(
+            escaping_after(&["<p class="]),
+            Escaping::Unsafe(UNQUOTED_ATTR)
+        );
+        assert_eq!(
+            escaping_after(&["<p class=a"]),
+            Escaping::Unsafe(UNQUOTED_ATTR)
+        );
+        assert_eq!(
+            escaping_after(&["<p class=a", ""]),
+            Escaping::Unsafe(UNQUOTED_ATTR)
+        );
+    }
+
+    #[test]
+    fn test_url_attributes() {
+        assert_eq!(escaping_after(&["<a href=\""]), Escaping::Url);
+        assert_eq!(escaping_after(&["<a href=' "]), Escaping::Url);
+        assert_eq!(escaping_after(&["<a HREF=\"java"]), Escaping::Url);
+        assert_eq!(escaping_after(&["<a href=\"", ""]), Escaping::Url);
+        assert_eq!(escaping_after(&["<a href=\"", "/"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<a href=\"/"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<a href=\"https://"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<img src=\"?q="]), Escaping::Html);
+        assert_eq!(escaping_after(&["<a href=\"a\" title=\""]), Escaping::Html);
+    }
+
+    #[test]
+    fn test_js() {
+        assert_eq!(
+            escaping_after(&["<script>"]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(
+            escaping_after(&["<script type=module>let x = "]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(escaping_after(&["<script>let x = '"]), Escaping::Js);
+        assert_eq!(escaping_after(&["<script>let x = \"a\\\""]), Escaping::Js);
+        assert_eq!(escaping_after(&["<script>let x = `a"]), Escaping::Js);
+        assert_eq!(
+            escaping_after(&["<script>let x = `${"]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(
+            escaping_after(&["<script>let x = `${ {a: 1} }"]),
+            Escaping::Js
+        );
+        assert_eq!(escaping_after(&["<script>// '"]), Escaping::Js);
+        assert_eq!(
+            escaping_after(&["<script>// '\n"]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(escaping_after(&["<script>/* x"]), Escaping::Js);
+        assert_eq!(escaping_after(&["<script>x = /['/]"]), Escaping::Js);
+        assert_eq!(
+            escaping_after(&["<script>x = a / 2 + '/' + /'/.source + "]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(escaping_after(&["<script>x = (a) / '"]), Escaping::Js);
+        assert_eq!(escaping_after(&["<script>return /'"]), Escaping::Js);
+        assert_eq!(
+            escaping_after(&["<script>let x = '", "' + "]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(
+            escaping_after(&["<script>let s = '</script>'; </script><p>"]),
+            Escaping::Html
+        );
+        assert_eq!(
+            escaping_after(&["<script type=\"text/template\"><p>"]),
+            Escaping::Html
+        );
+        assert_eq!(
+            escaping_after(&["<script type=\"application/ld+json\">"]),
+            Escaping::JsValue { in_attr: false }
+        );
+        assert_eq!(
+            escaping_after(&["<button onclick=\"go("]),
+            Escaping::JsValue { in_attr: true }
+        );
+        assert_eq!(escaping_after(&["<button onclick='go(\""]), Escaping::Js);
+        assert_eq!(escaping_after(&["<button onclick='go()'>"]), Escaping::Html);
+    }
+
+    #[test]
+    fn test_css() {
+        assert_eq!(escaping_after(&["<style>p { color: "]), Escaping::Css);
+        assert_eq!(escaping_after(&["<style>p {}</style>"]), Escaping::Html);
+        assert_eq!(escaping_after(&["<p style=\"color: "]), Escaping::Css);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut ctx = HtmlContext::default();
+        ctx.feed("<input");
+        let mut other = ctx.clone();
+        ctx.feed(" checked");
+        ctx.merge(other.clone());
+        assert_eq!(ctx.escaping(), Escaping::Unsafe(IN_TAG));
+        ctx.feed(">");
+        assert_eq!(ctx.states, [State::Text]);
+
+        other.feed(" onclick='");
+        ctx.merge(other);
+        assert_eq!(ctx.escaping(), Escaping::Unsafe(AMBIGUOUS));
+        ctx.feed("'>");
+        assert_eq!(ctx.escaping(), Escaping::Html);
+    }
+}
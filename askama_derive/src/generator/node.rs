@@ -8,9 +8,9 @@ use std::str::FromStr;
 use parser::expr::BinOp;
 use parser::node::{
     Call, Comment, Compound, Cond, CondTest, Declare, FilterBlock, If, Include, Let, Lit, Loop,
-    Match, Whitespace, Ws,
+    Match, Whitespace, WithValues, Ws,
 };
-use parser::{Expr, LetValueOrBlock, Node, Span, Target, WithSpan};
+use parser::{Expr, LetValueOrBlock, Node, Span, StrLit, Target, WithSpan};
 use proc_macro2::TokenStream;
 use quote::quote_spanned;
 use rustc_hash::FxBuildHasher;
@@ -19,6 +19,7 @@ use syn::Token;
 use super::{
     DisplayWrap, Generator, LocalMeta, MapChain, RenderFor, compile_time_escape, is_copyable,
 };
+use crate::generator::html_context::{self, Escaping};
 use crate::generator::{LocalCallerMeta, Writable, helpers, logic_op};
 use crate::heritage::{Context, Heritage};
 use crate::integration::{Buffer, string_escape};
@@ -158,6 +159,9 @@ impl<'a> Generator<'a, '_> {
                 Node::FilterBlock(ref filter) => {
                     size_hint += self.write_filter_block(ctx, buf, filter)?;
                 }
+                Node::WithValues(ref with_values) => {
+                    size_hint += self.write_with_values(ctx, buf, with_values, render_for)?;
+                }
                 Node::Macro(ref m) => {
                     if level != AstLevel::Top {
                         return Err(ctx.generate_error(
@@ -240,6 +244,8 @@ impl<'a> Generator<'a, '_> {
             | Expr::AssociatedItem(_, _)
             | Expr::Index(_, _)
             | Expr::Filter(_)
+            | Expr::Test(_)
+            | Expr::Conditional(_)
             | Expr::Range(_)
             | Expr::Call { .. }
             | Expr::Struct(_)
@@ -368,6 +374,18 @@ impl<'a> Generator<'a, '_> {
             self.handle_ws(ws_before);
         }
 
+        // A `cfg(...)` condition is lowered to `#[cfg]` attributes, so the branches following it
+        // are nested: `else { #[cfg(P)] { … } #[cfg(not(P))] { <following branches> } }`. The
+        // buffers of these nested branches are stacked until the end of the `if`.
+        let mut nested: Vec<(Buffer, Option<TokenStream>, proc_macro2::Span)> = vec![];
+        // The position of the branch in the current `if … else if …` chain.
+        let mut chain_pos = 0;
+
+        // Every branch starts in the HTML context before the `if`.
+        let mut html_start = None;
+        let mut html_end = None;
+        let mut last_is_unconditional = false;
+
         let mut iter = conds.conds.iter().enumerate().peekable();
         while let Some((pos, cond_info)) = iter.next() {
             let cond = cond_info.cond;
@@ -375,16 +393,34 @@ impl<'a> Generator<'a, '_> {
             if pos == 0 {
                 self.handle_ws(cond.ws);
                 flushed += self.write_buf_writable(ctx, buf)?;
+                html_start.clone_from(&self.buf_writable.html);
+            } else {
+                self.buf_writable.html.clone_from(&html_start);
             }
 
+            let cfg = match self.cfg_condition(cond) {
+                Some(args) => Some(self.cfg_predicate(ctx, args, cond.span())?),
+                None => None,
+            };
+            let cond_span = ctx.span_for_node(cond.span());
+            if cfg.is_some() && chain_pos != 0 {
+                nested.push((Buffer::new(), None, cond_span));
+            }
+            let buf = match nested.last_mut() {
+                Some((nested, ..)) => nested,
+                None => &mut *buf,
+            };
+
             self.push_locals(|this| {
                 let mut has_cond = true;
 
-                if let Some(CondTest { target, expr, .. }) = cond.cond.as_deref() {
+                if let Some(predicate) = &cfg {
+                    quote_into!(buf, cond_span, { #[cfg(#predicate)] });
+                } else if let Some(CondTest { target, expr, .. }) = cond.cond.as_deref() {
                     let expr = cond_info.cond_expr.as_ref().unwrap_or(expr);
                     let expr_span = ctx.span_for_node(expr.span());
 
-                    if pos == 0 {
+                    if chain_pos == 0 {
                         if cond_info.generate_condition {
                             buf.write_token(Token![if], expr_span);
                         } else {
@@ -432,7 +468,7 @@ impl<'a> Generator<'a, '_> {
                     } else if cond_info.generate_condition {
                         this.visit_condition(ctx, buf, expr)?;
                     }
-                } else if pos != 0 {
+                } else if chain_pos != 0 {
                     buf.write_token(Token![else], ctx.span_for_node(cond.span()));
                     has_else = true;
                 } else {
@@ -468,8 +504,40 @@ impl<'a> Generator<'a, '_> {
                 } else {
                     buf.write_buf(block_buf);
                 }
+                last_is_unconditional = !has_cond;
                 Ok(0)
             })?;
+            html_end = html_context::merge(html_end, self.buf_writable.html.take());
+
+            match cfg {
+                Some(predicate) => {
+                    nested.push((Buffer::new(), Some(predicate), cond_span));
+                    chain_pos = 0;
+                }
+                None => chain_pos += 1,
+            }
+        }
+
+        while let Some((nested_buf, predicate, span)) = nested.pop() {
+            let parent = match nested.last_mut() {
+                Some((parent, ..)) => parent,
+                None => &mut *buf,
+            };
+            let nested_buf = nested_buf.into_token_stream();
+            match predicate {
+                Some(predicate) => {
+                    quote_into!(parent, span, { #[cfg(not(#predicate))] { #nested_buf } });
+                }
+                None => quote_into!(parent, span, { else { #nested_buf } }),
+            }
+        }
+
+        if !conds.conds.is_empty() {
+            // If no branch is taken, the HTML context does not change.
+            if !has_else && !last_is_unconditional {
+                html_end = html_context::merge(html_end, html_start);
+            }
+            self.buf_writable.html = html_end;
         }
 
         if !has_else && !conds.conds.is_empty() {
@@ -478,6 +546,88 @@ impl<'a> Generator<'a, '_> {
         Ok(flushed + median(&mut arm_sizes))
     }
 
+    /// The arguments of the condition of `cond` if it is `cfg(…)`, which is evaluated at compile
+    /// time, like Rust's `#[cfg]` attribute.
+    fn cfg_condition<'c>(&self, cond: &'c Cond<'a>) -> Option<&'c [WithSpan<Box<Expr<'a>>>]> {
+        let CondTest {
+            target: None, expr, ..
+        } = cond.cond.as_deref()?
+        else {
+            return None;
+        };
+        let Expr::Call(call) = &***expr else {
+            return None;
+        };
+        // A variable named `cfg` is called like any other function.
+        (**call.path == Expr::Var("cfg") && call.generics.is_none() && !self.is_var_defined("cfg"))
+            .then_some(call.args.as_slice())
+    }
+
+    /// Converts the arguments of `cfg(…)` into a configuration predicate of Rust.
+    fn cfg_predicate(
+        &self,
+        ctx: &Context<'_>,
+        args: &[WithSpan<Box<Expr<'a>>>],
+        node: Span,
+    ) -> Result<TokenStream, CompileError> {
+        let [arg] = args else {
+            return Err(ctx.generate_error("`cfg` expects exactly one predicate", node));
+        };
+        let span = ctx.span_for_node(arg.span());
+        Ok(match &***arg {
+            // `cfg(unix)`
+            Expr::Var(name) => {
+                let name = field_new(name, span);
+                quote_spanned!(span => #name)
+            }
+            // `cfg(feature = "serde")`
+            Expr::NamedArgument(name, value) => {
+                let Expr::StrLit(lit @ StrLit { prefix: None, .. }) = &***value else {
+                    return Err(ctx.generate_error(
+                        format_args!(
+                            "the value of `{}` in `cfg` must be a string literal",
+                            **name
+                        ),
+                        value.span(),
+                    ));
+                };
+                let name = field_new(name, span);
+                let mut value =
+                    proc_macro2::Literal::from_str(&format!("\"{}\"", lit.content)).unwrap();
+                value.set_span(span);
+                quote_spanned!(span => #name = #value)
+            }
+            // `cfg(all(…))`, `cfg(any(…))` and `cfg(not(…))`
+            Expr::Call(call)
+                if matches!(**call.path, Expr::Var("all" | "any" | "not"))
+                    && call.generics.is_none() =>
+            {
+                let Expr::Var(op) = **call.path else {
+                    unreachable!();
+                };
+                if op == "not" && call.args.len() != 1 {
+                    return Err(
+                        ctx.generate_error("`not` expects exactly one predicate", arg.span())
+                    );
+                }
+                let predicates = call
+                    .args
+                    .iter()
+                    .map(|arg| self.cfg_predicate(ctx, std::slice::from_ref(arg), arg.span()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let op = field_new(op, span);
+                quote_spanned!(span => #op(#(#predicates),*))
+            }
+            _ => {
+                return Err(ctx.generate_error(
+                    "invalid `cfg` predicate, expected e.g. `feature = \"name\"`, `unix`, \
+                    `all(…)`, `any(…)` or `not(…)`",
+                    arg.span(),
+                ));
+            }
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn write_match(
         &mut self,
@@ -503,9 +653,15 @@ impl<'a> Generator<'a, '_> {
         let mut arm_size = SizeHint::EMPTY;
         let mut iter = arms.iter().enumerate().peekable();
         let mut arms = Buffer::new();
+        // Every arm starts in the HTML context before the `match`.
+        let mut html_start = None;
+        let mut html_end = None;
         while let Some((i, arm)) = iter.next() {
             if i == 0 {
                 self.handle_ws(arm.ws);
+                html_start.clone_from(&self.buf_writable.html);
+            } else {
+                self.buf_writable.html.clone_from(&html_start);
             }
 
             // FIXME: When `Target` is wrapped in `WithSpan`, update the spans.
@@ -534,6 +690,10 @@ impl<'a> Generator<'a, '_> {
                 quote_into!(&mut arms, span, { #targets_buf => { #arm_buf } });
                 Ok(0)
             })?;
+            html_end = html_context::merge(html_end, self.buf_writable.html.take());
+        }
+        if html_start.is_some() {
+            self.buf_writable.html = html_end;
         }
 
         let arms = arms.into_token_stream();
@@ -559,6 +719,7 @@ impl<'a> Generator<'a, '_> {
             let var_iter = crate::var_iter();
 
             let flushed = this.write_buf_writable(ctx, buf)?;
+            let html_start = this.buf_writable.html.clone();
             let mut loop_buf = Buffer::new();
             if has_else_nodes {
                 quote_into!(&mut loop_buf, span, { let mut #var_did_loop = false; });
@@ -608,6 +769,15 @@ impl<'a> Generator<'a, '_> {
                 )?;
                 this.handle_ws(loop_block.ws2);
                 size_hint1 += this.write_buf_writable(ctx, &mut loop_body_buf)?;
+                // The next iteration starts where the previous one ended, so the expressions in
+                // the loop body would need a different escaping.
+                if this.buf_writable.html != html_start {
+                    return Err(ctx.generate_error(
+                        "the loop body ends in a different HTML context than it starts in, \
+                        e.g. inside of a tag or an attribute value that is not closed",
+                        loop_block.span(),
+                    ));
+                }
                 let loop_body_buf = loop_body_buf.into_token_stream();
                 quote_into!(&mut loop_buf, span, {
                     for (#target_buf, #var_item) in askama::helpers::TemplateLoop::new(#var_iter) {
@@ -620,6 +790,7 @@ impl<'a> Generator<'a, '_> {
             let else_size_hint = if has_else_nodes {
                 let mut cond_buf = Buffer::new();
                 let else_size_hint = this.push_locals(|this| {
+                    this.buf_writable.html.clone_from(&html_start);
                     let mut size_hint = this.handle(
                         ctx,
                         &loop_block.else_nodes,
@@ -631,6 +802,8 @@ impl<'a> Generator<'a, '_> {
                     size_hint += this.write_buf_writable(ctx, &mut cond_buf)?;
                     Ok(size_hint)
                 })?;
+                this.buf_writable.html =
+                    html_context::merge(this.buf_writable.html.take(), html_start);
                 let cond_buf = cond_buf.into_token_stream();
                 quote_into!(&mut loop_buf, span, {
                     if !#var_did_loop {
@@ -675,6 +848,7 @@ impl<'a> Generator<'a, '_> {
                     call.span(),
                 )
             })?;
+            self.input.lints.use_import(*s, path);
             let mctx = self.contexts.get(path).ok_or_else(|| {
                 ctx.generate_error(
                     format_args!("context for `{}` not found", path.display()),
@@ -731,6 +905,10 @@ impl<'a> Generator<'a, '_> {
         size_hint += self.write_buf_writable(ctx, buf)?;
         let span = ctx.span_for_node(filter.span());
 
+        // The content is written through the filter, which is escaped in the current HTML context.
+        let html = self.buf_writable.html.clone();
+        let escaping = self.buf_writable.escaping();
+
         // build `FmtCell` that contains the inner block
         let mut filter_def_buf = Buffer::new();
         size_hint += self.push_locals(|this| {
@@ -746,6 +924,10 @@ impl<'a> Generator<'a, '_> {
             size_hint += this.write_buf_writable(ctx, &mut filter_def_buf)?;
             Ok(size_hint)
         })?;
+        self.buf_writable.html = html;
+        if let Some(html) = &mut self.buf_writable.html {
+            html.skip_expr();
+        }
         let filter_def_buf = filter_def_buf.into_token_stream();
         let var_writer = crate::var_writer();
         let filter_def_buf = quote_spanned!(span=>
@@ -769,19 +951,14 @@ impl<'a> Generator<'a, '_> {
         let filter_buf = filter_buf.into_token_stream();
         let filter_buf = match display_wrap {
             DisplayWrap::Wrapped => filter_buf,
-            DisplayWrap::Unwrapped => {
-                let escaper = TokenStream::from_str(self.input.escaper).unwrap();
-                quote_spanned!(span=>
-                    (&&askama::filters::AutoEscaper::new(
-                        &(#filter_buf), #escaper
-                    )).askama_auto_escape()?
-                )
-            }
+            DisplayWrap::Unwrapped => self.auto_escape(ctx, filter_buf, escaping, filter.span())?,
         };
         quote_into!(buf, span, { {
             #filter_def_buf
-            if askama::helpers::core::write!(#var_writer, "{}", #filter_buf).is_err() {
-                return #var_filter_source.take_err();
+            if let askama::helpers::core::result::Result::Err(__askama_err) =
+                askama::helpers::core::write!(#var_writer, "{}", #filter_buf)
+            {
+                return #var_filter_source.take_err_or(__askama_err);
             }
         } });
 
@@ -790,6 +967,60 @@ impl<'a> Generator<'a, '_> {
         Ok(size_hint)
     }
 
+    fn write_with_values(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        with_values: &'a WithSpan<WithValues<'_>>,
+        render_for: RenderFor,
+    ) -> Result<SizeHint, CompileError> {
+        self.flush_ws(with_values.ws1);
+        let mut size_hint = self.write_buf_writable(ctx, buf)?;
+        let span = ctx.span_for_node(with_values.span());
+
+        let mut layer = Buffer::new();
+        for (key, value) in &with_values.values {
+            let key_span = ctx.span_for_node(key.span());
+            let mut key_buf = Buffer::new();
+            key_buf.write_literal(&format!(r#""{}""#, **key), key_span);
+            let key = key_buf.into_token_stream();
+            let value = self.visit_expr_root(ctx, value)?;
+            quote_into!(&mut layer, key_span, {
+                (#key, &(#value) as &dyn askama::helpers::core::any::Any),
+            });
+        }
+
+        // The new layer shadows the values of the surrounding scope inside the block.
+        let mut nodes_buf = Buffer::new();
+        size_hint += self.push_locals(|this| {
+            this.prepare_ws(with_values.ws1);
+            let mut size_hint = this.handle(
+                ctx,
+                &with_values.nodes,
+                &mut nodes_buf,
+                AstLevel::Nested,
+                render_for,
+            )?;
+            this.flush_ws(with_values.ws2);
+            size_hint += this.write_buf_writable(ctx, &mut nodes_buf)?;
+            Ok(size_hint)
+        })?;
+
+        let layer = layer.into_token_stream();
+        let nodes_buf = nodes_buf.into_token_stream();
+        let var_values = crate::var_values();
+        let var_values_layer = crate::var_values_layer();
+        quote_into!(buf, span, { {
+            let #var_values_layer = [#layer];
+            let #var_values: &dyn askama::Values =
+                &askama::Layered::new(&#var_values_layer, #var_values);
+            #nodes_buf
+        } });
+
+        self.prepare_ws(with_values.ws2);
+        Ok(size_hint)
+    }
+
     fn handle_include(
         &mut self,
         ctx: &Context<'a>,
@@ -963,7 +1194,18 @@ impl<'a> Generator<'a, '_> {
             }
         }
 
+        // Only new variables are tracked, not the assignment of a variable declared before.
+        let mut names = vec![];
+        target_names(&l.var, &mut names);
+        names.retain(|name| self.locals.get(name).is_none_or(|meta| meta.initialized));
+
         self.visit_target(ctx, buf, true, true, &l.var, span);
+
+        for name in names {
+            if let Some(used) = self.input.lints.declare_variable(ctx, name, name.span()) {
+                self.locals.track(name, used);
+            }
+        }
         Ok(size_hint)
     }
 
@@ -983,6 +1225,9 @@ impl<'a> Generator<'a, '_> {
         size_hint += self.write_buf_writable(ctx, buf)?;
         let span = ctx.span_for_node(l.span());
 
+        // The content is not written here, so it does not change the HTML context.
+        let html = self.buf_writable.html.clone();
+
         // build `FmtCell` that contains the inner block
         let mut filter_def_buf = Buffer::new();
         size_hint += self.push_locals(|this| {
@@ -998,6 +1243,7 @@ impl<'a> Generator<'a, '_> {
             size_hint += this.write_buf_writable(ctx, &mut filter_def_buf)?;
             Ok(size_hint)
         })?;
+        self.buf_writable.html = html;
         let filter_def_buf = filter_def_buf.into_token_stream();
 
         size_hint += self.write_let_target(ctx, buf, l, span)?;
@@ -1026,7 +1272,10 @@ impl<'a> Generator<'a, '_> {
         quote_into!(buf, span, { {
             #filter_def_buf
             let mut __askama_tmp_write = String::new();
-            if askama::helpers::core::write!(&mut __askama_tmp_write, "{}", #filter_buf).is_err() {
+            if askama::helpers::core::fmt::Write::write_fmt(
+                &mut __askama_tmp_write,
+                askama::helpers::core::format_args!("{}", #filter_buf),
+            ).is_err() {
                 return #var_let_source.take_err();
             }
             __askama_tmp_write
@@ -1051,6 +1300,7 @@ impl<'a> Generator<'a, '_> {
             return Ok(None);
         };
         // FIXME: Try to merge this code with `write_let_block` to avoid the duplication.
+        let html = self.buf_writable.html.clone();
         // build `FmtCell` that contains the inner block
         let mut filter_def_buf = Buffer::new();
         let mut size_hint = match self.push_locals(|this| {
@@ -1071,6 +1321,7 @@ impl<'a> Generator<'a, '_> {
             Some(size_hint) => size_hint,
             None => return Ok(None),
         };
+        self.buf_writable.html = html;
         self.handle_ws(l.ws);
         let filter_def_buf = filter_def_buf.into_token_stream();
 
@@ -1102,7 +1353,10 @@ impl<'a> Generator<'a, '_> {
         quote_into!(buf, span, { {
             #filter_def_buf
             let mut __askama_tmp_write = String::new();
-            if askama::helpers::core::write!(&mut __askama_tmp_write, "{}", #filter_buf).is_err() {
+            if askama::helpers::core::fmt::Write::write_fmt(
+                &mut __askama_tmp_write,
+                askama::helpers::core::format_args!("{}", #filter_buf),
+            ).is_err() {
                 return #var_let_caller.take_err();
             }
             __askama_tmp_write
@@ -1351,9 +1605,13 @@ impl<'a> Generator<'a, '_> {
                 }
             }
             _ => {
-                self.buf_writable.push(
-                    compile_time_escape(expr, self.input.escaper).unwrap_or(Writable::Expr(expr)),
-                );
+                let escaping = self.buf_writable.escaping();
+                let writable = match escaping {
+                    None | Some(Escaping::Html) => compile_time_escape(expr, self.input.escaper),
+                    Some(_) => None,
+                };
+                self.buf_writable
+                    .push(writable.unwrap_or(Writable::Expr(expr, escaping)));
             }
         }
     }
@@ -1520,10 +1778,11 @@ impl<'a> Generator<'a, '_> {
             && let [scope, macro_name] = path_components.as_slice()
             && scope.generics.is_none()
             && macro_name.generics.is_none()
-            && let Some(scope) = ctx.imports.get(*scope.name)
-            && let Some(macro_ctx) = self.contexts.get(scope)
+            && let Some(path) = ctx.imports.get(*scope.name)
+            && let Some(macro_ctx) = self.contexts.get(path)
             && let Some(macro_def) = macro_ctx.macros.get(*macro_name.name)
         {
+            self.input.lints.use_import(*scope.name, path);
             return helpers::MacroInvocation {
                 callsite_ctx: ctx,
                 callsite_span: span,
@@ -1541,10 +1800,11 @@ impl<'a> Generator<'a, '_> {
             && let [scope, macro_name] = path_components.as_slice()
             && scope.generics.is_none()
             && macro_name.generics.is_none()
-            && let Some(scope) = ctx.imports.get(*scope.name)
-            && let Some(macro_ctx) = self.contexts.get(scope)
+            && let Some(path) = ctx.imports.get(*scope.name)
+            && let Some(macro_ctx) = self.contexts.get(path)
             && let Some(macro_def) = macro_ctx.macros.get(*macro_name.name)
         {
+            self.input.lints.use_import(*scope.name, path);
             return helpers::MacroInvocation {
                 callsite_ctx: ctx,
                 callsite_span: span,
@@ -1576,7 +1836,7 @@ impl<'a> Generator<'a, '_> {
         };
         let start_span = match start {
             Writable::Lit(v) => v.span(),
-            Writable::Expr(v) => v.span(),
+            Writable::Expr(v, _) => v.span(),
         };
 
         if let Some((_, Writable::Lit(lit))) = it.peek() {
@@ -1623,21 +1883,19 @@ impl<'a> Generator<'a, '_> {
                         break;
                     }
                 }
-                Writable::Expr(s) => {
+                Writable::Expr(s, escaping) => {
                     size_hint += 3;
 
                     let mut expr_buf = Buffer::new();
                     let span = ctx.span_for_node(s.span());
                     let expr = match self.visit_expr(ctx, &mut expr_buf, s)? {
                         DisplayWrap::Wrapped => expr_buf.into_token_stream(),
-                        DisplayWrap::Unwrapped => {
-                            let escaper = TokenStream::from_str(self.input.escaper).unwrap();
-                            let expr_buf = expr_buf.into_token_stream();
-                            quote_spanned!(span=>
-                                (&&askama::filters::AutoEscaper::new(&(#expr_buf), #escaper)).
-                                    askama_auto_escape()?
-                            )
-                        }
+                        DisplayWrap::Unwrapped => self.auto_escape(
+                            ctx,
+                            expr_buf.into_token_stream(),
+                            *escaping,
+                            s.span(),
+                        )?,
                     };
 
                     let (id, entry);
@@ -1690,6 +1948,39 @@ impl<'a> Generator<'a, '_> {
         Ok(size_hint)
     }
 
+    /// Escapes the output of `expr` with the template's escaper, or with the escaper
+    /// that fits its HTML context
+    fn auto_escape(
+        &self,
+        ctx: &Context<'_>,
+        expr: TokenStream,
+        escaping: Option<Escaping>,
+        node_span: Span,
+    ) -> Result<TokenStream, CompileError> {
+        let span = ctx.span_for_node(node_span);
+        let escape = |expr: TokenStream, escaper: &str| {
+            let escaper = TokenStream::from_str(escaper).unwrap();
+            quote_spanned!(span=>
+                (&&askama::filters::AutoEscaper::new(&(#expr), #escaper)).askama_auto_escape()?
+            )
+        };
+        Ok(match escaping {
+            None | Some(Escaping::Html) => escape(expr, self.input.escaper),
+            Some(Escaping::Js) => escape(expr, "askama::filters::Js"),
+            Some(Escaping::JsValue { in_attr: false }) => escape(expr, "askama::filters::JsValue"),
+            Some(Escaping::JsValue { in_attr: true }) => escape(
+                escape(expr, "askama::filters::JsValue"),
+                "askama::filters::Html",
+            ),
+            Some(Escaping::Css) => escape(expr, "askama::filters::Css"),
+            Some(Escaping::Url) => escape(
+                escape(expr, "askama::filters::CheckedUrl"),
+                "askama::filters::Html",
+            ),
+            Some(Escaping::Unsafe(msg)) => return Err(ctx.generate_error(msg, node_span)),
+        })
+    }
+
     fn write_comment(&mut self, comment: &'a WithSpan<Comment<'_>>) {
         self.handle_ws(comment.ws);
     }
@@ -1959,6 +2250,12 @@ fn is_cacheable(expr: &WithSpan<Box<Expr<'_>>>) -> bool {
         Expr::AssociatedItem(lhs, _) => is_cacheable(lhs),
         Expr::Index(lhs, rhs) => is_cacheable(lhs) && is_cacheable(rhs),
         Expr::Filter(v) => v.arguments.iter().all(is_cacheable),
+        Expr::Test(v) => v.arguments.iter().all(is_cacheable),
+        Expr::Conditional(v) => {
+            is_cacheable(&v.cond)
+                && is_cacheable(&v.then)
+                && v.otherwise.as_ref().is_none_or(is_cacheable)
+        }
         Expr::Unary(_, arg) => is_cacheable(arg),
         Expr::BinOp(v) => is_cacheable(&v.lhs) && is_cacheable(&v.rhs),
         Expr::IsDefined(_) | Expr::IsNotDefined(_) => true,
@@ -2008,3 +2305,28 @@ fn check_num_args<'a>(
         Ok(())
     }
 }
+
+/// The variables declared by `target`.
+fn target_names<'t, 'a>(target: &'t Target<'a>, names: &mut Vec<&'t WithSpan<&'a str>>) {
+    match target {
+        Target::Name(name) => names.push(name),
+        Target::Rest(rest) => names.extend(rest.as_ref()),
+        Target::Tuple(v) => v.1.iter().for_each(|target| target_names(target, names)),
+        Target::Array(targets) | Target::OrChain(targets) => {
+            targets
+                .iter()
+                .for_each(|target| target_names(target, names));
+        }
+        Target::Struct(v) => {
+            for named in &v.1 {
+                target_names(&named.dest, names);
+            }
+        }
+        Target::NumLit(..)
+        | Target::StrLit(_)
+        | Target::CharLit(_)
+        | Target::BoolLit(_)
+        | Target::Path(_)
+        | Target::Placeholder(_) => {}
+    }
+}
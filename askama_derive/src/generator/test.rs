@@ -0,0 +1,145 @@
+use parser::{Expr, PathComponent, PathOrIdentifier, Span, Test, WithSpan};
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use syn::Token;
+
+use super::{DisplayWrap, Generator};
+use crate::heritage::Context;
+use crate::integration::Buffer;
+use crate::{CompileError, field_new, quote_into};
+
+impl<'a> Generator<'a, '_> {
+    pub(super) fn visit_test(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        test: &Test<'a>,
+        node: Span,
+    ) -> Result<DisplayWrap, CompileError> {
+        let span = ctx.span_for_node(node);
+        let name = match &test.name {
+            PathOrIdentifier::Identifier(name) => Some(*name),
+            PathOrIdentifier::Path(path) => match path.as_slice() {
+                [arg] if arg.generics.is_none() => Some(arg.name),
+                _ => None,
+            },
+        };
+
+        let mut tmp = Buffer::new();
+        match name.as_deref().copied() {
+            Some(name @ ("even" | "odd" | "none" | "some" | "empty")) => {
+                let [value] = test_args(ctx, name, &test.arguments, node)?;
+                self.visit_builtin_test(ctx, &mut tmp, name, value, None, span)?;
+            }
+            Some(name @ ("divisibleby" | "startingwith" | "in")) => {
+                let [value, arg] = test_args(ctx, name, &test.arguments, node)?;
+                // The divisor is passed by value, so its type can be inferred from the value.
+                let arg = match name {
+                    "divisibleby" => self.visit_expr_to_tokens(ctx, arg)?,
+                    _ => self.visit_test_arg(ctx, arg)?,
+                };
+                self.visit_builtin_test(ctx, &mut tmp, name, value, Some(arg), span)?;
+            }
+            _ => self.visit_custom_test(ctx, &mut tmp, test, name, node)?,
+        }
+
+        let tmp = tmp.into_token_stream();
+        if test.negated {
+            buf.write_token(Token![!], span);
+        }
+        quote_into!(buf, span, { (#tmp) });
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_custom_test(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        test: &Test<'a>,
+        name: Option<WithSpan<&'a str>>,
+        node: Span,
+    ) -> Result<(), CompileError> {
+        let path = match (name, &test.name) {
+            // A custom test is looked up in the `template_tests` module, like custom filters are
+            // looked up in the `filters` module. The module gets the span of the test, so where
+            // spans are available, an unknown test is reported at its location in the template.
+            (Some(name), _) => &[
+                PathComponent::new_with_name(WithSpan::new("template_tests", name.span())),
+                PathComponent::new_with_name(name),
+            ],
+            (None, PathOrIdentifier::Path(path)) => path.as_slice(),
+            (None, PathOrIdentifier::Identifier(_)) => unreachable!(),
+        };
+        self.visit_custom_filter_with_path(ctx, buf, path, &test.arguments, node)?;
+        Ok(())
+    }
+
+    fn visit_builtin_test(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        value: &WithSpan<Box<Expr<'a>>>,
+        arg: Option<TokenStream>,
+        span: proc_macro2::Span,
+    ) -> Result<(), CompileError> {
+        let name = field_new(name, span);
+        let value = self.visit_test_arg(ctx, value)?;
+        let arg = arg.map(|arg| quote_spanned!(span => , #arg));
+        quote_into!(buf, span, { askama::tests::#name(#value #arg) });
+        Ok(())
+    }
+
+    /// Built-in tests take their arguments by reference.
+    fn visit_test_arg(
+        &mut self,
+        ctx: &Context<'_>,
+        arg: &WithSpan<Box<Expr<'a>>>,
+    ) -> Result<TokenStream, CompileError> {
+        let span = ctx.span_for_node(arg.span());
+        let arg = self.visit_expr_to_tokens(ctx, arg)?;
+        Ok(quote_spanned!(span => &(#arg)))
+    }
+
+    pub(super) fn visit_expr_to_tokens(
+        &mut self,
+        ctx: &Context<'_>,
+        expr: &WithSpan<Box<Expr<'a>>>,
+    ) -> Result<TokenStream, CompileError> {
+        let mut tmp = Buffer::new();
+        self.visit_expr(ctx, &mut tmp, expr)?;
+        Ok(tmp.into_token_stream())
+    }
+}
+
+/// Returns the tested value and the arguments of a built-in test.
+fn test_args<'a, 'b, const N: usize>(
+    ctx: &Context<'_>,
+    name: &str,
+    args: &'b [WithSpan<Box<Expr<'a>>>],
+    node: Span,
+) -> Result<[&'b WithSpan<Box<Expr<'a>>>; N], CompileError> {
+    if let Some(arg) = args
+        .iter()
+        .find(|arg| matches!(****arg, Expr::NamedArgument(..)))
+    {
+        return Err(ctx.generate_error(
+            format_args!("`{name}` test cannot accept named arguments"),
+            arg.span(),
+        ));
+    }
+    let args = args.iter().collect::<Vec<_>>();
+    args.try_into()
+        .map_err(|args: Vec<&WithSpan<Box<Expr<'a>>>>| {
+            let msg = match N {
+                1 => format!("`{name}` test does not have any arguments"),
+                _ => format!(
+                    "`{name}` test expects {} argument{}, found {}",
+                    N - 1,
+                    if N == 2 { "" } else { "s" },
+                    args.len() - 1,
+                ),
+            };
+            ctx.generate_error(msg, args.get(N).map_or(node, |arg| arg.span()))
+        })
+}
@@ -11,6 +11,7 @@ use syn::spanned::Spanned;
 use syn::{Attribute, Expr, ExprLit, ExprPath, Ident, Lit, LitBool, LitStr, Meta, Token};
 
 use crate::config::{Config, SyntaxAndCache};
+use crate::lints::{Lint, Lints};
 use crate::spans::SourceSpan;
 use crate::{CompileError, FileInfo, HashMap, MsgValidEscapers};
 
@@ -29,6 +30,10 @@ pub(crate) struct TemplateInput<'a> {
     pub(crate) escaper: &'a str,
     pub(crate) path: Arc<Path>,
     pub(crate) fields: Arc<[String]>,
+    pub(crate) reload: bool,
+    pub(crate) render_blocks: bool,
+    pub(crate) values: Option<&'a [ValueDecl]>,
+    pub(crate) lints: &'a Lints,
 }
 
 impl TemplateInput<'_> {
@@ -40,6 +45,7 @@ impl TemplateInput<'_> {
         enum_ast: Option<&'n syn::DeriveInput>,
         config: &'n Config,
         args: &'n TemplateArgs,
+        lints: &'n Lints,
     ) -> Result<TemplateInput<'n>, CompileError> {
         let TemplateArgs {
             template_span,
@@ -51,6 +57,9 @@ impl TemplateInput<'_> {
             ext,
             ext_span,
             syntax,
+            reload,
+            render_blocks,
+            values,
             ..
         } = args;
 
@@ -145,6 +154,10 @@ impl TemplateInput<'_> {
             escaper,
             path,
             fields: fields.into(),
+            reload: *reload,
+            render_blocks: *render_blocks,
+            values: values.as_deref(),
+            lints,
         })
     }
 
@@ -175,17 +188,22 @@ impl TemplateInput<'_> {
         while let Some((path, source, source_path)) = check.pop() {
             let parsed = match self.syntax.parse(Arc::clone(&source), source_path) {
                 Ok(parsed) => parsed,
-                Err(err) => {
-                    let msg = err
-                        .message
-                        .unwrap_or_else(|| "failed to parse template source".into());
-                    let file_path = err
-                        .file_path
-                        .as_deref()
-                        .unwrap_or(Path::new("<source attribute>"));
-                    let file_info =
-                        FileInfo::new(file_path, Some(&source), Some(&source[err.offset..]));
-                    return Err(CompileError::new(msg, Some(file_info)));
+                Err(errors) => {
+                    let mut errors = errors.into_iter().map(|err| {
+                        let msg = err
+                            .message
+                            .unwrap_or_else(|| "failed to parse template source".into());
+                        let file_path = err
+                            .file_path
+                            .as_deref()
+                            .unwrap_or(Path::new("<source attribute>"));
+                        let file_info =
+                            FileInfo::new(file_path, Some(&source), Some(&source[err.offset..]));
+                        CompileError::new(msg, Some(file_info))
+                    });
+                    let mut err = errors.next().unwrap();
+                    err.others = errors.collect();
+                    return Err(err);
                 }
             };
 
@@ -280,6 +298,9 @@ impl TemplateInput<'_> {
                         Node::FilterBlock(f) => {
                             nested.push(&f.nodes);
                         }
+                        Node::WithValues(w) => {
+                            nested.push(&w.nodes);
+                        }
                         Node::Include(include) => {
                             #[cfg(not(feature = "external-sources"))]
                             {
@@ -447,11 +468,54 @@ pub(crate) struct TemplateArgs {
     crate_name: Option<ExprPath>,
     pub(crate) whitespace: Option<Whitespace>,
     pub(crate) config_span: Option<Span>,
+    reload: bool,
+    render_blocks: bool,
+    values: Option<Vec<ValueDecl>>,
+    pub(crate) allow: Vec<Lint>,
+    pub(crate) named: Vec<NamedTemplate>,
+}
+
+/// An additional template of a `struct`, declared with `#[template(name = "...", ...)]`
+pub(crate) struct NamedTemplate {
+    pub(crate) name: String,
+    pub(crate) args: TemplateArgs,
 }
 
 impl TemplateArgs {
     pub(crate) fn new(ast: &syn::DeriveInput) -> Result<Self, CompileError> {
-        Self::from_partial(ast, PartialTemplateArgs::new(ast, &ast.attrs, false)?)
+        let (attrs, named) = PartialTemplateArgs::split_named(&ast.attrs)?;
+        let main = PartialTemplateArgs::new(ast, &attrs, false)?;
+        if main.is_none()
+            && let Some((name, _)) = named.first()
+        {
+            return Err(CompileError::no_file_info(
+                "named templates need a main `#[template]` attribute without `name`",
+                Some(name.span()),
+            ));
+        }
+        let mut args = Self::from_partial(ast, main)?;
+        for (name, attrs) in named {
+            let Some(partial) = PartialTemplateArgs::new(ast, &attrs, false)? else {
+                unreachable!("named templates have a `template` attribute");
+            };
+            let unsupported = [
+                ("askama", partial.crate_name.is_some()),
+                ("blocks", partial.blocks.is_some()),
+                ("reload", partial.reload.is_some()),
+                ("render_blocks", partial.render_blocks.is_some()),
+            ];
+            if let Some((arg, _)) = unsupported.into_iter().find(|(_, used)| *used) {
+                return Err(CompileError::no_file_info(
+                    format_args!("template attribute `{arg}` cannot be used in a named template"),
+                    Some(name.span()),
+                ));
+            }
+            args.named.push(NamedTemplate {
+                name: name.value(),
+                args: Self::from_partial(ast, Some(partial))?,
+            });
+        }
+        Ok(args)
     }
 
     pub(crate) fn from_partial(
@@ -465,6 +529,26 @@ impl TemplateArgs {
                 Some(ast.ident.span()),
             ));
         };
+        let reload = match &args.reload {
+            Some(reload) if reload.value() => match args.source {
+                #[cfg(feature = "external-sources")]
+                Some(PartialTemplateArgsSource::Path(_)) if cfg!(not(feature = "reload")) => {
+                    return Err(CompileError::no_file_info(
+                        "template attribute `reload` requires the `reload` feature to be enabled",
+                        Some(reload.span()),
+                    ));
+                }
+                #[cfg(feature = "external-sources")]
+                Some(PartialTemplateArgsSource::Path(_)) => true,
+                _ => {
+                    return Err(CompileError::no_file_info(
+                        "template attribute `reload` can only be used together with `path`",
+                        Some(reload.span()),
+                    ));
+                }
+            },
+            _ => false,
+        };
         Ok(Self {
             template_span: args.template_span,
             source: match args.source {
@@ -508,6 +592,11 @@ impl TemplateArgs {
             crate_name: args.crate_name,
             whitespace: args.whitespace,
             config_span: args.config.as_ref().map(|value| value.span()),
+            reload,
+            render_blocks: args.render_blocks.is_some_and(|value| value.value()),
+            values: args.values,
+            allow: args.allow.unwrap_or_default(),
+            named: vec![],
         })
     }
 
@@ -526,6 +615,11 @@ impl TemplateArgs {
             crate_name: None,
             whitespace: None,
             config_span: None,
+            reload: false,
+            render_blocks: false,
+            values: None,
+            allow: vec![],
+            named: vec![],
         }
     }
 
@@ -781,6 +875,33 @@ pub(crate) struct PartialTemplateArgs {
     pub(crate) whitespace: Option<Whitespace>,
     pub(crate) crate_name: Option<ExprPath>,
     pub(crate) blocks: Option<Vec<LitStr>>,
+    pub(crate) reload: Option<LitBool>,
+    pub(crate) render_blocks: Option<LitBool>,
+    pub(crate) values: Option<Vec<ValueDecl>>,
+    pub(crate) allow: Option<Vec<Lint>>,
+}
+
+/// A runtime value declared with `#[template(values(name: Type, ...))]`
+#[derive(Clone)]
+pub(crate) struct ValueDecl {
+    pub(crate) name: Ident,
+    pub(crate) ty: syn::Type,
+}
+
+impl ValueDecl {
+    /// The key of the value in the `askama::Values`
+    pub(crate) fn key(&self) -> String {
+        self.name.to_string().trim_start_matches("r#").to_owned()
+    }
+}
+
+impl syn::parse::Parse for ValueDecl {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        Ok(Self { name, ty })
+    }
 }
 
 #[derive(Clone)]
@@ -816,6 +937,108 @@ const _: () = {
         }
     }
 
+    impl PartialTemplateArgs {
+        /// Splits the attributes into the ones of the main template, and the ones of every
+        /// template that is named with `#[template(name = "...")]`.
+        ///
+        /// All other attributes, e.g. the docs, are kept for every template.
+        #[allow(clippy::type_complexity)]
+        pub(crate) fn split_named(
+            attrs: &[Attribute],
+        ) -> Result<(Vec<Attribute>, Vec<(LitStr, Vec<Attribute>)>), CompileError> {
+            let mut main = vec![];
+            let mut others = vec![];
+            let mut named: Vec<(LitStr, Vec<Attribute>)> = vec![];
+            for attr in attrs {
+                if !attr.path().is_ident("template") {
+                    others.push(attr.clone());
+                    continue;
+                }
+                let Some(name) = named_template_name(attr)? else {
+                    main.push(attr.clone());
+                    continue;
+                };
+                match named.iter_mut().find(|(n, _)| n.value() == name.value()) {
+                    Some((_, attrs)) => attrs.push(attr.clone()),
+                    None => named.push((name, vec![attr.clone()])),
+                }
+            }
+            // `blocks = ["..."]` generates a method `as_<block>()`, the same as a named template.
+            for block in main.iter().flat_map(template_blocks) {
+                if let Some((name, _)) = named.iter().find(|(n, _)| n.value() == block) {
+                    return Err(CompileError::no_file_info(
+                        format_args!(
+                            "the named template `{block}` clashes with the block `{block}` of \
+                            `blocks`: both would generate a method `as_{block}()`",
+                        ),
+                        Some(name.span()),
+                    ));
+                }
+            }
+            main.extend(others.iter().cloned());
+            for (_, attrs) in &mut named {
+                attrs.extend(others.iter().cloned());
+            }
+            Ok((main, named))
+        }
+    }
+
+    /// Returns the `name = "..."` argument of a `#[template]` attribute.
+    ///
+    /// Unparsable attributes are reported later by `PartialTemplateArgs::new()`.
+    fn named_template_name(attr: &Attribute) -> Result<Option<LitStr>, CompileError> {
+        let Ok(args) = attr.parse_args_with(<Punctuated<Meta, Token![,]>>::parse_terminated) else {
+            return Ok(None);
+        };
+        for arg in args {
+            let Meta::NameValue(pair) = arg else {
+                continue;
+            };
+            let Some(ident) = pair.path.get_ident() else {
+                continue;
+            };
+            if ident != "name" {
+                continue;
+            }
+            let name = get_strlit(ident, get_lit(ident, pair.value)?)?;
+            if syn::parse_str::<Ident>(&name.value()).is_err() {
+                return Err(CompileError::no_file_info(
+                    "the name of a template must be an identifier",
+                    Some(name.span()),
+                ));
+            }
+            return Ok(Some(name));
+        }
+        Ok(None)
+    }
+
+    /// Returns the names in the `blocks = [...]` argument of a `#[template]` attribute.
+    ///
+    /// Unparsable attributes are reported later by `PartialTemplateArgs::new()`.
+    fn template_blocks(attr: &Attribute) -> Vec<String> {
+        let Ok(args) = attr.parse_args_with(<Punctuated<Meta, Token![,]>>::parse_terminated) else {
+            return vec![];
+        };
+        let Some(Meta::NameValue(pair)) =
+            args.into_iter().find(|arg| arg.path().is_ident("blocks"))
+        else {
+            return vec![];
+        };
+        let Expr::Array(array) = pair.value else {
+            return vec![];
+        };
+        array
+            .elems
+            .into_iter()
+            .filter_map(|elem| match elem {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => Some(s.value()),
+                _ => None,
+            })
+            .collect()
+    }
+
     #[inline]
     fn new(
         ast: &syn::DeriveInput,
@@ -846,6 +1069,10 @@ const _: () = {
             whitespace: None,
             crate_name: None,
             blocks: None,
+            reload: None,
+            render_blocks: None,
+            values: None,
+            allow: None,
         };
         let mut has_data = false;
 
@@ -875,6 +1102,18 @@ const _: () = {
             for arg in args {
                 let pair = match arg {
                     Meta::NameValue(pair) => pair,
+                    Meta::List(list) if list.path.is_ident("values") => {
+                        let ident = list.path.get_ident().unwrap();
+                        ensure_only_once(ident, &mut this.values)?;
+                        this.values = Some(get_value_decls(&list)?);
+                        continue;
+                    }
+                    Meta::List(list) if list.path.is_ident("allow") => {
+                        let ident = list.path.get_ident().unwrap();
+                        ensure_only_once(ident, &mut this.allow)?;
+                        this.allow = Some(get_lints(&list)?);
+                        continue;
+                    }
                     v => {
                         return Err(CompileError::no_file_info(
                             "unsupported attribute argument",
@@ -959,6 +1198,32 @@ const _: () = {
                             Source::Source("".into()),
                         ));
                     }
+                } else if ident == "reload" {
+                    if let syn::Data::Enum(_) = ast.data {
+                        return Err(CompileError::no_file_info(
+                            "template attribute `reload` can only be used on a `struct`",
+                            Some(ident.span()),
+                        ));
+                    }
+                    ensure_only_once(ident, &mut this.reload)?;
+                    this.reload = Some(get_boollit(ident, value)?);
+                } else if ident == "name" {
+                    if let syn::Data::Enum(_) = ast.data {
+                        return Err(CompileError::no_file_info(
+                            "template attribute `name` can only be used on a `struct`",
+                            Some(ident.span()),
+                        ));
+                    }
+                    // already handled in `PartialTemplateArgs::split_named()`
+                } else if ident == "render_blocks" {
+                    if let syn::Data::Enum(_) = ast.data {
+                        return Err(CompileError::no_file_info(
+                            "template attribute `render_blocks` can only be used on a `struct`",
+                            Some(ident.span()),
+                        ));
+                    }
+                    ensure_only_once(ident, &mut this.render_blocks)?;
+                    this.render_blocks = Some(get_boollit(ident, value)?);
                 } else if ident == "block" {
                     set_strlit_pair(ident, value, &mut this.block)?;
                 } else if ident == "print" {
@@ -997,6 +1262,46 @@ const _: () = {
         Ok(Some(this))
     }
 
+    fn get_value_decls(list: &syn::MetaList) -> Result<Vec<ValueDecl>, CompileError> {
+        let decls = list
+            .parse_args_with(<Punctuated<ValueDecl, Token![,]>>::parse_terminated)
+            .map_err(|e| {
+                CompileError::no_file_info(
+                    format_args!("unable to parse value declarations: {e}"),
+                    Some(e.span()),
+                )
+            })?;
+        let mut names = crate::HashSet::default();
+        for decl in &decls {
+            if !names.insert(decl.key()) {
+                return Err(CompileError::no_file_info(
+                    format_args!("value `{}` is declared more than once", decl.name),
+                    Some(decl.name.span()),
+                ));
+            }
+        }
+        Ok(decls.into_iter().collect())
+    }
+
+    fn get_lints(list: &syn::MetaList) -> Result<Vec<Lint>, CompileError> {
+        let names = list
+            .parse_args_with(<Punctuated<Ident, Token![,]>>::parse_terminated)
+            .map_err(|e| {
+                CompileError::no_file_info(
+                    format_args!("unable to parse allowed lints: {e}"),
+                    Some(e.span()),
+                )
+            })?;
+        names
+            .iter()
+            .map(|name| {
+                name.to_string()
+                    .parse()
+                    .map_err(|msg| CompileError::no_file_info(msg, Some(name.span())))
+            })
+            .collect()
+    }
+
     fn set_strlit_pair(
         name: &Ident,
         value: ExprLit,
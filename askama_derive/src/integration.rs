@@ -77,6 +77,15 @@ fn impl_fast_writable(ast: &DeriveInput, buf: &mut Buffer) {
             ) -> askama::Result<()> {
                 askama::Template::render_into_with_values(self, dest, values)
             }
+
+            #[inline]
+            fn write_bytes_into(
+                &self,
+                dest: &mut dyn askama::ByteSink,
+                values: &dyn askama::Values,
+            ) -> askama::Result<()> {
+                askama::Template::write_bytes_with_values(self, dest, values)
+            }
         }
     });
 }
@@ -335,6 +344,8 @@ pub(crate) fn build_template_enum(
             set_default(&mut var_args, enum_args, |v| &mut v.syntax);
             set_default(&mut var_args, enum_args, |v| &mut v.config);
             set_default(&mut var_args, enum_args, |v| &mut v.whitespace);
+            set_default(&mut var_args, enum_args, |v| &mut v.values);
+            set_default(&mut var_args, enum_args, |v| &mut v.allow);
         }
         let size_hint = biggest_size_hint.max(build_template_item(
             buf,
@@ -374,7 +385,7 @@ pub(crate) fn build_template_enum(
         render_into_arms.extend(quote_spanned! {
             span =>
             ref #var_arg => {
-                <_ as askama::helpers::EnumVariantTemplate>::render_into_with_values(
+                <_ as askama::helpers::TemplateBody>::render_body(
                     #var_arg,
                     #var_writer,
                     #var_values,
@@ -395,9 +406,25 @@ pub(crate) fn build_template_enum(
     methods.extend(quote_spanned!(span =>
         fn render_into_with_values(
             &self,
-            #var_writer: &mut dyn askama::helpers::core::fmt::Write,
+            mut #var_writer: &mut dyn askama::helpers::core::fmt::Write,
             #var_values: &dyn askama::Values,
         ) -> askama::Result<()> {
+            let #var_writer: &mut dyn askama::helpers::TemplateWriter = &mut #var_writer;
+            match *self {
+                #render_into_arms
+            }
+        }
+
+        fn write_bytes_with_values<__AskamaSink: askama::ByteSink + ?askama::helpers::core::marker::Sized>(
+            &self,
+            mut #var_writer: &mut __AskamaSink,
+            #var_values: &dyn askama::Values,
+        ) -> askama::Result<()> {
+            let size_hint = match self {
+                #size_hint_arms
+            };
+            askama::ByteSink::reserve(#var_writer, size_hint);
+            let #var_writer: &mut dyn askama::helpers::TemplateWriter = &mut #var_writer;
             match *self {
                 #render_into_arms
             }
@@ -543,7 +570,7 @@ fn type_for_enum_variant(
     )
 }
 
-/// Generates a `match` arm for an `enum` variant, that calls `<_ as EnumVariantTemplate>::render_into()`
+/// Generates a `match` arm for an `enum` variant, that calls `<_ as TemplateBody>::render_body()`
 /// for that type and data
 fn variant_as_arm(
     var_ast: &DeriveInput,
@@ -621,7 +648,7 @@ fn variant_as_arm(
     render_into_arms.extend(quote_spanned! {
         span =>
         Self :: #ident { #src } => {
-            <_ as askama::helpers::EnumVariantTemplate>::render_into_with_values(
+            <_ as askama::helpers::TemplateBody>::render_body(
                 & #var_id #ty_generics { #this },
                 #var_writer,
                 #var_values,
@@ -16,9 +16,13 @@ mod heritage;
 mod html;
 mod input;
 mod integration;
+mod lints;
 mod spans;
 #[cfg(test)]
 mod tests;
+#[doc(hidden)]
+pub mod tooling;
+mod values;
 
 #[doc(hidden)]
 #[cfg(feature = "proc-macro")]
@@ -45,11 +49,13 @@ use syn::spanned::Spanned;
 use syn::{Ident, parse2};
 
 use crate::config::{Config, read_config_file};
-pub use crate::filter_fn::derive_filter_fn;
+pub use crate::filter_fn::{derive_filter_fn, derive_test_fn};
 use crate::generator::{TmplKind, template_to_string};
 use crate::heritage::{Context, Heritage};
-use crate::input::{AnyTemplateArgs, Print, TemplateArgs, TemplateInput};
+use crate::input::{AnyTemplateArgs, NamedTemplate, Print, TemplateArgs, TemplateInput};
 use crate::integration::{Buffer, build_template_enum};
+use crate::lints::Lints;
+pub use crate::values::derive_values;
 
 /// [`true`] if and only if [`crate`] is compiled with feature `"external-sources"`.
 pub const CAN_USE_EXTERNAL_SOURCES: bool = cfg!(feature = "external-sources");
@@ -201,6 +207,13 @@ macro_rules! make_derive_template {
         /// Set the syntax name for a parser defined in the configuration file.
         /// The default syntax, `"default"`,  is the one provided by Askama.
         ///
+        /// ### allow
+        ///
+        /// E.g. `allow(unused_variables, unused_macros)`
+        ///
+        /// Silence the warnings about unused items of the template: `unused_variables`,
+        /// `unused_macros`, `unused_imports` and `unused_blocks`.
+        ///
         /// ### askama
         ///
         /// E.g. `askama = askama`
@@ -267,6 +280,79 @@ macro_rules! make_filter_fn {
     }
 }
 
+#[macro_export]
+#[cfg(feature = "proc-macro")]
+macro_rules! make_test_fn {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident() {
+            $($import:stmt)+
+        }
+    ) => {
+        $(#[$meta])*
+        $vis fn $name(
+            attr: $crate::__macro_support::TokenStream1,
+            item: $crate::__macro_support::TokenStream1,
+        ) -> $crate::__macro_support::TokenStream1 {
+            fn import_askama() -> $crate::__macro_support::TokenStream2 {
+                $crate::__macro_support::quote!($($import)*)
+            }
+
+            $crate::derive_test_fn(attr.into(), item.into(), import_askama).into()
+        }
+    }
+}
+
+#[macro_export]
+#[cfg(feature = "proc-macro")]
+macro_rules! make_derive_values {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident() {
+            $($import:stmt)+
+        }
+    ) => {
+        /// The `Values` derive macro and its `values()` attribute.
+        ///
+        /// Implements [`Values`](../askama/trait.Values.html) for a struct with named fields.
+        /// Each field is available as runtime value with the name of the field. For each field,
+        /// an associated constant with the uppercase name of the field is generated, that holds
+        /// the typed [`Key`](../askama/struct.Key.html) of the value.
+        ///
+        /// The field types must be `'static`, because the values are accessed as `dyn Any`.
+        ///
+        /// ## Attributes
+        ///
+        /// * `#[values(rename = "name")]` on a field: use another name for the value.
+        /// * `#[values(skip)]` on a field: don't make the field available.
+        /// * `#[values(askama = path)]` on the struct: the path to the `askama` crate,
+        ///   cf. the `askama` attribute of `#[derive(Template)]`.
+        ///
+        /// ```rust,ignore
+        /// #[derive(askama::Values)]
+        /// struct RequestValues {
+        ///     user: String,
+        ///     #[values(rename = "csrf-token")]
+        ///     csrf_token: String,
+        /// }
+        ///
+        /// // in a template: {{ RequestValues::USER|value }}
+        /// let values = RequestValues { user: "Ferris".into(), csrf_token: "…".into() };
+        /// assert_eq!(RequestValues::USER.get(&values).unwrap(), "Ferris");
+        /// ```
+        $(#[$meta])*
+        $vis fn $name(
+            input: $crate::__macro_support::TokenStream1,
+        ) -> $crate::__macro_support::TokenStream1 {
+            fn import_askama() -> $crate::__macro_support::TokenStream2 {
+                $crate::__macro_support::quote!($($import)*)
+            }
+
+            $crate::derive_values(input.into(), import_askama).into()
+        }
+    };
+}
+
 pub fn derive_template(input: TokenStream, import_askama: fn() -> TokenStream) -> TokenStream {
     let ast = match parse_ts_or_compile_error(input, import_askama) {
         ControlFlow::Continue(ast) => ast,
@@ -282,11 +368,14 @@ pub fn derive_template(input: TokenStream, import_askama: fn() -> TokenStream) -
 
     let ts = match args.and_then(|args| build_template(&mut buf, &ast, args)) {
         Ok(_) => buf.into_token_stream(),
-        Err(CompileError { msg, span }) => {
-            let mut ts = quote::quote_spanned! {
-                span.unwrap_or(ast.ident.span()) =>
-                askama::helpers::core::compile_error!(#msg);
-            };
+        Err(err) => {
+            let mut ts = TokenStream::new();
+            for CompileError { msg, span, .. } in [&err].into_iter().chain(&err.others) {
+                ts.extend(quote::quote_spanned! {
+                    span.unwrap_or(ast.ident.span()) =>
+                    askama::helpers::core::compile_error!(#msg);
+                });
+            }
             buf.clear();
             if build_skeleton(&mut buf, &ast).is_ok() {
                 let source: TokenStream = buf.into_token_stream();
@@ -346,7 +435,8 @@ fn parse_ts_or_compile_error<T: Parse>(
 fn build_skeleton(buf: &mut Buffer, ast: &syn::DeriveInput) -> Result<SizeHint, CompileError> {
     let template_args = TemplateArgs::fallback();
     let config = Config::new("", None, None, None, None)?;
-    let input = TemplateInput::new(ast, None, config, &template_args)?;
+    let lints = Lints::default();
+    let input = TemplateInput::new(ast, None, config, &template_args, &lints)?;
     let mut contexts = HashMap::default();
     let parsed = parser::Parsed::default();
     contexts.insert(&input.path, Context::empty(&parsed, ast.span()));
@@ -369,7 +459,12 @@ pub(crate) fn build_template(
     let mut result = match args {
         AnyTemplateArgs::Struct(item) => {
             err_span = Some(item.source.1.config_span());
-            build_template_item(buf, ast, None, &item, TmplKind::Struct)
+            build_template_item(buf, ast, None, &item, TmplKind::Struct).and_then(|size_hint| {
+                for named in &item.named {
+                    build_named_template(buf, ast, named)?;
+                }
+                Ok(size_hint)
+            })
         }
         AnyTemplateArgs::Enum {
             enum_args,
@@ -384,14 +479,49 @@ pub(crate) fn build_template(
             build_template_enum(buf, ast, enum_args, vars_args, has_default_impl)
         }
     };
-    if let Err(err) = &mut result
-        && err.span.is_none()
-    {
-        err.span = err_span;
+    if let Err(err) = &mut result {
+        err.set_default_span(err_span);
     }
     result
 }
 
+/// Generates the method `as_<name>()` for a template declared with `#[template(name = "...")]`
+fn build_named_template(
+    buf: &mut Buffer,
+    ast: &syn::DeriveInput,
+    named: &NamedTemplate,
+) -> Result<(), CompileError> {
+    let ident = &ast.ident;
+    let doc = format!(
+        "The template `{}` of [`{ident}`], rendered with the same data.",
+        named.name,
+    );
+    let trait_name = format!("__Askama__{ident}__named__{}", named.name);
+
+    let mut template_buf = Buffer::new();
+    let size_hint = build_template_item(
+        &mut template_buf,
+        ast,
+        None,
+        &named.args,
+        TmplKind::Block(&trait_name),
+    )
+    .map_err(|mut err| {
+        err.set_default_span(Some(named.args.source.1.config_span()));
+        err
+    })?;
+    generator::impl_sub_template(
+        buf,
+        ast,
+        &named.name,
+        &trait_name,
+        &doc,
+        template_buf.into_token_stream(),
+        size_hint,
+    );
+    Ok(())
+}
+
 #[derive(Default)]
 pub(crate) struct CalledBlocks<'a> {
     pub(crate) called_blocks: HashMap<&'a str, Vec<FileInfo<'a>>>,
@@ -425,7 +555,8 @@ fn build_template_item(
         template_args.config_span,
         full_config_path,
     )?;
-    let input = TemplateInput::new(ast, enum_ast, config, template_args)?;
+    let lints = Lints::new(&template_args.allow);
+    let input = TemplateInput::new(ast, enum_ast, config, template_args, &lints)?;
 
     let mut templates = HashMap::default();
     input.find_used_templates(&mut templates)?;
@@ -484,6 +615,9 @@ fn build_template_item(
     }
 
     let size_hint = template_to_string(buf, &input, &contexts, heritage.as_ref(), tmpl_kind)?;
+    for warning in lints.finish(&input, &contexts) {
+        buf.write_tokens(warning.to_token_stream());
+    }
 
     if input.print == Print::Code || input.print == Print::All {
         eprintln!("== Askama code ==\n{}", buf.to_token_stream());
@@ -496,6 +630,8 @@ fn build_template_item(
 struct CompileError {
     msg: String,
     span: Option<Span>,
+    /// Errors reported together with this one, e.g. the other syntax errors of a template.
+    others: Vec<CompileError>,
 }
 
 impl CompileError {
@@ -529,13 +665,29 @@ impl CompileError {
             (None, Some(file_info)) => format!("{msg}{file_info}"),
             _ => msg.to_string(),
         };
-        Self { msg, span }
+        Self {
+            msg,
+            span,
+            others: Vec::new(),
+        }
+    }
+
+    /// Sets the span of this error and of the errors reported with it, if they don't have one.
+    fn set_default_span(&mut self, span: Option<Span>) {
+        let spans =
+            std::iter::once(&mut self.span).chain(self.others.iter_mut().map(|e| &mut e.span));
+        for err_span in spans {
+            if err_span.is_none() {
+                *err_span = span;
+            }
+        }
     }
 
     fn no_file_info<S: ToString>(msg: S, span: Option<Span>) -> Self {
         Self {
             msg: msg.to_string(),
             span,
+            others: Vec::new(),
         }
     }
 }
@@ -770,6 +922,10 @@ fn var_values() -> Ident {
     syn::Ident::new("__askama_values", proc_macro2::Span::call_site())
 }
 
+fn var_values_layer() -> Ident {
+    syn::Ident::new("__askama_values_layer", proc_macro2::Span::call_site())
+}
+
 fn var_arg() -> Ident {
     syn::Ident::new("__askama_arg", proc_macro2::Span::call_site())
 }
@@ -778,6 +934,10 @@ fn var_item() -> Ident {
     syn::Ident::new("__askama_item", proc_macro2::Span::call_site())
 }
 
+fn var_elem() -> Ident {
+    syn::Ident::new("__askama_elem", proc_macro2::Span::call_site())
+}
+
 fn var_len() -> Ident {
     syn::Ident::new("__askama_len", proc_macro2::Span::call_site())
 }
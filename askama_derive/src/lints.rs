@@ -0,0 +1,285 @@
+//! Warnings about the unused items of a template, which can be silenced with
+//! `#[template(allow(...))]`.
+//!
+//! A proc-macro cannot emit warnings on stable Rust, so every warning is reported as the use of a
+//! deprecated constant, named like the lint, whose deprecation note is the actual message.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use parser::node::Macro;
+use parser::{Node, Span};
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+
+use crate::heritage::Context;
+use crate::input::TemplateInput;
+use crate::{HashMap, HashSet, field_new};
+
+// The variants are named like the lints of `rustc`.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Lint {
+    /// A variable declared with `{% let %}` is never read.
+    UnusedVariables,
+    /// A macro of the template is never called.
+    UnusedMacros,
+    /// The scope of an `{% import %}` is never used.
+    UnusedImports,
+    /// A block of an extending template is not declared by any template it extends.
+    UnusedBlocks,
+}
+
+impl Lint {
+    const ALL: &[Self] = &[
+        Self::UnusedVariables,
+        Self::UnusedMacros,
+        Self::UnusedImports,
+        Self::UnusedBlocks,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::UnusedVariables => "unused_variables",
+            Self::UnusedMacros => "unused_macros",
+            Self::UnusedImports => "unused_imports",
+            Self::UnusedBlocks => "unused_blocks",
+        }
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(|lint| lint.name());
+                format!(
+                    "unknown lint `{s}`, expected one of {}",
+                    names
+                        .map(|name| format!("`{name}`"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+            })
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Collects the warnings of a template while its code is generated.
+#[derive(Default)]
+pub(crate) struct Lints {
+    allowed: Vec<Lint>,
+    /// The variables declared with `{% let %}`, and whether they were read.
+    ///
+    /// The same declaration can be generated multiple times, e.g. in a macro that is called
+    /// twice, or in a block that is rendered on its own, so a variable is only reported if none
+    /// of its declarations was read.
+    variables: RefCell<Vec<(Rc<Cell<bool>>, Warning)>>,
+    /// The addresses of the called macros.
+    called_macros: RefCell<HashSet<usize>>,
+    /// The scopes and paths of the used imports.
+    used_imports: RefCell<HashSet<(String, Arc<Path>)>>,
+}
+
+impl Lints {
+    pub(crate) fn new(allowed: &[Lint]) -> Self {
+        Self {
+            allowed: allowed.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    fn is_allowed(&self, lint: Lint) -> bool {
+        self.allowed.contains(&lint)
+    }
+
+    /// Tracks a variable declared with `{% let %}`. The returned flag must be set once the
+    /// variable is read.
+    pub(crate) fn declare_variable(
+        &self,
+        ctx: &Context<'_>,
+        name: &str,
+        node: Span,
+    ) -> Option<Rc<Cell<bool>>> {
+        if self.is_allowed(Lint::UnusedVariables) || name.starts_with('_') {
+            return None;
+        }
+        let used = Rc::new(Cell::new(false));
+        let warning = Warning::new(
+            Lint::UnusedVariables,
+            ctx,
+            format_args!(
+                "variable `{name}` is never used; if this is intentional, prefix it with an \
+                underscore: `_{name}`",
+            ),
+            node,
+        );
+        self.variables
+            .borrow_mut()
+            .push((Rc::clone(&used), warning));
+        Some(used)
+    }
+
+    pub(crate) fn call_macro(&self, def: &Macro<'_>) {
+        self.called_macros
+            .borrow_mut()
+            .insert(std::ptr::from_ref(def).addr());
+    }
+
+    pub(crate) fn use_import(&self, scope: &str, path: &Arc<Path>) {
+        self.used_imports
+            .borrow_mut()
+            .insert((scope.to_owned(), Arc::clone(path)));
+    }
+
+    /// Returns the warnings of the template, once its code was generated.
+    pub(crate) fn finish(
+        &self,
+        input: &TemplateInput<'_>,
+        contexts: &HashMap<&Arc<Path>, Context<'_>>,
+    ) -> Vec<Warning> {
+        let mut warnings = vec![];
+
+        let mut variables: HashMap<&WarningKey, (bool, &Warning)> = HashMap::default();
+        let declared = self.variables.borrow();
+        for (used, warning) in declared.iter() {
+            let entry = variables.entry(&warning.key).or_insert((false, warning));
+            entry.0 |= used.get();
+        }
+        warnings.extend(
+            variables
+                .into_values()
+                .filter(|(used, _)| !used)
+                .map(|(_, warning)| warning.clone()),
+        );
+
+        // Only the top-level items of the derive's own template are checked. Rendering a single
+        // block skips the rest of the template, so the items it uses are unknown.
+        let ctx = &contexts[&input.path];
+        if input.block.is_none() {
+            if !self.is_allowed(Lint::UnusedMacros) {
+                let called = self.called_macros.borrow();
+                for def in ctx.macros.values() {
+                    if !called.contains(&std::ptr::from_ref(*def).addr()) {
+                        warnings.push(Warning::new(
+                            Lint::UnusedMacros,
+                            ctx,
+                            format_args!("macro `{}` is never called", *def.name),
+                            def.name.span(),
+                        ));
+                    }
+                }
+            }
+            if !self.is_allowed(Lint::UnusedImports) {
+                let used = self.used_imports.borrow();
+                for node in ctx.nodes {
+                    let Node::Import(import) = &**node else {
+                        continue;
+                    };
+                    let Some(path) = ctx.imports.get(import.scope) else {
+                        continue;
+                    };
+                    if !used.contains(&(import.scope.to_owned(), Arc::clone(path))) {
+                        warnings.push(Warning::new(
+                            Lint::UnusedImports,
+                            ctx,
+                            format_args!("import `{}` is never used", import.scope),
+                            import.span(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if ctx.extends.is_some() && !self.is_allowed(Lint::UnusedBlocks) {
+            // Blocks declared by another template that this template uses, e.g. by a template
+            // included by the extended template, are rendered, too.
+            let declared = contexts
+                .iter()
+                .filter(|(path, _)| ***path != input.path)
+                .flat_map(|(_, ctx)| ctx.blocks.keys())
+                .collect::<HashSet<_>>();
+            for node in ctx.nodes {
+                let Node::BlockDef(block) = &**node else {
+                    continue;
+                };
+                let name = *block.name;
+                if !declared.contains(&name)
+                    && !input.blocks.iter().any(|b| b.name == name)
+                    && !input.render_blocks
+                {
+                    warnings.push(Warning::new(
+                        Lint::UnusedBlocks,
+                        ctx,
+                        format_args!(
+                            "block `{name}` is never rendered, because no extended template \
+                            declares it",
+                        ),
+                        block.name.span(),
+                    ));
+                }
+            }
+        }
+
+        warnings.sort_by(|a, b| a.key.cmp(&b.key));
+        warnings
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Warning {
+    lint: Lint,
+    msg: String,
+    span: proc_macro2::Span,
+    key: WarningKey,
+}
+
+/// The file and offset of a warning, to sort and deduplicate warnings.
+type WarningKey = (Option<Arc<Path>>, usize);
+
+impl Warning {
+    fn new(lint: Lint, ctx: &Context<'_>, msg: impl fmt::Display, node: Span) -> Self {
+        let err = ctx.generate_error(msg, node);
+        Self {
+            lint,
+            msg: err.msg,
+            span: err.span.unwrap_or(ctx.template_span),
+            key: (
+                ctx.path.map(Arc::from),
+                node.byte_range().map_or(0, |range| range.start),
+            ),
+        }
+    }
+
+    /// Code that makes the compiler emit the warning.
+    pub(crate) fn to_token_stream(&self) -> TokenStream {
+        let span = self.span;
+        let name = field_new(self.lint.name(), span);
+        let note = format!(
+            "{}\n`#[template(allow({}))]` can be used to silence this warning",
+            self.msg, self.lint,
+        );
+        quote_spanned! { span =>
+            const _: () = {
+                #[allow(non_upper_case_globals)]
+                #[deprecated(note = #note)]
+                const #name: () = ();
+                #name
+            };
+        }
+    }
+}
@@ -49,20 +49,51 @@ fn compare_ex(
         .expect("`TokenStream` failed to parse input");
     let expected: syn::File = syn::parse_quote! {
         #[automatically_derived]
-        impl askama::Template for Foo {
-            fn render_into_with_values(
+        impl askama::helpers::TemplateBody for Foo {
+            fn render_body(
                 &self,
-                __askama_writer: &mut dyn askama::helpers::core::fmt::Write,
+                __askama_writer: &mut dyn askama::helpers::TemplateWriter,
                 __askama_values: &dyn askama::Values,
             ) -> askama::Result<()> {
                 #[allow(unused_imports)]
                 use askama::{
                     filters::{AutoEscape as _, WriteWritable as _},
-                    helpers::{ResultConverter as _, core::fmt::Write as _},
+                    helpers::{ResultConverter as _, TemplateWriter as _},
                 };
                 #expected
                 askama::Result::Ok(())
             }
+        }
+
+        #[automatically_derived]
+        impl askama::Template for Foo {
+            #[inline]
+            fn render_into_with_values(
+                &self,
+                mut __askama_writer: &mut dyn askama::helpers::core::fmt::Write,
+                __askama_values: &dyn askama::Values,
+            ) -> askama::Result<()> {
+                <Self as askama::helpers::TemplateBody>::render_body(
+                    self,
+                    &mut __askama_writer,
+                    __askama_values,
+                )
+            }
+
+            #[inline]
+            fn write_bytes_with_values<__AskamaSink: askama::ByteSink + ?askama::helpers::core::marker::Sized>(
+                &self,
+                mut __askama_writer: &mut __AskamaSink,
+                __askama_values: &dyn askama::Values,
+            ) -> askama::Result<()> {
+                askama::ByteSink::reserve(__askama_writer, #size_hint);
+                <Self as askama::helpers::TemplateBody>::render_body(
+                    self,
+                    &mut __askama_writer,
+                    __askama_values,
+                )
+            }
+
             const SIZE_HINT: askama::helpers::core::primitive::usize = #size_hint;
         }
 
@@ -87,6 +118,15 @@ fn compare_ex(
             ) -> askama::Result<()> {
                 askama::Template::render_into_with_values(self, dest, values)
             }
+
+            #[inline]
+            fn write_bytes_into(
+                &self,
+                dest: &mut dyn askama::ByteSink,
+                values: &dyn askama::Values,
+            ) -> askama::Result<()> {
+                askama::Template::write_bytes_with_values(self, dest, values)
+            }
         }
     };
 
@@ -0,0 +1,75 @@
+//! Configuration resolution for tools working on template files outside of the derive macro,
+//! like `askama-lsp`.
+//!
+//! The configuration is read and interpreted exactly like `#[derive(Template)]` does it for the
+//! crate in the given root directory.
+//!
+//! This module is not part of the stable API of `askama_derive`. It is only meant to be used by
+//! `askama_lsp` and `askama_cli`, and may change in any release.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parser::Syntax;
+use parser::node::Whitespace;
+
+use crate::config::{self, read_config_file_in};
+
+/// The configuration of a crate, read from its `askama.toml` file.
+#[derive(Debug, Clone, Copy)]
+pub struct Config(&'static config::Config);
+
+impl Config {
+    /// Reads the `askama.toml` file in `root`, or returns the default configuration if there is
+    /// none. Template directories are relative to `root`.
+    ///
+    /// Like in the derive macro, configurations are cached, so loading the same configuration
+    /// multiple times is cheap.
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let (source, full_config_path) =
+            read_config_file_in(root, None, None).map_err(|err| err.to_string())?;
+        config::Config::new_in(
+            root.to_path_buf(),
+            &source,
+            None,
+            None,
+            None,
+            full_config_path,
+        )
+        .map(Self)
+        .map_err(|err| err.to_string())
+    }
+
+    /// The directories containing the templates.
+    #[must_use]
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.0.dirs
+    }
+
+    /// The path of the configuration file, if there is one.
+    #[must_use]
+    pub fn config_path(&self) -> Option<&Path> {
+        self.0.full_config_path.as_deref()
+    }
+
+    /// Returns the syntax named `name`, or the default syntax if `name` is `None`.
+    #[must_use]
+    pub fn syntax(&self, name: Option<&str>) -> Option<Syntax<'static>> {
+        let name = name.unwrap_or(self.0.default_syntax);
+        self.0.syntaxes.get(name).map(|syntax| **syntax)
+    }
+
+    /// How whitespace is handled by the tags without whitespace control character.
+    #[must_use]
+    pub fn whitespace(&self) -> Whitespace {
+        self.0.whitespace
+    }
+
+    /// Resolves the path of a template used in `extends`, `include` or `import`. If `start_at` is
+    /// given, the path is first searched relative to it.
+    pub fn find_template(&self, path: &str, start_at: Option<&Path>) -> Result<Arc<Path>, String> {
+        self.0
+            .find_template(path, start_at, None, None)
+            .map_err(|err| err.to_string())
+    }
+}
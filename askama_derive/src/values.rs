@@ -0,0 +1,220 @@
+//! `#[derive(Values)]` - Proc macro implementation
+//! Every named field of the struct is made available as runtime value with the name of the field,
+//! and an associated constant `askama::Key` with the uppercase name of the field is generated.
+
+use std::ops::ControlFlow;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{DeriveInput, ExprPath, Field, LitStr, Meta, Token};
+
+use crate::{CompileError, HashSet, parse_ts_or_compile_error};
+
+pub fn derive_values(input: TokenStream, import_askama: fn() -> TokenStream) -> TokenStream {
+    let ast: DeriveInput = match parse_ts_or_compile_error(input, import_askama) {
+        ControlFlow::Continue(ast) => ast,
+        ControlFlow::Break(err) => return err,
+    };
+    let (ts, crate_name) = match values_impl(&ast) {
+        Ok(result) => result,
+        Err(CompileError { msg, span, .. }) => {
+            let ts = quote_spanned! {
+                span.unwrap_or(ast.ident.span()) =>
+                askama::helpers::core::compile_error!(#msg);
+            };
+            (ts, None)
+        }
+    };
+    let import_askama = match crate_name {
+        Some(crate_name) => quote!(use #crate_name as askama;),
+        None => import_askama(),
+    };
+    quote! {
+        const _: () = {
+            #import_askama
+            #ts
+        };
+    }
+}
+
+fn values_impl(ast: &DeriveInput) -> Result<(TokenStream, Option<ExprPath>), CompileError> {
+    let crate_name = parse_container_args(ast)?;
+    let fields = match &ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(CompileError::no_file_info(
+                "`Values` can only be derived for structs with named fields",
+                Some(ast.ident.span()),
+            ));
+        }
+    };
+
+    let mut names = HashSet::default();
+    let mut arms = TokenStream::new();
+    let mut keys = TokenStream::new();
+    let mut bounds = TokenStream::new();
+    for field in fields {
+        let Some(name) = parse_field_args(field)? else {
+            continue;
+        };
+        let span = field.span();
+        if !names.insert(name.value()) {
+            return Err(CompileError::no_file_info(
+                format_args!("duplicated value name `{}`", name.value()),
+                Some(name.span()),
+            ));
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let vis = &field.vis;
+        let key = format_ident!(
+            "{}",
+            ident.to_string().trim_start_matches("r#").to_uppercase(),
+            span = ident.span()
+        );
+        let doc = format!("Typed key of the runtime value `{}`", name.value());
+        arms.extend(quote_spanned! {span=>
+            #name => askama::helpers::core::option::Option::Some(&self.#ident),
+        });
+        keys.extend(quote_spanned! {span=>
+            #[doc = #doc]
+            #vis const #key: askama::Key<#ty> = askama::Key::new(#name);
+        });
+        bounds.extend(quote_spanned! {span=>
+            #ty: askama::helpers::core::any::Any,
+        });
+    }
+
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    let ts = quote! {
+        #[automatically_derived]
+        impl #impl_generics askama::Values for #ident #ty_generics
+        where
+            #predicates
+            #bounds
+        {
+            fn get_value<'__askama_a>(
+                &'__askama_a self,
+                key: &askama::helpers::core::primitive::str,
+            ) -> askama::helpers::core::option::Option<
+                &'__askama_a dyn askama::helpers::core::any::Any
+            > {
+                match key {
+                    #arms
+                    _ => askama::helpers::core::option::Option::None,
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #keys
+        }
+    };
+    Ok((ts, crate_name))
+}
+
+/// Parses `#[values(askama = path)]` on the struct
+fn parse_container_args(ast: &DeriveInput) -> Result<Option<ExprPath>, CompileError> {
+    let mut crate_name = None;
+    for attr in &ast.attrs {
+        if !attr.path().is_ident("values") {
+            continue;
+        }
+        for arg in parse_args(attr)? {
+            match arg {
+                Meta::NameValue(pair) if pair.path.is_ident("askama") => {
+                    if crate_name.is_some() {
+                        return Err(CompileError::no_file_info(
+                            "attribute `askama` already set",
+                            Some(pair.span()),
+                        ));
+                    }
+                    match pair.value {
+                        syn::Expr::Path(path) => crate_name = Some(path),
+                        value => {
+                            return Err(CompileError::no_file_info(
+                                "expected a path to the `askama` crate",
+                                Some(value.span()),
+                            ));
+                        }
+                    }
+                }
+                arg => {
+                    return Err(CompileError::no_file_info(
+                        "unsupported attribute, expected `askama = path`",
+                        Some(arg.span()),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(crate_name)
+}
+
+/// Parses `#[values(rename = "name")]` and `#[values(skip)]` on a field, and returns the name of
+/// the value, or `None` if the field is skipped
+fn parse_field_args(field: &Field) -> Result<Option<LitStr>, CompileError> {
+    let ident = field.ident.as_ref().unwrap();
+    let mut name = None;
+    let mut skip = false;
+    for attr in &field.attrs {
+        if !attr.path().is_ident("values") {
+            continue;
+        }
+        for arg in parse_args(attr)? {
+            match arg {
+                Meta::Path(path) if path.is_ident("skip") => skip = true,
+                Meta::NameValue(pair) if pair.path.is_ident("rename") => {
+                    if name.is_some() {
+                        return Err(CompileError::no_file_info(
+                            "attribute `rename` already set",
+                            Some(pair.span()),
+                        ));
+                    }
+                    match pair.value {
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(s),
+                            ..
+                        }) => name = Some(s),
+                        value => {
+                            return Err(CompileError::no_file_info(
+                                "expected a string literal",
+                                Some(value.span()),
+                            ));
+                        }
+                    }
+                }
+                arg => {
+                    return Err(CompileError::no_file_info(
+                        "unsupported attribute, expected `rename = \"name\"` or `skip`",
+                        Some(arg.span()),
+                    ));
+                }
+            }
+        }
+    }
+    if skip {
+        return Ok(None);
+    }
+    Ok(Some(name.unwrap_or_else(|| {
+        let name = ident.to_string();
+        LitStr::new(name.trim_start_matches("r#"), ident.span())
+    })))
+}
+
+fn parse_args(attr: &syn::Attribute) -> Result<Vec<Meta>, CompileError> {
+    attr.parse_args_with(syn::punctuated::Punctuated::<Meta, Token![,]>::parse_terminated)
+        .map(|args| args.into_iter().collect())
+        .map_err(|err| {
+            let span = err.span();
+            CompileError::no_file_info(err, Some(span))
+        })
+}
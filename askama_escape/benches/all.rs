@@ -4,25 +4,69 @@ use askama_escape::escape_html;
 use criterion::{Criterion, Throughput, criterion_group, criterion_main};
 
 criterion_main!(benches);
-criterion_group!(benches, functions);
+criterion_group!(benches, functions, big_table, long_texts);
 
 fn functions(c: &mut Criterion) {
-    c.bench_function("escape_html", escaping);
+    c.bench_function("escape_html", |b| escaping(b, STRINGS));
     let mut g = c.benchmark_group("all");
-    let bytes = STRINGS.iter().map(|s| s.len() as u64).sum();
-    g.throughput(Throughput::Bytes(bytes));
-    g.bench_function("escape_html", escaping);
+    g.throughput(Throughput::Bytes(total_len(STRINGS)));
+    g.bench_function("escape_html", |b| escaping(b, STRINGS));
     g.finish();
 }
 
-fn escaping(b: &mut criterion::Bencher<'_>) {
+/// Like the cells of the `big-table.html` benchmark, but with user content instead of numbers
+fn big_table(c: &mut Criterion) {
+    const SIZE: usize = 100;
+
+    let cells = (0..SIZE * SIZE)
+        .map(|i| match i % 7 {
+            0 => format!("Tom & Jerry #{i}"),
+            1 => format!("<b>{i}</b>"),
+            _ => format!("user{i}@example.com"),
+        })
+        .collect::<Vec<_>>();
+    let cells = cells.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let mut g = c.benchmark_group("big table");
+    g.throughput(Throughput::Bytes(total_len(&cells)));
+    g.bench_function("escape_html", |b| escaping(b, &cells));
+    g.finish();
+}
+
+fn long_texts(c: &mut Criterion) {
+    let plain = STRINGS
+        .iter()
+        .flat_map(|s| s.chars())
+        .filter(|c| !matches!(c, '"' | '&' | '\'' | '<' | '>'))
+        .collect::<String>();
+    let some = plain.replace(". ", " & ");
+    let only = r#""&'<>"#.repeat(plain.len() / 5);
+
+    let mut g = c.benchmark_group("long text");
+    for (name, text) in [
+        ("no escapes", &plain),
+        ("some escapes", &some),
+        ("only escapes", &only),
+    ] {
+        let texts = [text.as_str()];
+        g.throughput(Throughput::Bytes(total_len(&texts)));
+        g.bench_function(name, |b| escaping(b, &texts));
+    }
+    g.finish();
+}
+
+fn escaping(b: &mut criterion::Bencher<'_>, strings: &[&str]) {
     let mut dest = String::new();
     b.iter(|| {
-        for &s in black_box(STRINGS) {
+        for &s in black_box(strings) {
             dest.clear();
             black_box(escape_html(&mut dest, s)).unwrap();
         }
     });
 }
 
+fn total_len(strings: &[&str]) -> u64 {
+    strings.iter().map(|s| s.len() as u64).sum()
+}
+
 const STRINGS: &[&str] = include!("strings.inc");
@@ -0,0 +1 @@
+../../askama/src/escapers.rs
\ No newline at end of file
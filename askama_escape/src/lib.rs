@@ -5,10 +5,15 @@
 #![deny(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use core::fmt;
 
 mod ascii_str;
+mod escapers;
 mod html;
+mod unescape;
 
 /// Escape for HTML or XML.
 #[derive(Debug, Clone, Copy, Default)]
@@ -18,6 +23,30 @@ pub struct Html;
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Text;
 
+/// Escape for the content of JavaScript strings, template literals and regular expressions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Js;
+
+/// Escape for the content of CSS strings and identifiers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Css;
+
+/// Percent-encode for URL components, e.g. path segments or query parameters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Url;
+
+/// Escape for XML texts and attributes, retaining tabs and line breaks in attribute values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xml;
+
+/// Escape for the content of JSON strings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+/// Escape for (La)TeX texts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tex;
+
 /// An escaper for some context, e.g. [`Html`].
 pub trait Escaper {
     /// Escaped the input string `string` into `dest`
@@ -38,6 +67,48 @@ impl Escaper for Text {
     }
 }
 
+impl Escaper for Js {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_js_escaped(dest, string)
+    }
+}
+
+impl Escaper for Css {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_css_escaped(dest, string)
+    }
+}
+
+impl Escaper for Url {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_url_escaped(dest, string)
+    }
+}
+
+impl Escaper for Xml {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_xml_escaped(dest, string)
+    }
+}
+
+impl Escaper for Json {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_json_escaped(dest, string)
+    }
+}
+
+impl Escaper for Tex {
+    #[inline]
+    fn write_escaped<W: fmt::Write>(&self, dest: W, string: &str) -> fmt::Result {
+        escapers::write_tex_escaped(dest, string)
+    }
+}
+
 /// The return type of [`escape()`].
 ///
 /// ## Example
@@ -115,6 +186,28 @@ pub fn escape_html_char(dest: impl fmt::Write, c: char) -> fmt::Result {
     html::write_escaped_char(dest, c)
 }
 
+/// Replace HTML character references in `src` with the characters they represent, and write the
+/// result into `dest`.
+///
+/// Numeric references like `&#60;` or `&#x3c;` are decoded like browsers do, i.e. invalid
+/// codepoints become U+FFFD and the range `0x80..=0x9F` is read as Windows-1252.
+/// Of the named references, the ones defined in HTML 4 like `&lt;`, `&eacute;` or `&nbsp;` are
+/// supported, plus `&apos;`. Unknown or malformed references are written verbatim.
+///
+/// ## Example
+///
+/// ```rust
+/// use askama_escape::unescape_html;
+///
+/// let mut dest = String::new();
+/// unescape_html(&mut dest, "&#60;b&#62;Caf&eacute; &amp; bar&#60;/b&#62;").unwrap();
+/// assert_eq!(dest, "<b>Café & bar</b>");
+/// ```
+#[inline]
+pub fn unescape_html(dest: impl fmt::Write, src: &str) -> fmt::Result {
+    unescape::write_unescaped_str(dest, src)
+}
+
 #[cfg(test)]
 mod tests {
     extern crate std;
@@ -131,4 +224,27 @@ mod tests {
         assert_eq!(escape("<foo", Html).to_string(), "&#60;foo");
         assert_eq!(escape("bla&h", Html).to_string(), "bla&#38;h");
     }
+
+    #[test]
+    fn test_unescape_roundtrip() {
+        let src = "<p title=\"Tom & 'Jerry'\">a > b</p>";
+        let mut escaped = std::string::String::new();
+        escape_html(&mut escaped, src).unwrap();
+        let mut unescaped = std::string::String::new();
+        unescape_html(&mut unescaped, &escaped).unwrap();
+        assert_eq!(unescaped, src);
+    }
+
+    #[test]
+    fn test_other_escapers() {
+        assert_eq!(escape("a'</b>", Js).to_string(), r"a\u0027\u003C\/b\u003E");
+        assert_eq!(escape("a;b", Css).to_string(), r"a\3b b");
+        assert_eq!(escape("a b/ä", Url).to_string(), "a%20b%2F%C3%A4");
+        assert_eq!(escape("<a\n>", Xml).to_string(), "&#60;a&#10;&#62;");
+        assert_eq!(
+            escape("\"a\\</b>", Json).to_string(),
+            r#"\"a\\\u003c/b\u003e"#
+        );
+        assert_eq!(escape("$5 & 10%", Tex).to_string(), r"\$5 \& 10\%");
+    }
 }
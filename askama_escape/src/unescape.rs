@@ -0,0 +1 @@
+../../askama/src/unescape.rs
\ No newline at end of file
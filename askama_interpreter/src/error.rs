@@ -0,0 +1,127 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::{fmt, io};
+
+use parser::ParseError;
+
+/// The result type of the interpreter
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error that occurred while loading, parsing or rendering a template
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// The [`Loader`](crate::Loader) could not find a template with this name
+    NotFound(String),
+    /// The template could not be read
+    Io {
+        /// The name of the template
+        name: String,
+        /// The error returned by the file system
+        err: io::Error,
+    },
+    /// The template could not be parsed
+    Parse(ParseError),
+    /// The template could not be rendered
+    Render {
+        /// A description of the problem
+        message: Cow<'static, str>,
+        /// The location in the template source that caused the error
+        location: Option<Location>,
+    },
+    /// Formatting error, e.g. if the output [`fmt::Write`] returned an error
+    Fmt,
+}
+
+impl Error {
+    pub(crate) fn render(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::Render {
+            message: message.into(),
+            location: None,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { err, .. } => Some(err),
+            Error::Parse(err) => Some(err),
+            Error::NotFound(_) | Error::Render { .. } | Error::Fmt => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(name) => write!(f, "template {name:?} not found"),
+            Error::Io { name, err } => write!(f, "could not read template {name:?}: {err}"),
+            Error::Parse(err) => err.fmt(f),
+            Error::Render {
+                message,
+                location: Some(location),
+            } => write!(f, "{message}\n  --> {location}"),
+            Error::Render {
+                message,
+                location: None,
+            } => f.write_str(message),
+            Error::Fmt => f.write_str("formatting error"),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    #[inline]
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<Infallible> for Error {
+    #[inline]
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+impl From<fmt::Error> for Error {
+    #[inline]
+    fn from(_: fmt::Error) -> Self {
+        Self::Fmt
+    }
+}
+
+/// A position in the source of a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The name of the template, or `None` for a template that was rendered from a string
+    pub name: Option<Arc<str>>,
+    /// The line number, starting at 1
+    pub line: usize,
+    /// The column number in characters, starting at 1
+    pub column: usize,
+}
+
+impl Location {
+    pub(crate) fn new(name: Option<Arc<str>>, source: &str, offset: usize) -> Self {
+        let before = source.get(..offset).unwrap_or(source);
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        Self {
+            name,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Location { name, line, column } = self;
+        match name {
+            Some(name) => write!(f, "{name}:{line}:{column}"),
+            None => write!(f, "{line}:{column}"),
+        }
+    }
+}
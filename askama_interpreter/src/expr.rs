@@ -0,0 +1,772 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use parser::expr::{BinOp, Call, Conditional, Range};
+use parser::node::CondTest;
+use parser::{AssociatedItem, Expr, Num, PathComponent, Span, StrLit, Target, WithSpan};
+use serde_json::Value;
+
+use crate::Result;
+use crate::render::Renderer;
+use crate::template::Context;
+use crate::value::{self, Val};
+
+type Node<'t> = WithSpan<Box<Expr<'t>>>;
+
+impl<'t> Renderer<'t, '_> {
+    pub(crate) fn eval(&self, ctx: &'t Context<'t>, expr: &'t Node<'t>) -> Result<Val<'_>> {
+        let err = |message: String| ctx.tmpl.error(message, expr.span());
+        Ok(match &***expr {
+            Expr::BoolLit(b) => Val::new(Value::Bool(*b)),
+            Expr::NumLit(src, num) => Val::new(
+                parse_num(num).ok_or_else(|| err(format!("invalid number literal `{src}`")))?,
+            ),
+            Expr::StrLit(s) => Val::new(Value::String(parse_str(s).map_err(err)?)),
+            Expr::CharLit(c) => Val::new(Value::String(unescape(c.content))),
+            Expr::Var(name) => match self.lookup(name) {
+                Some(Some(value)) => Val::borrowed(value),
+                Some(None) => return Err(err(format!("variable `{name}` was not assigned"))),
+                None => return Err(err(format!("variable `{name}` not found"))),
+            },
+            Expr::Path(path) => Val::new(path_value(path)),
+            Expr::Array(items) | Expr::Tuple(items) => {
+                let mut arr = Vec::with_capacity(items.len());
+                for item in items {
+                    arr.push(self.eval(ctx, item)?.into_owned());
+                }
+                Val::new(Value::Array(arr))
+            }
+            Expr::ArrayRepeat(item, count) => {
+                let item = self.eval(ctx, item)?.into_owned();
+                let count = self.eval_usize(ctx, count)?;
+                Val::new(Value::Array(vec![item; count]))
+            }
+            Expr::AssociatedItem(obj, item) => self.eval_associated_item(ctx, obj, item)?,
+            Expr::Index(obj, index) => self.eval_index(ctx, obj, index)?,
+            Expr::Filter(filter) => self.eval_filter(ctx, filter, expr.span())?,
+            Expr::Test(test) => self.eval_test(ctx, test, expr.span())?,
+            Expr::Conditional(Conditional {
+                cond,
+                then,
+                otherwise,
+            }) => {
+                if value::is_truthy(&self.eval(ctx, cond)?.value) {
+                    self.eval(ctx, then)?
+                } else if let Some(otherwise) = otherwise {
+                    self.eval(ctx, otherwise)?
+                } else {
+                    Val::new(Value::String(String::new()))
+                }
+            }
+            Expr::As(expr, ty) => {
+                let value = self.eval(ctx, expr)?;
+                Val::new(cast(&value.value, ty).ok_or_else(|| {
+                    err(format!(
+                        "cannot cast a value of type {} as `{}`",
+                        value::type_name(&value.value),
+                        **ty,
+                    ))
+                })?)
+            }
+            Expr::NamedArgument(..) => {
+                return Err(err(
+                    "named arguments are only allowed in macro and filter calls".into(),
+                ));
+            }
+            Expr::Unary(op, operand) => {
+                let value = self.eval(ctx, operand)?;
+                match *op {
+                    "*" | "&" => value,
+                    "!" => Val::new(match &*value.value {
+                        Value::Number(_) => value::as_int(&value.value)
+                            .and_then(|i| value::from_int(!i))
+                            .ok_or_else(|| err("cannot apply `!` to a float".into()))?,
+                        v => Value::Bool(!value::is_truthy(v)),
+                    }),
+                    "-" => Val::new(
+                        value::arithmetic("-", &Value::from(0), &value.value).map_err(err)?,
+                    ),
+                    op => return Err(err(format!("unknown unary operator `{op}`"))),
+                }
+            }
+            Expr::BinOp(op) => self.eval_bin_op(ctx, op, expr.span())?,
+            Expr::Range(range) => {
+                Val::new(Value::Array(self.eval_range(ctx, range, expr.span())?))
+            }
+            Expr::Group(expr) | Expr::Try(expr) => self.eval(ctx, expr)?,
+            Expr::Call(call) => self.eval_call(ctx, call, expr.span())?,
+            Expr::RustMacro(..) => {
+                return Err(err(
+                    "Rust macros cannot be used in interpreted templates".into()
+                ));
+            }
+            Expr::Struct(_) => {
+                return Err(err(
+                    "struct expressions cannot be used in interpreted templates".into(),
+                ));
+            }
+            Expr::FilterSource => match &self.filter_source {
+                Some(source) => Val::new(Value::String(source.clone())),
+                None => return Err(err("no filter block source".into())),
+            },
+            Expr::IsDefined(name) => Val::new(Value::Bool(self.lookup(name).is_some())),
+            Expr::IsNotDefined(name) => Val::new(Value::Bool(self.lookup(name).is_none())),
+            Expr::Concat(items) => {
+                let mut result = String::new();
+                for item in items {
+                    let value = self.eval(ctx, item)?;
+                    result.push_str(&display(ctx, &value.value, item.span())?);
+                }
+                Val::new(Value::String(result))
+            }
+            Expr::LetCond(_) => {
+                return Err(err("`let` is only allowed in conditions".into()));
+            }
+            Expr::ArgumentPlaceholder => {
+                return Err(err("unexpected argument placeholder".into()));
+            }
+        })
+    }
+
+    pub(crate) fn eval_usize(&self, ctx: &'t Context<'t>, expr: &'t Node<'t>) -> Result<usize> {
+        let value = self.eval(ctx, expr)?;
+        value::as_int(&value.value)
+            .and_then(|i| usize::try_from(i).ok())
+            .ok_or_else(|| {
+                ctx.tmpl.error(
+                    format!(
+                        "expected a non-negative integer, found a value of type {}",
+                        value::type_name(&value.value),
+                    ),
+                    expr.span(),
+                )
+            })
+    }
+
+    fn eval_associated_item(
+        &self,
+        ctx: &'t Context<'t>,
+        obj: &'t Node<'t>,
+        item: &'t AssociatedItem<'t>,
+    ) -> Result<Val<'_>> {
+        if let Expr::Var("loop") = ***obj
+            && self.lookup("loop").is_none()
+        {
+            let state = self.loop_state(ctx, obj.span())?;
+            return Ok(Val::new(match *item.name {
+                "index0" => state.index0.into(),
+                "index" => (state.index0 + 1).into(),
+                "first" => (state.index0 == 0).into(),
+                "last" => (state.index0 + 1 == state.len).into(),
+                name => {
+                    return Err(ctx.tmpl.error(
+                        format!("unknown loop variable `{}`", name.escape_debug()),
+                        item.name.span(),
+                    ));
+                }
+            }));
+        }
+
+        let value = self.eval(ctx, obj)?;
+        let type_name = value::type_name(&value.value);
+        value.field(&item.name).ok_or_else(|| {
+            ctx.tmpl.error(
+                format!("no field `{}` on a value of type {type_name}", *item.name),
+                item.name.span(),
+            )
+        })
+    }
+
+    fn loop_state(&self, ctx: &Context<'_>, span: Span) -> Result<&crate::render::LoopState> {
+        self.loops.last().ok_or_else(|| {
+            ctx.tmpl
+                .error("`loop` can only be used inside of loops", span)
+        })
+    }
+
+    fn eval_index(
+        &self,
+        ctx: &'t Context<'t>,
+        obj: &'t Node<'t>,
+        index: &'t Node<'t>,
+    ) -> Result<Val<'_>> {
+        let value = self.eval(ctx, obj)?;
+        let err = |message: String| ctx.tmpl.error(message, index.span());
+
+        if let Expr::Range(range) = &***index {
+            let start = match &range.lhs {
+                Some(lhs) => self.eval_usize(ctx, lhs)?,
+                None => 0,
+            };
+            let end = match &range.rhs {
+                Some(rhs) => Some(self.eval_usize(ctx, rhs)? + usize::from(range.op == "..=")),
+                None => None,
+            };
+            let out_of_range = || err("the range is out of bounds".into());
+            return Ok(Val::new(match &*value.value {
+                Value::Array(arr) => {
+                    let end = end.unwrap_or(arr.len());
+                    Value::Array(arr.get(start..end).ok_or_else(out_of_range)?.to_vec())
+                }
+                Value::String(s) => {
+                    let end = end.unwrap_or(s.len());
+                    Value::String(s.get(start..end).ok_or_else(out_of_range)?.to_owned())
+                }
+                v => {
+                    return Err(err(format!(
+                        "a value of type {} cannot be sliced",
+                        value::type_name(v),
+                    )));
+                }
+            }));
+        }
+
+        let index = self.eval(ctx, index)?;
+        let key = match &*index.value {
+            Value::String(key) => key.clone(),
+            Value::Number(_) if value::as_int(&index.value).is_some() => index.value.to_string(),
+            v => {
+                return Err(err(format!(
+                    "a value of type {} cannot be used as index",
+                    value::type_name(v),
+                )));
+            }
+        };
+        let type_name = value::type_name(&value.value);
+        value.field(&key).ok_or_else(|| {
+            err(format!(
+                "index `{key}` not found in a value of type {type_name}"
+            ))
+        })
+    }
+
+    fn eval_bin_op(&self, ctx: &'t Context<'t>, op: &'t BinOp<'t>, span: Span) -> Result<Val<'_>> {
+        let lhs = self.eval(ctx, &op.lhs)?;
+        match op.op {
+            "&&" if !value::is_truthy(&lhs.value) => return Ok(Val::new(false.into())),
+            "||" if value::is_truthy(&lhs.value) => return Ok(Val::new(true.into())),
+            _ => {}
+        }
+        let rhs = self.eval(ctx, &op.rhs)?;
+        let (lhs, rhs) = (&*lhs.value, &*rhs.value);
+
+        let ordering = || {
+            value::compare(lhs, rhs).ok_or_else(|| {
+                ctx.tmpl.error(
+                    format!(
+                        "cannot compare a value of type {} with a value of type {}",
+                        value::type_name(lhs),
+                        value::type_name(rhs),
+                    ),
+                    span,
+                )
+            })
+        };
+        let result = match op.op {
+            "&&" | "||" => Value::Bool(value::is_truthy(rhs)),
+            "==" => Value::Bool(value::equals(lhs, rhs)),
+            "!=" => Value::Bool(!value::equals(lhs, rhs)),
+            "<" => Value::Bool(ordering()? == Ordering::Less),
+            "<=" => Value::Bool(ordering()? != Ordering::Greater),
+            ">" => Value::Bool(ordering()? == Ordering::Greater),
+            ">=" => Value::Bool(ordering()? != Ordering::Less),
+            op => {
+                value::arithmetic(op, lhs, rhs).map_err(|message| ctx.tmpl.error(message, span))?
+            }
+        };
+        Ok(Val::new(result))
+    }
+
+    fn eval_range(
+        &self,
+        ctx: &'t Context<'t>,
+        range: &'t Range<'t>,
+        span: Span,
+    ) -> Result<Vec<Value>> {
+        let bound = |bound: &'t Option<Node<'t>>| -> Result<i128> {
+            let Some(bound) = bound else {
+                return Err(ctx.tmpl.error("only bounded ranges can be evaluated", span));
+            };
+            let value = self.eval(ctx, bound)?;
+            value::as_int(&value.value).ok_or_else(|| {
+                ctx.tmpl
+                    .error("range bounds must be integers", bound.span())
+            })
+        };
+        let start = bound(&range.lhs)?;
+        let end = bound(&range.rhs)? + i128::from(range.op == "..=");
+        Ok((start..end).filter_map(value::from_int).collect())
+    }
+
+    fn eval_call(&self, ctx: &'t Context<'t>, call: &'t Call<'t>, span: Span) -> Result<Val<'_>> {
+        let err = |message: String| ctx.tmpl.error(message, span);
+        let (obj, method) = match &**call.path {
+            Expr::AssociatedItem(obj, method) => (obj, *method.name),
+            Expr::Path(path) => {
+                return match (path.as_slice(), call.args.as_slice()) {
+                    ([PathComponent { name, .. }], [arg]) if matches!(**name, "Some" | "Ok") => {
+                        self.eval(ctx, arg)
+                    }
+                    ([krate, func], [key])
+                        if *krate.name == "askama" && *func.name == "get_value" =>
+                    {
+                        let key = self.eval(ctx, key)?;
+                        let Value::String(key) = &*key.value else {
+                            return Err(err(
+                                "runtime values can only be looked up by string".into()
+                            ));
+                        };
+                        let value = self.runtime_value(ctx, key, span)?;
+                        Ok(Val::new(value.unwrap_or(Value::Null)))
+                    }
+                    _ => Err(err(
+                        "only `Some(…)`, `Ok(…)` and `askama::get_value(…)` can be called".into(),
+                    )),
+                };
+            }
+            Expr::Var(name) => {
+                return Err(err(match ctx.macros.contains_key(name) {
+                    true => format!("macro `{name}` can only be called in `{{{{ {name}() }}}}`"),
+                    false => format!("function `{name}` not found"),
+                }));
+            }
+            _ => return Err(err("only methods can be called".into())),
+        };
+
+        if let Expr::Var("loop") = ***obj
+            && method == "cycle"
+            && self.lookup("loop").is_none()
+        {
+            let [arg] = call.args.as_slice() else {
+                return Err(err("loop.cycle(…) cannot use an empty array".into()));
+            };
+            let index0 = self.loop_state(ctx, obj.span())?.index0;
+            let items = self.eval(ctx, arg)?;
+            let Value::Array(items) = &*items.value else {
+                return Err(err("loop.cycle(…) expects an array".into()));
+            };
+            if items.is_empty() {
+                return Err(err("loop.cycle(…) cannot use an empty array".into()));
+            }
+            return Ok(Val::new(items[index0 % items.len()].clone()));
+        }
+
+        let recv = self.eval(ctx, obj)?;
+        let mut args = Vec::with_capacity(call.args.len());
+        for arg in &call.args {
+            args.push(self.eval(ctx, arg)?.into_owned());
+        }
+        let recv = &*recv.value;
+        let result = match (method, recv, args.as_slice()) {
+            ("len", Value::String(s), []) => s.len().into(),
+            ("len", Value::Array(arr), []) => arr.len().into(),
+            ("len", Value::Object(map), []) => map.len().into(),
+            ("is_empty", v, [])
+                if matches!(v, Value::String(_) | Value::Array(_) | Value::Object(_)) =>
+            {
+                (!value::is_truthy(v)).into()
+            }
+            ("contains", Value::String(s), [Value::String(pat)]) => s.contains(&**pat).into(),
+            ("contains", Value::Array(arr), [item]) => {
+                arr.iter().any(|v| value::equals(v, item)).into()
+            }
+            ("contains_key", Value::Object(map), [Value::String(key)]) => {
+                map.contains_key(key).into()
+            }
+            ("starts_with", Value::String(s), [Value::String(pat)]) => s.starts_with(&**pat).into(),
+            ("ends_with", Value::String(s), [Value::String(pat)]) => s.ends_with(&**pat).into(),
+            ("to_string" | "to_owned" | "clone", v, []) => match v {
+                Value::Array(_) | Value::Object(_) => v.clone(),
+                v => Value::String(display(ctx, v, span)?.into_owned()),
+            },
+            ("to_lowercase", Value::String(s), []) => s.to_lowercase().into(),
+            ("to_uppercase", Value::String(s), []) => s.to_uppercase().into(),
+            ("trim", Value::String(s), []) => s.trim().into(),
+            ("first", Value::Array(arr), []) => arr.first().cloned().unwrap_or_default(),
+            ("last", Value::Array(arr), []) => arr.last().cloned().unwrap_or_default(),
+            ("get", Value::Array(_) | Value::Object(_), [key]) => {
+                let key = match key {
+                    Value::String(key) => Some(key.as_str().into()),
+                    key => value::as_int(key).map(|i| Cow::Owned(i.to_string())),
+                };
+                key.and_then(|key| Val::borrowed(recv).field(&key))
+                    .map(Val::into_owned)
+                    .unwrap_or_default()
+            }
+            ("unwrap" | "as_ref" | "as_deref", v, []) => v.clone(),
+            (method, v, args) => {
+                return Err(err(format!(
+                    "no method `{method}` with {} argument{} on a value of type {}",
+                    args.len(),
+                    if args.len() == 1 { "" } else { "s" },
+                    value::type_name(v),
+                )));
+            }
+        };
+        Ok(Val::new(result))
+    }
+
+    /// Evaluates the condition of an `{% if %}` or `{% else if %}`
+    ///
+    /// Variables bound in `if let` patterns are added to the current scope.
+    pub(crate) fn evaluate_condition(
+        &mut self,
+        ctx: &'t Context<'t>,
+        cond: &'t CondTest<'t>,
+    ) -> Result<bool> {
+        match &cond.target {
+            Some(target) => {
+                let value = self.eval(ctx, &cond.expr)?.into_owned();
+                self.match_target(ctx, target, value)
+            }
+            None => self.eval_condition_expr(ctx, &cond.expr),
+        }
+    }
+
+    /// Evaluates the truthiness of `expr`, which may contain `&& let` patterns
+    pub(crate) fn eval_condition_expr(
+        &mut self,
+        ctx: &'t Context<'t>,
+        expr: &'t Node<'t>,
+    ) -> Result<bool> {
+        match &***expr {
+            Expr::BinOp(BinOp { op: "&&", lhs, rhs }) => {
+                Ok(self.eval_condition_expr(ctx, lhs)? && self.eval_condition_expr(ctx, rhs)?)
+            }
+            Expr::LetCond(cond) => self.evaluate_condition(ctx, cond),
+            Expr::Group(expr) => self.eval_condition_expr(ctx, expr),
+            _ => Ok(value::is_truthy(&self.eval(ctx, expr)?.value)),
+        }
+    }
+
+    /// Matches `value` against the pattern `target`, and binds its variables in the current scope
+    ///
+    /// Patterns work like for the JSON representation `serde` uses: `None` matches `null`,
+    /// `Some(x)` matches any other value, unit variants match their name, and other enum
+    /// variants match an object with the variant name as its only key.
+    pub(crate) fn match_target(
+        &mut self,
+        ctx: &'t Context<'t>,
+        target: &'t Target<'t>,
+        value: Value,
+    ) -> Result<bool> {
+        Ok(match target {
+            Target::Name(name) => {
+                self.bind(name, value);
+                true
+            }
+            Target::Placeholder(_) => true,
+            Target::Rest(rest) => {
+                return Err(ctx
+                    .tmpl
+                    .error("`..` can only be used in tuples and arrays", rest.span()));
+            }
+            Target::NumLit(src, num) => match parse_num(num) {
+                Some(num) => value::equals(&num, &value),
+                None => {
+                    return Err(ctx
+                        .tmpl
+                        .error(format!("invalid number literal `{}`", **src), src.span()));
+                }
+            },
+            Target::StrLit(s) => {
+                let s = parse_str(s).map_err(|message| ctx.tmpl.error(message, target.span()))?;
+                value.as_str() == Some(&s)
+            }
+            Target::CharLit(c) => value.as_str() == Some(&unescape(c.content)),
+            Target::BoolLit(b) => value.as_bool() == Some(**b == "true"),
+            Target::Path(path) => path_value(path) == value,
+            Target::Tuple(tuple) => {
+                let (path, targets) = &**tuple;
+                match path.as_slice() {
+                    [] => self.match_seq(ctx, targets, value)?,
+                    [PathComponent { name, .. }] if matches!(**name, "Some" | "Ok") => {
+                        match (targets.as_slice(), value) {
+                            (_, Value::Null) => false,
+                            ([target], value) => self.match_target(ctx, target, value)?,
+                            (targets, value) => self.match_seq(ctx, targets, value)?,
+                        }
+                    }
+                    [.., last] => match variant(value, &last.name) {
+                        None => false,
+                        Some(inner) => match targets.as_slice() {
+                            [target] if !matches!(target, Target::Rest(_)) => {
+                                self.match_target(ctx, target, inner)?
+                            }
+                            targets => self.match_seq(ctx, targets, inner)?,
+                        },
+                    },
+                }
+            }
+            Target::Array(targets) => self.match_seq(ctx, targets, value)?,
+            Target::Struct(s) => {
+                let (path, fields) = &**s;
+                let value = match path.last() {
+                    Some(last) => variant(value.clone(), &last.name).unwrap_or(value),
+                    None => value,
+                };
+                let Value::Object(mut map) = value else {
+                    return Ok(false);
+                };
+                for field in fields {
+                    if *field.src == ".." {
+                        continue;
+                    }
+                    let Some(value) = map.remove(*field.src) else {
+                        return Ok(false);
+                    };
+                    if !self.match_target(ctx, &field.dest, value)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Target::OrChain(targets) => {
+                for target in &**targets {
+                    if self.match_target(ctx, target, value.clone())? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+        })
+    }
+
+    fn match_seq(
+        &mut self,
+        ctx: &'t Context<'t>,
+        targets: &'t [Target<'t>],
+        value: Value,
+    ) -> Result<bool> {
+        let Value::Array(mut items) = value else {
+            return Ok(false);
+        };
+
+        let rest = targets.iter().position(|t| matches!(t, Target::Rest(_)));
+        let (before, after) = match rest {
+            Some(pos) => (&targets[..pos], &targets[pos + 1..]),
+            None if targets.len() == items.len() => (targets, &[][..]),
+            None => return Ok(false),
+        };
+        if before.len() + after.len() > items.len() {
+            return Ok(false);
+        }
+
+        let tail = items.split_off(items.len() - after.len());
+        let middle = items.split_off(before.len());
+        for (target, item) in before.iter().zip(items).chain(after.iter().zip(tail)) {
+            if !self.match_target(ctx, target, item)? {
+                return Ok(false);
+            }
+        }
+        if let Some(Target::Rest(rest)) = rest.map(|pos| &targets[pos])
+            && let Some(name) = &**rest
+        {
+            self.bind(name, Value::Array(middle));
+        }
+        Ok(true)
+    }
+
+    /// Handles `{% mut x = y %}` and compound assignments like `{% mut x += y %}`
+    pub(crate) fn write_compound(
+        &mut self,
+        ctx: &'t Context<'t>,
+        op: &str,
+        lhs: &'t Node<'t>,
+        rhs: &'t Node<'t>,
+        span: Span,
+    ) -> Result<()> {
+        let rhs = self.eval(ctx, rhs)?.into_owned();
+
+        // Find the place that is assigned to, e.g. `x` in `x.field[0]`.
+        let mut keys = Vec::new();
+        let mut place = lhs;
+        let name = loop {
+            match &***place {
+                Expr::Var(name) => break *name,
+                Expr::Group(inner) => place = inner,
+                Expr::AssociatedItem(obj, item) => {
+                    keys.push((*item.name).to_owned());
+                    place = obj;
+                }
+                Expr::Index(obj, index) => {
+                    let index = self.eval(ctx, index)?;
+                    keys.push(match &*index.value {
+                        Value::String(key) => key.clone(),
+                        value => value.to_string(),
+                    });
+                    place = obj;
+                }
+                _ => {
+                    return Err(ctx
+                        .tmpl
+                        .error("cannot assign to this expression", lhs.span()));
+                }
+            }
+        };
+
+        let Some(mut target) = self.lookup_mut(name) else {
+            return Err(ctx.tmpl.error(
+                format!("cannot assign to `{name}`, it is not a local variable"),
+                lhs.span(),
+            ));
+        };
+        for key in keys.iter().rev() {
+            let next = match target {
+                Value::Object(map) => map.get_mut(key),
+                Value::Array(arr) => key.parse::<usize>().ok().and_then(|i| arr.get_mut(i)),
+                _ => None,
+            };
+            target = next.ok_or_else(|| {
+                ctx.tmpl.error(
+                    format!("cannot assign to `{key}`, it does not exist"),
+                    lhs.span(),
+                )
+            })?;
+        }
+
+        *target = match op.strip_suffix('=').filter(|op| !op.is_empty()) {
+            Some(op) => value::arithmetic(op, target, &rhs)
+                .map_err(|message| ctx.tmpl.error(message, span))?,
+            None => rhs,
+        };
+        Ok(())
+    }
+}
+
+/// Returns the output of `value`, or an error if it cannot be displayed
+pub(crate) fn display<'a>(ctx: &Context<'_>, value: &'a Value, span: Span) -> Result<Cow<'a, str>> {
+    value::display(value).ok_or_else(|| {
+        ctx.tmpl.error(
+            format!(
+                "a value of type {} cannot be displayed, use e.g. the `json` filter",
+                value::type_name(value),
+            ),
+            span,
+        )
+    })
+}
+
+/// `None` is `null`, other paths like `Enum::Variant` are the name of the (unit) variant
+fn path_value(path: &[PathComponent<'_>]) -> Value {
+    match path {
+        [PathComponent { name, .. }] if **name == "None" => Value::Null,
+        [.., last] => Value::String((*last.name).to_owned()),
+        [] => Value::Null,
+    }
+}
+
+/// Returns the content of a newtype, tuple or struct variant called `name`
+fn variant(value: Value, name: &str) -> Option<Value> {
+    let Value::Object(map) = value else {
+        return None;
+    };
+    if map.len() != 1 {
+        return None;
+    }
+    let (key, value) = map.into_iter().next()?;
+    (key == name).then_some(value)
+}
+
+fn cast(value: &Value, ty: &str) -> Option<Value> {
+    let int = match value {
+        Value::Bool(b) => Some(i128::from(*b)),
+        value => value::as_int(value),
+    };
+    let float = value.as_f64();
+    match ty {
+        "f32" | "f64" => value::from_float(float?),
+        "bool" => value.as_bool().map(Value::Bool),
+        _ => {
+            // `as` truncates floats towards zero, and wraps integers
+            let i = int.or_else(|| float.map(|f| f as i128))?;
+            let i = match ty {
+                "i8" => i128::from(i as i8),
+                "i16" => i128::from(i as i16),
+                "i32" => i128::from(i as i32),
+                "i64" | "isize" => i128::from(i as i64),
+                "i128" => i,
+                "u8" => i128::from(i as u8),
+                "u16" => i128::from(i as u16),
+                "u32" => i128::from(i as u32),
+                "u64" | "usize" | "u128" => i128::from(i as u64),
+                _ => return None,
+            };
+            value::from_int(i)
+        }
+    }
+}
+
+/// Parses a number literal, ignoring its type suffix
+pub(crate) fn parse_num(num: &Num<'_>) -> Option<Value> {
+    match *num {
+        Num::Int(src, _) => {
+            let src = src.replace('_', "");
+            let (negative, src) = match src.strip_prefix('-') {
+                Some(src) => (true, src),
+                None => (false, src.as_str()),
+            };
+            let (radix, digits) = match src.get(..2) {
+                Some("0x") => (16, &src[2..]),
+                Some("0o") => (8, &src[2..]),
+                Some("0b") => (2, &src[2..]),
+                _ => (10, src),
+            };
+            let i = i128::from_str_radix(digits, radix).ok()?;
+            value::from_int(if negative { -i } else { i })
+        }
+        Num::Float(src, _) => value::from_float(src.replace('_', "").parse().ok()?),
+    }
+}
+
+fn parse_str(s: &StrLit<'_>) -> Result<String, String> {
+    match s.prefix {
+        None => Ok(unescape(s.content)),
+        Some(prefix) => Err(format!(
+            "{}-prefixed string literals cannot be used in interpreted templates",
+            prefix.to_char(),
+        )),
+    }
+}
+
+/// Resolves the escape sequences in a (validated) Rust string or char literal
+pub(crate) fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some('x') => {
+                let hex = chars.as_str().get(..2).unwrap_or_default();
+                if let Ok(b) = u8::from_str_radix(hex, 16) {
+                    result.push(char::from(b));
+                    chars.nth(1);
+                }
+            }
+            Some('u') => {
+                let rest = chars.as_str();
+                if let Some(end) = rest.find('}') {
+                    let hex = rest[1..end].replace('_', "");
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        result.push(c);
+                    }
+                    chars = rest[end + 1..].chars();
+                }
+            }
+            Some('\n') => {
+                // A line continuation skips the following whitespace.
+                chars = chars.as_str().trim_start().chars();
+            }
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
@@ -0,0 +1,866 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use askama::filters::{self, Html};
+use parser::{Expr, Filter, PathOrIdentifier, Span, StrLit, Test, WithSpan};
+use serde_json::{Value, json};
+
+use crate::Result;
+use crate::expr::display;
+use crate::render::{Escaping, Renderer};
+use crate::template::Context;
+use crate::value::{self, Val};
+
+type Node<'t> = WithSpan<Box<Expr<'t>>>;
+
+impl<'t> Renderer<'t, '_> {
+    /// Applies a built-in filter, with the same escaping semantics as compiled templates
+    pub(crate) fn eval_filter(
+        &self,
+        ctx: &'t Context<'t>,
+        filter: &'t Filter<'t>,
+        span: Span,
+    ) -> Result<Val<'_>> {
+        let name = match &filter.name {
+            PathOrIdentifier::Identifier(name) => **name,
+            PathOrIdentifier::Path(path) => match path.as_slice() {
+                [component] => *component.name,
+                _ => {
+                    return Err(ctx.tmpl.error(
+                        "custom filters cannot be used in interpreted templates",
+                        span,
+                    ));
+                }
+            },
+        };
+        let [source, args @ ..] = filter.arguments.as_slice() else {
+            return Err(ctx.tmpl.error("filter without source", span));
+        };
+
+        // These filters decide which of their arguments is evaluated at all.
+        match name {
+            "default" => {
+                let [fallback, boolean] =
+                    self.filter_args(ctx, name, span, args, ["default_value", "boolean"])?;
+                let fallback = required(ctx, name, span, "default_value", fallback)?;
+                return match boolean.map(|b| &***b) {
+                    None | Some(Expr::BoolLit(false)) => {
+                        self.defined_or(ctx, name, span, source, fallback)
+                    }
+                    Some(Expr::BoolLit(true)) => self.assigned_or(ctx, source, fallback),
+                    Some(_) => Err(ctx.tmpl.error(
+                        "the `default` filter takes a boolean literal as its optional second \
+                         argument",
+                        span,
+                    )),
+                };
+            }
+            "defined_or" | "assigned_or" => {
+                let [fallback] = self.filter_args(ctx, name, span, args, ["fallback"])?;
+                let fallback = required(ctx, name, span, "fallback", fallback)?;
+                return match name {
+                    "defined_or" => self.defined_or(ctx, name, span, source, fallback),
+                    _ => self.assigned_or(ctx, source, fallback),
+                };
+            }
+            "pluralize" => {
+                let [singular, plural] =
+                    self.filter_args(ctx, name, span, args, ["singular", "plural"])?;
+                let count = self.eval(ctx, source)?;
+                let is_singular = match &*count.value {
+                    Value::Number(_) => matches!(value::as_int(&count.value), Some(-1 | 1)),
+                    v => {
+                        return Err(ctx.tmpl.error(
+                            format!("cannot pluralize a value of type {}", value::type_name(v)),
+                            source.span(),
+                        ));
+                    }
+                };
+                return match (is_singular, singular, plural) {
+                    (true, Some(expr), _) | (false, _, Some(expr)) => self.eval(ctx, expr),
+                    (true, None, _) => Ok(Val::new("".into())),
+                    (false, _, None) => Ok(Val::new("s".into())),
+                };
+            }
+            _ => {}
+        }
+
+        let val = self.eval(ctx, source)?;
+        let text = || display(ctx, &val.value, source.span());
+        let string = |s: String| Ok(Val::new(Value::String(s)));
+        let safe = |s: String| Ok(Val::safe(Value::String(s)));
+        match name {
+            "safe" => {
+                self.no_args(ctx, name, span, args)?;
+                Ok(Val { safe: true, ..val })
+            }
+            "escape" | "e" => {
+                let [escaper] = self.filter_args(ctx, name, span, args, ["escaper"])?;
+                let escaping = match escaper {
+                    Some(escaper) => match &***escaper {
+                        Expr::StrLit(ext) => Escaping::from_extension(ext.content)
+                            .map_err(|err| ctx.tmpl.error(err.to_string(), escaper.span()))?,
+                        _ => {
+                            return Err(ctx.tmpl.error(
+                                "the `escape` filter takes a string literal as its optional \
+                                 argument",
+                                escaper.span(),
+                            ));
+                        }
+                    },
+                    None => self.escaping,
+                };
+                let mut out = String::new();
+                escaping.write(&mut out, &text()?)?;
+                safe(out)
+            }
+            "sanitize" => {
+                let [tags, attributes] =
+                    self.filter_args(ctx, name, span, args, ["tags", "attributes"])?;
+                let strings = |arg: Option<&Node<'t>>, default: &[&str]| {
+                    let Some(arg) = arg else {
+                        return Ok(default.iter().map(|s| s.to_string()).collect());
+                    };
+                    let list = self.eval(ctx, arg)?;
+                    let Value::Array(items) = &*list.value else {
+                        return Err(self.type_error(ctx, name, &list.value, arg.span()));
+                    };
+                    items
+                        .iter()
+                        .map(|item| Ok(display(ctx, item, arg.span())?.into_owned()))
+                        .collect::<Result<Vec<_>>>()
+                };
+                let tags = strings(tags, filters::DEFAULT_SANITIZE_TAGS)?;
+                let attributes = strings(attributes, filters::DEFAULT_SANITIZE_ATTRIBUTES)?;
+                safe(filters::sanitize(text()?, &tags, &attributes)?.to_string())
+            }
+            "lower" | "lowercase" | "upper" | "uppercase" | "trim" | "capitalize" | "title"
+            | "titlecase" => {
+                self.no_args(ctx, name, span, args)?;
+                let text = text()?;
+                let result = match name {
+                    "lower" | "lowercase" => filters::lower(&text)?.to_string(),
+                    "upper" | "uppercase" => filters::upper(&text)?.to_string(),
+                    "trim" => filters::trim(&text)?.to_string(),
+                    "capitalize" => filters::capitalize(&text)?.to_string(),
+                    _ => filters::title(&text)?.to_string(),
+                };
+                string(result)
+            }
+            "truncate" | "center" => {
+                let [length] = self.filter_args(ctx, name, span, args, ["length"])?;
+                let length = required(ctx, name, span, "length", length)?;
+                let length = self.eval_usize(ctx, length)?;
+                let text = text()?;
+                string(match name {
+                    "truncate" => filters::truncate(&text, length)?.to_string(),
+                    _ => filters::center(&text, length)?.to_string(),
+                })
+            }
+            "indent" => {
+                let [width, first, blank] =
+                    self.filter_args(ctx, name, span, args, ["width", "first", "blank"])?;
+                let width = required(ctx, name, span, "width", width)?;
+                let first = self.eval_bool(ctx, first)?;
+                let blank = self.eval_bool(ctx, blank)?;
+                let text = text()?;
+                let width_val = self.eval(ctx, width)?;
+                string(match &*width_val.value {
+                    Value::String(indent) => {
+                        filters::indent(&text, indent.as_str(), first, blank)?.to_string()
+                    }
+                    _ => {
+                        let width = self.eval_usize(ctx, width)?;
+                        filters::indent(&text, width, first, blank)?.to_string()
+                    }
+                })
+            }
+            "join" => {
+                let [separator] = self.filter_args(ctx, name, span, args, ["separator"])?;
+                let separator = required(ctx, name, span, "separator", separator)?;
+                let separator = self.eval(ctx, separator)?;
+                let separator = display(ctx, &separator.value, span)?;
+                let Value::Array(items) = &*val.value else {
+                    return Err(self.type_error(ctx, name, &val.value, source.span()));
+                };
+                let items = items
+                    .iter()
+                    .map(|item| display(ctx, item, source.span()))
+                    .collect::<Result<Vec<_>>>()?;
+                string(filters::join(items.iter(), &separator)?.to_string())
+            }
+            "linebreaks" | "linebreaksbr" | "paragraphbreaks" => {
+                self.no_args(ctx, name, span, args)?;
+                let mut text = text()?.into_owned();
+                if !val.safe && self.escaping == Escaping::Html {
+                    text = filters::escape(&text, Html)?.to_string();
+                }
+                safe(match name {
+                    "linebreaks" => filters::linebreaks(&text)?.to_string(),
+                    "linebreaksbr" => filters::linebreaksbr(&text)?.to_string(),
+                    _ => filters::paragraphbreaks(&text)?.to_string(),
+                })
+            }
+            "urlencode" | "urlencode_strict" => {
+                self.no_args(ctx, name, span, args)?;
+                let text = text()?;
+                safe(match name {
+                    "urlencode" => filters::urlencode(&text)?.to_string(),
+                    _ => filters::urlencode_strict(&text)?.to_string(),
+                })
+            }
+            "filesizeformat" | "humansize" => {
+                let [precision] = self.filter_args(ctx, name, span, args, ["precision"])?;
+                let precision = match precision {
+                    Some(precision) => u8::try_from(self.eval_usize(ctx, precision)?)
+                        .map_err(|_| ctx.tmpl.error("precision too large", precision.span()))?,
+                    None => 2,
+                };
+                let bytes = match value::as_int(&val.value) {
+                    Some(i) => u128::try_from(i).unwrap_or_default(),
+                    None => match val.value.as_f64() {
+                        Some(f) => f as u128,
+                        None => return Err(self.type_error(ctx, name, &val.value, source.span())),
+                    },
+                };
+                safe(filters::filesizeformat(bytes, precision)?.to_string())
+            }
+            "json" | "tojson" => {
+                let [indent] = self.filter_args(ctx, name, span, args, ["indent"])?;
+                let result = match indent {
+                    None => filters::json(&*val.value)?.to_string(),
+                    Some(indent) => {
+                        let indent_val = self.eval(ctx, indent)?;
+                        match &*indent_val.value {
+                            Value::String(indent) => {
+                                filters::json_pretty(&*val.value, indent.as_str())?.to_string()
+                            }
+                            _ => {
+                                let width = self.eval_usize(ctx, indent)?;
+                                filters::json_pretty(&*val.value, width)?.to_string()
+                            }
+                        }
+                    }
+                };
+                string(result)
+            }
+            "wordcount" => {
+                self.no_args(ctx, name, span, args)?;
+                let text = text()?;
+                Ok(Val::new(filters::wordcount(&text).into_count().into()))
+            }
+            "unique" => {
+                self.no_args(ctx, name, span, args)?;
+                let Value::Array(items) = &*val.value else {
+                    return Err(self.type_error(ctx, name, &val.value, source.span()));
+                };
+                let mut seen = HashSet::new();
+                let items = items
+                    .iter()
+                    .filter(|item| seen.insert(item.to_string()))
+                    .cloned()
+                    .collect();
+                Ok(Val::new(Value::Array(items)))
+            }
+            "reject" => {
+                let [rejected] = self.filter_args(ctx, name, span, args, ["filter"])?;
+                let rejected = required(ctx, name, span, "filter", rejected)?;
+                let rejected = self.eval(ctx, rejected)?;
+                let Value::Array(items) = &*val.value else {
+                    return Err(self.type_error(ctx, name, &val.value, source.span()));
+                };
+                let items = items
+                    .iter()
+                    .filter(|item| !value::equals(item, &rejected.value))
+                    .cloned()
+                    .collect();
+                Ok(Val::new(Value::Array(items)))
+            }
+            "first" | "last" => {
+                self.no_args(ctx, name, span, args)?;
+                let mut items = self.items(ctx, name, val, source.span())?;
+                let item = match name {
+                    "first" => items.drain(..).next(),
+                    _ => items.pop(),
+                };
+                Ok(Val::new(item.unwrap_or_default()))
+            }
+            "min" | "max" => {
+                let [attribute] = self.filter_args(ctx, name, span, args, ["attribute"])?;
+                let path = attribute
+                    .map(|attribute| attribute_path(ctx, name, attribute))
+                    .transpose()?;
+                let items = self.items(ctx, name, val, source.span())?;
+                let mut result: Option<(Value, Value)> = None;
+                for item in items {
+                    let key = item_key(ctx, name, &item, path.as_deref(), span)?;
+                    // like `Iterator::min_by_key()` and `max_by_key()`, `min` returns the first
+                    // of several equal items, and `max` the last one
+                    let replace = match &result {
+                        Some((_, current)) => {
+                            let ordering = compare_keys(ctx, name, &key, current, span)?;
+                            match name {
+                                "min" => ordering.is_lt(),
+                                _ => ordering.is_ge(),
+                            }
+                        }
+                        None => true,
+                    };
+                    if replace {
+                        result = Some((item, key));
+                    }
+                }
+                Ok(Val::new(result.map(|(item, _)| item).unwrap_or_default()))
+            }
+            "sum" => {
+                let [attribute, start] =
+                    self.filter_args(ctx, name, span, args, ["attribute", "start"])?;
+                let path = attribute
+                    .map(|attribute| attribute_path(ctx, name, attribute))
+                    .transpose()?;
+                let mut sum = match start {
+                    Some(start) => self.eval(ctx, start)?.into_owned(),
+                    None => 0.into(),
+                };
+                for item in self.items(ctx, name, val, source.span())? {
+                    let key = item_key(ctx, name, &item, path.as_deref(), span)?;
+                    sum = value::arithmetic("+", &sum, &key)
+                        .map_err(|err| ctx.tmpl.error(err, span))?;
+                }
+                Ok(Val::new(sum))
+            }
+            "map" => {
+                let [attribute] = self.filter_args(ctx, name, span, args, ["attribute"])?;
+                let attribute = required(ctx, name, span, "attribute", attribute)?;
+                let path = attribute_path(ctx, name, attribute)?;
+                let items = self
+                    .items(ctx, name, val, source.span())?
+                    .iter()
+                    .map(|item| item_key(ctx, name, item, Some(&path), span))
+                    .collect::<Result<_>>()?;
+                Ok(Val::new(Value::Array(items)))
+            }
+            "selectattr" => {
+                let [attribute, test, test_arg] =
+                    self.filter_args(ctx, name, span, args, ["attribute", "test", "value"])?;
+                let attribute = required(ctx, name, span, "attribute", attribute)?;
+                let path = attribute_path(ctx, name, attribute)?;
+                let test = match test {
+                    Some(test) => {
+                        let Expr::StrLit(StrLit {
+                            prefix: None,
+                            content,
+                            ..
+                        }) = ***test
+                        else {
+                            return Err(ctx.tmpl.error(
+                                "`selectattr` filter expects the name of a test as string \
+                                 literal, like `\"odd\"`",
+                                test.span(),
+                            ));
+                        };
+                        let arity = test_arity(ctx, content, test.span())?;
+                        if arity != usize::from(test_arg.is_some()) {
+                            return Err(ctx.tmpl.error(
+                                format!(
+                                    "`{content}` test expects {arity} positional argument{}",
+                                    if arity == 1 { "" } else { "s" },
+                                ),
+                                test.span(),
+                            ));
+                        }
+                        Some(content)
+                    }
+                    None if test_arg.is_some() => {
+                        return Err(ctx.tmpl.error(
+                            "`selectattr` filter needs a `test` to compare the `value` with",
+                            span,
+                        ));
+                    }
+                    None => None,
+                };
+                let test_arg = match test_arg {
+                    Some(arg) => Some((self.eval(ctx, arg)?, arg.span())),
+                    None => None,
+                };
+                let mut selected = Vec::new();
+                for item in self.items(ctx, name, val, source.span())? {
+                    let key = item_key(ctx, name, &item, Some(&path), span)?;
+                    let keep = match test {
+                        Some(test) => {
+                            let arg = test_arg.as_ref().map(|(arg, span)| (&*arg.value, *span));
+                            apply_test(ctx, test, &key, attribute.span(), arg)?
+                        }
+                        None => value::is_truthy(&key),
+                    };
+                    if keep {
+                        selected.push(item);
+                    }
+                }
+                Ok(Val::new(Value::Array(selected)))
+            }
+            "sort" => {
+                let [reverse, attribute] =
+                    self.filter_args(ctx, name, span, args, ["reverse", "attribute"])?;
+                let reverse = self.eval_bool(ctx, reverse)?;
+                let path = attribute
+                    .map(|attribute| attribute_path(ctx, name, attribute))
+                    .transpose()?;
+                let items = self.items(ctx, name, val, source.span())?;
+                let items = sort_items(ctx, name, items, path.as_deref(), reverse, span)?;
+                Ok(Val::new(items.into_iter().map(|(item, _)| item).collect()))
+            }
+            "groupby" => {
+                let [attribute] = self.filter_args(ctx, name, span, args, ["attribute"])?;
+                let attribute = required(ctx, name, span, "attribute", attribute)?;
+                let path = attribute_path(ctx, name, attribute)?;
+                let items = self.items(ctx, name, val, source.span())?;
+                let items = sort_items(ctx, name, items, Some(&path), false, span)?;
+                let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+                for (item, key) in items {
+                    match groups.last_mut() {
+                        Some((grouper, list)) if value::equals(grouper, &key) => list.push(item),
+                        _ => groups.push((key, vec![item])),
+                    }
+                }
+                let groups = groups
+                    .into_iter()
+                    .map(|(grouper, list)| json!({ "grouper": grouper, "list": list }))
+                    .collect();
+                Ok(Val::new(Value::Array(groups)))
+            }
+            "batch" | "slice" => {
+                let count_name = match name {
+                    "batch" => "linecount",
+                    _ => "slices",
+                };
+                let [count, fill_with] =
+                    self.filter_args(ctx, name, span, args, [count_name, "fill_with"])?;
+                let count = required(ctx, name, span, count_name, count)?;
+                let count = self.eval_usize(ctx, count)?;
+                let fill_with = match fill_with {
+                    Some(fill_with) => Some(self.eval(ctx, fill_with)?.into_owned()),
+                    None => None,
+                };
+                let items = self.items(ctx, name, val, source.span())?;
+                let result = match name {
+                    "batch" => batch(items, count, fill_with),
+                    _ => slice(items, count, fill_with),
+                };
+                match result {
+                    Some(result) => Ok(Val::new(Value::Array(result))),
+                    None => Err(ctx.tmpl.error(
+                        match name {
+                            "batch" => "the `linecount` of a batch must not be zero",
+                            _ => "the number of slices must not be zero",
+                        },
+                        span,
+                    )),
+                }
+            }
+            "deref" | "ref" => {
+                self.no_args(ctx, name, span, args)?;
+                Ok(val)
+            }
+            "value" => {
+                self.no_args(ctx, name, span, args)?;
+                let Value::String(key) = &*val.value else {
+                    return Err(self.type_error(ctx, name, &val.value, source.span()));
+                };
+                // Like `Err(_)`, a missing value does not match `Ok(_)`.
+                let value = self.runtime_value(ctx, key, span)?;
+                Ok(Val::new(value.unwrap_or(Value::Null)))
+            }
+            "fmt" | "format" => Err(ctx.tmpl.error(
+                format!("the `{name}` filter cannot be used in interpreted templates"),
+                span,
+            )),
+            _ => Err(ctx.tmpl.error(
+                format!("filter `{name}` not found, custom filters cannot be used in interpreted templates"),
+                span,
+            )),
+        }
+    }
+
+    /// Applies a built-in test, e.g. `x is divisibleby(3)`
+    pub(crate) fn eval_test(
+        &self,
+        ctx: &'t Context<'t>,
+        test: &'t Test<'t>,
+        span: Span,
+    ) -> Result<Val<'_>> {
+        let name = match &test.name {
+            PathOrIdentifier::Identifier(name) => **name,
+            PathOrIdentifier::Path(path) => match path.as_slice() {
+                [component] => *component.name,
+                _ => {
+                    return Err(ctx
+                        .tmpl
+                        .error("custom tests cannot be used in interpreted templates", span));
+                }
+            },
+        };
+        let [source, args @ ..] = test.arguments.as_slice() else {
+            return Err(ctx.tmpl.error("test without value", span));
+        };
+        let arity = test_arity(ctx, name, span)?;
+        if args.len() != arity
+            || args
+                .iter()
+                .any(|arg| matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(ctx.tmpl.error(
+                format!(
+                    "`{name}` test expects {arity} positional argument{}",
+                    if arity == 1 { "" } else { "s" },
+                ),
+                span,
+            ));
+        }
+
+        let val = self.eval(ctx, source)?;
+        let arg = args.first().map(|arg| self.eval(ctx, arg)).transpose()?;
+        let arg = arg.as_ref().map(|arg| (&*arg.value, args[0].span()));
+        let result = apply_test(ctx, name, &val.value, source.span(), arg)?;
+        Ok(Val::new(Value::Bool(result != test.negated)))
+    }
+
+    /// `value|defined_or(fallback)`: `value` must be a variable
+    fn defined_or(
+        &self,
+        ctx: &'t Context<'t>,
+        name: &str,
+        span: Span,
+        source: &'t Node<'t>,
+        fallback: &'t Node<'t>,
+    ) -> Result<Val<'_>> {
+        let Expr::Var(var_name) = ***source else {
+            return Err(ctx.tmpl.error(
+                format!("the `{name}` filter requires a variable name on its left-hand side"),
+                span,
+            ));
+        };
+        match self.lookup(var_name) {
+            Some(Some(_)) => self.eval(ctx, source),
+            _ => self.eval(ctx, fallback),
+        }
+    }
+
+    /// `value|assigned_or(fallback)`: uses `fallback` if `value` is empty, zero or `null`
+    fn assigned_or(
+        &self,
+        ctx: &'t Context<'t>,
+        source: &'t Node<'t>,
+        fallback: &'t Node<'t>,
+    ) -> Result<Val<'_>> {
+        if let Expr::Var(var_name) = ***source
+            && !matches!(self.lookup(var_name), Some(Some(_)))
+        {
+            return self.eval(ctx, fallback);
+        }
+        let val = self.eval(ctx, source)?;
+        match &*val.value {
+            Value::Null | Value::Bool(false) => self.eval(ctx, fallback),
+            Value::String(s) if s.is_empty() => self.eval(ctx, fallback),
+            Value::Number(_) if !value::is_truthy(&val.value) => self.eval(ctx, fallback),
+            _ => Ok(val),
+        }
+    }
+
+    fn eval_bool(&self, ctx: &'t Context<'t>, expr: Option<&'t Node<'t>>) -> Result<bool> {
+        match expr {
+            Some(expr) => Ok(value::is_truthy(&self.eval(ctx, expr)?.value)),
+            None => Ok(false),
+        }
+    }
+
+    fn no_args(
+        &self,
+        ctx: &'t Context<'t>,
+        name: &str,
+        span: Span,
+        args: &'t [Node<'t>],
+    ) -> Result<()> {
+        let [] = self.filter_args(ctx, name, span, args, [])?;
+        Ok(())
+    }
+
+    /// Sorts the positional and named arguments of a filter call by the parameter names
+    fn filter_args<const N: usize>(
+        &self,
+        ctx: &'t Context<'t>,
+        name: &str,
+        span: Span,
+        args: &'t [Node<'t>],
+        params: [&str; N],
+    ) -> Result<[Option<&'t Node<'t>>; N]> {
+        let mut result = [None; N];
+        for (index, arg) in args.iter().enumerate() {
+            let (index, arg) = match &***arg {
+                Expr::NamedArgument(arg_name, value) => {
+                    match params.iter().position(|param| *param == **arg_name) {
+                        Some(index) => (index, value),
+                        None => {
+                            return Err(ctx.tmpl.error(
+                                format!(
+                                    "`{name}` filter does not have an argument `{}`",
+                                    **arg_name
+                                ),
+                                arg.span(),
+                            ));
+                        }
+                    }
+                }
+                _ if index < N => (index, arg),
+                _ => {
+                    return Err(ctx.tmpl.error(
+                        format!(
+                            "`{name}` filter expects {N} argument{}, found {}",
+                            if N == 1 { "" } else { "s" },
+                            args.len(),
+                        ),
+                        span,
+                    ));
+                }
+            };
+            if result[index].replace(arg).is_some() {
+                return Err(ctx.tmpl.error(
+                    format!("`{name}` filter got argument `{}` twice", params[index]),
+                    arg.span(),
+                ));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns the items of a list, like a `{% for %}` loop would iterate over them
+    fn items(&self, ctx: &Context<'_>, name: &str, val: Val<'_>, span: Span) -> Result<Vec<Value>> {
+        value::into_items(val.value).map_err(|type_name| {
+            ctx.tmpl.error(
+                format!("the `{name}` filter cannot be applied to a value of type {type_name}"),
+                span,
+            )
+        })
+    }
+
+    fn type_error(&self, ctx: &Context<'_>, name: &str, value: &Value, span: Span) -> crate::Error {
+        ctx.tmpl.error(
+            format!(
+                "the `{name}` filter cannot be applied to a value of type {}",
+                value::type_name(value),
+            ),
+            span,
+        )
+    }
+}
+
+fn required<'a>(
+    ctx: &Context<'_>,
+    name: &str,
+    span: Span,
+    param: &str,
+    arg: Option<&'a Node<'a>>,
+) -> Result<&'a Node<'a>> {
+    arg.ok_or_else(|| {
+        ctx.tmpl
+            .error(format!("`{name}` filter needs a `{param}` argument"), span)
+    })
+}
+
+/// Returns the number of arguments a built-in test takes, not counting the tested value
+fn test_arity(ctx: &Context<'_>, name: &str, span: Span) -> Result<usize> {
+    match name {
+        "even" | "odd" | "none" | "some" | "empty" => Ok(0),
+        "divisibleby" | "startingwith" | "in" => Ok(1),
+        _ => Err(ctx.tmpl.error(
+            format!(
+                "test `{name}` not found, custom tests cannot be used in interpreted templates"
+            ),
+            span,
+        )),
+    }
+}
+
+/// Applies a built-in test to a value, and to the argument of the test if it takes one
+fn apply_test(
+    ctx: &Context<'_>,
+    name: &str,
+    val: &Value,
+    val_span: Span,
+    arg: Option<(&Value, Span)>,
+) -> Result<bool> {
+    let type_error = |value: &Value, span: Span| {
+        ctx.tmpl.error(
+            format!(
+                "the `{name}` test cannot be applied to a value of type {}",
+                value::type_name(value),
+            ),
+            span,
+        )
+    };
+    let int =
+        |value: &Value, span: Span| value::as_int(value).ok_or_else(|| type_error(value, span));
+    let str = |value: &'_ Value, span: Span| match value {
+        Value::String(s) => Ok(s.clone()),
+        value => Err(type_error(value, span)),
+    };
+
+    Ok(match (name, arg) {
+        ("even", _) => int(val, val_span)? % 2 == 0,
+        ("odd", _) => int(val, val_span)? % 2 != 0,
+        ("divisibleby", Some((num, num_span))) => {
+            let value = int(val, val_span)?;
+            match int(num, num_span)? {
+                0 => value == 0,
+                num => value % num == 0,
+            }
+        }
+        ("none", _) => val.is_null(),
+        ("some", _) => !val.is_null(),
+        ("empty", _) => match val {
+            Value::Null => true,
+            Value::String(s) => s.is_empty(),
+            Value::Array(items) => items.is_empty(),
+            Value::Object(fields) => fields.is_empty(),
+            value => return Err(type_error(value, val_span)),
+        },
+        ("startingwith", Some((prefix, prefix_span))) => {
+            let prefix = str(prefix, prefix_span)?;
+            str(val, val_span)?.starts_with(&prefix)
+        }
+        ("in", Some((container, container_span))) => match container {
+            Value::String(s) => s.contains(&str(val, val_span)?),
+            Value::Array(items) => items.iter().any(|item| value::equals(item, val)),
+            Value::Object(fields) => fields.contains_key(&str(val, val_span)?),
+            container => return Err(type_error(container, container_span)),
+        },
+        _ => unreachable!(),
+    })
+}
+
+/// Splits the attribute argument of a collection filter like `"address.city"` into field names
+fn attribute_path<'t>(ctx: &Context<'_>, name: &str, attribute: &Node<'t>) -> Result<Vec<&'t str>> {
+    match ***attribute {
+        Expr::StrLit(StrLit {
+            prefix: None,
+            content,
+            ..
+        }) => Ok(content.split('.').collect()),
+        _ => Err(ctx.tmpl.error(
+            format!(
+                "`{name}` filter expects the name of a field as string literal, like `\"name\"` \
+                 or `\"address.city\"`"
+            ),
+            attribute.span(),
+        )),
+    }
+}
+
+/// Returns the item itself, or the field that `path` selects in it
+fn item_key(
+    ctx: &Context<'_>,
+    name: &str,
+    item: &Value,
+    path: Option<&[&str]>,
+    span: Span,
+) -> Result<Value> {
+    let mut val = Val::borrowed(item);
+    for field in path.into_iter().flatten() {
+        let type_name = value::type_name(&val.value);
+        val = val.field(field).ok_or_else(|| {
+            ctx.tmpl.error(
+                format!("`{name}` filter: a value of type {type_name} has no field `{field}`"),
+                span,
+            )
+        })?;
+    }
+    Ok(val.into_owned())
+}
+
+/// Returns the items with their keys, stably sorted by the keys
+fn sort_items(
+    ctx: &Context<'_>,
+    name: &str,
+    items: Vec<Value>,
+    path: Option<&[&str]>,
+    reverse: bool,
+    span: Span,
+) -> Result<Vec<(Value, Value)>> {
+    let mut items = items
+        .into_iter()
+        .map(|item| {
+            let key = item_key(ctx, name, &item, path, span)?;
+            Ok((item, key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut error = None;
+    items.sort_by(|(_, a), (_, b)| {
+        let (a, b) = if reverse { (b, a) } else { (a, b) };
+        compare_keys(ctx, name, a, b, span).unwrap_or_else(|err| {
+            error.get_or_insert(err);
+            Ordering::Equal
+        })
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(items),
+    }
+}
+
+fn compare_keys(
+    ctx: &Context<'_>,
+    name: &str,
+    lhs: &Value,
+    rhs: &Value,
+    span: Span,
+) -> Result<Ordering> {
+    value::compare(lhs, rhs).ok_or_else(|| {
+        ctx.tmpl.error(
+            format!(
+                "`{name}` filter cannot compare values of type {} and {}",
+                value::type_name(lhs),
+                value::type_name(rhs),
+            ),
+            span,
+        )
+    })
+}
+
+/// Splits the items into rows of `linecount` items, or `None` if `linecount` is zero
+fn batch(items: Vec<Value>, linecount: usize, fill_with: Option<Value>) -> Option<Vec<Value>> {
+    if linecount == 0 {
+        return None;
+    }
+    let rows = items.chunks(linecount).map(|row| {
+        let mut row = row.to_vec();
+        if let Some(fill_with) = &fill_with {
+            row.resize(linecount, fill_with.clone());
+        }
+        Value::Array(row)
+    });
+    Some(rows.collect())
+}
+
+/// Splits the items into `slices` columns, or `None` if `slices` is zero
+fn slice(items: Vec<Value>, slices: usize, fill_with: Option<Value>) -> Option<Vec<Value>> {
+    if slices == 0 {
+        return None;
+    }
+    let per_slice = items.len() / slices;
+    let with_extra = items.len() % slices;
+    let mut items = items.into_iter();
+    let columns = (0..slices).map(|index| {
+        let mut column = items
+            .by_ref()
+            .take(per_slice + usize::from(index < with_extra))
+            .collect::<Vec<_>>();
+        if let Some(fill_with) = &fill_with
+            && with_extra > 0
+            && index >= with_extra
+        {
+            column.push(fill_with.clone());
+        }
+        Value::Array(column)
+    });
+    Some(columns.collect())
+}
@@ -0,0 +1,271 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+mod error;
+mod expr;
+mod filters;
+mod loader;
+pub mod reload;
+mod render;
+mod template;
+#[cfg(test)]
+mod tests;
+mod value;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+pub use parser::node::Whitespace;
+pub use parser::{ParseError, Syntax, SyntaxBuilder};
+pub use serde_json::Value;
+
+pub use crate::error::{Error, Location, Result};
+pub use crate::loader::{FileLoader, Loader, MemoryLoader};
+use crate::render::{Escaping, Renderer};
+use crate::template::{Context, Template};
+
+/// Loads, caches and renders templates
+///
+/// Parsed templates are cached by their canonical name, cf. [`Loader::resolve()`].
+/// Use [`Environment::clear_cache()`] to pick up changes to the template sources.
+///
+/// Like for compiled templates, the escaper is selected by the file extension of the rendered
+/// template: `.html`, `.htm`, `.svg`, `.j2`, `.jinja`, `.jinja2` and `.askama` files are
+/// HTML-escaped, `.xml`, `.js`, `.mjs`, `.json`, `.css`, `.url`, `.tex` and `.latex` files use the
+/// respective built-in escaper, `.txt`, `.md`, `.yml`, `.none` and files without an extension are
+/// not escaped.
+pub struct Environment<L = FileLoader> {
+    loader: L,
+    syntax: Syntax<'static>,
+    whitespace: Whitespace,
+    cache: Mutex<HashMap<String, Arc<Template>>>,
+}
+
+impl Default for Environment {
+    /// Loads templates from the directory `templates`, relative to the current directory
+    fn default() -> Self {
+        Self::new(FileLoader::default())
+    }
+}
+
+impl<L: Loader> Environment<L> {
+    /// Creates an environment that uses `loader` to find templates
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            syntax: Syntax::default(),
+            whitespace: Whitespace::default(),
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Uses a custom syntax, e.g. other block delimiters
+    ///
+    /// The cache is cleared, because the cached templates were parsed with the old syntax.
+    pub fn with_syntax(mut self, syntax: Syntax<'static>) -> Self {
+        self.syntax = syntax;
+        self.clear_cache();
+        self
+    }
+
+    /// Sets the default whitespace handling, cf. the `whitespace` option of compiled templates
+    pub fn with_whitespace(mut self, whitespace: Whitespace) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+
+    /// The loader of this environment
+    #[inline]
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Forgets all parsed templates, so they are loaded again when they are rendered next
+    pub fn clear_cache(&self) {
+        self.lock_cache().clear();
+    }
+
+    /// Renders the template `name` with the variables in `values`
+    ///
+    /// `values` should be an object: its fields are the variables of the template. The whole
+    /// object is accessible as `self`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template could not be loaded, parsed or rendered.
+    pub fn render(&self, name: &str, values: &Value) -> Result<String> {
+        self.render_with_values(name, values, askama::NO_VALUES)
+    }
+
+    /// Renders the template `name` with the variables in `values`, and the runtime values in
+    /// `runtime_values`
+    ///
+    /// Like in compiled templates, the runtime values are read with the `value` filter or with
+    /// `askama::get_value()`. Only strings, characters, booleans, numbers and
+    /// [`serde_json::Value`]s, and [`Option`]s of them, can be read by the interpreter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template could not be loaded, parsed or rendered.
+    pub fn render_with_values(
+        &self,
+        name: &str,
+        values: &Value,
+        runtime_values: &dyn askama::Values,
+    ) -> Result<String> {
+        let name = self.loader.resolve(name, None)?;
+        let ext = Path::new(&name).extension().and_then(|ext| ext.to_str());
+        let escaping = Escaping::from_extension(ext.unwrap_or_default())?;
+        let mut buf = String::new();
+        self.render_resolved(&name, escaping, values, runtime_values, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Renders the template `name` with the variables in `values` into `dest`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template could not be loaded, parsed or rendered,
+    /// or if `dest` returned an error.
+    pub fn render_into(
+        &self,
+        name: &str,
+        values: &Value,
+        dest: &mut (impl fmt::Write + ?Sized),
+    ) -> Result<()> {
+        let name = self.loader.resolve(name, None)?;
+        let ext = Path::new(&name).extension().and_then(|ext| ext.to_str());
+        let escaping = Escaping::from_extension(ext.unwrap_or_default())?;
+        self.render_resolved(
+            &name,
+            escaping,
+            values,
+            askama::NO_VALUES,
+            &mut FmtWriter(dest),
+        )
+    }
+
+    pub(crate) fn render_resolved(
+        &self,
+        name: &str,
+        escaping: Escaping,
+        values: &Value,
+        runtime_values: &dyn askama::Values,
+        dest: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        let tmpl = self.load(name)?;
+        self.render_template(&tmpl, escaping, values, runtime_values, dest)
+    }
+
+    /// Renders the template `source` with the variables in `values`
+    ///
+    /// `ext` selects the escaper, like the `ext` argument of inline compiled templates. Paths in
+    /// `{% extends %}`, `{% include %}` and `{% import %}` are resolved by the loader.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template could not be loaded, parsed or rendered.
+    pub fn render_str(&self, source: &str, ext: &str, values: &Value) -> Result<String> {
+        let escaping = Escaping::from_extension(ext)?;
+        let tmpl = Arc::new(Template::new(
+            None,
+            source.to_owned(),
+            &self.syntax,
+            &self.loader,
+        )?);
+        let mut buf = String::new();
+        self.render_template(&tmpl, escaping, values, askama::NO_VALUES, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn render_template(
+        &self,
+        tmpl: &Arc<Template>,
+        escaping: Escaping,
+        values: &Value,
+        runtime_values: &dyn askama::Values,
+        dest: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        let templates = self.collect(tmpl)?;
+        let contexts = templates
+            .iter()
+            .map(|(name, tmpl)| Ok((name.clone(), Context::new(tmpl)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let inline;
+        let root = match &tmpl.name {
+            Some(name) => &contexts[&**name],
+            None => {
+                inline = Context::new(tmpl)?;
+                &inline
+            }
+        };
+        Renderer::new(&contexts, escaping, self.whitespace, values, runtime_values)
+            .render(root, dest)
+    }
+
+    /// Loads `tmpl` and all templates it references, directly or indirectly
+    fn collect(&self, tmpl: &Arc<Template>) -> Result<HashMap<String, Arc<Template>>> {
+        let mut templates = HashMap::new();
+        if let Some(name) = &tmpl.name {
+            templates.insert(name.to_string(), Arc::clone(tmpl));
+        }
+        let mut pending: Vec<String> = tmpl.paths.values().cloned().collect();
+        while let Some(name) = pending.pop() {
+            if templates.contains_key(&name) {
+                continue;
+            }
+            let tmpl = self.load(&name)?;
+            pending.extend(tmpl.paths.values().cloned());
+            templates.insert(name, tmpl);
+        }
+        Ok(templates)
+    }
+
+    fn load(&self, name: &str) -> Result<Arc<Template>> {
+        if let Some(tmpl) = self.lock_cache().get(name) {
+            return Ok(Arc::clone(tmpl));
+        }
+        let source = self.loader.load(name)?;
+        let tmpl = Arc::new(Template::new(
+            Some(name),
+            source,
+            &self.syntax,
+            &self.loader,
+        )?);
+        self.lock_cache().insert(name.to_owned(), Arc::clone(&tmpl));
+        Ok(tmpl)
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Template>>> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for Environment<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Environment")
+            .field("loader", &self.loader)
+            .field("whitespace", &self.whitespace)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Makes an unsized [`fmt::Write`] usable as `&mut dyn fmt::Write`
+struct FmtWriter<'a, W: fmt::Write + ?Sized>(&'a mut W);
+
+impl<W: fmt::Write + ?Sized> fmt::Write for FmtWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.0.write_char(c)
+    }
+}
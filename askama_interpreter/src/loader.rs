@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result};
+
+/// Finds and reads the source of templates
+///
+/// Templates reference other templates with `{% extends %}`, `{% include %}` and
+/// `{% import %}`. [`Loader::resolve()`] turns such a reference into the canonical name of the
+/// template, which is then used to [load](Loader::load) and to cache it.
+pub trait Loader {
+    /// Returns the canonical name of the template `name`
+    ///
+    /// `parent` is the canonical name of the template that references `name`, or `None` if `name`
+    /// was requested directly, or from a template that was rendered from a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if no such template exists.
+    fn resolve(&self, name: &str, parent: Option<&str>) -> Result<String>;
+
+    /// Returns the source of the template with the canonical name `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template could not be read.
+    fn load(&self, name: &str) -> Result<String>;
+}
+
+impl<L: Loader + ?Sized> Loader for &L {
+    #[inline]
+    fn resolve(&self, name: &str, parent: Option<&str>) -> Result<String> {
+        L::resolve(self, name, parent)
+    }
+
+    #[inline]
+    fn load(&self, name: &str) -> Result<String> {
+        L::load(self, name)
+    }
+}
+
+impl<L: Loader + ?Sized> Loader for Box<L> {
+    #[inline]
+    fn resolve(&self, name: &str, parent: Option<&str>) -> Result<String> {
+        L::resolve(self, name, parent)
+    }
+
+    #[inline]
+    fn load(&self, name: &str) -> Result<String> {
+        L::load(self, name)
+    }
+}
+
+/// Loads templates from the file system
+///
+/// Like for compiled templates, a template is first looked up relative to the template that
+/// references it, then in each of the template directories in order. A single trailing newline
+/// of a template file is removed.
+#[derive(Debug, Clone)]
+pub struct FileLoader {
+    dirs: Vec<PathBuf>,
+}
+
+impl FileLoader {
+    /// Creates a loader that looks up templates in the directories `dirs`
+    pub fn new<I: IntoIterator<Item: Into<PathBuf>>>(dirs: I) -> Self {
+        Self {
+            dirs: dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The template directories of this loader
+    #[inline]
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+}
+
+impl Default for FileLoader {
+    /// Looks up templates in the directory `templates`, relative to the current directory
+    fn default() -> Self {
+        Self::new(["templates"])
+    }
+}
+
+impl Loader for FileLoader {
+    fn resolve(&self, name: &str, parent: Option<&str>) -> Result<String> {
+        let relative = parent.map(|parent| Path::new(parent).with_file_name(name));
+        let rooted = self.dirs.iter().map(|dir| dir.join(name));
+        relative
+            .into_iter()
+            .chain(rooted)
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().into_owned())
+            .ok_or_else(|| Error::NotFound(name.to_owned()))
+    }
+
+    fn load(&self, name: &str) -> Result<String> {
+        let mut source = fs::read_to_string(name).map_err(|err| Error::Io {
+            name: name.to_owned(),
+            err,
+        })?;
+        if source.ends_with('\n') {
+            source.pop();
+        }
+        Ok(source)
+    }
+}
+
+/// Holds the source of templates in memory
+///
+/// Template names can contain slashes. A template is first looked up relative to the "directory"
+/// of the template that references it, then by its full name.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    templates: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    /// Creates an empty loader
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the template `name`
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) -> &mut Self {
+        self.templates.insert(name.into(), source.into());
+        self
+    }
+
+    /// Removes the template `name`, and returns its source if it existed
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.templates.remove(name)
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for MemoryLoader {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self {
+            templates: iter
+                .into_iter()
+                .map(|(name, source)| (name.into(), source.into()))
+                .collect(),
+        }
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn resolve(&self, name: &str, parent: Option<&str>) -> Result<String> {
+        if let Some((dir, _)) = parent.and_then(|parent| parent.rsplit_once('/')) {
+            let relative = format!("{dir}/{name}");
+            if self.templates.contains_key(&relative) {
+                return Ok(relative);
+            }
+        }
+        match self.templates.contains_key(name) {
+            true => Ok(name.to_owned()),
+            false => Err(Error::NotFound(name.to_owned())),
+        }
+    }
+
+    fn load(&self, name: &str) -> Result<String> {
+        self.templates
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(name.to_owned()))
+    }
+}
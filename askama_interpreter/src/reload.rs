@@ -0,0 +1,64 @@
+//! Development hot-reload support for `#[derive(Template)]`
+//!
+//! A template that is derived with `#[template(path = "…", reload = true)]` is rendered by its
+//! compiled code as long as none of its files was modified. Call [`install()`] at the start of
+//! your program, so the template is rendered by this interpreter once a file changed. See
+//! [`askama::reload`] for more information.
+//!
+//! Release builds don't contain any reloading code.
+
+use std::fmt;
+
+use askama::reload::TemplateFile;
+
+use crate::render::Escaping;
+use crate::{Environment, FileLoader, SyntaxBuilder, Value, Whitespace};
+
+/// Lets the interpreter render the reloadable templates once their files changed
+///
+/// Returns `false` if another renderer was installed already.
+pub fn install() -> bool {
+    askama::reload::set_renderer(render_changed)
+}
+
+fn render_changed(
+    tmpl: &'static TemplateFile,
+    values: &Value,
+    runtime_values: &dyn askama::Values,
+    dest: &mut dyn fmt::Write,
+) -> askama::Result<()> {
+    let [
+        block_start,
+        block_end,
+        expr_start,
+        expr_end,
+        comment_start,
+        comment_end,
+    ] = tmpl.syntax;
+    let syntax = SyntaxBuilder {
+        name: "",
+        block_start: Some(block_start),
+        block_end: Some(block_end),
+        expr_start: Some(expr_start),
+        expr_end: Some(expr_end),
+        comment_start: Some(comment_start),
+        comment_end: Some(comment_end),
+    }
+    .to_syntax()
+    .map_err(|err| askama::Error::custom(crate::Error::render(err)))?;
+
+    // The environment is not cached, so all templates are read again.
+    let env = Environment::new(FileLoader::new(tmpl.dirs))
+        .with_syntax(syntax)
+        .with_whitespace(match tmpl.whitespace {
+            askama::reload::Whitespace::Preserve => Whitespace::Preserve,
+            askama::reload::Whitespace::Suppress => Whitespace::Suppress,
+            askama::reload::Whitespace::Minimize => Whitespace::Minimize,
+        });
+    Escaping::from_extension(tmpl.ext)
+        .and_then(|escaping| env.render_resolved(tmpl.path, escaping, values, runtime_values, dest))
+        .map_err(|err| match err {
+            crate::Error::Fmt => askama::Error::Fmt,
+            err => askama::Error::custom(err),
+        })
+}
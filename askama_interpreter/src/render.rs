@@ -0,0 +1,1064 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+use askama::filters::{Css, Escaper, Html, Js, Json, Tex, Text, Url, Xml};
+use parser::node::{
+    Call, FilterBlock, If, Include, Let, Lit, Loop, Macro, Match, Whitespace, WithValues, Ws,
+};
+use parser::{Expr, LetValueOrBlock, Node, Span, Target, WithSpan};
+use serde_json::Value;
+
+use crate::template::{Context, Heritage};
+use crate::value::{self, Val};
+use crate::{Error, Result};
+
+/// How much templates may nest through `include`, macro calls and `caller()`
+const MAX_DEPTH: usize = 128;
+
+/// The escaper that is applied to the output of expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Escaping {
+    Html,
+    Xml,
+    Js,
+    Json,
+    Css,
+    Url,
+    Tex,
+    Text,
+}
+
+impl Escaping {
+    /// The same defaults as for compiled templates
+    pub(crate) fn from_extension(ext: &str) -> Result<Self> {
+        match ext {
+            "askama" | "html" | "htm" | "j2" | "jinja" | "jinja2" | "rinja" | "svg" => {
+                Ok(Self::Html)
+            }
+            "xml" => Ok(Self::Xml),
+            "js" | "mjs" => Ok(Self::Js),
+            "json" => Ok(Self::Json),
+            "css" => Ok(Self::Css),
+            "url" => Ok(Self::Url),
+            "latex" | "tex" => Ok(Self::Tex),
+            "md" | "none" | "txt" | "yml" | "" => Ok(Self::Text),
+            _ => Err(Error::render(format!(
+                "no escaper defined for extension `{}`",
+                ext.escape_debug(),
+            ))),
+        }
+    }
+
+    pub(crate) fn write(self, dest: &mut dyn fmt::Write, s: &str) -> fmt::Result {
+        match self {
+            Escaping::Html => Html.write_escaped_str(dest, s),
+            Escaping::Xml => Xml.write_escaped_str(dest, s),
+            Escaping::Js => Js.write_escaped_str(dest, s),
+            Escaping::Json => Json.write_escaped_str(dest, s),
+            Escaping::Css => Css.write_escaped_str(dest, s),
+            Escaping::Url => Url.write_escaped_str(dest, s),
+            Escaping::Tex => Tex.write_escaped_str(dest, s),
+            Escaping::Text => Text.write_escaped_str(dest, s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderFor {
+    Template,
+    /// Only the variables of a child template are needed, its output is discarded
+    Extends,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AstLevel {
+    Top,
+    Nested,
+}
+
+/// What a `{% break %}` or `{% continue %}` asks the surrounding loop to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+/// The `{% call %}` block that `caller()` renders
+#[derive(Clone, Copy)]
+struct Caller<'t> {
+    call: &'t Call<'t>,
+    ctx: &'t Context<'t>,
+}
+
+#[derive(Default)]
+struct Scope<'t> {
+    /// `None` if the variable was declared with `{% let x %}`, but was not assigned yet
+    vars: HashMap<&'t str, Option<Value>>,
+    /// `Some(None)` while rendering a `caller()`, so it cannot call itself
+    caller: Option<Option<Caller<'t>>>,
+}
+
+pub(crate) struct LoopState {
+    pub(crate) index0: usize,
+    pub(crate) len: usize,
+}
+
+pub(crate) struct Renderer<'t, 'v> {
+    pub(crate) contexts: &'t HashMap<String, Context<'t>>,
+    pub(crate) escaping: Escaping,
+    whitespace: Whitespace,
+    values: &'v Value,
+    /// The runtime values of [`askama::Values`], e.g. `{{ "key"|value }}`
+    runtime_values: &'v dyn askama::Values,
+    /// The values of the surrounding `{% with_values %}` blocks, they shadow `runtime_values`
+    with_values: Vec<(&'t str, Value)>,
+    scopes: Vec<Scope<'t>>,
+    pub(crate) loops: Vec<LoopState>,
+    heritage: Option<Heritage<'t>>,
+    super_block: Option<(&'t str, usize)>,
+    /// The templates whose macros are visible in included templates and blocks
+    fallbacks: Vec<&'t Context<'t>>,
+    pub(crate) filter_source: Option<String>,
+    next_ws: Option<&'t str>,
+    skip_ws: Whitespace,
+    render_for: RenderFor,
+    depth: usize,
+}
+
+impl<'t, 'v> Renderer<'t, 'v> {
+    pub(crate) fn new(
+        contexts: &'t HashMap<String, Context<'t>>,
+        escaping: Escaping,
+        whitespace: Whitespace,
+        values: &'v Value,
+        runtime_values: &'v dyn askama::Values,
+    ) -> Self {
+        Self {
+            contexts,
+            escaping,
+            whitespace,
+            values,
+            runtime_values,
+            with_values: Vec::new(),
+            scopes: vec![Scope::default()],
+            loops: Vec::new(),
+            heritage: None,
+            super_block: None,
+            fallbacks: Vec::new(),
+            filter_source: None,
+            next_ws: None,
+            skip_ws: Whitespace::Preserve,
+            render_for: RenderFor::Template,
+            depth: 0,
+        }
+    }
+
+    pub(crate) fn render(&mut self, ctx: &'t Context<'t>, out: &mut dyn fmt::Write) -> Result<()> {
+        if ctx.extends.is_some() || !ctx.blocks.is_empty() {
+            let heritage = Heritage::new(ctx, self.contexts)?;
+            let root = heritage.root;
+            self.heritage = Some(heritage);
+            if ctx.extends.is_some() {
+                // The output of the child template is discarded, only its variables are needed.
+                self.render_for = RenderFor::Extends;
+                self.handle(ctx, ctx.nodes, AstLevel::Top, &mut Discard)?;
+                self.render_for = RenderFor::Template;
+                self.next_ws = None;
+            }
+            self.handle(root, root.nodes, AstLevel::Top, out)?;
+        } else {
+            self.handle(ctx, ctx.nodes, AstLevel::Top, out)?;
+        }
+        self.flush_ws(Ws(None, None), out)
+    }
+
+    fn handle(
+        &mut self,
+        ctx: &'t Context<'t>,
+        nodes: &'t [Box<Node<'t>>],
+        level: AstLevel,
+        out: &mut dyn fmt::Write,
+    ) -> Result<Flow> {
+        for n in nodes {
+            let flow = match **n {
+                Node::Lit(ref lit) => {
+                    if self.render_for == RenderFor::Template {
+                        self.write_lit(lit, out)?;
+                    }
+                    Flow::Normal
+                }
+                Node::Comment(ref comment) => {
+                    if self.render_for == RenderFor::Template {
+                        self.handle_ws(comment.ws, out)?;
+                    }
+                    Flow::Normal
+                }
+                Node::Expr(ws, ref val) => {
+                    self.write_expr(ctx, ws, val, out)?;
+                    Flow::Normal
+                }
+                Node::Let(ref l) => {
+                    self.write_let(ctx, l, out)?;
+                    Flow::Normal
+                }
+                Node::Compound(ref c) => {
+                    self.handle_ws(c.ws, out)?;
+                    self.write_compound(ctx, c.op.op, &c.op.lhs, &c.op.rhs, c.span())?;
+                    Flow::Normal
+                }
+                Node::Declare(ref d) => {
+                    self.handle_ws(d.ws, out)?;
+                    self.top_scope().vars.insert(*d.var_name, None);
+                    Flow::Normal
+                }
+                Node::If(ref i) => self.write_if(ctx, i, out)?,
+                Node::Match(ref m) => self.write_match(ctx, m, out)?,
+                Node::Loop(ref l) => {
+                    self.write_loop(ctx, l, out)?;
+                    Flow::Normal
+                }
+                Node::BlockDef(ref b) => {
+                    if self.render_for == RenderFor::Template {
+                        self.write_block(ctx, Some(*b.name), Ws(b.ws1.0, b.ws2.1), b.span(), out)?;
+                    }
+                    Flow::Normal
+                }
+                Node::Include(ref i) => {
+                    self.write_include(ctx, i, out)?;
+                    Flow::Normal
+                }
+                Node::Call(ref call) => {
+                    let (def, macro_ctx) = self.find_macro(ctx, call.scope, call.name)?;
+                    let callsite_ws = Ws(call.ws1.0, call.ws2.1);
+                    let args = call.args.as_deref().unwrap_or_default();
+                    let span = call.span();
+                    self.write_macro(
+                        ctx,
+                        span,
+                        Some(call),
+                        callsite_ws,
+                        args,
+                        def,
+                        macro_ctx,
+                        out,
+                    )?;
+                    Flow::Normal
+                }
+                Node::FilterBlock(ref filter) => {
+                    self.write_filter_block(ctx, filter, out)?;
+                    Flow::Normal
+                }
+                Node::WithValues(ref with_values) => {
+                    self.write_with_values(ctx, with_values, out)?
+                }
+                Node::Macro(ref m) => {
+                    self.flush_ws(m.ws1, out)?;
+                    self.prepare_ws(m.ws2);
+                    Flow::Normal
+                }
+                Node::Raw(ref raw) => {
+                    if self.render_for == RenderFor::Template {
+                        self.handle_ws(raw.ws1, out)?;
+                        self.write_lit(&raw.lit, out)?;
+                        self.handle_ws(raw.ws2, out)?;
+                    }
+                    Flow::Normal
+                }
+                Node::Import(ref i) => {
+                    self.handle_ws(i.ws, out)?;
+                    Flow::Normal
+                }
+                Node::Extends(_) => Flow::Normal,
+                Node::Break(ref ws) => {
+                    self.handle_ws(**ws, out)?;
+                    Flow::Break
+                }
+                Node::Continue(ref ws) => {
+                    self.handle_ws(**ws, out)?;
+                    Flow::Continue
+                }
+            };
+            if flow != Flow::Normal {
+                return Ok(flow);
+            }
+        }
+
+        if level == AstLevel::Top && self.next_ws.is_some() {
+            // Handle any pending whitespace.
+            self.flush_ws(Ws(Some(self.skip_ws), None), out)?;
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn write_if(
+        &mut self,
+        ctx: &'t Context<'t>,
+        if_: &'t If<'t>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<Flow> {
+        let mut flow = Flow::Normal;
+        for (pos, cond) in if_.branches.iter().enumerate() {
+            if pos == 0 {
+                self.handle_ws(cond.ws, out)?;
+            }
+
+            self.push_scope();
+            let taken = match &cond.cond {
+                Some(test) => self.evaluate_condition(ctx, test)?,
+                None => true,
+            };
+            if taken {
+                // The whitespace handling is the same as if all branches were rendered.
+                self.next_ws = None;
+                self.prepare_ws(cond.ws);
+                flow = self.handle(ctx, &cond.nodes, AstLevel::Nested, out)?;
+                if flow == Flow::Normal {
+                    match if_.branches.get(pos + 1) {
+                        Some(next) => self.handle_ws(next.ws, out)?,
+                        None => self.handle_ws(if_.ws, out)?,
+                    }
+                }
+            }
+            self.pop_scope();
+            if taken {
+                break;
+            }
+        }
+        self.next_ws = None;
+        self.prepare_ws(if_.ws);
+        Ok(flow)
+    }
+
+    fn write_match(
+        &mut self,
+        ctx: &'t Context<'t>,
+        m: &'t Match<'t>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<Flow> {
+        self.flush_ws(m.ws1, out)?;
+        let value = self.eval(ctx, &m.expr)?.into_owned();
+
+        let mut flow = Flow::Normal;
+        for (i, arm) in m.arms.iter().enumerate() {
+            if i == 0 {
+                self.handle_ws(arm.ws, out)?;
+            }
+
+            self.push_scope();
+            let mut taken = false;
+            for target in &arm.target {
+                if self.match_target(ctx, target, value.clone())? {
+                    taken = true;
+                    break;
+                }
+            }
+            if taken {
+                self.next_ws = None;
+                self.prepare_ws(arm.ws);
+                flow = self.handle(ctx, &arm.nodes, AstLevel::Nested, out)?;
+                if flow == Flow::Normal {
+                    match m.arms.get(i + 1) {
+                        Some(next) => self.handle_ws(next.ws, out)?,
+                        None => self.handle_ws(m.ws2, out)?,
+                    }
+                }
+            }
+            self.pop_scope();
+            if taken {
+                break;
+            }
+        }
+        self.next_ws = None;
+        self.prepare_ws(m.ws2);
+        Ok(flow)
+    }
+
+    fn write_loop(
+        &mut self,
+        ctx: &'t Context<'t>,
+        loop_block: &'t WithSpan<Loop<'t>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        self.handle_ws(loop_block.ws1, out)?;
+
+        let iter = self.eval(ctx, &loop_block.iter)?.value;
+        let mut items = value::into_items(iter).map_err(|kind| {
+            ctx.tmpl.error(
+                format!("cannot iterate over a value of type {kind}"),
+                loop_block.iter.span(),
+            )
+        })?;
+        if let Some(cond) = &loop_block.cond {
+            let mut kept = Vec::with_capacity(items.len());
+            for item in items {
+                self.push_scope();
+                self.bind_loop_var(ctx, &loop_block.var, item.clone())?;
+                let keep = self.eval_condition_expr(ctx, cond)?;
+                self.pop_scope();
+                if keep {
+                    kept.push(item);
+                }
+            }
+            items = kept;
+        }
+
+        let len = items.len();
+        for (index0, item) in items.into_iter().enumerate() {
+            self.next_ws = None;
+            self.prepare_ws(loop_block.ws1);
+            self.push_scope();
+            self.bind_loop_var(ctx, &loop_block.var, item)?;
+            self.loops.push(LoopState { index0, len });
+            let flow = self.handle(ctx, &loop_block.body, AstLevel::Nested, out)?;
+            self.loops.pop();
+            if flow == Flow::Normal {
+                self.handle_ws(loop_block.ws2, out)?;
+            }
+            self.pop_scope();
+            if flow == Flow::Break {
+                break;
+            }
+        }
+
+        if len == 0 && !loop_block.else_nodes.is_empty() {
+            self.next_ws = None;
+            self.prepare_ws(loop_block.ws2);
+            self.push_scope();
+            self.handle(ctx, &loop_block.else_nodes, AstLevel::Nested, out)?;
+            self.handle_ws(loop_block.ws3, out)?;
+            self.pop_scope();
+        }
+        self.next_ws = None;
+        self.prepare_ws(loop_block.ws3);
+        Ok(())
+    }
+
+    fn bind_loop_var(
+        &mut self,
+        ctx: &'t Context<'t>,
+        target: &'t Target<'t>,
+        item: Value,
+    ) -> Result<()> {
+        match self.match_target(ctx, target, item)? {
+            true => Ok(()),
+            false => Err(ctx.tmpl.error(
+                "the loop variable does not match the pattern",
+                target.span(),
+            )),
+        }
+    }
+
+    fn write_let(
+        &mut self,
+        ctx: &'t Context<'t>,
+        l: &'t WithSpan<Let<'t>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        self.handle_ws(l.ws, out)?;
+        let value = match &l.val {
+            LetValueOrBlock::Value(val) => self.eval(ctx, val)?.into_owned(),
+            LetValueOrBlock::Block { nodes, ws } => {
+                let mut buf = String::new();
+                self.push_scope();
+                self.prepare_ws(l.ws);
+                self.handle(ctx, nodes, AstLevel::Nested, &mut buf)?;
+                self.flush_ws(*ws, &mut buf)?;
+                self.pop_scope();
+                self.prepare_ws(*ws);
+                Value::String(buf)
+            }
+        };
+
+        match &l.var {
+            Target::Name(name) => {
+                self.assign(name, value);
+                Ok(())
+            }
+            target => match self.match_target(ctx, target, value)? {
+                true => Ok(()),
+                false => Err(ctx
+                    .tmpl
+                    .error("the value does not match the pattern", l.span())),
+            },
+        }
+    }
+
+    fn write_filter_block(
+        &mut self,
+        ctx: &'t Context<'t>,
+        filter: &'t WithSpan<FilterBlock<'t>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        self.flush_ws(filter.ws1, out)?;
+
+        let mut buf = String::new();
+        self.push_scope();
+        self.prepare_ws(filter.ws1);
+        self.handle(ctx, &filter.nodes, AstLevel::Nested, &mut buf)?;
+        self.flush_ws(filter.ws2, &mut buf)?;
+        self.pop_scope();
+
+        let prev_source = self.filter_source.replace(buf);
+        let result = self.eval_filter(ctx, &filter.filters, filter.span());
+        let result = result.and_then(|val| self.write_val(ctx, &val, filter.span(), out));
+        self.filter_source = prev_source;
+        result?;
+
+        self.prepare_ws(filter.ws2);
+        Ok(())
+    }
+
+    fn write_with_values(
+        &mut self,
+        ctx: &'t Context<'t>,
+        with_values: &'t WithSpan<WithValues<'t>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<Flow> {
+        self.flush_ws(with_values.ws1, out)?;
+
+        let mut layer = Vec::with_capacity(with_values.values.len());
+        for (key, value) in &with_values.values {
+            layer.push((**key, self.eval(ctx, value)?.into_owned()));
+        }
+        // The layer stays visible in included templates and called macros, like in compiled code.
+        let len = self.with_values.len();
+        self.with_values.extend(layer);
+        self.push_scope();
+        self.prepare_ws(with_values.ws1);
+        let flow = self.handle(ctx, &with_values.nodes, AstLevel::Nested, out)?;
+        if flow == Flow::Normal {
+            self.flush_ws(with_values.ws2, out)?;
+        }
+        self.pop_scope();
+        self.with_values.truncate(len);
+
+        self.prepare_ws(with_values.ws2);
+        Ok(flow)
+    }
+
+    fn write_include(
+        &mut self,
+        ctx: &'t Context<'t>,
+        i: &'t WithSpan<Include<'t>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        self.flush_ws(i.ws, out)?;
+
+        let child = &self.contexts[&ctx.tmpl.paths[i.path]];
+        let heritage = match child.extends.is_some() || !child.blocks.is_empty() {
+            true => Some(Heritage::new(child, self.contexts)?),
+            false => None,
+        };
+        let handle_ctx = heritage.as_ref().map_or(child, |heritage| heritage.root);
+
+        // Like a new generator in `askama_derive`, but the variables stay visible.
+        let prev_heritage = mem::replace(&mut self.heritage, heritage);
+        let prev_super_block = self.super_block.take();
+        let prev_skip_ws = mem::replace(&mut self.skip_ws, Whitespace::Preserve);
+        self.enter(ctx, i.span())?;
+        self.fallbacks.push(ctx);
+        self.push_scope();
+        let result = self.handle(handle_ctx, handle_ctx.nodes, AstLevel::Top, out);
+        self.pop_scope();
+        self.fallbacks.pop();
+        self.depth -= 1;
+        self.heritage = prev_heritage;
+        self.super_block = prev_super_block;
+        self.skip_ws = prev_skip_ws;
+        result?;
+
+        self.prepare_ws(i.ws);
+        Ok(())
+    }
+
+    // If `name` is `Some`, this is a call to a block definition, and we have to find
+    // the first block for that name from the ancestry chain. If name is `None`, this
+    // is from a `super()` call, and we can get the name from `self.super_block`.
+    fn write_block(
+        &mut self,
+        ctx: &'t Context<'t>,
+        name: Option<&'t str>,
+        outer: Ws,
+        span: Span,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        // Flush preceding whitespace according to the outer WS spec
+        self.flush_ws(outer, out)?;
+
+        let cur = match (name, self.super_block) {
+            // The top-level context contains a block definition, or
+            // a block definition contains a definition of another block
+            (Some(cur_name), _) => (cur_name, 0),
+            // `super()` was called inside a block
+            (None, Some((prev_name, r#gen))) => (prev_name, r#gen + 1),
+            // `super()` is called from outside a block
+            (None, None) => {
+                return Err(ctx.tmpl.error("cannot call `super()` outside block", span));
+            }
+        };
+
+        let found = self
+            .heritage
+            .as_ref()
+            .and_then(|heritage| heritage.blocks.get(cur.0)?.get(cur.1).copied());
+        let Some((child_ctx, def)) = found else {
+            let message = match name {
+                None => format!("no `super()` block found for block `{}`", cur.0),
+                Some(name) => format!("no block found for name `{name}`"),
+            };
+            return Err(ctx.tmpl.error(message, span));
+        };
+
+        let prev_super_block = self.super_block.replace(cur);
+        let prev_skip_ws = mem::replace(&mut self.skip_ws, Whitespace::Preserve);
+        self.fallbacks.push(ctx);
+        self.push_scope();
+        // Handle inner whitespace suppression spec and process block nodes
+        self.prepare_ws(def.ws1);
+        let result = self.handle(child_ctx, &def.nodes, AstLevel::Nested, out);
+        let result = result.and_then(|_| self.flush_ws(def.ws2, out));
+        self.pop_scope();
+        self.fallbacks.pop();
+        self.super_block = prev_super_block;
+        self.skip_ws = prev_skip_ws;
+        result?;
+
+        // Restore original block context and set whitespace suppression for
+        // succeeding whitespace according to the outer WS spec
+        self.prepare_ws(outer);
+        Ok(())
+    }
+
+    fn write_expr(
+        &mut self,
+        ctx: &'t Context<'t>,
+        ws: Ws,
+        mut expr: &'t WithSpan<Box<Expr<'t>>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        while let Expr::Group(inner) = &***expr {
+            expr = inner;
+        }
+
+        if let Expr::Call(call) = &***expr
+            && self.write_expr_call(ctx, ws, expr.span(), call, out)?
+        {
+            return self.handle_ws(ws, out);
+        }
+
+        if self.render_for == RenderFor::Template {
+            self.handle_ws(ws, out)?;
+            self.write_expr_item(ctx, expr, out)?;
+        }
+        Ok(())
+    }
+
+    fn write_expr_item(
+        &mut self,
+        ctx: &'t Context<'t>,
+        expr: &'t WithSpan<Box<Expr<'t>>>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        match &***expr {
+            Expr::Group(expr) => self.write_expr_item(ctx, expr, out),
+            Expr::Concat(items) => {
+                for expr in items {
+                    self.write_expr_item(ctx, expr, out)?;
+                }
+                Ok(())
+            }
+            _ => {
+                let val = self.eval(ctx, expr)?;
+                self.write_val(ctx, &val, expr.span(), out)
+            }
+        }
+    }
+
+    pub(crate) fn write_val(
+        &self,
+        ctx: &Context<'_>,
+        val: &Val<'_>,
+        span: Span,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        let Some(s) = value::display(&val.value) else {
+            return Err(ctx.tmpl.error(
+                format!(
+                    "a value of type {} cannot be displayed, use e.g. the `json` filter",
+                    value::type_name(&val.value),
+                ),
+                span,
+            ));
+        };
+        match val.safe {
+            true => out.write_str(&s)?,
+            false => self.escaping.write(out, &s)?,
+        }
+        Ok(())
+    }
+
+    /// Handles `super()`, `caller()` and macro calls, returns `false` for other calls
+    fn write_expr_call(
+        &mut self,
+        ctx: &'t Context<'t>,
+        ws: Ws,
+        span: Span,
+        call: &'t parser::expr::Call<'t>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<bool> {
+        let (scope, name) = match &**call.path {
+            Expr::Var("super") => {
+                if !call.args.is_empty() {
+                    return Err(ctx.tmpl.error("`super()` takes no arguments", span));
+                }
+                self.write_block(ctx, None, ws, span, out)?;
+                return Ok(true);
+            }
+            Expr::Var("caller") => {
+                self.write_caller(ctx, ws, span, &call.args, out)?;
+                return Ok(true);
+            }
+            Expr::Var(name) => (None, WithSpan::new(*name, call.path.span())),
+            Expr::Path(path) => match path.as_slice() {
+                [scope, name] if ctx.imports.contains_key(*scope.name) => {
+                    (Some(scope.name), name.name)
+                }
+                _ => return Ok(false),
+            },
+            _ => return Ok(false),
+        };
+        if scope.is_none() && self.lookup_macro(ctx, &name).is_none() {
+            return Ok(false);
+        }
+
+        let (def, macro_ctx) = self.find_macro(ctx, scope, name)?;
+        self.write_macro(ctx, span, None, ws, &call.args, def, macro_ctx, out)?;
+        Ok(true)
+    }
+
+    fn lookup_macro(
+        &self,
+        ctx: &'t Context<'t>,
+        name: &str,
+    ) -> Option<(&'t Macro<'t>, &'t Context<'t>)> {
+        [ctx]
+            .into_iter()
+            .chain(self.fallbacks.iter().rev().copied())
+            .find_map(|ctx| Some((*ctx.macros.get(name)?, ctx)))
+    }
+
+    fn find_macro(
+        &self,
+        ctx: &'t Context<'t>,
+        scope: Option<WithSpan<&'t str>>,
+        name: WithSpan<&'t str>,
+    ) -> Result<(&'t Macro<'t>, &'t Context<'t>)> {
+        let Some(scope) = scope else {
+            return self.lookup_macro(ctx, &name).ok_or_else(|| {
+                ctx.tmpl
+                    .error(format!("macro `{}` not found", *name), name.span())
+            });
+        };
+
+        let import = [ctx]
+            .into_iter()
+            .chain(self.fallbacks.iter().rev().copied())
+            .find_map(|ctx| ctx.imports.get(*scope));
+        let Some(import) = import else {
+            return Err(ctx.tmpl.error(
+                format!("no import found for scope `{}`", *scope),
+                scope.span(),
+            ));
+        };
+        let macro_ctx = &self.contexts[*import];
+        match macro_ctx.macros.get(*name) {
+            Some(def) => Ok((def, macro_ctx)),
+            None => Err(ctx.tmpl.error(
+                format!("macro `{}` not found in scope `{}`", *name, *scope),
+                name.span(),
+            )),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_macro(
+        &mut self,
+        ctx: &'t Context<'t>,
+        span: Span,
+        call: Option<&'t Call<'t>>,
+        callsite_ws: Ws,
+        args: &'t [WithSpan<Box<Expr<'t>>>],
+        def: &'t Macro<'t>,
+        macro_ctx: &'t Context<'t>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        if args.len() > def.args.len() {
+            return Err(ctx.tmpl.error(
+                format!(
+                    "macro `{}` expected {} argument{}, found {}",
+                    *def.name,
+                    def.args.len(),
+                    if def.args.len() == 1 { "" } else { "s" },
+                    args.len(),
+                ),
+                span,
+            ));
+        }
+
+        // The arguments are evaluated at the call site ...
+        let mut values = vec![None; def.args.len()];
+        for (index, arg) in args.iter().enumerate() {
+            let (index, expr) = match &***arg {
+                Expr::NamedArgument(arg_name, expr) => {
+                    match def.args.iter().position(|arg| arg.name == *arg_name) {
+                        Some(index) => (index, expr),
+                        None => {
+                            return Err(ctx.tmpl.error(
+                                format!(
+                                    "no argument named `{}` in macro `{}`",
+                                    **arg_name, *def.name,
+                                ),
+                                arg.span(),
+                            ));
+                        }
+                    }
+                }
+                _ => (index, arg),
+            };
+            values[index] = Some(self.eval(ctx, expr)?.into_owned());
+        }
+
+        self.enter(ctx, span)?;
+        self.push_scope();
+        self.top_scope().caller = call.map(|call| Some(Caller { call, ctx }));
+        // ... while default values can use the preceding arguments.
+        for (arg, value) in def.args.iter().zip(values) {
+            let value = match (value, &arg.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.eval(macro_ctx, default)?.into_owned(),
+                (None, None) => {
+                    return Err(ctx
+                        .tmpl
+                        .error(format!("missing `{}` argument", *arg.name), span));
+                }
+            };
+            self.top_scope().vars.insert(*arg.name, Some(value));
+        }
+
+        self.flush_ws(callsite_ws, out)?; // Cannot handle_ws() here: whitespace from macro definition comes first
+        self.prepare_ws(def.ws1);
+        self.handle(macro_ctx, &def.nodes, AstLevel::Nested, out)?;
+        self.flush_ws(def.ws2, out)?;
+        self.pop_scope();
+        self.depth -= 1;
+        self.prepare_ws(callsite_ws);
+        Ok(())
+    }
+
+    fn write_caller(
+        &mut self,
+        ctx: &'t Context<'t>,
+        ws: Ws,
+        span: Span,
+        args: &'t [WithSpan<Box<Expr<'t>>>],
+        out: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        let caller = self.scopes.iter().rev().find_map(|scope| scope.caller);
+        let Some(Some(Caller {
+            call,
+            ctx: call_ctx,
+        })) = caller
+        else {
+            return Err(ctx.tmpl.error("block is not defined for `caller`", span));
+        };
+        if args.len() != call.caller_args.len() {
+            return Err(ctx.tmpl.error(
+                format!(
+                    "expected {} argument{} for `caller`, found {}",
+                    call.caller_args.len(),
+                    if call.caller_args.len() == 1 { "" } else { "s" },
+                    args.len(),
+                ),
+                span,
+            ));
+        }
+
+        self.handle_ws(ws, out)?;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.eval(ctx, arg)?.into_owned());
+        }
+
+        self.enter(ctx, span)?;
+        self.push_scope();
+        // Block-out the special caller() variable from this scope onward until it is
+        // defined by a new call-block again. This prohibits a caller from calling itself.
+        self.top_scope().caller = Some(None);
+        for (name, value) in call.caller_args.iter().zip(values) {
+            self.top_scope().vars.insert(name, Some(value));
+        }
+        self.prepare_ws(call.ws1);
+        self.handle(call_ctx, &call.nodes, AstLevel::Nested, out)?;
+        self.flush_ws(call.ws2, out)?;
+        self.pop_scope();
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn enter(&mut self, ctx: &Context<'_>, span: Span) -> Result<()> {
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(ctx.tmpl.error(
+                format!("templates, macros or callers are nested more than {MAX_DEPTH} times"),
+                span,
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn top_scope(&mut self) -> &mut Scope<'t> {
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// Binds a new variable in the current scope
+    pub(crate) fn bind(&mut self, name: &'t str, value: Value) {
+        self.top_scope().vars.insert(name, Some(value));
+    }
+
+    /// Assigns a variable that was declared with `{% let x %}`, or binds a new one
+    fn assign(&mut self, name: &'t str, value: Value) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(var @ None) = scope.vars.get_mut(name) {
+                *var = Some(value);
+                return;
+            }
+        }
+        self.bind(name, value);
+    }
+
+    /// Returns `None` if no such variable exists, and `Some(None)` if it was not assigned yet
+    pub(crate) fn lookup(&self, name: &str) -> Option<Option<&Value>> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(value) = scope.vars.get(name) {
+                return Some(value.as_ref());
+            }
+        }
+        match name {
+            "self" => Some(Some(self.values)),
+            _ => self.values.get(name).map(Some),
+        }
+    }
+
+    /// Looks up the runtime value `key`, `Ok(None)` if there is no such value
+    pub(crate) fn runtime_value(
+        &self,
+        ctx: &'t Context<'t>,
+        key: &str,
+        span: Span,
+    ) -> Result<Option<Value>> {
+        if let Some((_, value)) = self.with_values.iter().rev().find(|(k, _)| *k == key) {
+            return Ok(Some(value.clone()));
+        }
+        let Some(value) = self.runtime_values.get_value(key) else {
+            return Ok(None);
+        };
+        match value::from_any(value) {
+            Some(value) => Ok(Some(value)),
+            None => Err(ctx.tmpl.error(
+                format!(
+                    "the runtime value {key:?} has a type that cannot be used in interpreted \
+                     templates",
+                ),
+                span,
+            )),
+        }
+    }
+
+    pub(crate) fn lookup_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.vars.get_mut(name))?
+            .as_mut()
+    }
+
+    fn write_lit(&mut self, lit: &'t WithSpan<Lit<'t>>, out: &mut dyn fmt::Write) -> Result<()> {
+        let Lit { lws, val, rws } = **lit;
+        if !lws.is_empty() {
+            match self.skip_ws {
+                Whitespace::Suppress => {}
+                _ if val.is_empty() => {
+                    self.next_ws = Some(*lws);
+                }
+                Whitespace::Preserve => out.write_str(&lws)?,
+                Whitespace::Minimize => out.write_str(match lws.contains('\n') {
+                    true => "\n",
+                    false => " ",
+                })?,
+            }
+        }
+
+        if !val.is_empty() {
+            self.skip_ws = Whitespace::Preserve;
+            out.write_str(&val)?;
+        }
+
+        if !rws.is_empty() {
+            self.next_ws = Some(*rws);
+        }
+        Ok(())
+    }
+
+    // Combines `flush_ws()` and `prepare_ws()` to handle both trailing whitespace from the
+    // preceding literal and leading whitespace from the succeeding literal.
+    fn handle_ws(&mut self, ws: Ws, out: &mut dyn fmt::Write) -> Result<()> {
+        self.flush_ws(ws, out)?;
+        self.prepare_ws(ws);
+        Ok(())
+    }
+
+    // If the previous literal left some trailing whitespace in `next_ws` and the
+    // prefix whitespace suppressor from the given argument, flush that whitespace.
+    // In either case, `next_ws` is reset to `None` (no trailing whitespace).
+    fn flush_ws(&mut self, ws: Ws, out: &mut dyn fmt::Write) -> Result<()> {
+        let Some(val) = self.next_ws.take() else {
+            return Ok(());
+        };
+        match ws.0.unwrap_or(self.whitespace) {
+            Whitespace::Preserve => out.write_str(val)?,
+            Whitespace::Minimize => out.write_str(match val.contains('\n') {
+                true => "\n",
+                false => " ",
+            })?,
+            Whitespace::Suppress => {}
+        }
+        Ok(())
+    }
+
+    // Sets `skip_ws` to match the suffix whitespace suppressor from the given
+    // argument, to determine whether to suppress leading whitespace from the
+    // next literal.
+    fn prepare_ws(&mut self, ws: Ws) {
+        self.skip_ws = ws.1.unwrap_or(self.whitespace);
+    }
+}
+
+/// Output sink for the top-level nodes of a child template
+struct Discard;
+
+impl fmt::Write for Discard {
+    #[inline]
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
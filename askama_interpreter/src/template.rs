@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use parser::node::{BlockDef, Macro};
+use parser::{LetValueOrBlock, Node, Parsed, Span, Syntax};
+
+use crate::{Error, Loader, Location, Result};
+
+/// A parsed template, and the canonical names of all templates it references
+#[derive(Debug)]
+pub(crate) struct Template {
+    pub(crate) name: Option<Arc<str>>,
+    pub(crate) parsed: Parsed,
+    /// Maps the paths used in `extends`, `include` and `import` to canonical template names
+    pub(crate) paths: HashMap<String, String>,
+}
+
+impl Template {
+    pub(crate) fn new(
+        name: Option<&str>,
+        source: String,
+        syntax: &Syntax<'_>,
+        loader: &(impl Loader + ?Sized),
+    ) -> Result<Self> {
+        let file_path = name.map(|name| Arc::from(Path::new(name)));
+        let parsed = Parsed::new(source.into(), file_path, syntax)?;
+
+        let mut paths = HashMap::new();
+        let mut result = Ok(());
+        for_each_node(parsed.nodes(), &mut |node| {
+            let path = match node {
+                Node::Extends(e) => e.path,
+                Node::Include(i) => i.path,
+                Node::Import(i) => i.path,
+                _ => return,
+            };
+            if result.is_ok() && !paths.contains_key(path) {
+                match loader.resolve(path, name) {
+                    Ok(resolved) => drop(paths.insert(path.to_owned(), resolved)),
+                    Err(err) => result = Err(err),
+                }
+            }
+        });
+        result?;
+
+        Ok(Self {
+            name: name.map(Arc::from),
+            parsed,
+            paths,
+        })
+    }
+
+    pub(crate) fn location(&self, span: Span) -> Option<Location> {
+        let range = span.byte_range()?;
+        Some(Location::new(
+            self.name.clone(),
+            self.parsed.source(),
+            range.start,
+        ))
+    }
+
+    pub(crate) fn error(
+        &self,
+        message: impl Into<std::borrow::Cow<'static, str>>,
+        span: Span,
+    ) -> Error {
+        Error::Render {
+            message: message.into(),
+            location: self.location(span),
+        }
+    }
+}
+
+/// The definitions of a template that are needed to render it, cf. `askama_derive`'s `Context`
+pub(crate) struct Context<'t> {
+    pub(crate) tmpl: &'t Template,
+    pub(crate) nodes: &'t [Box<Node<'t>>],
+    pub(crate) extends: Option<&'t str>,
+    pub(crate) blocks: HashMap<&'t str, &'t BlockDef<'t>>,
+    pub(crate) macros: HashMap<&'t str, &'t Macro<'t>>,
+    pub(crate) imports: HashMap<&'t str, &'t str>,
+}
+
+impl<'t> Context<'t> {
+    pub(crate) fn new(tmpl: &'t Template) -> Result<Self> {
+        let mut extends = None;
+        let mut blocks: HashMap<&'t str, &'t BlockDef<'t>> = HashMap::new();
+        let mut macros = HashMap::new();
+        let mut imports = HashMap::new();
+        let mut nested = vec![tmpl.parsed.nodes()];
+        let mut top = true;
+
+        while let Some(nodes) = nested.pop() {
+            for n in nodes {
+                match &**n {
+                    Node::Extends(e) => {
+                        ensure_top(tmpl, top, e.span(), "extends")?;
+                        if extends.is_some() {
+                            return Err(tmpl.error("multiple extend blocks found", e.span()));
+                        }
+                        extends = Some(tmpl.paths[e.path].as_str());
+                    }
+                    Node::Macro(m) => {
+                        ensure_top(tmpl, top, m.span(), "macro")?;
+                        macros.insert(*m.name, &**m);
+                    }
+                    Node::Import(import) => {
+                        ensure_top(tmpl, top, import.span(), "import")?;
+                        imports.insert(import.scope, tmpl.paths[import.path].as_str());
+                    }
+                    Node::BlockDef(b) => {
+                        if blocks.insert(*b.name, b).is_some() {
+                            return Err(tmpl.error(
+                                format!("block `{}` was already defined", &*b.name),
+                                b.span(),
+                            ));
+                        }
+                        nested.push(&b.nodes);
+                    }
+                    Node::If(i) => {
+                        for cond in &i.branches {
+                            nested.push(&cond.nodes);
+                        }
+                    }
+                    Node::Loop(l) => {
+                        nested.push(&l.body);
+                        nested.push(&l.else_nodes);
+                    }
+                    Node::Match(m) => {
+                        for arm in &m.arms {
+                            nested.push(&arm.nodes);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            top = false;
+        }
+
+        Ok(Self {
+            tmpl,
+            nodes: tmpl.parsed.nodes(),
+            extends,
+            blocks,
+            macros,
+            imports,
+        })
+    }
+}
+
+fn ensure_top(tmpl: &Template, top: bool, span: Span, kind: &str) -> Result<()> {
+    match top {
+        true => Ok(()),
+        false => Err(tmpl.error(
+            format!("`{kind}` blocks are not allowed below top level"),
+            span,
+        )),
+    }
+}
+
+/// The blocks of a template and all its ancestors, cf. `askama_derive`'s `Heritage`
+pub(crate) struct Heritage<'t> {
+    pub(crate) root: &'t Context<'t>,
+    pub(crate) blocks: HashMap<&'t str, Vec<(&'t Context<'t>, &'t BlockDef<'t>)>>,
+}
+
+impl<'t> Heritage<'t> {
+    pub(crate) fn new(
+        mut root: &'t Context<'t>,
+        contexts: &'t HashMap<String, Context<'t>>,
+    ) -> Result<Self> {
+        let mut blocks: HashMap<_, Vec<_>> = HashMap::new();
+        let mut depth = 0;
+        loop {
+            for (name, def) in &root.blocks {
+                blocks.entry(*name).or_default().push((root, *def));
+            }
+            let Some(path) = root.extends else {
+                break;
+            };
+            depth += 1;
+            if depth > contexts.len() {
+                return Err(Error::render(format!(
+                    "template {path:?} extends itself, directly or indirectly",
+                )));
+            }
+            root = &contexts[path];
+        }
+        Ok(Self { root, blocks })
+    }
+}
+
+/// Calls `f` for every node in `nodes`, recursively
+pub(crate) fn for_each_node<'a>(nodes: &'a [Box<Node<'a>>], f: &mut impl FnMut(&'a Node<'a>)) {
+    for node in nodes {
+        f(node);
+        match &**node {
+            Node::Call(call) => for_each_node(&call.nodes, f),
+            Node::Let(l) => {
+                if let LetValueOrBlock::Block { nodes, .. } = &l.val {
+                    for_each_node(nodes, f);
+                }
+            }
+            Node::If(i) => {
+                for cond in &i.branches {
+                    for_each_node(&cond.nodes, f);
+                }
+            }
+            Node::Match(m) => {
+                for arm in &m.arms {
+                    for_each_node(&arm.nodes, f);
+                }
+            }
+            Node::Loop(l) => {
+                for_each_node(&l.body, f);
+                for_each_node(&l.else_nodes, f);
+            }
+            Node::BlockDef(b) => for_each_node(&b.nodes, f),
+            Node::Macro(m) => for_each_node(&m.nodes, f),
+            Node::FilterBlock(b) => for_each_node(&b.nodes, f),
+            Node::WithValues(w) => for_each_node(&w.nodes, f),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use parser::Syntax;
+use parser::cst::{FormatOptions, format};
+use serde_json::json;
+
+use crate::{Environment, Error, FileLoader, MemoryLoader, Value, Whitespace};
+
+#[track_caller]
+fn render(templates: &[(&str, &str)], name: &str, values: &Value) -> String {
+    let env = Environment::new(templates.iter().copied().collect::<MemoryLoader>());
+    match env.render(name, values) {
+        Ok(output) => output,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+#[track_caller]
+fn render_str(source: &str, values: &Value) -> String {
+    let env = Environment::new(MemoryLoader::new());
+    match env.render_str(source, "html", values) {
+        Ok(output) => output,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+#[track_caller]
+fn render_err(source: &str, values: &Value) -> String {
+    let env = Environment::new(MemoryLoader::new());
+    match env.render_str(source, "html", values) {
+        Ok(output) => panic!("expected an error, got {output:?}"),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn test_expr() {
+    let values = json!({ "a": 5, "b": "<b>", "list": [1, 2, 3], "obj": { "x": { "y": true } } });
+    assert_eq!(
+        render_str("{{ a }} {{ b }} {{ b|safe }}", &values),
+        "5 &#60;b&#62; <b>"
+    );
+    assert_eq!(
+        render_str("{{ a * 2 + 1 }} {{ 7 / 2 }} {{ 7.0 / 2.0 }}", &values),
+        "11 3 3.5"
+    );
+    assert_eq!(render_str("{{ list[1] }} {{ list.len() }}", &values), "2 3");
+    assert_eq!(
+        render_str("{{ obj.x.y }} {{ obj[\"x\"].y }}", &values),
+        "true true"
+    );
+    assert_eq!(render_str("{{ a ~ \"-\" ~ b }}", &values), "5-&#60;b&#62;");
+    assert_eq!(render_str("{{ a as f64 / 2.0 }}", &values), "2.5");
+    assert_eq!(
+        render_str("{{ 0x10 + 1_000 }} {{ \"a\\tb\" }}", &values),
+        "1016 a\tb"
+    );
+    assert_eq!(render_str("{{ list[1..].len() }}", &values), "2");
+    assert_eq!(render_str("{{ self.a }}", &values), "5");
+}
+
+#[test]
+fn test_if_and_match() {
+    let values = json!({ "n": 2, "opt": null, "some": 5, "shape": { "Circle": 3 } });
+    assert_eq!(
+        render_str(
+            "{% if n == 1 %}one{% else if n == 2 %}two{% else %}many{% endif %}",
+            &values,
+        ),
+        "two",
+    );
+    assert_eq!(
+        render_str(
+            "{% if let Some(x) = some %}{{ x }}{% endif %}\
+            {% if let Some(x) = opt %}{{ x }}{% else %}none{% endif %}",
+            &values,
+        ),
+        "5none",
+    );
+    assert_eq!(
+        render_str(
+            "{% match shape %}\
+                {% when Shape::Square(s) %}square {{ s }}\
+                {% when Shape::Circle(r) %}circle {{ r }}\
+            {% endmatch %}",
+            &values,
+        ),
+        "circle 3",
+    );
+    assert_eq!(
+        render_str(
+            "{% match n %}{% when 1 or 2 %}small{% else %}big{% endmatch %}",
+            &values,
+        ),
+        "small",
+    );
+    assert_eq!(
+        render_str(
+            "{{ \"one\" if n == 1 else \"two\" if n == 2 else \"many\" }}\
+            [{{ n if opt }}] {{ some if some > n }}",
+            &values,
+        ),
+        "two[] 5",
+    );
+}
+
+#[test]
+fn test_loop() {
+    let values = json!({ "items": ["a", "b", "c"], "empty": [] });
+    assert_eq!(
+        render_str(
+            "{% for item in items %}{{ loop.index }}{{ item }}\
+            {% if !loop.last %},{% endif %}{% endfor %}",
+            &values,
+        ),
+        "1a,2b,3c",
+    );
+    assert_eq!(
+        render_str(
+            "{% for item in empty %}{{ item }}{% else %}nothing{% endfor %}",
+            &values,
+        ),
+        "nothing",
+    );
+    assert_eq!(
+        render_str(
+            "{% for i in 0..10 %}{% if i == 2 %}{% continue %}{% endif %}\
+            {% if i == 4 %}{% break %}{% endif %}{{ i }}{% endfor %}",
+            &values,
+        ),
+        "013",
+    );
+    assert_eq!(
+        render_str(
+            "{% for (i, item) in [(1, \"x\"), (2, \"y\")] if i > 1 %}\
+            {{ item }}{{ loop.first }}{% endfor %}",
+            &values,
+        ),
+        "ytrue",
+    );
+    assert_eq!(
+        render_str(
+            "{% for item in items %}{{ loop.cycle([\"odd\", \"even\"]) }} {% endfor %}",
+            &values,
+        ),
+        "odd even odd ",
+    );
+}
+
+#[test]
+fn test_let() {
+    assert_eq!(
+        render_str(
+            "{% let x = 1 %}{% decl mut y %}{% if x == 1 %}{% let y = 2 %}{% endif %}\
+            {{ x }}{{ y }}{% mut y += 40 %}{{ y }}",
+            &json!({}),
+        ),
+        "1242",
+    );
+    assert_eq!(
+        render_str(
+            "{% let (a, [b, ..]) = (1, [2, 3]) %}{{ a }}{{ b }}\
+            {% let s %}<{{ a }}>{% endlet %}{{ s }}",
+            &json!({}),
+        ),
+        "12&#60;1&#62;",
+    );
+}
+
+#[test]
+fn test_whitespace() {
+    let values = json!({ "a": 1 });
+    assert_eq!(render_str("  {{- a -}}  \n  {{ a }}  ", &values), "11  ");
+    assert_eq!(render_str("x \n {{~ a ~}} \n y", &values), "x\n1\ny");
+    assert_eq!(
+        render_str(
+            "<ul>\n  {%- for i in [1, 2] %}\n  <li>{{ i }}</li>\n  {%- endfor %}\n</ul>",
+            &values,
+        ),
+        "<ul>\n  <li>1</li>\n  <li>2</li>\n</ul>",
+    );
+
+    let env = Environment::new(MemoryLoader::new()).with_whitespace(Whitespace::Suppress);
+    assert_eq!(
+        env.render_str("a  {% if true %}  b  {% endif %}  c", "txt", &values)
+            .unwrap(),
+        "abc",
+    );
+}
+
+#[test]
+fn test_format_keeps_output() {
+    let values = json!({ "a": 1, "list": [1, 2] });
+    let templates = [
+        "{% if a %}\n  {% for i in list %}\n      <li>{{i}}</li>\n        {% endfor %}\n{% endif %}\n",
+        "<ul>\n{%- for i in list %}\n<li>{{i}}</li>\n      {%~ if i == 1 -%}\n  x\n        {%- else ~%}\n y \n  {% endif %}\n{%- endfor %}\n</ul>",
+        "{% if true %}\n  {#- c #}\n{% raw %}\n   {{ x }}\n    {%- endraw %}\n   {%~ endif +%}\n  {{ a }}",
+        "  {%~ if true %}{{ a }}  {%~ endif %}   {{ a }}\n\t{%+ if a -%} \n {%~ endif %}",
+        "{% match a %}\n   {%- when 1 %}\n one\n      {%~ else %}\n other\n {% endmatch %}",
+        "{% filter upper %}\n  {%- let x = 2 %}\n    {{ x }}\n{%- endfilter %}\n  {% if a %}b{% endif %}",
+    ];
+    for whitespace in [
+        Whitespace::Preserve,
+        Whitespace::Suppress,
+        Whitespace::Minimize,
+    ] {
+        let env = Environment::new(MemoryLoader::new()).with_whitespace(whitespace);
+        let options = FormatOptions {
+            whitespace,
+            ..FormatOptions::default()
+        };
+        for source in templates {
+            let formatted = format(source, &Syntax::default(), &options);
+            assert_eq!(
+                env.render_str(&formatted, "html", &values).unwrap(),
+                env.render_str(source, "html", &values).unwrap(),
+                "{source:?} formatted as {formatted:?} with {whitespace:?}",
+            );
+        }
+    }
+
+    // Tags are re-indented where the whitespace before them is suppressed or minimized.
+    assert_eq!(
+        format(templates[1], &Syntax::default(), &FormatOptions::default()),
+        "<ul>\n{%- for i in list %}\n<li>{{ i }}</li>\n    {%~ if i == 1 -%}\n  x\n    {%- else ~%}\n y \n  {% endif %}\n{%- endfor %}\n</ul>",
+    );
+}
+
+#[test]
+fn test_filters() {
+    let values = json!({ "s": "hello world", "n": 1, "list": ["a", "<b>", "a"], "html": "<p>" });
+    assert_eq!(
+        render_str("{{ s|upper }} {{ s|title }}", &values),
+        "HELLO WORLD Hello World"
+    );
+    assert_eq!(render_str("{{ s|truncate(5) }}", &values), "hello...");
+    assert_eq!(
+        render_str("{{ list|join(\", \") }}", &values),
+        "a, &#60;b&#62;, a"
+    );
+    assert_eq!(
+        render_str("{{ list|unique|join(\"\") }}", &values),
+        "a&#60;b&#62;"
+    );
+    assert_eq!(
+        render_str("{{ html|escape }} {{ html|e|e }}", &values),
+        "&#60;p&#62; &#38;#60;p&#38;#62;"
+    );
+    assert_eq!(
+        render_str("{{ \"a\\nb\"|linebreaksbr }}", &values),
+        "a<br/>b"
+    );
+    assert_eq!(
+        render_str("{{ html|linebreaks }}", &values),
+        "<p>&#60;p&#62;</p>"
+    );
+    assert_eq!(
+        render_str(
+            "{{ \"<b onclick=x>a</b><i>b</i>\"|sanitize }} {{ \"<p><i>c</i></p>\"|sanitize([\"i\"]) }}",
+            &values
+        ),
+        "<b>a</b><i>b</i> <i>c</i>",
+    );
+    assert_eq!(render_str("{{ 1024|filesizeformat }}", &values), "1.02 kB");
+    assert_eq!(render_str("{{ \"a b\"|urlencode }}", &values), "a%20b");
+    assert_eq!(
+        render_str("{{ list|json }}", &values),
+        "[&#34;a&#34;,&#34;\\u003cb\\u003e&#34;,&#34;a&#34;]",
+    );
+    assert_eq!(
+        render_str("{{ s|indent(2, first=true) }}", &values),
+        "  hello world"
+    );
+    assert_eq!(
+        render_str(
+            "item{{ n|pluralize }} item{{ 2|pluralize(plural=\"z\") }}",
+            &values
+        ),
+        "item itemz",
+    );
+    assert_eq!(
+        render_str(
+            "{{ missing|default(\"x\") }} {{ s|defined_or(\"x\") }}",
+            &values
+        ),
+        "x hello world",
+    );
+    assert_eq!(
+        render_str("{{ \"\"|assigned_or(\"empty\") }}", &values),
+        "empty"
+    );
+    assert_eq!(
+        render_str("{% filter upper %}a{{ html }}b{% endfilter %}", &values),
+        "A&#38;#60;P&#38;#62;B",
+    );
+}
+
+#[test]
+fn test_collection_filters() {
+    let values = json!({
+        "numbers": [3, -1, 4, 1],
+        "users": [
+            { "name": "Ferris", "age": 12, "admin": true, "address": { "city": "Paris" } },
+            { "name": "Corro", "age": 7, "admin": false, "address": { "city": "Berlin" } },
+            { "name": "Rusty", "age": 12, "admin": false, "address": { "city": "Paris" } },
+        ],
+    });
+    assert_eq!(
+        render_str(
+            "{{ numbers|sort|join(\",\") }} {{ numbers|sort(true)|join(\",\") }}",
+            &values
+        ),
+        "-1,1,3,4 4,3,1,-1",
+    );
+    assert_eq!(
+        render_str(
+            "{{ users|sort(reverse=true, attribute=\"age\")|map(attribute=\"name\")|join(\",\") }}",
+            &values
+        ),
+        "Ferris,Rusty,Corro",
+    );
+    assert_eq!(
+        render_str(
+            "{% for group in users|groupby(\"address.city\") %}\
+            {{ group.grouper }}: {{ group.list|map(attribute=\"name\")|join(\",\") }};\
+            {% endfor %}",
+            &values
+        ),
+        "Berlin: Corro;Paris: Ferris,Rusty;",
+    );
+    assert_eq!(
+        render_str(
+            "{{ users|selectattr(\"admin\")|map(attribute=\"name\")|join(\",\") }} \
+            {{ users|selectattr(\"age\", \"divisibleby\", 3)|map(attribute=\"name\")|join(\",\") }}",
+            &values
+        ),
+        "Ferris Ferris,Rusty",
+    );
+    assert_eq!(
+        render_str(
+            "{% for row in numbers|batch(3, 0) %}{{ row|join(\",\") }};{% endfor %} \
+            {% for column in numbers|slice(3) %}{{ column|join(\",\") }};{% endfor %}",
+            &values
+        ),
+        "3,-1,4;1,0,0; 3,-1;4;1;",
+    );
+    assert_eq!(
+        render_str(
+            "{{ numbers|first }} {{ numbers|last }} {{ numbers|min }} {{ numbers|max }} \
+            {{ (users|max(attribute=\"age\")).name }} {{ numbers|sum }} \
+            {{ users|sum(attribute=\"age\", start=0.5) }}",
+            &values
+        ),
+        "3 1 -1 4 Rusty 7 31.5",
+    );
+}
+
+#[test]
+fn test_extends() {
+    let templates = [
+        (
+            "base.html",
+            "<title>{% block title %}Base{% endblock %}</title>\
+            {% block body %}{% endblock %}",
+        ),
+        (
+            "child.html",
+            "{% extends \"base.html\" %}\
+            {% block title %}{% let who = \"child\" %}{{ who }} - {{ super() }}{% endblock %}\
+            {% block body %}<p>{{ text }}</p>{% endblock %}",
+        ),
+        (
+            "grandchild.html",
+            "{% extends \"child.html\" %}{% block body %}[{{ super() }}]{% endblock %}",
+        ),
+    ];
+    let values = json!({ "text": "&" });
+    assert_eq!(
+        render(&templates, "child.html", &values),
+        "<title>child - Base</title><p>&#38;</p>",
+    );
+    assert_eq!(
+        render(&templates, "grandchild.html", &values),
+        "<title>child - Base</title>[<p>&#38;</p>]",
+    );
+}
+
+#[test]
+fn test_include() {
+    let templates = [
+        (
+            "page.html",
+            "{% for item in items %}{% include \"item.html\" %}{% endfor %}",
+        ),
+        ("item.html", "<{{ item }}>"),
+        ("text.txt", "{% include \"item.html\" %}"),
+    ];
+    let values = json!({ "items": ["a", "b"], "item": "<" });
+    assert_eq!(render(&templates, "page.html", &values), "<a><b>");
+    // The escaper is selected by the rendered template, not by the included one.
+    assert_eq!(render(&templates, "text.txt", &values), "<<>");
+}
+
+#[test]
+fn test_macros() {
+    let templates = [
+        (
+            "macros.html",
+            "{% macro heading(text, level = 1) %}<h{{ level }}>{{ text }}</h{{ level }}>\
+            {% endmacro %}\
+            {% macro wrap(class) %}<div class=\"{{ class }}\">{{ caller(\"x\") }}</div>\
+            {% endmacro %}",
+        ),
+        (
+            "page.html",
+            "{% import \"macros.html\" as m %}\
+            {% macro local(a) %}[{{ a }}]{% endmacro %}\
+            {{ local(1) }}{{ m::heading(\"Hi\") }}{{ m::heading(level = 2, text = \"Ho\") }}\
+            {% call(arg) m::wrap(\"c\") %}{{ arg }}!{% endcall %}",
+        ),
+    ];
+    assert_eq!(
+        render(&templates, "page.html", &json!({})),
+        "[1]<h1>Hi</h1><h2>Ho</h2><div class=\"c\">x!</div>",
+    );
+}
+
+#[test]
+fn test_runtime_values() {
+    let templates = [(
+        "a.html",
+        "{{ \"name\"|value::<&str> }} {{ askama::get_value(\"age\")? }}\
+        {% if let Ok(x) = \"missing\"|value::<u32> %}{{ x }}{% else %}!{% endif %}",
+    )];
+    let env = Environment::new(templates.into_iter().collect::<MemoryLoader>());
+    let mut values: HashMap<&str, Box<dyn Any>> = HashMap::new();
+    values.insert("name", Box::new("<me>"));
+    values.insert("age", Box::new(42_u32));
+    assert_eq!(
+        env.render_with_values("a.html", &json!({}), &values)
+            .unwrap(),
+        "&#60;me&#62; 42!",
+    );
+    assert_eq!(env.render("a.html", &json!({})).unwrap(), " !");
+
+    // `with_values` blocks shadow the runtime values, also in included templates.
+    let templates = [
+        (
+            "b.html",
+            "{% with_values \"name\" = \"b\", \"n\" = n + 1 %}\
+            {% include \"c.html\" %}\
+            {% with_values \"name\" = \"c\" %}{{ \"name\"|value::<&str> }}{% endwith_values %}\
+            {% endwith_values %} {{ \"name\"|value::<&str> }}",
+        ),
+        (
+            "c.html",
+            "{{ \"name\"|value::<&str> }}{{ \"n\"|value::<u32> }}",
+        ),
+    ];
+    let nested = Environment::new(templates.into_iter().collect::<MemoryLoader>());
+    assert_eq!(
+        nested
+            .render_with_values("b.html", &json!({ "n": 1 }), &values)
+            .unwrap(),
+        "b2c &#60;me&#62;",
+    );
+
+    values.insert("name", Box::new(HashMap::<u32, u32>::new()));
+    let err = env
+        .render_with_values("a.html", &json!({}), &values)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.starts_with("the runtime value \"name\" has a type that cannot be used"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_errors() {
+    let err = render_err("a\n  {{ missing }}", &json!({}));
+    assert_eq!(err, "variable `missing` not found\n  --> 2:6");
+
+    let err = render_err("{{ [1]|upper }}", &json!({}));
+    assert!(
+        err.starts_with("a value of type array cannot be displayed"),
+        "{err}"
+    );
+
+    let err = render_err("{{ [1, 2]|batch(0) }}", &json!({}));
+    assert_eq!(
+        err,
+        "the `linecount` of a batch must not be zero\n  --> 1:10"
+    );
+
+    let err = render_err("{{ [1, \"a\"]|sort }}", &json!({}));
+    assert!(
+        err.starts_with("`sort` filter cannot compare values of type string and number"),
+        "{err}"
+    );
+
+    let err = render_err("{% if %}", &json!({}));
+    assert!(err.contains("expected"), "{err}");
+
+    let env = Environment::new(MemoryLoader::new());
+    assert!(matches!(
+        env.render("missing.html", &json!({})),
+        Err(Error::NotFound(name)) if name == "missing.html",
+    ));
+    assert!(matches!(
+        env.render_str("", "exe", &json!({})),
+        Err(Error::Render { .. }),
+    ));
+
+    let templates = [("a.html", "{% include \"a.html\" %}")];
+    let env = Environment::new(templates.into_iter().collect::<MemoryLoader>());
+    let err = env.render("a.html", &json!({})).unwrap_err().to_string();
+    assert!(err.contains("nested more than"), "{err}");
+}
+
+#[test]
+fn test_file_loader() {
+    let dir = std::env::temp_dir().join(format!("askama_interpreter-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("sub/page.txt"), "{% include \"part.txt\" %}!").unwrap();
+    std::fs::write(dir.join("sub/part.txt"), "relative {{ x }}").unwrap();
+
+    let env = Environment::new(FileLoader::new([&dir]));
+    assert_eq!(
+        env.render("sub/page.txt", &json!({ "x": "<1>" })).unwrap(),
+        "relative <1>!",
+    );
+
+    std::fs::write(dir.join("sub/part.txt"), "changed").unwrap();
+    assert_eq!(
+        env.render("sub/page.txt", &json!({ "x": 2 })).unwrap(),
+        "relative 2!"
+    );
+    env.clear_cache();
+    assert_eq!(env.render("sub/page.txt", &json!({})).unwrap(), "changed!");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
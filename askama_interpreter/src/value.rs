@@ -0,0 +1,274 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use serde_json::{Number, Value};
+
+/// The result of an expression, and whether it must not be escaped anymore
+#[derive(Debug, Clone)]
+pub(crate) struct Val<'a> {
+    pub(crate) value: Cow<'a, Value>,
+    pub(crate) safe: bool,
+}
+
+impl<'a> Val<'a> {
+    #[inline]
+    pub(crate) fn new(value: Value) -> Self {
+        Self {
+            value: Cow::Owned(value),
+            safe: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn borrowed(value: &'a Value) -> Self {
+        Self {
+            value: Cow::Borrowed(value),
+            safe: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn safe(value: Value) -> Self {
+        Self {
+            value: Cow::Owned(value),
+            safe: true,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_owned(self) -> Value {
+        self.value.into_owned()
+    }
+
+    /// Selects a field of an object, or an element of an array for numeric names like in `tuple.0`
+    pub(crate) fn field(self, name: &str) -> Option<Self> {
+        let index = name.parse::<usize>().ok();
+        let value = match self.value {
+            Cow::Borrowed(value) => Cow::Borrowed(match (value, index) {
+                (Value::Object(map), _) => map.get(name)?,
+                (Value::Array(arr), Some(index)) => arr.get(index)?,
+                _ => return None,
+            }),
+            Cow::Owned(value) => Cow::Owned(match (value, index) {
+                (Value::Object(mut map), _) => map.remove(name)?,
+                (Value::Array(mut arr), Some(index)) if index < arr.len() => arr.swap_remove(index),
+                _ => return None,
+            }),
+        };
+        Some(Self { value, safe: false })
+    }
+}
+
+/// Returns the output of a value, or `None` if it cannot be displayed
+pub(crate) fn display(value: &Value) -> Option<Cow<'_, str>> {
+    Some(match value {
+        Value::Null => Cow::Borrowed(""),
+        Value::Bool(true) => Cow::Borrowed("true"),
+        Value::Bool(false) => Cow::Borrowed("false"),
+        Value::Number(number) => Cow::Owned(display_number(number)),
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Array(_) | Value::Object(_) => return None,
+    })
+}
+
+fn display_number(number: &Number) -> String {
+    if let Some(i) = number.as_i64() {
+        i.to_string()
+    } else if let Some(u) = number.as_u64() {
+        u.to_string()
+    } else {
+        // Use Rust's formatting, e.g. `1` instead of `1.0`, like compiled templates do.
+        number.as_f64().unwrap_or(f64::NAN).to_string()
+    }
+}
+
+/// Returns the name of the type of a value, for error messages
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// `false`, `null`, `0`, and empty strings, arrays and objects are "falsy"
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(arr) => !arr.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+/// Returns the value as integer, if it is an integer number
+pub(crate) fn as_int(value: &Value) -> Option<i128> {
+    let Value::Number(number) = value else {
+        return None;
+    };
+    number
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| number.as_u64().map(i128::from))
+}
+
+pub(crate) fn from_int(i: i128) -> Option<Value> {
+    if let Ok(i) = i64::try_from(i) {
+        Some(i.into())
+    } else {
+        u64::try_from(i).ok().map(Into::into)
+    }
+}
+
+pub(crate) fn from_float(f: f64) -> Option<Value> {
+    Number::from_f64(f).map(Value::Number)
+}
+
+/// Converts a runtime value of [`askama::Values`] into a JSON value
+///
+/// Only strings, characters, booleans, numbers and [`Value`]s can be converted, and
+/// [`Option`]s of them.
+pub(crate) fn from_any(value: &dyn Any) -> Option<Value> {
+    macro_rules! convert {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if let Some(value) = value.downcast_ref::<$ty>() {
+                    return Some(Value::from(value.clone()));
+                }
+                if let Some(value) = value.downcast_ref::<Option<$ty>>() {
+                    return Some(value.clone().map_or(Value::Null, Value::from));
+                }
+            )*
+        };
+    }
+
+    convert!(
+        &'static str,
+        String,
+        bool,
+        i8,
+        i16,
+        i32,
+        i64,
+        isize,
+        u8,
+        u16,
+        u32,
+        u64,
+        usize,
+        f32,
+        f64,
+        Value,
+    );
+    if let Some(c) = value.downcast_ref::<char>() {
+        return Some(Value::String(c.to_string()));
+    }
+    None
+}
+
+/// Compares two values of the same type
+pub(crate) fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Number(_), Value::Number(_)) => match (as_int(lhs), as_int(rhs)) {
+            (Some(lhs), Some(rhs)) => Some(lhs.cmp(&rhs)),
+            _ => lhs.as_f64()?.partial_cmp(&rhs.as_f64()?),
+        },
+        (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Bool(lhs), Value::Bool(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Array(lhs), Value::Array(rhs)) => {
+            for (lhs, rhs) in lhs.iter().zip(rhs) {
+                match compare(lhs, rhs)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(lhs.len().cmp(&rhs.len()))
+        }
+        _ => None,
+    }
+}
+
+/// Like `==`, but `1 == 1.0`
+pub(crate) fn equals(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Number(_), Value::Number(_)) => compare(lhs, rhs) == Some(Ordering::Equal),
+        (Value::Array(lhs), Value::Array(rhs)) => {
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(lhs, rhs)| equals(lhs, rhs))
+        }
+        _ => lhs == rhs,
+    }
+}
+
+/// Applies an arithmetic or bitwise operator
+pub(crate) fn arithmetic(op: &str, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    let overflow = || format!("attempt to compute `{lhs} {op} {rhs}` with overflow");
+    if let (Some(l), Some(r)) = (as_int(lhs), as_int(rhs)) {
+        let result = match op {
+            "+" => l.checked_add(r),
+            "-" => l.checked_sub(r),
+            "*" => l.checked_mul(r),
+            "/" | "%" if r == 0 => return Err("attempt to divide by zero".into()),
+            "/" => l.checked_div(r),
+            "%" => l.checked_rem(r),
+            "&" => Some(l & r),
+            "|" => Some(l | r),
+            "^" => Some(l ^ r),
+            "<<" | ">>" => {
+                let shift = u32::try_from(r)
+                    .ok()
+                    .filter(|&r| r < 64)
+                    .ok_or_else(overflow)?;
+                match op {
+                    "<<" => l.checked_shl(shift),
+                    _ => l.checked_shr(shift),
+                }
+            }
+            _ => return Err(format!("unknown operator `{op}`")),
+        };
+        return result.and_then(from_int).ok_or_else(overflow);
+    }
+
+    if let (Some(l), Some(r)) = (lhs.as_f64(), rhs.as_f64()) {
+        let result = match op {
+            "+" => l + r,
+            "-" => l - r,
+            "*" => l * r,
+            "/" => l / r,
+            "%" => l % r,
+            _ => {
+                return Err(format!(
+                    "operator `{op}` cannot be applied to floating point numbers",
+                ));
+            }
+        };
+        return from_float(result).ok_or_else(overflow);
+    }
+
+    Err(format!(
+        "operator `{op}` cannot be applied to {} and {}",
+        type_name(lhs),
+        type_name(rhs),
+    ))
+}
+
+/// Returns the items to iterate over in a `{% for %}` loop
+///
+/// The items of an object are `[key, value]` pairs, so they can be destructured like tuples.
+pub(crate) fn into_items(value: Cow<'_, Value>) -> Result<Vec<Value>, &'static str> {
+    match value.into_owned() {
+        Value::Array(arr) => Ok(arr),
+        Value::Object(map) => Ok(map
+            .into_iter()
+            .map(|(key, value)| Value::Array(vec![Value::String(key), value]))
+            .collect()),
+        Value::String(s) => Ok(s.chars().map(|c| Value::String(c.into())).collect()),
+        value => Err(type_name(&value)),
+    }
+}
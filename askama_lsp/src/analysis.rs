@@ -0,0 +1,396 @@
+//! The language features, working on byte offsets in the template source.
+//!
+//! The features that run while the user is typing (go-to-definition, completion and hover) work
+//! on the tokens of the template, so they still work when the template does not parse.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use askama_derive::tooling::Config;
+use askama_parser::lexer::{Token, TokenKind, tokenize};
+use askama_parser::node::{BlockDef, Extends, Import, Include};
+use askama_parser::visit::{Visit, walk_block_def};
+use askama_parser::{Ast, Syntax, WithSpan};
+
+use crate::filters;
+
+/// Returns the content of a file, preferring the unsaved content of open documents.
+pub(crate) type Sources<'s> = Box<dyn Fn(&Path) -> Option<String> + 's>;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub(crate) range: Range<usize>,
+    pub(crate) message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Definition {
+    pub(crate) path: PathBuf,
+    /// The range of the definition in the content of `path`.
+    pub(crate) range: Range<usize>,
+}
+
+pub(crate) struct Analysis<'s> {
+    config: Config,
+    syntax: Syntax<'static>,
+    sources: Sources<'s>,
+}
+
+impl<'s> Analysis<'s> {
+    pub(crate) fn new(config: Config, sources: Sources<'s>) -> Self {
+        Self {
+            config,
+            syntax: config.syntax(None).unwrap_or_default(),
+            sources,
+        }
+    }
+
+    /// Reports the syntax errors of the template, or if there are none, the templates it uses
+    /// that cannot be found.
+    pub(crate) fn diagnostics(&self, path: &Path, text: &str) -> Vec<Diagnostic> {
+        let ast = match Ast::from_str_with_recovery(text, Some(Arc::from(path)), &self.syntax) {
+            Ok(ast) => ast,
+            Err(errors) => {
+                return errors
+                    .into_iter()
+                    .map(|err| Diagnostic {
+                        range: err.offset..next_char_boundary(text, err.offset),
+                        message: err.message.map_or_else(
+                            || "failed to parse template source".into(),
+                            Cow::into_owned,
+                        ),
+                    })
+                    .collect();
+            }
+        };
+
+        let mut paths = TemplatePaths::default();
+        paths.visit_nodes(ast.nodes());
+        paths
+            .0
+            .into_iter()
+            .filter_map(|template| {
+                let err = self.config.find_template(template, Some(path)).err()?;
+                let start = offset_in(text, template)?;
+                Some(Diagnostic {
+                    range: start..start + template.len(),
+                    message: err,
+                })
+            })
+            .collect()
+    }
+
+    /// Resolves the path of an `extends`, `include` or `import` tag, or the macro of a `call` tag.
+    pub(crate) fn definition(&self, path: &Path, text: &str, offset: usize) -> Option<Definition> {
+        let tokens = tokenize(text, &self.syntax);
+        let tag = Tag::at(&tokens, offset)?;
+        let index = tag.index_at(offset)?;
+        let token = tag.tokens[index];
+        match (tag.keyword(text)?, token.kind) {
+            ("extends" | "include" | "import", TokenKind::StrLit) => {
+                let template = self.find_template(path, unquote(&text[token.span.clone()]))?;
+                Some(Definition {
+                    path: template.to_path_buf(),
+                    range: 0..0,
+                })
+            }
+            ("call", TokenKind::Identifier) => {
+                let (scope, name) = tag.macro_path(text)?;
+                let name = text[name.span.clone()].to_owned();
+                let template = match scope {
+                    Some(scope) => self.find_import(path, text, &text[scope.span.clone()])?,
+                    None => path.into(),
+                };
+                if scope.is_some_and(|scope| scope.span == token.span) {
+                    return Some(Definition {
+                        path: template.to_path_buf(),
+                        range: 0..0,
+                    });
+                }
+                let source;
+                let text = match scope {
+                    Some(_) => {
+                        source = (self.sources)(&template)?;
+                        &source
+                    }
+                    None => text,
+                };
+                Some(Definition {
+                    path: template.to_path_buf(),
+                    range: self.find_macro(text, &name)?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Completes the names of the blocks defined in the templates the template extends.
+    pub(crate) fn complete_blocks(&self, path: &Path, text: &str, offset: usize) -> Vec<String> {
+        let tokens = tokenize(text, &self.syntax);
+        let Some(tag) = Tag::at(&tokens, offset) else {
+            return vec![];
+        };
+        let before = tag
+            .tokens
+            .iter()
+            .take_while(|token| token.span.end <= offset)
+            .count();
+        let in_name = match &tag.tokens[..before] {
+            [_] => true,
+            [_, name] => name.kind == TokenKind::Identifier && name.span.end == offset,
+            _ => false,
+        };
+        if !in_name || tag.keyword(text) != Some("block") {
+            return vec![];
+        }
+
+        let mut names = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut parent = self.extends(path, text);
+        while let Some(template) = parent.take() {
+            if !visited.insert(Arc::clone(&template)) {
+                break;
+            }
+            let Some(source) = (self.sources)(&template) else {
+                break;
+            };
+            let Ok(ast) = Ast::from_str(&source, None, &self.syntax) else {
+                break;
+            };
+            let mut heritage = Heritage::default();
+            heritage.visit_nodes(ast.nodes());
+            names.extend(heritage.blocks.into_iter().map(str::to_owned));
+            parent = heritage
+                .extends
+                .and_then(|extends| self.config.find_template(extends, Some(&template)).ok());
+        }
+        names.into_iter().collect()
+    }
+
+    /// Describes the built-in filter under the cursor.
+    pub(crate) fn hover(&self, text: &str, offset: usize) -> Option<(Range<usize>, String)> {
+        let tokens = tokenize(text, &self.syntax);
+        let token = tokens
+            .iter()
+            .find(|token| token.kind == TokenKind::Filter && token.span.contains(&offset))?;
+        let filter = filters::find(&text[token.span.clone()])?;
+
+        let mut doc = format!(
+            "```jinja\n{{{{ {} }}}}\n```\n\n{}",
+            filter.usage, filter.doc
+        );
+        if let [_, aliases @ ..] = filter.names
+            && !aliases.is_empty()
+        {
+            let aliases = aliases
+                .iter()
+                .map(|alias| format!("`{alias}`"))
+                .collect::<Vec<_>>();
+            doc.push_str(&format!("\n\nAlso available as {}.", aliases.join(", ")));
+        }
+        if let Some(feature) = filter.feature {
+            doc.push_str(&format!("\n\nNeeds the `{feature}` feature of `askama`."));
+        }
+        Some((token.span.clone(), doc))
+    }
+
+    fn find_template(&self, path: &Path, template: &str) -> Option<Arc<Path>> {
+        self.config.find_template(template, Some(path)).ok()
+    }
+
+    /// The template extended by `text`.
+    fn extends(&self, path: &Path, text: &str) -> Option<Arc<Path>> {
+        let tokens = tokenize(text, &self.syntax);
+        Tag::all(&tokens).find_map(|tag| match tag.tokens[..] {
+            [keyword, template, ..]
+                if &text[keyword.span.clone()] == "extends"
+                    && template.kind == TokenKind::StrLit =>
+            {
+                self.find_template(path, unquote(&text[template.span.clone()]))
+            }
+            _ => None,
+        })
+    }
+
+    /// The template imported as `scope` in `text`.
+    fn find_import(&self, path: &Path, text: &str, scope: &str) -> Option<Arc<Path>> {
+        let tokens = tokenize(text, &self.syntax);
+        Tag::all(&tokens).find_map(|tag| match tag.tokens[..] {
+            [keyword, template, r#as, name]
+                if &text[keyword.span.clone()] == "import"
+                    && template.kind == TokenKind::StrLit
+                    && &text[r#as.span.clone()] == "as"
+                    && &text[name.span.clone()] == scope =>
+            {
+                self.find_template(path, unquote(&text[template.span.clone()]))
+            }
+            _ => None,
+        })
+    }
+
+    /// The range of the name of the macro `name` defined in `text`.
+    fn find_macro(&self, text: &str, name: &str) -> Option<Range<usize>> {
+        let tokens = tokenize(text, &self.syntax);
+        Tag::all(&tokens).find_map(|tag| match tag.tokens[..] {
+            [keyword, ident, ..]
+                if &text[keyword.span.clone()] == "macro" && &text[ident.span.clone()] == name =>
+            {
+                Some(ident.span.clone())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// The tokens of a block tag, without whitespace and delimiters.
+struct Tag<'t> {
+    tokens: Vec<&'t Token>,
+}
+
+impl<'t> Tag<'t> {
+    fn all(tokens: &'t [Token]) -> impl Iterator<Item = Tag<'t>> {
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.kind == TokenKind::BlockStart)
+            .map(|(start, _)| Self::new(tokens, start))
+    }
+
+    /// The block tag containing `offset`.
+    fn at(tokens: &'t [Token], offset: usize) -> Option<Self> {
+        let index = tokens
+            .iter()
+            .position(|token| token.span.start <= offset && offset <= token.span.end)?;
+        let start = tokens[..=index]
+            .iter()
+            .rposition(|token| token.kind == TokenKind::BlockStart)?;
+        if tokens
+            .get(start + 1..index)
+            .unwrap_or_default()
+            .iter()
+            .any(is_boundary)
+        {
+            return None;
+        }
+        Some(Self::new(tokens, start))
+    }
+
+    /// The tag starting with the `BlockStart` token at `start`.
+    fn new(tokens: &'t [Token], start: usize) -> Self {
+        let tokens = &tokens[start + 1..];
+        let end = tokens.iter().position(is_boundary).unwrap_or(tokens.len());
+        Self {
+            tokens: tokens[..end]
+                .iter()
+                .filter(|token| {
+                    !matches!(
+                        token.kind,
+                        TokenKind::Whitespace | TokenKind::WhitespaceControl
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn keyword<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let token = self.tokens.first()?;
+        (token.kind == TokenKind::Keyword).then(|| &text[token.span.clone()])
+    }
+
+    /// The index of the identifier or string at `offset`, or ending at `offset`.
+    fn index_at(&self, offset: usize) -> Option<usize> {
+        self.tokens.iter().position(|token| {
+            matches!(token.kind, TokenKind::Identifier | TokenKind::StrLit)
+                && token.span.start <= offset
+                && offset <= token.span.end
+        })
+    }
+
+    /// The scope and name of the macro called in a `{% call(args) scope::name(args) %}` tag.
+    fn macro_path(&self, text: &str) -> Option<(Option<&'t Token>, &'t Token)> {
+        let mut tokens = &self.tokens[1..];
+        // The arguments of the caller.
+        if let [open, ..] = tokens
+            && &text[open.span.clone()] == "("
+        {
+            let close = tokens.iter().position(|t| &text[t.span.clone()] == ")")?;
+            tokens = &tokens[close + 1..];
+        }
+        match tokens {
+            [scope, sep, name, ..]
+                if scope.kind == TokenKind::Identifier && &text[sep.span.clone()] == "::" =>
+            {
+                Some((Some(*scope), *name))
+            }
+            [name, ..] if name.kind == TokenKind::Identifier => Some((None, *name)),
+            _ => None,
+        }
+    }
+}
+
+fn is_boundary(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::BlockStart
+            | TokenKind::BlockEnd
+            | TokenKind::ExprStart
+            | TokenKind::ExprEnd
+            | TokenKind::Text
+            | TokenKind::Comment
+    )
+}
+
+/// Collects the paths used by `extends`, `include` and `import` tags.
+#[derive(Default)]
+struct TemplatePaths<'a>(Vec<&'a str>);
+
+impl<'a> Visit<'a> for TemplatePaths<'a> {
+    fn visit_extends(&mut self, extends: &WithSpan<Extends<'a>>) {
+        self.0.push(extends.path);
+    }
+
+    fn visit_include(&mut self, include: &WithSpan<Include<'a>>) {
+        self.0.push(include.path);
+    }
+
+    fn visit_import(&mut self, import: &WithSpan<Import<'a>>) {
+        self.0.push(import.path);
+    }
+}
+
+/// The blocks defined in a template, and the template it extends.
+#[derive(Default)]
+struct Heritage<'a> {
+    blocks: Vec<&'a str>,
+    extends: Option<&'a str>,
+}
+
+impl<'a> Visit<'a> for Heritage<'a> {
+    fn visit_extends(&mut self, extends: &WithSpan<Extends<'a>>) {
+        self.extends = Some(extends.path);
+    }
+
+    fn visit_block_def(&mut self, block: &WithSpan<BlockDef<'a>>) {
+        self.blocks.push(*block.name);
+        walk_block_def(self, block);
+    }
+}
+
+/// The offset of `part`, a subslice of `text`.
+fn offset_in(text: &str, part: &str) -> Option<usize> {
+    let offset = (part.as_ptr() as usize).checked_sub(text.as_ptr() as usize)?;
+    (offset + part.len() <= text.len()).then_some(offset)
+}
+
+fn next_char_boundary(text: &str, offset: usize) -> usize {
+    text.get(offset..)
+        .and_then(|rest| rest.chars().next())
+        .map_or(offset, |c| offset + c.len_utf8())
+}
+
+fn unquote(lit: &str) -> &str {
+    lit.trim_matches('"')
+}
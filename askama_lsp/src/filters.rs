@@ -0,0 +1,276 @@
+//! Documentation of the built-in filters, shown on hover.
+//!
+//! Keep in sync with `book/src/filters.md`.
+
+pub(crate) struct BuiltinFilter {
+    /// The name of the filter, followed by its aliases.
+    pub(crate) names: &'static [&'static str],
+    /// The arguments of the filter, in the form used in the book.
+    pub(crate) usage: &'static str,
+    pub(crate) doc: &'static str,
+    /// The cargo feature of `askama` needed to use the filter, if any.
+    pub(crate) feature: Option<&'static str>,
+}
+
+pub(crate) fn find(name: &str) -> Option<&'static BuiltinFilter> {
+    BUILTIN_FILTERS
+        .iter()
+        .find(|filter| filter.names.contains(&name))
+}
+
+const BUILTIN_FILTERS: &[BuiltinFilter] = &[
+    BuiltinFilter {
+        names: &["assigned_or"],
+        usage: "value | assigned_or(fallback)",
+        doc: "Prints `fallback` if the value is in its \"default\" state, e.g. an empty string, \
+              `0`, `None` or `Err(_)`. Otherwise the value is printed.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["batch"],
+        usage: "iterable | batch(linecount, fill_with = value)",
+        doc: "Splits the items into lists of `linecount` items. The last list is filled up with \
+              `fill_with` if it is given.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["capitalize"],
+        usage: "text | capitalize",
+        doc: "Capitalizes a value. The first character will be uppercase, all others lowercase.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["center"],
+        usage: "text | center(length)",
+        doc: "Centers the value in a field of the given width.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["default"],
+        usage: "value | default(default_value, [[boolean =] true])",
+        doc: "Behaves like `defined_or`, or like `assigned_or` if the second argument is `true`. \
+              Exists for compatibility with Jinja.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["defined_or"],
+        usage: "variable | defined_or(fallback)",
+        doc: "Prints `fallback` if the identifier on the left-hand side is not defined.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["deref"],
+        usage: "expression | deref",
+        doc: "Dereferences the given argument.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["escape", "e"],
+        usage: "text | escape([escaper])",
+        doc: "Escapes the value. Without argument, the escaper of the template is used, \
+              e.g. `escape(\"html\")` selects the HTML escaper.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["filesizeformat"],
+        usage: "number_of_bytes | filesizeformat([precision])",
+        doc: "Returns a human readable representation of a number of bytes, e.g. `1.02 KB`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["fmt"],
+        usage: "expression | fmt(\"format_string\")",
+        doc: "Formats the value with `format!()`. The format string must be a string literal.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["first"],
+        usage: "iterable | first",
+        doc: "Returns the first item as `Option`, or `None` if there are no items.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["format"],
+        usage: "\"format_string\" | format([variables ...])",
+        doc: "Formats the arguments with `format!()`. The format string must be a string literal.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["groupby"],
+        usage: "iterable | groupby(\"field\")",
+        doc: "Sorts the items by a field, and returns groups with the field value as `grouper` \
+              and the items as `list`.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["indent"],
+        usage: "text | indent(width, [first], [blank])",
+        doc: "Indents new lines with `width` spaces, or with `width` if it is a string. The first \
+              line and blank lines are only indented if `first` and `blank` are `true`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["join"],
+        usage: "iterable | join(separator)",
+        doc: "Joins the items of an iterable into a string, separated by `separator`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["json", "tojson"],
+        usage: "value | json([indent])",
+        doc: "Serializes the value to JSON. The output does not contain `&`, `<`, `>` or `'`.",
+        feature: Some("serde_json"),
+    },
+    BuiltinFilter {
+        names: &["last"],
+        usage: "iterable | last",
+        doc: "Returns the last item as `Option`, or `None` if there are no items.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["linebreaks"],
+        usage: "text | linebreaks",
+        doc: "Replaces line breaks with `<br />`, and blank lines with paragraph breaks `<p>`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["linebreaksbr"],
+        usage: "text | linebreaksbr",
+        doc: "Converts all newlines to `<br />`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["lower", "lowercase"],
+        usage: "text | lower",
+        doc: "Converts to lowercase.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["map"],
+        usage: "iterable | map(attribute = \"field\")",
+        doc: "Lazily returns a field of every item, e.g. \
+              `users | map(attribute = \"address.city\")`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["max"],
+        usage: "iterable | max(attribute = \"field\")",
+        doc: "Returns the largest item as `Option`, optionally compared by a field.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["min"],
+        usage: "iterable | min(attribute = \"field\")",
+        doc: "Returns the smallest item as `Option`, optionally compared by a field.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["paragraphbreaks"],
+        usage: "text | paragraphbreaks",
+        doc: "Replaces blank lines with paragraph breaks `<p>`. Single new lines are kept.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["pluralize"],
+        usage: "integer | pluralize([singular = \"\"], [plural = \"s\"])",
+        doc: "Returns `singular` if the value is 1 or -1, `plural` otherwise.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["ref"],
+        usage: "expression | ref",
+        doc: "Creates a reference to the given argument.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["reject"],
+        usage: "iterable | reject(value_or_callback)",
+        doc: "Filters out the items equal to `value`, or for which `callback` returns `true`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["safe"],
+        usage: "expression | safe",
+        doc: "Marks the value as safe, so it is not escaped.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["sanitize"],
+        usage: "untrusted_html | sanitize(tags = [...], attributes = [...])",
+        doc: "Keeps only the allowed HTML tags and attributes, and removes scripts and unsafe \
+              URLs. The output is marked as safe.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["selectattr"],
+        usage: "iterable | selectattr(\"field\", \"test\", value)",
+        doc: "Lazily keeps the items whose field is truthy, or passes the given `is` test, e.g. \
+              `selectattr(\"age\", \"divisibleby\", 3)`.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["slice"],
+        usage: "iterable | slice(slices, fill_with = value)",
+        doc: "Splits the items into `slices` lists of about the same length. Shorter lists are \
+              filled up with `fill_with` if it is given.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["sort"],
+        usage: "iterable | sort(reverse = false, attribute = \"field\")",
+        doc: "Returns the items in ascending order, or ordered by the value of a field. The sort \
+              is stable.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["sum"],
+        usage: "iterable | sum(attribute = \"field\", start = 0)",
+        doc: "Returns the sum of the items or of a field, starting at `start`. Use \
+              `start = 0.0` for floating point numbers.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["title", "titlecase"],
+        usage: "text | title",
+        doc: "Returns a title cased version of the value: words start with an uppercase letter, \
+              all other characters are lowercase.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["trim"],
+        usage: "text | trim",
+        doc: "Strips leading and trailing whitespace.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["truncate"],
+        usage: "text | truncate(length)",
+        doc: "Limits the length of the string, and appends `...` if it was truncated.",
+        feature: None,
+    },
+    BuiltinFilter {
+        names: &["unique"],
+        usage: "iterable | unique",
+        doc: "Returns an iterator with all duplicates removed.",
+        feature: Some("std"),
+    },
+    BuiltinFilter {
+        names: &["upper", "uppercase"],
+        usage: "text | upper",
+        doc: "Converts to uppercase.",
+        feature: Some("alloc"),
+    },
+    BuiltinFilter {
+        names: &["urlencode", "urlencode_strict"],
+        usage: "text | urlencode",
+        doc: "Percent encodes the string. All characters except ASCII letters, digits and \
+              `_.-~/` are escaped. `urlencode_strict` escapes `/` too.",
+        feature: Some("urlencode"),
+    },
+    BuiltinFilter {
+        names: &["wordcount"],
+        usage: "text | wordcount",
+        doc: "Counts the words in the string.",
+        feature: None,
+    },
+];
@@ -0,0 +1,279 @@
+//! A language server for askama templates.
+//!
+//! The configuration of a template is read from the `askama.toml` file of the crate containing
+//! it, i.e. the nearest ancestor directory containing an `askama.toml` or `Cargo.toml` file.
+
+mod analysis;
+mod filters;
+mod position;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use askama_derive::tooling::Config;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover,
+    HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind,
+    OneOf, PublishDiagnosticsParams, SaveOptions, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, Url,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::analysis::Analysis;
+use crate::position::{to_offset, to_range};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+fn main() -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    Server::default().run(&connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                    include_text: Some(true),
+                })),
+                ..TextDocumentSyncOptions::default()
+            },
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![" ".into()]),
+            ..CompletionOptions::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    /// The content of the open documents.
+    documents: HashMap<PathBuf, String>,
+}
+
+impl Server {
+    fn run(&mut self, connection: &Connection) -> Result<(), BoxError> {
+        for msg in &connection.receiver {
+            match msg {
+                Message::Request(req) => {
+                    if connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.handle_request(req);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                Message::Notification(not) => {
+                    let method = not.method.clone();
+                    match self.handle_notification(not) {
+                        Ok(Some(diagnostics)) => {
+                            let not =
+                                Notification::new(PublishDiagnostics::METHOD.into(), diagnostics);
+                            connection.sender.send(Message::Notification(not))?;
+                        }
+                        Ok(None) => {}
+                        // Notifications have no response to carry an error, so a malformed one
+                        // is only logged: it must not bring the whole server down.
+                        Err(err) => eprintln!("askama-lsp: invalid {method:?} notification: {err}"),
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, req: Request) -> Response {
+        let Request { id, method, params } = req;
+        match method.as_str() {
+            GotoDefinition::METHOD => respond(id, params, |params| self.definition(params)),
+            Completion::METHOD => respond(id, params, |params| self.completion(params)),
+            HoverRequest::METHOD => respond(id, params, |params| self.hover(params)),
+            _ => Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unhandled method {method:?}"),
+            ),
+        }
+    }
+
+    /// Updates the open documents, and returns the diagnostics to publish, if any.
+    fn handle_notification(
+        &mut self,
+        not: Notification,
+    ) -> Result<Option<PublishDiagnosticsParams>, BoxError> {
+        let (uri, text) = match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams = parse(not.params)?;
+                (params.text_document.uri, Some(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams = parse(not.params)?;
+                if let (Ok(path), Some(change)) = (
+                    params.text_document.uri.to_file_path(),
+                    params.content_changes.into_iter().last(),
+                ) {
+                    self.documents.insert(path, change.text);
+                }
+                return Ok(None);
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: lsp_types::DidSaveTextDocumentParams = parse(not.params)?;
+                (params.text_document.uri, params.text)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams = parse(not.params)?;
+                if let Ok(path) = params.text_document.uri.to_file_path() {
+                    self.documents.remove(&path);
+                }
+                return Ok(Some(PublishDiagnosticsParams::new(
+                    params.text_document.uri,
+                    vec![],
+                    None,
+                )));
+            }
+            _ => return Ok(None),
+        };
+
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        if let Some(text) = text {
+            self.documents.insert(path.clone(), text);
+        }
+        let Some(text) = self.source(&path) else {
+            return Ok(None);
+        };
+        let diagnostics = match load_config(&path) {
+            Ok(config) => self
+                .analysis(config)
+                .diagnostics(&path, &text)
+                .into_iter()
+                .map(|diag| Diagnostic {
+                    range: to_range(&text, diag.range),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some("askama".into()),
+                    message: diag.message,
+                    ..Diagnostic::default()
+                })
+                .collect(),
+            Err(err) => vec![Diagnostic {
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("askama".into()),
+                message: err,
+                ..Diagnostic::default()
+            }],
+        };
+        Ok(Some(PublishDiagnosticsParams::new(uri, diagnostics, None)))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let params = params.text_document_position_params;
+        let (path, text, config) = self.document(&params.text_document.uri)?;
+        let offset = to_offset(&text, params.position);
+        let definition = self.analysis(config).definition(&path, &text, offset)?;
+        let target = self.source(&definition.path)?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: Url::from_file_path(&definition.path).ok()?,
+            range: to_range(&target, definition.range),
+        }))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let params = params.text_document_position;
+        let (path, text, config) = self.document(&params.text_document.uri)?;
+        let offset = to_offset(&text, params.position);
+        let items = self
+            .analysis(config)
+            .complete_blocks(&path, &text, offset)
+            .into_iter()
+            .map(|name| CompletionItem {
+                label: name,
+                kind: Some(CompletionItemKind::MODULE),
+                detail: Some("inherited block".into()),
+                ..CompletionItem::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let params = params.text_document_position_params;
+        let (_, text, config) = self.document(&params.text_document.uri)?;
+        let offset = to_offset(&text, params.position);
+        let (range, doc) = self.analysis(config).hover(&text, offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: doc,
+            }),
+            range: Some(to_range(&text, range)),
+        })
+    }
+
+    fn document(&self, uri: &Url) -> Option<(PathBuf, String, Config)> {
+        let path = uri.to_file_path().ok()?;
+        let text = self.source(&path)?;
+        let config = load_config(&path).ok()?;
+        Some((path, text, config))
+    }
+
+    fn analysis(&self, config: Config) -> Analysis<'_> {
+        Analysis::new(config, Box::new(|path| self.source(path)))
+    }
+
+    /// The content of the open document at `path`, or else of the file on disk.
+    fn source(&self, path: &Path) -> Option<String> {
+        match self.documents.get(path) {
+            Some(text) => Some(text.clone()),
+            None => std::fs::read_to_string(path).ok(),
+        }
+    }
+}
+
+/// Loads the configuration of the crate containing `path`.
+fn load_config(path: &Path) -> Result<Config, String> {
+    let root = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("askama.toml").is_file() || dir.join("Cargo.toml").is_file())
+        .or_else(|| path.parent())
+        .unwrap_or(path);
+    Config::load(root)
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, serde_json::Error> {
+    serde_json::from_value(params)
+}
+
+/// Calls `handler` with the deserialized `params`, and serializes its result.
+fn respond<P: DeserializeOwned, R: Serialize>(
+    id: RequestId,
+    params: Value,
+    handler: impl FnOnce(P) -> R,
+) -> Response {
+    match parse(params) {
+        Ok(params) => Response::new_ok(id, handler(params)),
+        Err(err) => Response::new_err(id, ErrorCode::InvalidParams as i32, err.to_string()),
+    }
+}
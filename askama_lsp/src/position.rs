@@ -0,0 +1,39 @@
+//! Conversion between byte offsets and LSP positions, which count UTF-16 code units.
+
+use std::ops::Range;
+
+use lsp_types::{Position, Range as LspRange};
+
+pub(crate) fn to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(pos) => line_start += pos + 1,
+            None => return text.len(),
+        }
+    }
+    let mut units = 0;
+    for (pos, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + pos;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+pub(crate) fn to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+pub(crate) fn to_range(text: &str, range: Range<usize>) -> LspRange {
+    LspRange {
+        start: to_position(text, range.start),
+        end: to_position(text, range.end),
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use askama_derive::tooling::Config;
+use lsp_server::{Connection, Message, Notification, Request, RequestId};
+use lsp_types::Position;
+
+use crate::Server;
+use crate::analysis::{Analysis, Definition, Diagnostic};
+use crate::position::{to_offset, to_position};
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+fn template(name: &str) -> PathBuf {
+    root().join("templates").join(name)
+}
+
+fn analysis() -> Analysis<'static> {
+    let config = Config::load(&root()).unwrap();
+    Analysis::new(config, Box::new(|path| std::fs::read_to_string(path).ok()))
+}
+
+/// Splits `source` at the `$` marking the cursor.
+fn cursor(source: &str) -> (String, usize) {
+    let offset = source.find('$').unwrap();
+    (source.replacen('$', "", 1), offset)
+}
+
+#[test]
+fn test_diagnostics() {
+    let analysis = analysis();
+    let page = template("page.html");
+
+    assert_eq!(
+        analysis.diagnostics(
+            &page,
+            "{% extends \"layout.html\" %}{% include \"macros.html\" %}"
+        ),
+        [],
+    );
+
+    let diagnostics = analysis.diagnostics(&page, "{% if %}{% endif %}{{ a + }}");
+    let ranges = diagnostics
+        .iter()
+        .map(|d| d.range.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ranges, [6..7, 26..27]);
+
+    let [Diagnostic { range, message }] =
+        &analysis.diagnostics(&page, "{% include \"missing.html\" %}")[..]
+    else {
+        panic!("expected a single diagnostic");
+    };
+    assert_eq!(*range, 12..24);
+    assert!(message.starts_with("template \"missing.html\" not found"));
+}
+
+#[test]
+fn test_definition() {
+    let analysis = analysis();
+    let page = template("page.html");
+    let definition = |source: &str| {
+        let (text, offset) = cursor(source);
+        analysis.definition(&page, &text, offset)
+    };
+    let file = |name: &str| {
+        Some(Definition {
+            path: template(name),
+            range: 0..0,
+        })
+    };
+
+    assert_eq!(
+        definition("{% extends \"lay$out.html\" %}"),
+        file("layout.html")
+    );
+    assert_eq!(
+        definition("{%- include $\"base.html\" -%}"),
+        file("base.html")
+    );
+    assert_eq!(
+        definition("{% import \"macros.html\" as m %}{% call m$::card(1) %}{% endcall %}"),
+        file("macros.html"),
+    );
+    assert_eq!(
+        definition("{% import \"macros.html\" as m %}{% call m::ca$rd(1) %}{% endcall %}"),
+        Some(Definition {
+            path: template("macros.html"),
+            range: 9..13,
+        }),
+    );
+    assert_eq!(
+        definition("{% macro x() %}{% endmacro %}{% call(a) x$() %}{% endcall %}"),
+        Some(Definition {
+            path: page.clone(),
+            range: 9..10,
+        }),
+    );
+
+    assert_eq!(definition("{% include \"missing$.html\" %}"), None);
+    assert_eq!(definition("{{ \"base$.html\" }}"), None);
+    assert_eq!(definition("{% extends \"base.html\" %} $"), None);
+    assert_eq!(definition("{% call unknown::x$() %}{% endcall %}"), None);
+}
+
+#[test]
+fn test_complete_blocks() {
+    let analysis = analysis();
+    let page = template("page.html");
+    let complete = |source: &str| {
+        let (text, offset) = cursor(source);
+        analysis.complete_blocks(&page, &text, offset)
+    };
+
+    let all = ["content", "inner", "sidebar", "title"];
+    assert_eq!(complete("{% extends \"layout.html\" %}{% block $"), all);
+    assert_eq!(
+        complete("{% extends \"layout.html\" %}{%- block ti$ -%}"),
+        all
+    );
+    assert_eq!(
+        complete("{% extends \"base.html\" %}{% block $ %}"),
+        ["content", "inner", "title"]
+    );
+
+    assert!(complete("{% extends \"layout.html\" %}{% if $ %}").is_empty());
+    assert!(complete("{% extends \"layout.html\" %}{{ block $ }}").is_empty());
+    assert!(complete("{% extends \"layout.html\" %}{% block a %}$").is_empty());
+    assert!(complete("{% block $ %}").is_empty());
+}
+
+#[test]
+fn test_hover() {
+    let analysis = analysis();
+    let hover = |source: &str| {
+        let (text, offset) = cursor(source);
+        analysis.hover(&text, offset)
+    };
+
+    let (range, doc) = hover("{{ name|e$scape }}").unwrap();
+    assert_eq!(range, 8..14);
+    assert!(doc.contains("text | escape([escaper])"));
+    assert!(doc.contains("Also available as `e`."));
+
+    let (range, doc) = hover("{% for x in list | u$nique %}").unwrap();
+    assert_eq!(range, 19..25);
+    assert!(doc.contains("Needs the `std` feature of `askama`."));
+
+    assert_eq!(hover("{{ name|my_fil$ter }}"), None);
+    assert_eq!(hover("{{ na$me|upper }}"), None);
+    assert_eq!(hover("upp$er"), None);
+}
+
+#[test]
+fn test_position() {
+    let text = "a\né😀b\n";
+    let positions = [
+        (0, 0, 0),
+        (2, 1, 0),
+        (4, 1, 1),
+        (8, 1, 3),
+        (9, 1, 4),
+        (10, 2, 0),
+    ];
+    for (offset, line, character) in positions {
+        let position = Position { line, character };
+        assert_eq!(to_position(text, offset), position);
+        assert_eq!(to_offset(text, position), offset);
+    }
+    // Positions after the end of a line are clamped.
+    assert_eq!(to_offset(text, Position::new(0, 10)), 1);
+    assert_eq!(to_offset(text, Position::new(5, 0)), text.len());
+}
+
+#[test]
+fn test_invalid_notification() {
+    let (server, client) = Connection::memory();
+    client
+        .sender
+        .send(Message::Notification(Notification::new(
+            "textDocument/didOpen".into(),
+            "not an object",
+        )))
+        .unwrap();
+    client
+        .sender
+        .send(Message::Request(Request::new(
+            RequestId::from(1),
+            "unknown".into(),
+            (),
+        )))
+        .unwrap();
+    drop(client.sender);
+
+    // The invalid notification is skipped, and the following request still gets its answer.
+    Server::default().run(&server).unwrap();
+    match client.receiver.try_recv() {
+        Ok(Message::Response(resp)) => assert_eq!(resp.id, RequestId::from(1)),
+        msg => panic!("expected a response, got {msg:?}"),
+    }
+}
@@ -16,3 +16,17 @@ askama_derive::make_filter_fn! {
         extern crate askama;
     }
 }
+
+askama_derive::make_test_fn! {
+    #[proc_macro_attribute]
+    pub fn test_fn() {
+        extern crate askama;
+    }
+}
+
+askama_derive::make_derive_values! {
+    #[proc_macro_derive(Values, attributes(values))]
+    pub fn derive_values() {
+        extern crate askama;
+    }
+}
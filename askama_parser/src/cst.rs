@@ -0,0 +1,751 @@
+//! Lossless concrete syntax tree of a template, and a formatter built on top of it.
+//!
+//! Unlike [`Ast`](crate::Ast), the [`Cst`] keeps every byte of the source: the delimiters, the
+//! whitespace control characters and the whitespace inside of the tags. Writing it back with
+//! [`Display`](fmt::Display) returns the source unchanged. The tags are the ones found by the
+//! parser. In templates that contain syntax errors or that are incomplete, they are found by a
+//! tolerant scanner instead, so the tree can be built for any template.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use crate::node::Whitespace;
+use crate::{Ast, Syntax};
+
+/// A lossless concrete syntax tree of a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst<'a> {
+    pub nodes: Vec<CstNode<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CstNode<'a> {
+    /// Text outside of any tag.
+    Text(&'a str),
+    /// A tag that doesn't open a block, e.g. `{{ expr }}`, `{# comment #}` or `{% include %}`.
+    Tag(Tag<'a>),
+    /// A block tag like `{% if %}` together with its content, its branches and its end tag.
+    Block(Block<'a>),
+}
+
+/// The kind of a [`Tag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagKind {
+    /// `{% ... %}`
+    Block,
+    /// `{{ ... }}`
+    Expr,
+    /// `{# ... #}`
+    Comment,
+}
+
+/// A single tag. Concatenating all its fields returns the source of the tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tag<'a> {
+    pub kind: TagKind,
+    /// Byte offset of the tag in the source.
+    pub offset: usize,
+    /// The opening delimiter, e.g. `{%`.
+    pub start: &'a str,
+    /// The whitespace control character after the opening delimiter (`-`, `+` or `~`), if any.
+    pub ws_start: &'a str,
+    /// The whitespace between the opening delimiter and the content.
+    pub lws: &'a str,
+    /// The content of the tag, without surrounding whitespace.
+    pub content: &'a str,
+    /// The whitespace between the content and the closing delimiter.
+    pub rws: &'a str,
+    /// The whitespace control character before the closing delimiter, if any.
+    pub ws_end: &'a str,
+    /// The closing delimiter, e.g. `%}`. It is empty if the tag is not terminated.
+    pub end: &'a str,
+}
+
+impl<'a> Tag<'a> {
+    /// The first word of a block tag, e.g. `if` for `{% if x %}`.
+    #[must_use]
+    pub fn keyword(&self) -> Option<&'a str> {
+        if self.kind != TagKind::Block {
+            return None;
+        }
+        let len = self
+            .content
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(self.content.len());
+        (len > 0).then(|| &self.content[..len])
+    }
+
+    /// Returns `false` if the closing delimiter of the tag is missing.
+    #[must_use]
+    pub fn is_terminated(&self) -> bool {
+        !self.end.is_empty()
+    }
+
+    /// The byte range of the tag in the source.
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len()
+    }
+
+    fn len(&self) -> usize {
+        self.parts().iter().map(|s| s.len()).sum()
+    }
+
+    fn parts(&self) -> [&'a str; 7] {
+        [
+            self.start,
+            self.ws_start,
+            self.lws,
+            self.content,
+            self.rws,
+            self.ws_end,
+            self.end,
+        ]
+    }
+}
+
+impl fmt::Display for Tag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.parts().iter().try_for_each(|s| f.write_str(s))
+    }
+}
+
+/// A block tag like `{% for %}` with its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<'a> {
+    pub open: Tag<'a>,
+    pub body: Vec<CstNode<'a>>,
+    /// The `{% else %}`, `{% elif %}` and `{% when %}` tags of the block with their content.
+    pub branches: Vec<Branch<'a>>,
+    /// The end tag, e.g. `{% endfor %}`. It is `None` if the block is not closed.
+    pub close: Option<Tag<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch<'a> {
+    pub tag: Tag<'a>,
+    pub body: Vec<CstNode<'a>>,
+}
+
+impl<'a> Cst<'a> {
+    /// Builds the concrete syntax tree of `source`. This never fails: text that cannot be
+    /// understood is kept as is in the tree.
+    #[must_use]
+    pub fn parse(source: &'a str, syntax: &Syntax<'_>) -> Self {
+        let mut stack: Vec<Block<'a>> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut flat = flat_nodes(source, syntax).into_iter().peekable();
+        while let Some(node) = flat.next() {
+            let tag = match node {
+                CstNode::Tag(tag) => tag,
+                node => {
+                    push_node(&mut stack, &mut nodes, node);
+                    continue;
+                }
+            };
+            let keyword = tag.keyword().unwrap_or_default();
+            if keyword == "raw" {
+                let body = flat
+                    .next_if(|node| matches!(node, CstNode::Text(_)))
+                    .into_iter()
+                    .collect();
+                let close = match flat.next_if(is_endraw) {
+                    Some(CstNode::Tag(close)) => Some(close),
+                    _ => None,
+                };
+                let block = Block {
+                    open: tag,
+                    body,
+                    branches: vec![],
+                    close,
+                };
+                push_node(&mut stack, &mut nodes, CstNode::Block(block));
+            } else if is_block_opener(&tag) {
+                stack.push(Block {
+                    open: tag,
+                    body: vec![],
+                    branches: vec![],
+                    close: None,
+                });
+            } else if let Some(name) = keyword.strip_prefix("end")
+                && let Some(pos) = stack.iter().rposition(|b| b.open.keyword() == Some(name))
+            {
+                // Blocks opened after the one being closed are not terminated.
+                while stack.len() > pos + 1 {
+                    let block = stack.pop().unwrap();
+                    push_node(&mut stack, &mut nodes, CstNode::Block(block));
+                }
+                let mut block = stack.pop().unwrap();
+                block.close = Some(tag);
+                push_node(&mut stack, &mut nodes, CstNode::Block(block));
+            } else if let Some(block) = stack.last_mut()
+                && is_branch_of(keyword, block.open.keyword().unwrap_or_default())
+            {
+                block.branches.push(Branch { tag, body: vec![] });
+            } else {
+                push_node(&mut stack, &mut nodes, CstNode::Tag(tag));
+            }
+        }
+        while let Some(block) = stack.pop() {
+            push_node(&mut stack, &mut nodes, CstNode::Block(block));
+        }
+        Self { nodes }
+    }
+
+    /// Formats the template with the given options.
+    #[must_use]
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let mut formatter = Formatter {
+            buf: String::new(),
+            options,
+            ws_start: 0,
+            suppressed: false,
+        };
+        formatter.nodes(&self.nodes, 0);
+        formatter.buf
+    }
+}
+
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_nodes(f, &self.nodes)
+    }
+}
+
+impl fmt::Display for CstNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Tag(tag) => write!(f, "{tag}"),
+            Self::Block(block) => write!(f, "{block}"),
+        }
+    }
+}
+
+impl fmt::Display for Block<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.open)?;
+        fmt_nodes(f, &self.body)?;
+        for branch in &self.branches {
+            write!(f, "{}", branch.tag)?;
+            fmt_nodes(f, &branch.body)?;
+        }
+        match &self.close {
+            Some(close) => write!(f, "{close}"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn fmt_nodes(f: &mut fmt::Formatter<'_>, nodes: &[CstNode<'_>]) -> fmt::Result {
+    nodes.iter().try_for_each(|node| write!(f, "{node}"))
+}
+
+fn push_node<'a>(stack: &mut [Block<'a>], nodes: &mut Vec<CstNode<'a>>, node: CstNode<'a>) {
+    let nodes = match stack.last_mut() {
+        Some(block) => match block.branches.last_mut() {
+            Some(branch) => &mut branch.body,
+            None => &mut block.body,
+        },
+        None => nodes,
+    };
+    nodes.push(node);
+}
+
+pub(crate) fn is_block_opener(tag: &Tag<'_>) -> bool {
+    match tag.keyword() {
+        Some("block" | "call" | "filter" | "for" | "if" | "macro" | "match" | "with_values") => {
+            true
+        }
+        // `{% let x %}...{% endlet %}` is a block, `{% let x = y %}` is not.
+        Some("let" | "set") => !contains_outside_strings(tag.content, '='),
+        _ => false,
+    }
+}
+
+fn is_branch_of(keyword: &str, opener: &str) -> bool {
+    matches!(
+        (keyword, opener),
+        ("else" | "elif", "if") | ("else", "for") | ("else" | "when", "match")
+    )
+}
+
+fn is_endraw(node: &CstNode<'_>) -> bool {
+    matches!(node, CstNode::Tag(tag) if tag.kind == TagKind::Block && tag.content == "endraw")
+}
+
+fn contains_outside_strings(content: &str, needle: char) -> bool {
+    let mut in_str = false;
+    let mut escaped = false;
+    for c in content.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_str => escaped = true,
+            '"' => in_str = !in_str,
+            c if !in_str && c == needle => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Returns the text and the tags of `source`, in order, without building the tree. The body of a
+/// `{% raw %}` block is a single text node.
+///
+/// The tags are the ones found by the parser. If `source` contains a syntax error, the tolerant
+/// scanner is used instead.
+fn flat_nodes<'a>(source: &'a str, syntax: &Syntax<'_>) -> Vec<CstNode<'a>> {
+    Ast::tag_spans(source, syntax)
+        .and_then(|spans| nodes_from_spans(source, syntax, &spans))
+        .unwrap_or_else(|| {
+            Scanner {
+                source,
+                pos: 0,
+                syntax,
+            }
+            .nodes()
+        })
+}
+
+/// Splits `source` at the tags found by the parser. Returns `None` if the spans don't match the
+/// delimiters of `syntax`, which should not happen.
+fn nodes_from_spans<'a>(
+    source: &'a str,
+    syntax: &Syntax<'_>,
+    spans: &BTreeMap<usize, (usize, TagKind)>,
+) -> Option<Vec<CstNode<'a>>> {
+    let mut nodes = Vec::new();
+    let mut pos = 0;
+    for (&start, &(end, kind)) in spans {
+        let (start_delim, end_delim) = delimiters(syntax, kind);
+        let tag_source = source.get(start..end).filter(|_| start >= pos)?;
+        if !tag_source.starts_with(start_delim)
+            || !tag_source.ends_with(end_delim)
+            || tag_source.len() < start_delim.len() + end_delim.len()
+        {
+            return None;
+        }
+        if start > pos {
+            nodes.push(CstNode::Text(&source[pos..start]));
+        }
+        nodes.push(CstNode::Tag(split_tag(
+            source,
+            start,
+            kind,
+            start_delim.len(),
+            end - end_delim.len(),
+            end_delim.len(),
+        )));
+        pos = end;
+    }
+    if pos < source.len() {
+        nodes.push(CstNode::Text(&source[pos..]));
+    }
+    Some(nodes)
+}
+
+/// Returns all the tags of `source`, in order, without building the tree.
+pub(crate) fn tags<'a>(source: &'a str, syntax: &Syntax<'_>) -> Vec<Tag<'a>> {
+    flat_nodes(source, syntax)
+        .into_iter()
+        .filter_map(|node| match node {
+            CstNode::Tag(tag) => Some(tag),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the tag at the start of `source`, if `source` starts with a tag.
+pub(crate) fn leading_tag<'a>(source: &'a str, syntax: &Syntax<'_>) -> Option<Tag<'a>> {
+    let mut scanner = Scanner {
+        source,
+        pos: 0,
+        syntax,
+    };
+    match scanner.next_node()? {
+        CstNode::Tag(tag) => Some(tag),
+        _ => None,
+    }
+}
+
+fn delimiters<'s>(syntax: &Syntax<'s>, kind: TagKind) -> (&'s str, &'s str) {
+    match kind {
+        TagKind::Block => (syntax.block_start, syntax.block_end),
+        TagKind::Expr => (syntax.expr_start, syntax.expr_end),
+        TagKind::Comment => (syntax.comment_start, syntax.comment_end),
+    }
+}
+
+/// Splits the tag at `offset` into its parts. `inner_end` is the offset of its closing delimiter.
+fn split_tag(
+    source: &str,
+    offset: usize,
+    kind: TagKind,
+    start_len: usize,
+    inner_end: usize,
+    end_len: usize,
+) -> Tag<'_> {
+    let after_start = offset + start_len;
+    let inner_start = (after_start + ws_char_len(&source[after_start..])).min(inner_end);
+
+    let mut inner = &source[inner_start..inner_end];
+    let mut ws_end = "";
+    if end_len > 0 && matches!(inner.as_bytes().last(), Some(b'-' | b'+' | b'~')) {
+        ws_end = &inner[inner.len() - 1..];
+        inner = &inner[..inner.len() - 1];
+    }
+    let content = inner.trim();
+    let lws_len = inner.len() - inner.trim_start().len();
+    let lws = &inner[..lws_len];
+    let rws = &inner[lws_len + content.len()..];
+    Tag {
+        kind,
+        offset,
+        start: &source[offset..after_start],
+        ws_start: &source[after_start..inner_start],
+        lws,
+        content,
+        rws,
+        ws_end,
+        end: &source[inner_end..inner_end + end_len],
+    }
+}
+
+struct Scanner<'a, 's> {
+    source: &'a str,
+    pos: usize,
+    syntax: &'s Syntax<'s>,
+}
+
+impl<'a> Scanner<'a, '_> {
+    fn nodes(mut self) -> Vec<CstNode<'a>> {
+        let mut nodes = Vec::new();
+        while let Some(node) = self.next_node() {
+            let is_raw = matches!(node, CstNode::Tag(tag) if tag.keyword() == Some("raw"));
+            nodes.push(node);
+            if is_raw {
+                let (body, close) = self.raw_body();
+                if !body.is_empty() {
+                    nodes.push(CstNode::Text(body));
+                }
+                nodes.extend(close.map(CstNode::Tag));
+            }
+        }
+        nodes
+    }
+
+    fn next_node(&mut self) -> Option<CstNode<'a>> {
+        let rest = &self.source[self.pos..];
+        if rest.is_empty() {
+            return None;
+        }
+        let Some((start, kind)) = self.find_tag_start(rest) else {
+            self.pos = self.source.len();
+            return Some(CstNode::Text(rest));
+        };
+        if start > 0 {
+            self.pos += start;
+            return Some(CstNode::Text(&rest[..start]));
+        }
+        Some(CstNode::Tag(self.tag(kind)))
+    }
+
+    /// Finds the earliest tag start in `rest`. If several delimiters match at the same position,
+    /// the longest one wins.
+    fn find_tag_start(&self, rest: &str) -> Option<(usize, TagKind)> {
+        [TagKind::Block, TagKind::Expr, TagKind::Comment]
+            .into_iter()
+            .filter_map(|kind| {
+                let (start, _) = delimiters(self.syntax, kind);
+                rest.find(start).map(|pos| (pos, start.len(), kind))
+            })
+            .min_by_key(|&(pos, len, _)| (pos, usize::MAX - len))
+            .map(|(pos, _, kind)| (pos, kind))
+    }
+
+    fn tag(&mut self, kind: TagKind) -> Tag<'a> {
+        let offset = self.pos;
+        let (start_delim, end_delim) = delimiters(self.syntax, kind);
+        let after_start = offset + start_delim.len();
+        let inner_start = after_start + ws_char_len(&self.source[after_start..]);
+        let (inner_end, end_len) = match kind {
+            TagKind::Comment => self.find_comment_end(inner_start),
+            TagKind::Block | TagKind::Expr => find_tag_end(self.source, inner_start, end_delim),
+        };
+        self.pos = inner_end + end_len;
+        split_tag(
+            self.source,
+            offset,
+            kind,
+            start_delim.len(),
+            inner_end,
+            end_len,
+        )
+    }
+
+    /// Comments can be nested, so the end of the comment is the end delimiter that matches the
+    /// start delimiter.
+    fn find_comment_end(&self, from: usize) -> (usize, usize) {
+        let (start, end) = (self.syntax.comment_start, self.syntax.comment_end);
+        let mut depth = 0_usize;
+        let mut pos = from;
+        loop {
+            let rest = &self.source[pos..];
+            let Some(end_pos) = rest.find(end) else {
+                return (self.source.len(), 0);
+            };
+            match rest[..end_pos].find(start) {
+                Some(start_pos) => {
+                    depth += 1;
+                    pos += start_pos + start.len();
+                }
+                None if depth == 0 => return (pos + end_pos, end.len()),
+                None => {
+                    depth -= 1;
+                    pos += end_pos + end.len();
+                }
+            }
+        }
+    }
+
+    /// Everything until `{% endraw %}` is text.
+    fn raw_body(&mut self) -> (&'a str, Option<Tag<'a>>) {
+        let body_start = self.pos;
+        let start = self.syntax.block_start;
+        let mut search = body_start;
+        while let Some(found) = self.source[search..].find(start) {
+            self.pos = search + found;
+            let tag = self.tag(TagKind::Block);
+            if tag.content == "endraw" {
+                return (&self.source[body_start..tag.offset], Some(tag));
+            }
+            search = tag.offset + start.len();
+        }
+        self.pos = self.source.len();
+        (&self.source[body_start..], None)
+    }
+}
+
+fn ws_char_len(s: &str) -> usize {
+    match s.as_bytes().first() {
+        Some(b'-' | b'+' | b'~') => 1,
+        _ => 0,
+    }
+}
+
+/// Finds the end delimiter of a block or expression tag, skipping string and character literals.
+/// Returns the position of the delimiter and its length, which is `0` if the tag is not
+/// terminated.
+fn find_tag_end(source: &str, from: usize, end: &str) -> (usize, usize) {
+    let bytes = source.as_bytes();
+    let mut pos = from;
+    while pos < bytes.len() {
+        if bytes[pos..].starts_with(end.as_bytes()) {
+            return (pos, end.len());
+        }
+        pos = literal_end(bytes, pos).unwrap_or(pos + 1);
+    }
+    (source.len(), 0)
+}
+
+/// If a string or character literal starts at `pos`, returns the position after it. Like in Rust,
+/// string literals can be prefixed with `b`, `c` and `r`, and `'a` without a closing quote is
+/// a lifetime.
+fn literal_end(bytes: &[u8], pos: usize) -> Option<usize> {
+    let is_ident = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+    let mut start = pos;
+    if matches!(bytes[pos], b'b' | b'c' | b'r') {
+        if pos > 0 && is_ident(&bytes[pos - 1]) {
+            return None;
+        }
+        start += usize::from(bytes[pos] != b'r');
+        if bytes.get(start) == Some(&b'r') {
+            // Raw string: `r#"…"#` ends at the first quote followed by as many hashes.
+            let hashes = bytes[start + 1..]
+                .iter()
+                .take_while(|&&b| b == b'#')
+                .count();
+            let quote = start + 1 + hashes;
+            if bytes.get(quote) != Some(&b'"') {
+                return None;
+            }
+            let mut end = quote + 1;
+            loop {
+                let close = end + bytes.get(end..)?.iter().position(|&b| b == b'"')?;
+                end = close + 1;
+                if bytes[end..].iter().take_while(|&&b| b == b'#').count() >= hashes {
+                    return Some(end + hashes);
+                }
+            }
+        }
+    }
+    match bytes.get(start)? {
+        b'"' => {
+            let mut end = start + 1;
+            while *bytes.get(end)? != b'"' {
+                end += if bytes[end] == b'\\' { 2 } else { 1 };
+            }
+            Some(end + 1)
+        }
+        b'\'' => {
+            // An escape sequence like `'\''` or `'\u{…}'`, or a single character.
+            let close = match *bytes.get(start + 1)? {
+                b'\\' => start + 3 + bytes.get(start + 3..)?.iter().position(|&b| b == b'\'')?,
+                b => start + 1 + utf8_len(b),
+            };
+            (bytes.get(close) == Some(&b'\'')).then_some(close + 1)
+        }
+        _ => None,
+    }
+}
+
+fn utf8_len(first_byte: u8) -> usize {
+    match first_byte.leading_ones() {
+        0 => 1,
+        n => n as usize,
+    }
+}
+
+/// Options of [`Cst::format()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The indentation used for each nesting level of blocks.
+    pub indent: String,
+    /// How whitespace is handled by tags without whitespace control character, i.e. the
+    /// `whitespace` option of the configuration.
+    pub whitespace: Whitespace,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: "    ".into(),
+            whitespace: Whitespace::Preserve,
+        }
+    }
+}
+
+/// Formats `source` with the given syntax and options.
+///
+/// The formatter only changes whitespace that does not change the rendered output:
+///
+/// * the content of `{{ }}` and `{% %}` tags is surrounded by exactly one space,
+/// * tags that start a line are indented according to how many blocks they are nested in, if the
+///   whitespace before them is suppressed or minimized, by a `-` or `~` character or by
+///   [`FormatOptions::whitespace`].
+///
+/// Text, comments, multi-line tag content and the content of `{% raw %}` blocks are left
+/// untouched, as are tags that are not terminated. Formatting is idempotent.
+#[must_use]
+pub fn format(source: &str, syntax: &Syntax<'_>, options: &FormatOptions) -> String {
+    Cst::parse(source, syntax).format(options)
+}
+
+struct Formatter<'o> {
+    buf: String,
+    options: &'o FormatOptions,
+    /// The offset in `buf` of the whitespace that precedes the next tag.
+    ws_start: usize,
+    /// Set if only whitespace follows a tag that suppresses the whitespace after it.
+    suppressed: bool,
+}
+
+impl Formatter<'_> {
+    fn nodes(&mut self, nodes: &[CstNode<'_>], depth: usize) {
+        for node in nodes {
+            match node {
+                CstNode::Text(text) => self.text(text),
+                CstNode::Tag(tag) => self.tag(tag, depth),
+                CstNode::Block(block) => {
+                    self.tag(&block.open, depth);
+                    if block.open.keyword() == Some("raw") {
+                        block
+                            .body
+                            .iter()
+                            .for_each(|node| self.text(&node.to_string()));
+                    } else {
+                        self.nodes(&block.body, depth + 1);
+                    }
+                    for branch in &block.branches {
+                        self.tag(&branch.tag, depth);
+                        self.nodes(&branch.body, depth + 1);
+                    }
+                    if let Some(close) = &block.close {
+                        self.tag(close, depth);
+                    }
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        self.buf.push_str(text);
+        let content = text.trim_ascii_end();
+        if !content.is_empty() {
+            self.ws_start = self.buf.len() - (text.len() - content.len());
+            self.suppressed = false;
+        }
+    }
+
+    fn tag(&mut self, tag: &Tag<'_>, depth: usize) {
+        self.indent(tag, depth);
+
+        let buf = &mut self.buf;
+        if tag.kind == TagKind::Comment
+            || !tag.is_terminated()
+            || tag.lws.contains('\n')
+            || tag.rws.contains('\n')
+        {
+            let _ = write!(buf, "{tag}");
+        } else {
+            buf.push_str(tag.start);
+            buf.push_str(tag.ws_start);
+            buf.push(' ');
+            if !tag.content.is_empty() {
+                buf.push_str(tag.content);
+                buf.push(' ');
+            }
+            buf.push_str(tag.ws_end);
+            buf.push_str(tag.end);
+        }
+
+        self.ws_start = self.buf.len();
+        self.suppressed = tag.is_terminated() && self.handling(tag.ws_end) == Whitespace::Suppress;
+    }
+
+    /// Re-indents the tag if only whitespace precedes it on its line, and if changing this
+    /// whitespace does not change the rendered output.
+    fn indent(&mut self, tag: &Tag<'_>, depth: usize) {
+        let line_start = self.buf.rfind('\n').map_or(0, |pos| pos + 1);
+        if line_start < self.ws_start {
+            return;
+        }
+        // Whitespace that only follows a tag is removed if this tag suppresses it. Otherwise, the
+        // whitespace before a tag is handled by the tag.
+        let handling = match self.suppressed {
+            true => Whitespace::Suppress,
+            false => self.handling(tag.ws_start),
+        };
+        let reindent = match handling {
+            Whitespace::Suppress => true,
+            // Minimized whitespace only depends on whether it contains a newline.
+            Whitespace::Minimize => line_start > self.ws_start,
+            Whitespace::Preserve => false,
+        };
+        if reindent {
+            self.buf.truncate(line_start);
+            for _ in 0..depth {
+                self.buf.push_str(&self.options.indent);
+            }
+        }
+    }
+
+    fn handling(&self, ws_char: &str) -> Whitespace {
+        ws_char
+            .bytes()
+            .next()
+            .and_then(Whitespace::parse_byte)
+            .unwrap_or(self.options.whitespace)
+    }
+}
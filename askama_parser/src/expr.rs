@@ -4,7 +4,7 @@ use winnow::combinator::{
     alt, cut_err, empty, fail, not, opt, peek, preceded, repeat, separated, terminated,
 };
 use winnow::error::ErrMode;
-use winnow::stream::Stream;
+use winnow::stream::{Location, Stream};
 use winnow::token::{any, one_of, take, take_until};
 
 use crate::node::CondTest;
@@ -142,6 +142,20 @@ fn check_expr<'a>(expr: &WithSpan<Box<Expr<'a>>>, allowed: Allowed) -> ParseResu
             }
             Ok(())
         }
+        Expr::Test(test) => {
+            for arg in &test.arguments {
+                check_expr(arg, Allowed::default())?;
+            }
+            Ok(())
+        }
+        Expr::Conditional(v) => {
+            check_expr(&v.cond, Allowed::default())?;
+            check_expr(&v.then, Allowed::default())?;
+            if let Some(otherwise) = &v.otherwise {
+                check_expr(otherwise, Allowed::default())?;
+            }
+            Ok(())
+        }
         Expr::Struct(s) => {
             check_expr(
                 &s.path,
@@ -243,6 +257,10 @@ pub enum Expr<'a> {
     FilterSource,
     IsDefined(&'a str),
     IsNotDefined(&'a str),
+    /// A test like `x is divisibleby(3)` or `x is not none`.
+    Test(Test<'a>),
+    /// An inline conditional like `a if cond else b` or `a if cond`.
+    Conditional(Conditional<'a>),
     Concat(Vec<WithSpan<Box<Expr<'a>>>>),
     /// If you have `&& let Some(y)`, this variant handles it.
     LetCond(WithSpan<CondTest<'a>>),
@@ -258,6 +276,14 @@ pub struct Call<'a> {
     pub args: Vec<WithSpan<Box<Expr<'a>>>>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conditional<'a> {
+    pub cond: WithSpan<Box<Expr<'a>>>,
+    pub then: WithSpan<Box<Expr<'a>>>,
+    /// If `None`, the expression evaluates to an empty value when `cond` is `false`.
+    pub otherwise: Option<WithSpan<Box<Expr<'a>>>>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Range<'a> {
     pub op: &'a str,
@@ -367,15 +393,70 @@ impl<'a: 'l, 'l> Expr<'a> {
     pub(super) fn parse(
         i: &mut InputStream<'a, 'l>,
         allow_underscore: bool,
+    ) -> ParseResult<'a, WithSpan<Box<Self>>> {
+        Self::parse_inner(i, allow_underscore, true)
+    }
+
+    /// Like [`Expr::parse`], but a trailing `if` is not parsed as an inline conditional, so it
+    /// can be used for the iterable of a `{% for x in xs if cond %}` loop.
+    pub(super) fn parse_without_conditional(
+        i: &mut InputStream<'a, 'l>,
+        allow_underscore: bool,
+    ) -> ParseResult<'a, WithSpan<Box<Self>>> {
+        Self::parse_inner(i, allow_underscore, false)
+    }
+
+    fn parse_inner(
+        i: &mut InputStream<'a, 'l>,
+        allow_underscore: bool,
+        allow_conditional: bool,
     ) -> ParseResult<'a, WithSpan<Box<Self>>> {
         let _level_guard = i.state.level.nest(i)?;
-        let mut result = Self::range(i, allow_underscore);
+        let mut result = match allow_conditional {
+            true => Self::conditional(i, allow_underscore),
+            false => Self::range(i, allow_underscore),
+        };
         if let Err(err) = &mut result {
             try_assign_fallback_error(i, err);
         }
         result
     }
 
+    fn conditional(
+        i: &mut InputStream<'a, 'l>,
+        allow_underscore: bool,
+    ) -> ParseResult<'a, WithSpan<Box<Self>>> {
+        let start = i.current_token_start();
+        let then = Self::range(i, allow_underscore)?;
+        let Some(if_span) = opt(ws(keyword("if").span())).parse_next(i)? else {
+            return Ok(then);
+        };
+
+        let Some(cond) = opt(ws(Self::or)).parse_next(i)? else {
+            return cut_error!("expected a condition after `if`", if_span);
+        };
+        check_expr(&cond, Allowed::default())?;
+        let otherwise = match opt(ws(keyword("else").span())).parse_next(i)? {
+            Some(else_span) => {
+                let otherwise =
+                    opt(ws(move |i: &mut _| Self::parse(i, allow_underscore))).parse_next(i)?;
+                match otherwise {
+                    Some(otherwise) => Some(otherwise),
+                    None => return cut_error!("expected an expression after `else`", else_span),
+                }
+            }
+            None => None,
+        };
+        Ok(WithSpan::new(
+            Box::new(Self::Conditional(Conditional {
+                cond,
+                then,
+                otherwise,
+            })),
+            start..i.current_token_start(),
+        ))
+    }
+
     fn range(
         i: &mut InputStream<'a, 'l>,
         allow_underscore: bool,
@@ -499,13 +580,51 @@ impl<'a: 'l, 'l> Expr<'a> {
         lhs: WithSpan<Box<Expr<'a>>>,
         span: std::ops::Range<usize>,
     ) -> ParseResult<'a, WithSpan<Box<Self>>> {
-        let mut rhs = opt(terminated(opt(keyword("not")), ws(keyword("defined"))));
-        let ctor = match rhs.parse_next(i)? {
+        let mut rhs = (opt(ws(keyword("not"))), opt(ws(path_or_identifier)));
+        let (negation, name) = rhs.parse_next(i)?;
+        let negated = negation.is_some();
+        // Unlike filters, a test cannot be an expression, e.g. in `x is blue.red`.
+        if name.is_some() && opt(peek(ws(('.', not('.'))))).parse_next(i)?.is_some() {
+            return cut_error!(
+                "expected the name of a test after `is`, e.g. `even` or `template_tests::my_test`, \
+                 found a field access",
+                span,
+            );
+        }
+        let name = match name {
+            Some(PathOrIdentifier::Identifier(name)) if *name == "defined" => {
+                if opt(peek(ws('('))).parse_next(i)?.is_some() {
+                    return cut_error!("`defined` test does not have any arguments", span);
+                }
+                return Self::is_as_handle_is_defined(lhs, negated, span);
+            }
+            Some(name) => name,
             None => {
-                return cut_error!("expected `defined` or `not defined` after `is`", span);
+                return cut_error!(
+                    "expected the name of a test after `is`, e.g. `defined` or `even`",
+                    span,
+                );
             }
-            Some(None) => Self::IsDefined,
-            Some(Some(_)) => Self::IsNotDefined,
+        };
+        let arguments = opt(Self::arguments).parse_next(i)?;
+        let mut arguments = arguments.map_or_else(Vec::new, |arguments| arguments.inner);
+        arguments.insert(0, lhs);
+        let test = Test {
+            name,
+            arguments,
+            negated,
+        };
+        Ok(WithSpan::new(Box::new(Self::Test(test)), span))
+    }
+
+    fn is_as_handle_is_defined(
+        lhs: WithSpan<Box<Expr<'a>>>,
+        negated: bool,
+        span: std::ops::Range<usize>,
+    ) -> ParseResult<'a, WithSpan<Box<Self>>> {
+        let ctor = match negated {
+            false => Self::IsDefined,
+            true => Self::IsNotDefined,
         };
         let var_name = match &**lhs {
             Self::Var(var_name) => var_name,
@@ -781,6 +900,8 @@ impl<'a: 'l, 'l> Expr<'a> {
             | Self::Struct(_)
             | Self::NamedArgument(_, _)
             | Self::Filter(_)
+            | Self::Test(_)
+            | Self::Conditional(_)
             | Self::AssociatedItem(_, _)
             | Self::Index(_, _)
             | Self::Tuple(_)
@@ -832,6 +953,16 @@ impl<'a: 'l, 'l> Filter<'a> {
     }
 }
 
+/// A test like `x is divisibleby(3)`. Like for [`Filter`], the tested value is the first
+/// element of `arguments`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Test<'a> {
+    pub name: PathOrIdentifier<'a>,
+    pub arguments: Vec<WithSpan<Box<Expr<'a>>>>,
+    /// `true` for `is not`.
+    pub negated: bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct AssociatedItem<'a> {
     pub name: WithSpan<&'a str>,
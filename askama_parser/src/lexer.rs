@@ -0,0 +1,260 @@
+//! Token-level lexer, for syntax highlighting and editor tooling.
+//!
+//! Unlike [`Ast::from_str`](crate::Ast::from_str), [`tokenize`] never fails: incomplete or
+//! invalid input produces [`TokenKind::Error`] tokens or unterminated tokens, and the spans of the
+//! returned tokens always cover the whole source, without gaps or overlaps.
+//!
+//! Outside of `{% raw %}` blocks, the tokens of a tag don't depend on what comes before the tag.
+//! To re-highlight a template after an edit, it is enough to tokenize again from the start of the
+//! tag or the text containing the edit.
+
+use std::ops::Range;
+
+use crate::cst::{self, Tag, TagKind};
+use crate::{Syntax, is_rust_keyword};
+
+/// A token with its byte span in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Text outside of tags, including the content of `{% raw %}` blocks.
+    Text,
+    /// A whole comment, including its delimiters.
+    Comment,
+    /// `{%`
+    BlockStart,
+    /// `%}`
+    BlockEnd,
+    /// `{{`
+    ExprStart,
+    /// `}}`
+    ExprEnd,
+    /// `-`, `+` or `~` after an opening or before a closing delimiter.
+    WhitespaceControl,
+    /// Whitespace inside of a tag.
+    Whitespace,
+    /// The tag keyword, like `if` or `endfor`, and keywords inside expressions, like `in` or `as`.
+    Keyword,
+    Identifier,
+    /// The name of a filter, e.g. `upper` in `{{ name|upper }}`.
+    Filter,
+    /// Operators like `+`, `==` or `|`.
+    Operator,
+    /// Delimiters like `(`, `]` or `,`, and path separators.
+    Punctuation,
+    BoolLit,
+    NumLit,
+    StrLit,
+    CharLit,
+    /// A character that cannot start any token.
+    Error,
+}
+
+/// Keywords of askama, that are not Rust keywords.
+const ASKAMA_KEYWORDS: &[&str] = &["defined", "elif", "is", "not", "set", "when"];
+
+/// Splits `source` into tokens. Custom delimiters are taken from `syntax`.
+#[must_use]
+pub fn tokenize(source: &str, syntax: &Syntax<'_>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    for tag in cst::tags(source, syntax) {
+        if tag.offset > pos {
+            push(&mut tokens, TokenKind::Text, pos, tag.offset);
+        }
+        lex_tag(&mut tokens, &tag);
+        pos = tag.range().end;
+    }
+    if pos < source.len() {
+        push(&mut tokens, TokenKind::Text, pos, source.len());
+    }
+    tokens
+}
+
+fn push(tokens: &mut Vec<Token>, kind: TokenKind, start: usize, end: usize) {
+    if start < end {
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+}
+
+fn lex_tag(tokens: &mut Vec<Token>, tag: &Tag<'_>) {
+    let (start_kind, end_kind) = match tag.kind {
+        TagKind::Comment => {
+            let range = tag.range();
+            push(tokens, TokenKind::Comment, range.start, range.end);
+            return;
+        }
+        TagKind::Block => (TokenKind::BlockStart, TokenKind::BlockEnd),
+        TagKind::Expr => (TokenKind::ExprStart, TokenKind::ExprEnd),
+    };
+    let mut pos = tag.offset;
+    let before = [
+        (start_kind, tag.start),
+        (TokenKind::WhitespaceControl, tag.ws_start),
+        (TokenKind::Whitespace, tag.lws),
+    ];
+    for (kind, s) in before {
+        push(tokens, kind, pos, pos + s.len());
+        pos += s.len();
+    }
+    lex_content(tokens, tag.content, pos, tag.kind == TagKind::Block);
+    pos += tag.content.len();
+    let after = [
+        (TokenKind::Whitespace, tag.rws),
+        (TokenKind::WhitespaceControl, tag.ws_end),
+        (end_kind, tag.end),
+    ];
+    for (kind, s) in after {
+        push(tokens, kind, pos, pos + s.len());
+        pos += s.len();
+    }
+}
+
+/// Lexes the content of a block or an expression tag, `offset` being its position in the source.
+fn lex_content(tokens: &mut Vec<Token>, content: &str, offset: usize, is_block: bool) {
+    let bytes = content.as_bytes();
+    let mut pos = 0;
+    let mut first_word = is_block;
+    let mut after_pipe = false;
+    while let Some(c) = content[pos..].chars().next() {
+        let start = pos;
+        let kind = if c.is_whitespace() {
+            pos += content[pos..]
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(content.len() - pos);
+            TokenKind::Whitespace
+        } else if let Some(len) = str_lit_len(&content[pos..]) {
+            pos += len;
+            TokenKind::StrLit
+        } else if let Some(len) = char_lit_len(&content[pos..]) {
+            pos += len;
+            TokenKind::CharLit
+        } else if c == '_' || unicode_ident::is_xid_start(c) {
+            pos += content[pos..]
+                .find(|c: char| !unicode_ident::is_xid_continue(c))
+                .unwrap_or(content.len() - pos);
+            let ident = &content[start..pos];
+            if after_pipe {
+                TokenKind::Filter
+            } else if ident == "true" || ident == "false" {
+                TokenKind::BoolLit
+            } else if first_word || is_rust_keyword(ident) || ASKAMA_KEYWORDS.contains(&ident) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        } else if c.is_ascii_digit() {
+            pos += 1;
+            while pos < bytes.len() {
+                match bytes[pos] {
+                    b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'_' => pos += 1,
+                    // `1.5`, but not `1..5` or `1.max(2)`
+                    b'.' if bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) => pos += 1,
+                    // exponent sign, e.g. `1e-5`
+                    b'+' | b'-' if matches!(bytes[pos - 1], b'e' | b'E') => pos += 1,
+                    _ => break,
+                }
+            }
+            TokenKind::NumLit
+        } else if let Some(len) = punctuation_len(&content[pos..]) {
+            pos += len;
+            TokenKind::Punctuation
+        } else if let Some(len) = operator_len(&content[pos..]) {
+            pos += len;
+            TokenKind::Operator
+        } else {
+            pos += c.len_utf8();
+            TokenKind::Error
+        };
+        if kind != TokenKind::Whitespace {
+            first_word = false;
+            after_pipe = kind == TokenKind::Operator && &content[start..pos] == "|";
+        }
+        push(tokens, kind, offset + start, offset + pos);
+    }
+}
+
+/// Length of a string literal, with its optional `b`, `c` or `r#` prefix. An unterminated string
+/// goes until the end of `s`.
+fn str_lit_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut pos = match bytes {
+        [b'b' | b'c', b'r', ..] => 2,
+        [b'b' | b'c' | b'r', ..] => 1,
+        _ => 0,
+    };
+    let raw = pos > 0 && bytes[pos - 1] == b'r';
+    let hashes = if raw {
+        bytes[pos..].iter().take_while(|&&b| b == b'#').count()
+    } else {
+        0
+    };
+    pos += hashes;
+    if bytes.get(pos) != Some(&b'"') {
+        return None;
+    }
+    pos += 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if !raw => pos += 2,
+            b'"' if bytes[pos + 1..].iter().take_while(|&&b| b == b'#').count() >= hashes => {
+                return Some(pos + 1 + hashes);
+            }
+            _ => pos += 1,
+        }
+    }
+    Some(s.len())
+}
+
+/// Length of a character literal with its optional `b` prefix.
+fn char_lit_len(s: &str) -> Option<usize> {
+    let prefix = usize::from(s.starts_with("b'"));
+    let rest = s[prefix..].strip_prefix('\'')?;
+    let mut chars = rest.char_indices();
+    let len = match chars.next()? {
+        // The escape goes until the closing quote, e.g. `'\n'` or `'\u{1F600}'`.
+        (_, '\\') => {
+            let (i, c) = chars.next()?;
+            let after = i + c.len_utf8();
+            after + rest[after..].find('\'')?
+        }
+        (_, '\'') => return None,
+        (_, c) => c.len_utf8(),
+    };
+    rest[len..]
+        .starts_with('\'')
+        .then_some(prefix + 1 + len + 1)
+}
+
+fn punctuation_len(s: &str) -> Option<usize> {
+    if s.starts_with("::") {
+        return Some(2);
+    }
+    let punctuation = matches!(
+        s.as_bytes()[0],
+        b'(' | b')' | b'[' | b']' | b'{' | b'}' | b',' | b';' | b':'
+    );
+    // `.`, but not `..`
+    let dot = s.starts_with('.') && !s.starts_with("..");
+    (punctuation || dot).then_some(1)
+}
+
+fn operator_len(s: &str) -> Option<usize> {
+    const OPERATORS: &[&str] = &[
+        "..=", "<<=", ">>=", "..", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "+=", "-=",
+        "*=", "/=", "%=", "&=", "|=", "^=", "->", "=>", "+", "-", "*", "/", "%", "!", "&", "|",
+        "^", "<", ">", "=", "?", "~", "#", "@", "$",
+    ];
+    OPERATORS
+        .iter()
+        .find(|op| s.starts_with(**op))
+        .map(|op| op.len())
+}
@@ -5,14 +5,18 @@
 #![doc = include_str!("../README.md")]
 
 mod ascii_str;
+pub mod cst;
 pub mod expr;
+pub mod lexer;
 pub mod node;
 mod target;
 #[cfg(test)]
 mod tests;
+pub mod visit;
 
 use std::borrow::{Borrow, BorrowMut, Cow};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::env::current_dir;
 use std::ops::{Deref, DerefMut, Range};
 use std::path::Path;
@@ -30,7 +34,10 @@ use winnow::token::{any, none_of, one_of, take, take_while};
 use winnow::{LocatingSlice, ModalParser, ModalResult, Parser, Stateful};
 
 use crate::ascii_str::{AsciiChar, AsciiStr};
-pub use crate::expr::{AssociatedItem, Expr, Filter, PathComponent, TyGenerics, TyGenericsKind};
+use crate::cst::TagKind;
+pub use crate::expr::{
+    AssociatedItem, Conditional, Expr, Filter, PathComponent, Test, TyGenerics, TyGenericsKind,
+};
 pub use crate::node::{LetValueOrBlock, Node};
 pub use crate::target::{NamedTarget, Target};
 
@@ -65,6 +72,19 @@ mod _parsed {
             Ok(Self { ast, source })
         }
 
+        /// Like [`Parsed::new`], but returns all the errors of the template, see
+        /// [`Ast::from_str_with_recovery`].
+        pub fn new_with_recovery(
+            source: Arc<str>,
+            file_path: Option<Arc<Path>>,
+            syntax: &Syntax<'_>,
+        ) -> Result<Self, Vec<ParseError>> {
+            // Same as above.
+            let src = unsafe { mem::transmute::<&str, &'static str>(source.as_ref()) };
+            let ast = Ast::from_str_with_recovery(src, file_path, syntax)?;
+            Ok(Self { ast, source })
+        }
+
         // The return value's lifetime must be limited to `self` to uphold the unsafe invariant.
         #[must_use]
         pub fn nodes(&self) -> &[Box<Node<'_>>] {
@@ -118,32 +138,89 @@ impl<'a> Ast<'a> {
         file_path: Option<Arc<Path>>,
         syntax: &Syntax<'_>,
     ) -> Result<Ast<'a>, ParseError> {
+        Self::parse(src, file_path, syntax, false).map_err(|mut errors| errors.swap_remove(0))
+    }
+
+    /// Like [`Ast::from_str`], but if the template contains errors, parsing resumes after the
+    /// tag containing the error, so all errors are returned instead of only the first one.
+    pub fn from_str_with_recovery(
+        src: &'a str,
+        file_path: Option<Arc<Path>>,
+        syntax: &Syntax<'_>,
+    ) -> Result<Ast<'a>, Vec<ParseError>> {
+        Self::parse(src, file_path, syntax, true)
+    }
+
+    fn parse(
+        src: &'a str,
+        file_path: Option<Arc<Path>>,
+        syntax: &Syntax<'_>,
+        recover: bool,
+    ) -> Result<Ast<'a>, Vec<ParseError>> {
         let state = State {
             syntax: *syntax,
+            recovery: recover.then(RefCell::default),
             ..State::default()
         };
         let mut src = InputStream {
             input: LocatingSlice::new(src),
             state: &state,
         };
-        match Node::parse_template(&mut src) {
-            Ok(nodes) if src.is_empty() => Ok(Self { nodes }),
+        let result = Node::parse_template(&mut src);
+        let finished = src.is_empty();
+
+        let mut errors = match state.recovery {
+            Some(recovery) => recovery.into_inner().errors,
+            None => Vec::new(),
+        };
+        match result {
+            Ok(nodes) if finished && errors.is_empty() => return Ok(Self { nodes }),
+            Ok(_) if finished => {}
             Ok(_) | Err(ErrMode::Incomplete(_)) => unreachable!(),
-            Err(
-                ErrMode::Backtrack(ErrorContext { span, message, .. })
-                | ErrMode::Cut(ErrorContext { span, message, .. }),
-            ) => Err(ParseError {
-                message,
-                offset: span.start,
-                file_path,
-            }),
+            Err(ErrMode::Backtrack(err) | ErrMode::Cut(err)) => errors.push(err),
         }
+        errors.sort_by_key(|err| err.span.start);
+        Err(errors
+            .into_iter()
+            .map(|err| ParseError {
+                message: err.message,
+                offset: err.span.start,
+                file_path: file_path.clone(),
+            })
+            .collect())
+    }
+
+    /// Returns the end offsets and the kinds of the tags of `src`, by their start offsets, as found
+    /// by the parser. Returns `None` if `src` contains a syntax error.
+    pub(crate) fn tag_spans(
+        src: &str,
+        syntax: &Syntax<'_>,
+    ) -> Option<BTreeMap<usize, (usize, TagKind)>> {
+        let state = State {
+            syntax: *syntax,
+            tag_spans: Some(TagSpans::default()),
+            ..State::default()
+        };
+        let mut src = InputStream {
+            input: LocatingSlice::new(src),
+            state: &state,
+        };
+        Node::parse_template(&mut src).ok()?;
+        if !src.is_empty() {
+            return None;
+        }
+        Some(state.tag_spans?.spans.into_inner())
     }
 
     #[must_use]
     pub fn nodes(&self) -> &[Box<Node<'a>>] {
         &self.nodes
     }
+
+    #[must_use]
+    pub fn nodes_mut(&mut self) -> &mut Vec<Box<Node<'a>>> {
+        &mut self.nodes
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -438,7 +515,7 @@ pub(crate) type ParseResult<'a, T = &'a str> = Result<T, ParseErr<'a>>;
 ///
 /// It cannot be used to replace `ParseError` because it expects a generic, which would make
 /// `askama`'s users experience less good (since this generic is only needed for `nom`).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct ErrorContext {
     pub(crate) span: Span,
     pub(crate) message: Option<Cow<'static, str>>,
@@ -1071,10 +1148,34 @@ struct State<'a> {
     syntax: Syntax<'a>,
     loop_depth: Cell<usize>,
     level: Level,
+    /// Only set if the parser recovers from errors.
+    recovery: Option<RefCell<Recovery>>,
+    /// Only set if the spans of the tags are collected, cf. [`Ast::tag_spans()`].
+    tag_spans: Option<TagSpans>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Recovery {
+    /// The errors the parser recovered from.
+    errors: Vec<ErrorContext>,
+    /// The end offsets of the nodes that were skipped, by their start offsets.
+    skipped: HashMap<usize, usize>,
+    /// Set once an error could not be recovered from, e.g. in an unterminated tag.
+    aborted: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TagSpans {
+    /// The start offset and the kind of the tag whose opening delimiter was parsed last.
+    open: Cell<Option<(usize, TagKind)>>,
+    /// The end offsets and the kinds of the parsed tags, by their start offsets.
+    spans: RefCell<BTreeMap<usize, (usize, TagKind)>>,
 }
 
 fn block_start<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
-    i.state.syntax.block_start.void().parse_next(i)
+    let span = i.state.syntax.block_start.span().parse_next(i)?;
+    i.state.open_tag(span.start, TagKind::Block);
+    Ok(())
 }
 
 fn block_end<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
@@ -1087,6 +1188,7 @@ fn block_end<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
     .parse_next(i)?;
 
     let Some(control) = control else {
+        i.state.close_tag(span.end);
         return Ok(());
     };
 
@@ -1102,11 +1204,15 @@ fn block_end<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
 }
 
 fn expr_start<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
-    i.state.syntax.expr_start.void().parse_next(i)
+    let span = i.state.syntax.expr_start.span().parse_next(i)?;
+    i.state.open_tag(span.start, TagKind::Expr);
+    Ok(())
 }
 
 fn expr_end<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
-    i.state.syntax.expr_end.void().parse_next(i)
+    let span = i.state.syntax.expr_end.span().parse_next(i)?;
+    i.state.close_tag(span.end);
+    Ok(())
 }
 
 impl State<'_> {
@@ -1121,6 +1227,72 @@ impl State<'_> {
     fn is_in_loop(&self) -> bool {
         self.loop_depth.get() > 0
     }
+
+    fn recovers(&self) -> bool {
+        self.recovery
+            .as_ref()
+            .is_some_and(|recovery| !recovery.borrow().aborted)
+    }
+
+    /// Stops recovering from errors, so the current error ends the parsing.
+    fn abort_recovery(&self) {
+        if let Some(recovery) = &self.recovery {
+            recovery.borrow_mut().aborted = true;
+        }
+    }
+
+    /// Remembers an error that the parser recovered from.
+    fn push_error(&self, err: ErrorContext) {
+        if let Some(recovery) = &self.recovery {
+            // Errors in a block may be found again when the block is skipped. Only the first
+            // error at any position is kept.
+            let errors = &mut recovery.borrow_mut().errors;
+            if !errors.iter().any(|e| e.span.start == err.span.start) {
+                errors.push(err);
+            }
+        }
+    }
+
+    /// Remembers that the node at `span` was skipped because it contained an error.
+    fn push_skipped(&self, span: Range<usize>) {
+        if let Some(recovery) = &self.recovery {
+            recovery.borrow_mut().skipped.insert(span.start, span.end);
+        }
+    }
+
+    /// Remembers that the opening delimiter of a tag was parsed at `start`.
+    ///
+    /// The parser does not parse delimiters inside of tags, so the next closing delimiter that
+    /// is parsed closes this tag.
+    fn open_tag(&self, start: usize, kind: TagKind) {
+        if let Some(tag_spans) = &self.tag_spans {
+            tag_spans.open.set(Some((start, kind)));
+        }
+    }
+
+    /// Remembers that the tag opened last ends at `end`.
+    fn close_tag(&self, end: usize) {
+        if let Some(tag_spans) = &self.tag_spans
+            && let Some((start, kind)) = tag_spans.open.take()
+        {
+            tag_spans.spans.borrow_mut().insert(start, (end, kind));
+        }
+    }
+
+    /// Remembers the span of a tag that is parsed as a whole, i.e. a comment.
+    fn push_tag(&self, span: Range<usize>, kind: TagKind) {
+        self.open_tag(span.start, kind);
+        self.close_tag(span.end);
+    }
+
+    /// Returns the end offset of the node starting at `start`, if it was skipped before.
+    ///
+    /// Skipping a block parses its content again. Without this lookup, nested blocks that all
+    /// contain errors would be parsed an exponential number of times.
+    fn skipped(&self, start: usize) -> Option<usize> {
+        let recovery = self.recovery.as_ref()?;
+        recovery.borrow().skipped.get(&start).copied()
+    }
 }
 
 #[derive(Default, Hash, PartialEq, Clone, Copy)]
@@ -1354,6 +1526,8 @@ impl Level {
     #[inline(never)]
     fn _fail<'a: 'l, 'l, T>(i: &InputStream<'a, 'l>) -> ParseResult<'a, T> {
         let msg = "your template code is too deeply nested, or the last expression is too complex";
+        // Skipping the erroneous node would only hit the limit again.
+        i.state.abort_recovery();
         Err(ErrorContext::new(msg, i).cut())
     }
 }
@@ -1662,6 +1836,7 @@ fn cut_context_err<'a, T>(gen_err: impl FnOnce() -> ErrorContext) -> ParseResult
 }
 
 type HashSet<T> = std::collections::hash_set::HashSet<T, FxBuildHasher>;
+type HashMap<K, V> = std::collections::hash_map::HashMap<K, V, FxBuildHasher>;
 
 fn deny_any_rust_token<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
     let (token, span) = any_rust_token.with_span().parse_next(i)?;
@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::str::{self, FromStr};
 
 use winnow::combinator::{
@@ -9,6 +10,7 @@ use winnow::stream::{Location, Stream};
 use winnow::token::{any, literal, rest, take, take_until};
 use winnow::{ModalParser, Parser};
 
+use crate::cst;
 use crate::expr::BinOp;
 use crate::{
     ErrorContext, Expr, Filter, HashSet, InputStream, ParseErr, ParseResult, Span, Target,
@@ -38,29 +40,36 @@ pub enum Node<'a> {
     Break(WithSpan<Ws>),
     Continue(WithSpan<Ws>),
     FilterBlock(WithSpan<FilterBlock<'a>>),
+    WithValues(WithSpan<WithValues<'a>>),
 }
 
 impl<'a: 'l, 'l> Node<'a> {
     pub(super) fn parse_template(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, Vec<Box<Self>>> {
         let mut nodes = vec![];
         let mut allow_extends = true;
-        while let Some(node) = parse_with_unexpected_fallback(
-            opt(move |i: &mut _| Self::one(i, allow_extends)),
-            unexpected_tag,
-        )
-        .parse_next(i)?
-        {
-            if allow_extends {
-                match &*node {
-                    // Since comments don't impact generated code, we allow them before `extends`.
-                    Node::Comment(_) => {}
-                    // If it only contains whitespace characters, it's fine too.
-                    Node::Lit(lit) if lit.val.is_empty() => {}
-                    // Everything else must not come before an `extends` block.
-                    _ => allow_extends = false,
+        loop {
+            while let Some(node) = parse_with_unexpected_fallback(
+                opt(move |i: &mut _| Self::one(i, allow_extends)),
+                unexpected_tag,
+            )
+            .parse_next(i)?
+            {
+                if allow_extends {
+                    match &*node {
+                        // Since comments don't impact generated code, we allow them before
+                        // `extends`.
+                        Node::Comment(_) => {}
+                        // If it only contains whitespace characters, it's fine too.
+                        Node::Lit(lit) if lit.val.is_empty() => {}
+                        // Everything else must not come before an `extends` block.
+                        _ => allow_extends = false,
+                    }
                 }
+                nodes.push(node);
+            }
+            if !skip_unexpected_tag(i)? {
+                break;
             }
-            nodes.push(node);
         }
 
         if !i.is_empty() {
@@ -76,15 +85,115 @@ impl<'a: 'l, 'l> Node<'a> {
     }
 
     fn many(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, Vec<Box<Self>>> {
-        repeat(0.., |i: &mut _| Self::one(i, false)).parse_next(i)
+        let mut nodes: Vec<_> = repeat(0.., |i: &mut _| Self::one(i, false)).parse_next(i)?;
+        // When recovering from errors, only the tags that end or continue the block end it.
+        while !ends_block(i) && skip_unexpected_tag(i)? {
+            let mut more: Vec<_> = repeat(0.., |i: &mut _| Self::one(i, false)).parse_next(i)?;
+            nodes.append(&mut more);
+        }
+        Ok(nodes)
     }
 
     fn one(i: &mut InputStream<'a, 'l>, allow_extends: bool) -> ParseResult<'a, Box<Self>> {
-        let node = alt((Lit::parse, Comment::parse, Self::expr, Self::parse)).parse_next(i)?;
-        if !allow_extends && let Node::Extends(node) = &*node {
-            return cut_error!("`extends` block must come first in a template", node.span());
+        let offset = i.current_token_start();
+        if let Some(end) = i.state.skipped(offset) {
+            i.next_slice(end - offset);
+            return Ok(Self::placeholder(offset..end));
+        }
+
+        let start = i.checkpoint();
+        let result = alt((Lit::parse, Comment::parse, Self::expr, Self::parse))
+            .parse_next(i)
+            .and_then(|node| {
+                if !allow_extends && let Node::Extends(node) = &*node {
+                    return cut_error!(
+                        "`extends` block must come first in a template",
+                        node.span()
+                    );
+                }
+                Ok(node)
+            });
+        match result {
+            Err(ErrMode::Cut(err)) if i.state.recovers() => {
+                i.reset(&start);
+                let result = Self::recover(i, err);
+                if result.is_err() {
+                    // Otherwise every enclosing block would try to recover from the error, too.
+                    i.state.abort_recovery();
+                }
+                result
+            }
+            result => result,
+        }
+    }
+
+    /// Skips the tag at the start of `i` that contains the error `err`, and if it opens a block,
+    /// the whole block, so the parser can go on after it. A placeholder node is returned.
+    #[cold]
+    fn recover(i: &mut InputStream<'a, 'l>, err: ErrorContext) -> ParseResult<'a, Box<Self>> {
+        // The same error that the parser returns without recovery.
+        let mut err = err.cut();
+        try_assign_fallback_error(i, &mut unexpected_tag, &mut err);
+        let (ErrMode::Backtrack(err) | ErrMode::Cut(err)) = err else {
+            return Err(err);
+        };
+
+        // Skipping a block parses its content, so it counts as a nesting level, too.
+        let Ok(_level) = i.state.level.nest(i) else {
+            return Err(err.cut());
+        };
+        let start = i.current_token_start();
+        let tag = skip_tag(i, err)?;
+        if cst::is_block_opener(&tag) {
+            let is_loop = tag.keyword() == Some("for");
+            if is_loop {
+                i.state.enter_loop();
+            }
+            let result = Self::skip_block(i, tag.keyword().unwrap_or_default());
+            if is_loop {
+                i.state.leave_loop();
+            }
+            result?;
+        }
+
+        let span = start..i.current_token_start();
+        i.state.push_skipped(span.clone());
+        Ok(Self::placeholder(span))
+    }
+
+    /// The node that replaces a skipped node.
+    fn placeholder(span: Range<usize>) -> Box<Self> {
+        let comment = Comment {
+            ws: Ws(None, None),
+            content: "",
+        };
+        Box::new(Self::Comment(WithSpan::new(comment, span)))
+    }
+
+    /// Skips the content of a block whose opening tag could not be parsed, including the tags
+    /// that continue the block, up to and including its end tag.
+    fn skip_block(i: &mut InputStream<'a, 'l>, keyword: &str) -> ParseResult<'a, ()> {
+        loop {
+            Self::many(i)?;
+            let Some(tag) = cst::leading_tag(i.peek_finish(), &i.state.syntax) else {
+                return Ok(());
+            };
+            match tag.keyword() {
+                Some(end) if end.strip_prefix("end") == Some(keyword) => {
+                    i.next_slice(tag.range().len());
+                    return Ok(());
+                }
+                Some(end) if end.starts_with("end") => {
+                    if !skip_unexpected_tag(i)? {
+                        i.next_slice(tag.range().len());
+                    }
+                }
+                Some("elif" | "else" | "when") => {
+                    i.next_slice(tag.range().len());
+                }
+                _ => return Ok(()),
+            }
         }
-        Ok(node)
     }
 
     fn parse(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, Box<Self>> {
@@ -112,6 +221,7 @@ impl<'a: 'l, 'l> Node<'a> {
             "match" => Match::parse,
             "mut" => Compound::parse,
             "raw" => Raw::parse,
+            "with_values" => WithValues::parse,
             _ => {
                 i.reset(&start);
                 return fail.parse_next(i);
@@ -214,6 +324,7 @@ impl<'a: 'l, 'l> Node<'a> {
             Self::Break(span) => span.span,
             Self::Continue(span) => span.span,
             Self::FilterBlock(span) => span.span,
+            Self::WithValues(span) => span.span,
         }
     }
 }
@@ -223,32 +334,6 @@ fn parse_with_unexpected_fallback<'a: 'l, 'l, O>(
     mut parser: impl ModalParser<InputStream<'a, 'l>, O, ErrorContext>,
     mut unexpected_parser: impl FnMut(&mut InputStream<'a, 'l>) -> ParseResult<'a, ()>,
 ) -> impl ModalParser<InputStream<'a, 'l>, O, ErrorContext> {
-    #[cold]
-    #[inline(never)]
-    fn try_assign_fallback_error<'a: 'l, 'l>(
-        i: &mut InputStream<'a, 'l>,
-        unexpected_parser: &mut dyn FnMut(&mut InputStream<'a, 'l>) -> ParseResult<'a, ()>,
-        err: &mut ErrMode<ErrorContext>,
-    ) {
-        let (ErrMode::Backtrack(err_ctx) | ErrMode::Cut(err_ctx)) = &err else {
-            return;
-        };
-        if err_ctx.message.is_some() {
-            return;
-        }
-
-        let checkpoint = i.checkpoint();
-        i.input.reset_to_start();
-        if take::<_, _, ()>(err_ctx.span.start).parse_next(i).is_ok()
-            && let Err(better_err) = opt(unexpected_parser).parse_next(i)
-            && let ErrMode::Backtrack(better_ctx) | ErrMode::Cut(better_ctx) = &better_err
-            && better_ctx.message.is_some()
-        {
-            *err = better_err;
-        }
-        i.reset(&checkpoint);
-    }
-
     move |i: &mut InputStream<'a, 'l>| {
         let mut result = parser.parse_next(i);
         if let Err(err) = &mut result {
@@ -258,6 +343,32 @@ fn parse_with_unexpected_fallback<'a: 'l, 'l, O>(
     }
 }
 
+#[cold]
+#[inline(never)]
+fn try_assign_fallback_error<'a: 'l, 'l>(
+    i: &mut InputStream<'a, 'l>,
+    unexpected_parser: &mut dyn FnMut(&mut InputStream<'a, 'l>) -> ParseResult<'a, ()>,
+    err: &mut ErrMode<ErrorContext>,
+) {
+    let (ErrMode::Backtrack(err_ctx) | ErrMode::Cut(err_ctx)) = &err else {
+        return;
+    };
+    if err_ctx.message.is_some() {
+        return;
+    }
+
+    let checkpoint = i.checkpoint();
+    i.input.reset_to_start();
+    if take::<_, _, ()>(err_ctx.span.start).parse_next(i).is_ok()
+        && let Err(better_err) = opt(unexpected_parser).parse_next(i)
+        && let ErrMode::Backtrack(better_ctx) | ErrMode::Cut(better_ctx) = &better_err
+        && better_ctx.message.is_some()
+    {
+        *err = better_err;
+    }
+    i.reset(&checkpoint);
+}
+
 #[inline]
 fn cut_node<'a: 'l, 'l, O>(
     kind: Option<&'static str>,
@@ -266,6 +377,59 @@ fn cut_node<'a: 'l, 'l, O>(
     parse_with_unexpected_fallback(cut_err(inner), move |i: &mut _| unexpected_raw_tag(kind, i))
 }
 
+/// Returns `true` if `i` starts with a tag that ends or continues a block.
+fn ends_block<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> bool {
+    let Some(tag) = cst::leading_tag(i.peek_finish(), &i.state.syntax) else {
+        return false;
+    };
+    matches!(
+        tag.keyword(),
+        Some(keyword) if keyword.starts_with("end") || matches!(keyword, "elif" | "else" | "when"),
+    )
+}
+
+/// When recovering from errors, records the error of the unexpected block tag at the start of
+/// `i`, and skips the tag. Returns `false` if nothing was skipped.
+fn skip_unexpected_tag<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, bool> {
+    if !i.state.recovers() {
+        return Ok(false);
+    }
+    let start = i.checkpoint();
+    let result = unexpected_tag(i);
+    i.reset(&start);
+    let err = match result {
+        Err(ErrMode::Cut(err)) if err.message.is_some() => err,
+        _ => return Ok(false),
+    };
+    let tag = skip_tag(i, err)?;
+    // Like in a `match` block, a `when` branch can be closed by an `endwhen` tag.
+    if tag.keyword() == Some("when") {
+        Node::many(i)?;
+        if let Some(tag) = cst::leading_tag(i.peek_finish(), &i.state.syntax)
+            && tag.keyword() == Some("endwhen")
+        {
+            i.next_slice(tag.range().len());
+        }
+    }
+    Ok(true)
+}
+
+/// Records the error `err` in the tag at the start of `i`, and skips the tag. If the tag is not
+/// terminated, `err` is returned.
+fn skip_tag<'a: 'l, 'l>(
+    i: &mut InputStream<'a, 'l>,
+    err: ErrorContext,
+) -> ParseResult<'a, cst::Tag<'a>> {
+    match cst::leading_tag(i.peek_finish(), &i.state.syntax) {
+        Some(tag) if tag.is_terminated() => {
+            i.state.push_error(err);
+            i.next_slice(tag.range().len());
+            Ok(tag)
+        }
+        _ => Err(err.cut()),
+    }
+}
+
 fn unexpected_tag<'a: 'l, 'l>(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, ()> {
     (block_start, opt(Whitespace::parse), |i: &mut _| {
         unexpected_raw_tag(None, i)
@@ -504,7 +668,7 @@ impl Whitespace {
         }
     }
 
-    fn parse_byte(b: u8) -> Option<Whitespace> {
+    pub(crate) fn parse_byte(b: u8) -> Option<Whitespace> {
         match b {
             b'+' => Some(Self::Preserve),
             b'-' => Some(Self::Suppress),
@@ -616,7 +780,7 @@ impl<'a: 'l, 'l> Loop<'a> {
                 cut_node(
                     Some("for"),
                     (
-                        ws(|i: &mut _| Expr::parse(i, true)),
+                        ws(|i: &mut _| Expr::parse_without_conditional(i, true)),
                         opt(if_cond),
                         opt(Whitespace::parse),
                         block_end,
@@ -842,6 +1006,77 @@ impl<'a: 'l, 'l> FilterBlock<'a> {
     }
 }
 
+/// `{% with_values "key" = expr, ... %}...{% endwith_values %}`: adds a layer of runtime values
+/// for the nodes inside the block
+#[derive(Debug, PartialEq)]
+pub struct WithValues<'a> {
+    pub ws1: Ws,
+    pub values: Vec<(WithSpan<&'a str>, WithSpan<Box<Expr<'a>>>)>,
+    pub nodes: Vec<Box<Node<'a>>>,
+    pub ws2: Ws,
+}
+
+impl<'a: 'l, 'l> WithValues<'a> {
+    fn parse(i: &mut InputStream<'a, 'l>) -> ParseResult<'a, Box<Node<'a>>> {
+        let (pws1, span) =
+            (opt(Whitespace::parse), ws(keyword("with_values").span())).parse_next(i)?;
+        let span = Span::new(span);
+
+        let value = |i: &mut _| {
+            let ((key, key_span), _, value) = (
+                ws(str_lit_without_prefix.with_span()),
+                '=',
+                ws(|i: &mut _| Expr::parse(i, false)),
+            )
+                .parse_next(i)?;
+            Ok((WithSpan::new(key, key_span), value))
+        };
+
+        let mut p = (
+            cut_node(
+                Some("with_values"),
+                (
+                    terminated(separated(1.., value, ','), opt(',')),
+                    opt(Whitespace::parse),
+                    block_end,
+                ),
+            ),
+            cut_node(Some("with_values"), Node::many),
+            cut_node(
+                Some("with_values"),
+                (
+                    |i: &mut _| check_block_start(i, span, "with_values", "endwith_values"),
+                    opt(Whitespace::parse),
+                    end_node("with_values", "endwith_values"),
+                    opt(Whitespace::parse),
+                ),
+            ),
+        );
+        let ((values, nws1, _), nodes, (_, pws2, _, nws2)) = p.parse_next(i)?;
+        let values: Vec<_> = values;
+
+        let mut names = HashSet::default();
+        for (key, _) in &values {
+            if !names.insert(key.inner) {
+                return cut_error!(
+                    format!("duplicated value `{}`", key.escape_debug()),
+                    key.span
+                );
+            }
+        }
+
+        Ok(Box::new(Node::WithValues(WithSpan::new(
+            Self {
+                ws1: Ws(pws1, nws1),
+                values,
+                nodes,
+                ws2: Ws(pws2, nws2),
+            },
+            span,
+        ))))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Import<'a> {
     pub ws: Ws,
@@ -1191,6 +1426,8 @@ impl<'a: 'l, 'l> Raw<'a> {
                     i.reset(&i_before_nws); // `block_start` might start with the `nws` character
                     continue;
                 }
+                // `{% endraw` was parsed without `block_start`, but the caller parses its `%}`.
+                i.state.open_tag(span.end, cst::TagKind::Block);
 
                 return Ok((
                     Ws(pws, nws),
@@ -1601,6 +1838,7 @@ impl<'a: 'l, 'l> Comment<'a> {
         }
 
         let (content, span) = comment.with_span().parse_next(i)?;
+        i.state.push_tag(span.clone(), cst::TagKind::Comment);
         let ws = match *content.as_bytes() {
             [b'-' | b'+' | b'~'] => {
                 return cut_error!(
@@ -2,11 +2,12 @@ use std::cell::Cell;
 
 use winnow::{LocatingSlice, Parser};
 
-use crate::expr::BinOp;
-use crate::node::{Let, Lit, Raw, Whitespace, Ws};
+use crate::cst::{Cst, CstNode, FormatOptions, TagKind, format};
+use crate::expr::{BinOp, Conditional};
+use crate::node::{Let, Lit, Raw, Whitespace, WithValues, Ws};
 use crate::{
     Ast, Expr, Filter, InnerSyntax, InputStream, LetValueOrBlock, Level, Node, Num, PathComponent,
-    PathOrIdentifier, State, StrLit, Syntax, SyntaxBuilder, Target, WithSpan,
+    PathOrIdentifier, State, StrLit, Syntax, SyntaxBuilder, Target, Test, WithSpan,
 };
 
 fn as_path<'a>(path: &'a [&'a str]) -> Vec<PathComponent<'a>> {
@@ -65,6 +66,114 @@ fn call<'a>(
     })))
 }
 
+#[test]
+fn test_parse_test() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ x is even }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        [Box::new(Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Box::new(Expr::Test(Test {
+                name: PathOrIdentifier::Identifier(WithSpan::no_span("even")),
+                arguments: vec![WithSpan::no_span(Box::new(Expr::Var("x")))],
+                negated: false,
+            }))),
+        ))],
+    );
+    assert_eq!(
+        Ast::from_str("{{ x is not divisibleby(3) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        [Box::new(Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Box::new(Expr::Test(Test {
+                name: PathOrIdentifier::Identifier(WithSpan::no_span("divisibleby")),
+                arguments: vec![WithSpan::no_span(Box::new(Expr::Var("x"))), int_lit("3")],
+                negated: true,
+            }))),
+        ))],
+    );
+    assert_eq!(
+        Ast::from_str("{{ x is not defined }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        [Box::new(Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Box::new(Expr::IsNotDefined("x"))),
+        ))],
+    );
+    assert!(Ast::from_str("{{ x is }}", None, &syntax).is_err());
+    assert!(
+        Ast::from_str("{{ x is blue.red }}", None, &syntax)
+            .unwrap_err()
+            .to_string()
+            .contains("found a field access"),
+    );
+    assert!(
+        Ast::from_str("{{ x is defined(y) }}", None, &syntax)
+            .unwrap_err()
+            .to_string()
+            .contains("`defined` test does not have any arguments"),
+    );
+    // A range is not a field access.
+    assert!(Ast::from_str("{{ x is even..3 }}", None, &syntax).is_ok());
+}
+
+#[test]
+fn test_parse_conditional() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ a if b else c if d }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        [Box::new(Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Box::new(Expr::Conditional(Conditional {
+                cond: WithSpan::no_span(Box::new(Expr::Var("b"))),
+                then: WithSpan::no_span(Box::new(Expr::Var("a"))),
+                otherwise: Some(WithSpan::no_span(Box::new(Expr::Conditional(
+                    Conditional {
+                        cond: WithSpan::no_span(Box::new(Expr::Var("d"))),
+                        then: WithSpan::no_span(Box::new(Expr::Var("c"))),
+                        otherwise: None,
+                    }
+                )))),
+            }))),
+        ))],
+    );
+    assert_eq!(
+        Ast::from_str("{{ 1 + 2 if a || b else 3 }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        [Box::new(Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Box::new(Expr::Conditional(Conditional {
+                cond: bin_op(
+                    "||",
+                    WithSpan::no_span(Box::new(Expr::Var("a"))),
+                    WithSpan::no_span(Box::new(Expr::Var("b"))),
+                ),
+                then: bin_op("+", int_lit("1"), int_lit("2")),
+                otherwise: Some(int_lit("3")),
+            }))),
+        ))],
+    );
+    // The span of the conditional starts at its `then` expression, not at the `if` keyword.
+    let source = "{{ 1 + 2 if a else 3 }}";
+    let ast = Ast::from_str(source, None, &syntax).unwrap();
+    let Node::Expr(_, expr) = &*ast.nodes[0] else {
+        panic!("expected an expression, got {:?}", ast.nodes[0]);
+    };
+    assert_eq!(
+        expr.span().as_infix_of(source).map(str::trim_end),
+        Some("1 + 2 if a else 3"),
+    );
+    assert!(Ast::from_str("{{ a if }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ a if b else }}", None, &syntax).is_err());
+}
+
 #[test]
 fn test_parse_filter() {
     let syntax = Syntax::default();
@@ -1311,6 +1420,8 @@ fn underscore_is_an_identifier() {
         syntax: Syntax::default(),
         loop_depth: Cell::new(0),
         level: Level::default(),
+        recovery: None,
+        tag_spans: None,
     };
     let mut input = InputStream {
         input: LocatingSlice::new("_"),
@@ -1779,3 +1890,441 @@ fn test_expr_end_catastrophic_backtracking() {
         .is_err()
     );
 }
+
+#[test]
+fn test_with_values() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            r#"{% with_values "theme" = "dark", "count" = 1 %}{{ x }}{% endwith_values -%}"#,
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        [Box::new(Node::WithValues(WithSpan::no_span(WithValues {
+            ws1: Ws(None, None),
+            values: vec![
+                (
+                    WithSpan::no_span("theme"),
+                    WithSpan::no_span(Box::new(Expr::StrLit(StrLit {
+                        content: "dark",
+                        prefix: None,
+                        contains_null: false,
+                        contains_unicode_character: false,
+                        contains_unicode_escape: false,
+                        contains_high_ascii: false,
+                    }))),
+                ),
+                (WithSpan::no_span("count"), int_lit("1")),
+            ],
+            nodes: vec![Box::new(Node::Expr(
+                Ws(None, None),
+                WithSpan::no_span(Box::new(Expr::Var("x"))),
+            ))],
+            ws2: Ws(None, Some(Whitespace::Suppress)),
+        })))],
+    );
+
+    let err = Ast::from_str(
+        r#"{% with_values "a" = 1, "a" = 2 %}{% endwith_values %}"#,
+        None,
+        &syntax,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("duplicated value `a`"), "{err}");
+    assert!(Ast::from_str("{% with_values %}{% endwith_values %}", None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{% with_values "a" = 1 %}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_cst_round_trip() {
+    let syntax = Syntax::default();
+    for src in [
+        "",
+        "hello",
+        "{{ a }}{{b}}{{- c -}}{{+ d ~}}",
+        "{%if x%}a{% elif y %}b{%- else -%}c{%endif%}",
+        "{% for x in y %}{{ x }}{% else %}none{% endfor %}",
+        "{% match x %}{% when Some(y) %}{{ y }}{% else %}{% endmatch %}",
+        "{# a {# nested #} comment #}{#- x -#}",
+        "{% raw %}{{ not a tag }}{% if %}{% endraw %}",
+        r#"{{ "%}}" }}{% let x = "}}" %}{% let y %}1{% endlet %}"#,
+        "{% if x %}{% for y in z %}unterminated",
+        "{% if x %}{% for y in z %}{% endif %}",
+        "{{ unterminated",
+        "{{ aä-}}ä{{..{Ւ{",
+        "{% endif %}{% else %}",
+        "{% macro m(a) %}{{ a }}{% endmacro %}{% call m(1) %}{% endcall %}",
+    ] {
+        let cst = Cst::parse(src, &syntax);
+        assert_eq!(cst.to_string(), src);
+    }
+}
+
+#[test]
+fn test_cst_non_ascii() {
+    // The scanner steps through the source byte by byte, it must not slice inside of a character.
+    let syntax = Syntax::default();
+    for src in [
+        "{% if café %}ü{% endif %}",
+        "{{ \"é\" }}{{ ü }}{{ x ü}}",
+        "{% if ä -%}ö{%- endif %}{{ unterminated é",
+    ] {
+        assert_eq!(Cst::parse(src, &syntax).to_string(), src);
+    }
+    assert_eq!(
+        format("{%if café%}ü{%endif%}", &syntax, &FormatOptions::default()),
+        "{% if café %}ü{% endif %}",
+    );
+}
+
+#[test]
+fn test_cst_tree() {
+    let src = "{% if a %}x{% else %}{{- y }}{% endif %}{% for i in j %}";
+    let cst = Cst::parse(src, &Syntax::default());
+    let [CstNode::Block(if_block), CstNode::Block(for_block)] = cst.nodes.as_slice() else {
+        panic!("unexpected tree: {cst:?}");
+    };
+    assert_eq!(if_block.open.keyword(), Some("if"));
+    assert_eq!(if_block.open.content, "if a");
+    assert_eq!(if_block.body, [CstNode::Text("x")]);
+    assert_eq!(if_block.branches.len(), 1);
+    let [CstNode::Tag(expr)] = if_block.branches[0].body.as_slice() else {
+        panic!("unexpected branch: {:?}", if_block.branches[0]);
+    };
+    assert_eq!(expr.kind, TagKind::Expr);
+    assert_eq!(expr.ws_start, "-");
+    assert_eq!(expr.content, "y");
+    assert_eq!(expr.range(), 21..29);
+    assert_eq!(if_block.close.map(|t| t.content), Some("endif"));
+    assert_eq!(for_block.close, None);
+}
+
+#[test]
+fn test_cst_format() {
+    let syntax = Syntax::default();
+    let options = FormatOptions::default();
+    for (src, expected) in [
+        ("{{a}}{%-if x-%}{%endif%}", "{{ a }}{%- if x -%}{% endif %}"),
+        // The indentation of tags is rendered, so it is kept.
+        (
+            "{% if a %}\n{% for b in c %}\n<p>{{b}}</p>\n  {% endfor %}\n    {% else %}\n{#x#}\n{% endif %}\n",
+            "{% if a %}\n{% for b in c %}\n<p>{{ b }}</p>\n  {% endfor %}\n    {% else %}\n{#x#}\n{% endif %}\n",
+        ),
+        // ... unless it is suppressed or minimized, before the tag or after the previous one.
+        (
+            "{% if a %}\n{%- for b in c %}\n<p>{{b}}</p>\n  {%~ endfor -%}\n    {% else %}\n{#x#}\n  {%+ endif %}\n",
+            "{% if a %}\n    {%- for b in c %}\n<p>{{ b }}</p>\n    {%~ endfor -%}\n{% else %}\n{#x#}\n  {%+ endif %}\n",
+        ),
+        // A minimized space without newline is not removed.
+        (
+            "{% if a %}\n{{ b }}  {%~ endif %}",
+            "{% if a %}\n{{ b }}  {%~ endif %}",
+        ),
+        (
+            "{% raw %}\n{{a}}\n   {%if%}\n{% endraw %}",
+            "{% raw %}\n{{a}}\n   {%if%}\n{% endraw %}",
+        ),
+        ("{{ a\n}}{{ unterminated", "{{ a\n}}{{ unterminated"),
+    ] {
+        let formatted = format(src, &syntax, &options);
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, &syntax, &options), formatted);
+    }
+
+    let options = FormatOptions {
+        whitespace: Whitespace::Suppress,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format(
+            "{% if a %}\n{% for b in c %}\n<p>{{b}}</p>\n  {% endfor %}\n    {% else %}\n{#x#}\n{%+ endif %}\n",
+            &syntax,
+            &options,
+        ),
+        "{% if a %}\n    {% for b in c %}\n<p>{{ b }}</p>\n    {% endfor %}\n{% else %}\n    {#x#}\n{%+ endif %}\n",
+    );
+
+    let syntax = SyntaxBuilder {
+        block_start: Some("<%"),
+        block_end: Some("%>"),
+        expr_start: Some("[["),
+        expr_end: Some("]]"),
+        ..SyntaxBuilder::default()
+    }
+    .to_syntax()
+    .unwrap();
+    let src = "<%if a%>\n[[a]]{{b}}\n<%endif%>";
+    assert_eq!(Cst::parse(src, &syntax).to_string(), src);
+    assert_eq!(
+        format(src, &syntax, &options),
+        "<% if a %>\n    [[ a ]]{{b}}\n<% endif %>"
+    );
+}
+
+#[test]
+fn test_cst_literals_in_tags() {
+    // Delimiters and quotes in string and character literals don't end the tags, neither in valid
+    // templates, whose tags are found by the parser, nor in templates with syntax errors.
+    let syntax = Syntax::default();
+    for (src, suffix) in [("", None), ("{% include %}", Some("include"))] {
+        let src = format!(
+            r##"{{{{ f!(r"\") }}}}{{{{ '"' }}}}{{{{ f!(r#""%}}"#) }}}}{{% let x = '}}' %}}{{{{ x }}}}{src}"##
+        );
+        assert_eq!(Ast::from_str(&src, None, &syntax).is_ok(), suffix.is_none());
+        let cst = Cst::parse(&src, &syntax);
+        assert_eq!(cst.to_string(), src);
+        let contents: Vec<_> = cst
+            .nodes
+            .iter()
+            .map(|node| match node {
+                CstNode::Tag(tag) => tag.content,
+                node => panic!("unexpected node {node:?}"),
+            })
+            .collect();
+        let expected = [
+            r#"f!(r"\")"#,
+            r#"'"'"#,
+            r##"f!(r#""%}"#)"##,
+            "let x = '}'",
+            "x",
+        ];
+        assert_eq!(contents[..5], expected);
+        assert_eq!(contents.get(5).copied(), suffix);
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    #[track_caller]
+    fn offsets(src: &str) -> Vec<usize> {
+        let errors = Ast::from_str_with_recovery(src, None, &Syntax::default()).unwrap_err();
+        assert_eq!(
+            errors[0].offset,
+            Ast::from_str(src, None, &Syntax::default())
+                .unwrap_err()
+                .offset,
+        );
+        errors.iter().map(|err| err.offset).collect()
+    }
+
+    assert!(Ast::from_str_with_recovery("{{ a }}", None, &Syntax::default()).is_ok());
+    assert_eq!(offsets("{{ a + }}\n{{ b }}\n{{ c( }}"), [7, 22]);
+    assert_eq!(
+        offsets("{% if a + %}\n{{ 1 + }}\n{% for in x %}{% endfor %}\n{% endif %}"),
+        [10, 20, 30],
+    );
+    assert_eq!(
+        offsets("{% match x %}{% when %}{% endmatch %}{% include %}"),
+        [21, 48],
+    );
+    assert_eq!(offsets("{% let %}{% endlet %}{{ ( }}"), [7, 26]);
+    assert_eq!(offsets("{{ a + }}{{ b"), [7, 9]);
+    assert_eq!(
+        offsets("{% endif %}{% if a %}{{ b + }}{% endfor %}{% endif %}"),
+        [3, 28, 33]
+    );
+
+    // Every erroneous block is skipped only once, even if the enclosing blocks are skipped, too.
+    let src = "{% if a %}".repeat(100) + &"{% else x %}{% endif %}".repeat(100);
+    assert_eq!(offsets(&src).len(), 100);
+    // Skipping the nodes that are too deeply nested would only hit the limit again.
+    assert_eq!(offsets(&"{% if a %}".repeat(1000)).len(), 1);
+}
+
+#[test]
+fn test_visit() {
+    use crate::visit::{Visit, VisitMut, walk_expr, walk_expr_mut};
+
+    #[derive(Default)]
+    struct Vars<'a>(Vec<&'a str>);
+
+    impl<'a> Visit<'a> for Vars<'a> {
+        fn visit_expr(&mut self, expr: &WithSpan<Box<Expr<'a>>>) {
+            if let Expr::Var(name) = ***expr {
+                self.0.push(name);
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    struct Rename;
+
+    impl<'a> VisitMut<'a> for Rename {
+        fn visit_expr_mut(&mut self, expr: &mut WithSpan<Box<Expr<'a>>>) {
+            if let Expr::Var(name) = &mut ***expr {
+                *name = "renamed";
+            }
+            walk_expr_mut(self, expr);
+        }
+    }
+
+    let src = r#"{{ a }}
+{%- if let Some(x) = b && c(d) -%}{{ e|f(g) }}{% else if h[i] %}{% endif %}
+{%- for x in j if k %}{% let l = m %}{% else %}{% call n(o) %}{{ p }}{% endcall %}{% endfor %}
+{%- match q %}{% when Some(r) %}{{ s.t + u }}{% endmatch %}
+{%- macro v(w = x) %}{% filter y(z) %}{{ (aa, [bb; 2]) }}{% endfilter %}{% endmacro %}
+{%- block cc %}{% with_values "k" = dd %}{{ ee? }}{% endwith_values %}{% endblock %}"#;
+    let mut ast = Ast::from_str(src, None, &Syntax::default()).unwrap();
+
+    let mut vars = Vars::default();
+    vars.visit_nodes(ast.nodes());
+    assert_eq!(
+        vars.0,
+        [
+            "a", "b", "c", "d", "e", "g", "h", "i", "j", "k", "m", "o", "p", "q", "s", "u", "x",
+            "z", "aa", "bb", "dd", "ee",
+        ],
+    );
+
+    Rename.visit_nodes_mut(ast.nodes_mut());
+    let mut vars = Vars::default();
+    vars.visit_nodes(ast.nodes());
+    assert_eq!(vars.0.len(), 22);
+    assert!(vars.0.iter().all(|name| *name == "renamed"));
+}
+
+#[test]
+fn test_lexer() {
+    use crate::lexer::{TokenKind as K, tokenize};
+
+    #[track_caller]
+    fn check(src: &str, syntax: &Syntax<'_>, expected: &[(K, &str)]) {
+        let tokens = tokenize(src, syntax);
+        let tokens: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.kind, &src[token.span.clone()]))
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    let syntax = Syntax::default();
+    check(
+        r#"<p>{%- if x.len() >= 1.5 -%}{{ "a\"}}"|upper ~ 'b' }}{# c #}{% endif %}"#,
+        &syntax,
+        &[
+            (K::Text, "<p>"),
+            (K::BlockStart, "{%"),
+            (K::WhitespaceControl, "-"),
+            (K::Whitespace, " "),
+            (K::Keyword, "if"),
+            (K::Whitespace, " "),
+            (K::Identifier, "x"),
+            (K::Punctuation, "."),
+            (K::Identifier, "len"),
+            (K::Punctuation, "("),
+            (K::Punctuation, ")"),
+            (K::Whitespace, " "),
+            (K::Operator, ">="),
+            (K::Whitespace, " "),
+            (K::NumLit, "1.5"),
+            (K::Whitespace, " "),
+            (K::WhitespaceControl, "-"),
+            (K::BlockEnd, "%}"),
+            (K::ExprStart, "{{"),
+            (K::Whitespace, " "),
+            (K::StrLit, r#""a\"}}""#),
+            (K::Operator, "|"),
+            (K::Filter, "upper"),
+            (K::Whitespace, " "),
+            (K::Operator, "~"),
+            (K::Whitespace, " "),
+            (K::CharLit, "'b'"),
+            (K::Whitespace, " "),
+            (K::ExprEnd, "}}"),
+            (K::Comment, "{# c #}"),
+            (K::BlockStart, "{%"),
+            (K::Whitespace, " "),
+            (K::Keyword, "endif"),
+            (K::Whitespace, " "),
+            (K::BlockEnd, "%}"),
+        ],
+    );
+    check(
+        "{% for (a, b) in 0..n %}{% raw %}{{ x }}{% endraw %}",
+        &syntax,
+        &[
+            (K::BlockStart, "{%"),
+            (K::Whitespace, " "),
+            (K::Keyword, "for"),
+            (K::Whitespace, " "),
+            (K::Punctuation, "("),
+            (K::Identifier, "a"),
+            (K::Punctuation, ","),
+            (K::Whitespace, " "),
+            (K::Identifier, "b"),
+            (K::Punctuation, ")"),
+            (K::Whitespace, " "),
+            (K::Keyword, "in"),
+            (K::Whitespace, " "),
+            (K::NumLit, "0"),
+            (K::Operator, ".."),
+            (K::Identifier, "n"),
+            (K::Whitespace, " "),
+            (K::BlockEnd, "%}"),
+            (K::BlockStart, "{%"),
+            (K::Whitespace, " "),
+            (K::Keyword, "raw"),
+            (K::Whitespace, " "),
+            (K::BlockEnd, "%}"),
+            (K::Text, "{{ x }}"),
+            (K::BlockStart, "{%"),
+            (K::Whitespace, " "),
+            (K::Keyword, "endraw"),
+            (K::Whitespace, " "),
+            (K::BlockEnd, "%}"),
+        ],
+    );
+    // Incomplete input.
+    check(
+        r#"a {{ b| "c"#,
+        &syntax,
+        &[
+            (K::Text, "a "),
+            (K::ExprStart, "{{"),
+            (K::Whitespace, " "),
+            (K::Identifier, "b"),
+            (K::Operator, "|"),
+            (K::Whitespace, " "),
+            (K::StrLit, "\"c"),
+        ],
+    );
+    check(
+        "{{ 'x }}",
+        &syntax,
+        &[
+            (K::ExprStart, "{{"),
+            (K::Whitespace, " "),
+            (K::Error, "'"),
+            (K::Identifier, "x"),
+            (K::Whitespace, " "),
+            (K::ExprEnd, "}}"),
+        ],
+    );
+
+    let syntax = SyntaxBuilder {
+        block_start: Some("<%"),
+        block_end: Some("%>"),
+        expr_start: Some("${"),
+        expr_end: Some("}$"),
+        ..SyntaxBuilder::default()
+    }
+    .to_syntax()
+    .unwrap();
+    check(
+        "<%if true%>${x}${{ y }}",
+        &syntax,
+        &[
+            (K::BlockStart, "<%"),
+            (K::Keyword, "if"),
+            (K::Whitespace, " "),
+            (K::BoolLit, "true"),
+            (K::BlockEnd, "%>"),
+            (K::ExprStart, "${"),
+            (K::Identifier, "x"),
+            (K::ExprEnd, "}$"),
+            (K::Text, "{{ y }}"),
+        ],
+    );
+}
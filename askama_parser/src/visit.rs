@@ -0,0 +1,712 @@
+//! Traversal of the template AST.
+//!
+//! The [`Visit`] trait walks a shared AST, the [`VisitMut`] trait walks a mutable one. Each
+//! method of the traits has a default implementation, that calls the matching `walk_*` function
+//! of this module to visit the children of the item. If you override a method, you can call the
+//! `walk_*` function yourself to continue the traversal into the children.
+//!
+//! ```
+//! use askama_parser::visit::{Visit, walk_node};
+//! use askama_parser::{Ast, Node, Syntax};
+//!
+//! #[derive(Default)]
+//! struct Includes<'a>(Vec<&'a str>);
+//!
+//! impl<'a> Visit<'a> for Includes<'a> {
+//!     fn visit_node(&mut self, node: &Node<'a>) {
+//!         if let Node::Include(include) = node {
+//!             self.0.push(include.path);
+//!         }
+//!         walk_node(self, node);
+//!     }
+//! }
+//!
+//! let source = r#"{% if a %}{% include "a.html" %}{% else %}{% include "b.html" %}{% endif %}"#;
+//! let ast = Ast::from_str(source, None, &Syntax::default()).unwrap();
+//! let mut includes = Includes::default();
+//! includes.visit_nodes(ast.nodes());
+//! assert_eq!(includes.0, ["a.html", "b.html"]);
+//! ```
+//!
+//! All `match`es in this module are exhaustive, so adding a variant to [`Node`] or [`Expr`]
+//! requires updating the traversal.
+
+use crate::expr::{BinOp, Call as CallExpr, Conditional, ExprStruct, Range};
+use crate::node::{
+    BlockDef, Call, Comment, Compound, Cond, CondTest, Declare, Extends, FilterBlock, If, Import,
+    Include, Let, Lit, Loop, Macro, MacroArg, Match, Raw, When, WithValues,
+};
+use crate::{Expr, Filter, LetValueOrBlock, Node, Target, Test, WithSpan};
+
+/// Visitor of a shared template AST.
+pub trait Visit<'a> {
+    fn visit_nodes(&mut self, nodes: &[Box<Node<'a>>]) {
+        walk_nodes(self, nodes);
+    }
+
+    fn visit_node(&mut self, node: &Node<'a>) {
+        walk_node(self, node);
+    }
+
+    fn visit_lit(&mut self, _lit: &WithSpan<Lit<'a>>) {}
+
+    fn visit_comment(&mut self, _comment: &WithSpan<Comment<'a>>) {}
+
+    fn visit_call(&mut self, call: &WithSpan<Call<'a>>) {
+        walk_call(self, call);
+    }
+
+    fn visit_let(&mut self, l: &WithSpan<Let<'a>>) {
+        walk_let(self, l);
+    }
+
+    fn visit_compound(&mut self, compound: &WithSpan<Compound<'a>>) {
+        walk_compound(self, compound);
+    }
+
+    fn visit_declare(&mut self, _declare: &WithSpan<Declare<'a>>) {}
+
+    fn visit_if(&mut self, i: &WithSpan<If<'a>>) {
+        walk_if(self, i);
+    }
+
+    fn visit_cond(&mut self, cond: &WithSpan<Cond<'a>>) {
+        walk_cond(self, cond);
+    }
+
+    fn visit_cond_test(&mut self, test: &WithSpan<CondTest<'a>>) {
+        walk_cond_test(self, test);
+    }
+
+    fn visit_match(&mut self, m: &WithSpan<Match<'a>>) {
+        walk_match(self, m);
+    }
+
+    fn visit_when(&mut self, when: &WithSpan<When<'a>>) {
+        walk_when(self, when);
+    }
+
+    fn visit_loop(&mut self, l: &WithSpan<Loop<'a>>) {
+        walk_loop(self, l);
+    }
+
+    fn visit_extends(&mut self, _extends: &WithSpan<Extends<'a>>) {}
+
+    fn visit_block_def(&mut self, block: &WithSpan<BlockDef<'a>>) {
+        walk_block_def(self, block);
+    }
+
+    fn visit_include(&mut self, _include: &WithSpan<Include<'a>>) {}
+
+    fn visit_import(&mut self, _import: &WithSpan<Import<'a>>) {}
+
+    fn visit_macro(&mut self, m: &WithSpan<Macro<'a>>) {
+        walk_macro(self, m);
+    }
+
+    fn visit_macro_arg(&mut self, arg: &MacroArg<'a>) {
+        walk_macro_arg(self, arg);
+    }
+
+    fn visit_raw(&mut self, _raw: &WithSpan<Raw<'a>>) {}
+
+    fn visit_filter_block(&mut self, filter: &WithSpan<FilterBlock<'a>>) {
+        walk_filter_block(self, filter);
+    }
+
+    fn visit_with_values(&mut self, with: &WithSpan<WithValues<'a>>) {
+        walk_with_values(self, with);
+    }
+
+    fn visit_expr(&mut self, expr: &WithSpan<Box<Expr<'a>>>) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_filter(&mut self, filter: &Filter<'a>) {
+        walk_filter(self, filter);
+    }
+
+    fn visit_test(&mut self, test: &Test<'a>) {
+        walk_test(self, test);
+    }
+
+    fn visit_target(&mut self, target: &Target<'a>) {
+        walk_target(self, target);
+    }
+}
+
+pub fn walk_nodes<'a, V: Visit<'a> + ?Sized>(v: &mut V, nodes: &[Box<Node<'a>>]) {
+    for node in nodes {
+        v.visit_node(node);
+    }
+}
+
+pub fn walk_node<'a, V: Visit<'a> + ?Sized>(v: &mut V, node: &Node<'a>) {
+    match node {
+        Node::Lit(lit) => v.visit_lit(lit),
+        Node::Comment(comment) => v.visit_comment(comment),
+        Node::Expr(_, expr) => v.visit_expr(expr),
+        Node::Call(call) => v.visit_call(call),
+        Node::Let(l) => v.visit_let(l),
+        Node::Compound(compound) => v.visit_compound(compound),
+        Node::Declare(declare) => v.visit_declare(declare),
+        Node::If(i) => v.visit_if(i),
+        Node::Match(m) => v.visit_match(m),
+        Node::Loop(l) => v.visit_loop(l),
+        Node::Extends(extends) => v.visit_extends(extends),
+        Node::BlockDef(block) => v.visit_block_def(block),
+        Node::Include(include) => v.visit_include(include),
+        Node::Import(import) => v.visit_import(import),
+        Node::Macro(m) => v.visit_macro(m),
+        Node::Raw(raw) => v.visit_raw(raw),
+        Node::Break(_) | Node::Continue(_) => {}
+        Node::FilterBlock(filter) => v.visit_filter_block(filter),
+        Node::WithValues(with) => v.visit_with_values(with),
+    }
+}
+
+pub fn walk_call<'a, V: Visit<'a> + ?Sized>(v: &mut V, call: &WithSpan<Call<'a>>) {
+    for arg in call.args.iter().flatten() {
+        v.visit_expr(arg);
+    }
+    v.visit_nodes(&call.nodes);
+}
+
+pub fn walk_let<'a, V: Visit<'a> + ?Sized>(v: &mut V, l: &WithSpan<Let<'a>>) {
+    v.visit_target(&l.var);
+    match &l.val {
+        LetValueOrBlock::Value(expr) => v.visit_expr(expr),
+        LetValueOrBlock::Block { nodes, .. } => v.visit_nodes(nodes),
+    }
+}
+
+pub fn walk_compound<'a, V: Visit<'a> + ?Sized>(v: &mut V, compound: &WithSpan<Compound<'a>>) {
+    v.visit_expr(&compound.op.lhs);
+    v.visit_expr(&compound.op.rhs);
+}
+
+pub fn walk_if<'a, V: Visit<'a> + ?Sized>(v: &mut V, i: &WithSpan<If<'a>>) {
+    for cond in &i.branches {
+        v.visit_cond(cond);
+    }
+}
+
+pub fn walk_cond<'a, V: Visit<'a> + ?Sized>(v: &mut V, cond: &WithSpan<Cond<'a>>) {
+    if let Some(test) = &cond.cond {
+        v.visit_cond_test(test);
+    }
+    v.visit_nodes(&cond.nodes);
+}
+
+pub fn walk_cond_test<'a, V: Visit<'a> + ?Sized>(v: &mut V, test: &WithSpan<CondTest<'a>>) {
+    if let Some(target) = &test.target {
+        v.visit_target(target);
+    }
+    v.visit_expr(&test.expr);
+}
+
+pub fn walk_match<'a, V: Visit<'a> + ?Sized>(v: &mut V, m: &WithSpan<Match<'a>>) {
+    v.visit_expr(&m.expr);
+    for arm in &m.arms {
+        v.visit_when(arm);
+    }
+}
+
+pub fn walk_when<'a, V: Visit<'a> + ?Sized>(v: &mut V, when: &WithSpan<When<'a>>) {
+    for target in &when.target {
+        v.visit_target(target);
+    }
+    v.visit_nodes(&when.nodes);
+}
+
+pub fn walk_loop<'a, V: Visit<'a> + ?Sized>(v: &mut V, l: &WithSpan<Loop<'a>>) {
+    v.visit_target(&l.var);
+    v.visit_expr(&l.iter);
+    if let Some(cond) = &l.cond {
+        v.visit_expr(cond);
+    }
+    v.visit_nodes(&l.body);
+    v.visit_nodes(&l.else_nodes);
+}
+
+pub fn walk_block_def<'a, V: Visit<'a> + ?Sized>(v: &mut V, block: &WithSpan<BlockDef<'a>>) {
+    v.visit_nodes(&block.nodes);
+}
+
+pub fn walk_macro<'a, V: Visit<'a> + ?Sized>(v: &mut V, m: &WithSpan<Macro<'a>>) {
+    for arg in &m.args {
+        v.visit_macro_arg(arg);
+    }
+    v.visit_nodes(&m.nodes);
+}
+
+pub fn walk_macro_arg<'a, V: Visit<'a> + ?Sized>(v: &mut V, arg: &MacroArg<'a>) {
+    if let Some(default) = &arg.default {
+        v.visit_expr(default);
+    }
+}
+
+pub fn walk_filter_block<'a, V: Visit<'a> + ?Sized>(v: &mut V, filter: &WithSpan<FilterBlock<'a>>) {
+    v.visit_filter(&filter.filters);
+    v.visit_nodes(&filter.nodes);
+}
+
+pub fn walk_with_values<'a, V: Visit<'a> + ?Sized>(v: &mut V, with: &WithSpan<WithValues<'a>>) {
+    for (_, value) in &with.values {
+        v.visit_expr(value);
+    }
+    v.visit_nodes(&with.nodes);
+}
+
+pub fn walk_expr<'a, V: Visit<'a> + ?Sized>(v: &mut V, expr: &WithSpan<Box<Expr<'a>>>) {
+    match &***expr {
+        Expr::BoolLit(_)
+        | Expr::NumLit(..)
+        | Expr::StrLit(_)
+        | Expr::CharLit(_)
+        | Expr::Var(_)
+        | Expr::Path(_)
+        | Expr::RustMacro(..)
+        | Expr::FilterSource
+        | Expr::IsDefined(_)
+        | Expr::IsNotDefined(_)
+        | Expr::ArgumentPlaceholder => {}
+        Expr::Array(exprs) | Expr::Tuple(exprs) | Expr::Concat(exprs) => {
+            for expr in exprs {
+                v.visit_expr(expr);
+            }
+        }
+        Expr::ArrayRepeat(lhs, rhs) | Expr::Index(lhs, rhs) => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::AssociatedItem(expr, _)
+        | Expr::As(expr, _)
+        | Expr::NamedArgument(_, expr)
+        | Expr::Unary(_, expr)
+        | Expr::Group(expr)
+        | Expr::Try(expr) => v.visit_expr(expr),
+        Expr::Filter(filter) => v.visit_filter(filter),
+        Expr::Test(test) => v.visit_test(test),
+        Expr::BinOp(BinOp { lhs, rhs, .. }) => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::Range(Range { lhs, rhs, .. }) => {
+            for expr in [lhs, rhs].into_iter().flatten() {
+                v.visit_expr(expr);
+            }
+        }
+        Expr::Conditional(Conditional {
+            cond,
+            then,
+            otherwise,
+        }) => {
+            v.visit_expr(cond);
+            v.visit_expr(then);
+            if let Some(otherwise) = otherwise {
+                v.visit_expr(otherwise);
+            }
+        }
+        Expr::Call(CallExpr { path, args, .. }) => {
+            v.visit_expr(path);
+            for arg in args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Struct(ExprStruct { path, fields, base }) => {
+            v.visit_expr(path);
+            for value in fields.iter().filter_map(|field| field.value.as_ref()) {
+                v.visit_expr(value);
+            }
+            if let Some(base) = base {
+                v.visit_expr(base);
+            }
+        }
+        Expr::LetCond(test) => v.visit_cond_test(test),
+    }
+}
+
+pub fn walk_filter<'a, V: Visit<'a> + ?Sized>(v: &mut V, filter: &Filter<'a>) {
+    for arg in &filter.arguments {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_test<'a, V: Visit<'a> + ?Sized>(v: &mut V, test: &Test<'a>) {
+    for arg in &test.arguments {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_target<'a, V: Visit<'a> + ?Sized>(v: &mut V, target: &Target<'a>) {
+    match target {
+        Target::Name(_)
+        | Target::NumLit(..)
+        | Target::StrLit(_)
+        | Target::CharLit(_)
+        | Target::BoolLit(_)
+        | Target::Path(_)
+        | Target::Placeholder(_)
+        | Target::Rest(_) => {}
+        Target::Tuple(tuple) => {
+            for target in &tuple.1 {
+                v.visit_target(target);
+            }
+        }
+        Target::Struct(s) => {
+            for field in &s.1 {
+                v.visit_target(&field.dest);
+            }
+        }
+        Target::Array(targets) | Target::OrChain(targets) => {
+            for target in &**targets {
+                v.visit_target(target);
+            }
+        }
+    }
+}
+
+/// Visitor of a mutable template AST.
+pub trait VisitMut<'a> {
+    fn visit_nodes_mut(&mut self, nodes: &mut [Box<Node<'a>>]) {
+        walk_nodes_mut(self, nodes);
+    }
+
+    fn visit_node_mut(&mut self, node: &mut Node<'a>) {
+        walk_node_mut(self, node);
+    }
+
+    fn visit_lit_mut(&mut self, _lit: &mut WithSpan<Lit<'a>>) {}
+
+    fn visit_comment_mut(&mut self, _comment: &mut WithSpan<Comment<'a>>) {}
+
+    fn visit_call_mut(&mut self, call: &mut WithSpan<Call<'a>>) {
+        walk_call_mut(self, call);
+    }
+
+    fn visit_let_mut(&mut self, l: &mut WithSpan<Let<'a>>) {
+        walk_let_mut(self, l);
+    }
+
+    fn visit_compound_mut(&mut self, compound: &mut WithSpan<Compound<'a>>) {
+        walk_compound_mut(self, compound);
+    }
+
+    fn visit_declare_mut(&mut self, _declare: &mut WithSpan<Declare<'a>>) {}
+
+    fn visit_if_mut(&mut self, i: &mut WithSpan<If<'a>>) {
+        walk_if_mut(self, i);
+    }
+
+    fn visit_cond_mut(&mut self, cond: &mut WithSpan<Cond<'a>>) {
+        walk_cond_mut(self, cond);
+    }
+
+    fn visit_cond_test_mut(&mut self, test: &mut WithSpan<CondTest<'a>>) {
+        walk_cond_test_mut(self, test);
+    }
+
+    fn visit_match_mut(&mut self, m: &mut WithSpan<Match<'a>>) {
+        walk_match_mut(self, m);
+    }
+
+    fn visit_when_mut(&mut self, when: &mut WithSpan<When<'a>>) {
+        walk_when_mut(self, when);
+    }
+
+    fn visit_loop_mut(&mut self, l: &mut WithSpan<Loop<'a>>) {
+        walk_loop_mut(self, l);
+    }
+
+    fn visit_extends_mut(&mut self, _extends: &mut WithSpan<Extends<'a>>) {}
+
+    fn visit_block_def_mut(&mut self, block: &mut WithSpan<BlockDef<'a>>) {
+        walk_block_def_mut(self, block);
+    }
+
+    fn visit_include_mut(&mut self, _include: &mut WithSpan<Include<'a>>) {}
+
+    fn visit_import_mut(&mut self, _import: &mut WithSpan<Import<'a>>) {}
+
+    fn visit_macro_mut(&mut self, m: &mut WithSpan<Macro<'a>>) {
+        walk_macro_mut(self, m);
+    }
+
+    fn visit_macro_arg_mut(&mut self, arg: &mut MacroArg<'a>) {
+        walk_macro_arg_mut(self, arg);
+    }
+
+    fn visit_raw_mut(&mut self, _raw: &mut WithSpan<Raw<'a>>) {}
+
+    fn visit_filter_block_mut(&mut self, filter: &mut WithSpan<FilterBlock<'a>>) {
+        walk_filter_block_mut(self, filter);
+    }
+
+    fn visit_with_values_mut(&mut self, with: &mut WithSpan<WithValues<'a>>) {
+        walk_with_values_mut(self, with);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut WithSpan<Box<Expr<'a>>>) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_filter_mut(&mut self, filter: &mut Filter<'a>) {
+        walk_filter_mut(self, filter);
+    }
+
+    fn visit_test_mut(&mut self, test: &mut Test<'a>) {
+        walk_test_mut(self, test);
+    }
+
+    fn visit_target_mut(&mut self, target: &mut Target<'a>) {
+        walk_target_mut(self, target);
+    }
+}
+
+pub fn walk_nodes_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, nodes: &mut [Box<Node<'a>>]) {
+    for node in nodes {
+        v.visit_node_mut(node);
+    }
+}
+
+pub fn walk_node_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, node: &mut Node<'a>) {
+    match node {
+        Node::Lit(lit) => v.visit_lit_mut(lit),
+        Node::Comment(comment) => v.visit_comment_mut(comment),
+        Node::Expr(_, expr) => v.visit_expr_mut(expr),
+        Node::Call(call) => v.visit_call_mut(call),
+        Node::Let(l) => v.visit_let_mut(l),
+        Node::Compound(compound) => v.visit_compound_mut(compound),
+        Node::Declare(declare) => v.visit_declare_mut(declare),
+        Node::If(i) => v.visit_if_mut(i),
+        Node::Match(m) => v.visit_match_mut(m),
+        Node::Loop(l) => v.visit_loop_mut(l),
+        Node::Extends(extends) => v.visit_extends_mut(extends),
+        Node::BlockDef(block) => v.visit_block_def_mut(block),
+        Node::Include(include) => v.visit_include_mut(include),
+        Node::Import(import) => v.visit_import_mut(import),
+        Node::Macro(m) => v.visit_macro_mut(m),
+        Node::Raw(raw) => v.visit_raw_mut(raw),
+        Node::Break(_) | Node::Continue(_) => {}
+        Node::FilterBlock(filter) => v.visit_filter_block_mut(filter),
+        Node::WithValues(with) => v.visit_with_values_mut(with),
+    }
+}
+
+pub fn walk_call_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, call: &mut WithSpan<Call<'a>>) {
+    for arg in call.args.iter_mut().flatten() {
+        v.visit_expr_mut(arg);
+    }
+    v.visit_nodes_mut(&mut call.nodes);
+}
+
+pub fn walk_let_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, l: &mut WithSpan<Let<'a>>) {
+    v.visit_target_mut(&mut l.var);
+    match &mut l.val {
+        LetValueOrBlock::Value(expr) => v.visit_expr_mut(expr),
+        LetValueOrBlock::Block { nodes, .. } => v.visit_nodes_mut(nodes),
+    }
+}
+
+pub fn walk_compound_mut<'a, V: VisitMut<'a> + ?Sized>(
+    v: &mut V,
+    compound: &mut WithSpan<Compound<'a>>,
+) {
+    v.visit_expr_mut(&mut compound.op.lhs);
+    v.visit_expr_mut(&mut compound.op.rhs);
+}
+
+pub fn walk_if_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, i: &mut WithSpan<If<'a>>) {
+    for cond in &mut i.branches {
+        v.visit_cond_mut(cond);
+    }
+}
+
+pub fn walk_cond_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, cond: &mut WithSpan<Cond<'a>>) {
+    if let Some(test) = &mut cond.cond {
+        v.visit_cond_test_mut(test);
+    }
+    v.visit_nodes_mut(&mut cond.nodes);
+}
+
+pub fn walk_cond_test_mut<'a, V: VisitMut<'a> + ?Sized>(
+    v: &mut V,
+    test: &mut WithSpan<CondTest<'a>>,
+) {
+    if let Some(target) = &mut test.target {
+        v.visit_target_mut(target);
+    }
+    v.visit_expr_mut(&mut test.expr);
+}
+
+pub fn walk_match_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, m: &mut WithSpan<Match<'a>>) {
+    v.visit_expr_mut(&mut m.expr);
+    for arm in &mut m.arms {
+        v.visit_when_mut(arm);
+    }
+}
+
+pub fn walk_when_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, when: &mut WithSpan<When<'a>>) {
+    for target in &mut when.target {
+        v.visit_target_mut(target);
+    }
+    v.visit_nodes_mut(&mut when.nodes);
+}
+
+pub fn walk_loop_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, l: &mut WithSpan<Loop<'a>>) {
+    v.visit_target_mut(&mut l.var);
+    v.visit_expr_mut(&mut l.iter);
+    if let Some(cond) = &mut l.cond {
+        v.visit_expr_mut(cond);
+    }
+    v.visit_nodes_mut(&mut l.body);
+    v.visit_nodes_mut(&mut l.else_nodes);
+}
+
+pub fn walk_block_def_mut<'a, V: VisitMut<'a> + ?Sized>(
+    v: &mut V,
+    block: &mut WithSpan<BlockDef<'a>>,
+) {
+    v.visit_nodes_mut(&mut block.nodes);
+}
+
+pub fn walk_macro_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, m: &mut WithSpan<Macro<'a>>) {
+    for arg in &mut m.args {
+        v.visit_macro_arg_mut(arg);
+    }
+    v.visit_nodes_mut(&mut m.nodes);
+}
+
+pub fn walk_macro_arg_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, arg: &mut MacroArg<'a>) {
+    if let Some(default) = &mut arg.default {
+        v.visit_expr_mut(default);
+    }
+}
+
+pub fn walk_filter_block_mut<'a, V: VisitMut<'a> + ?Sized>(
+    v: &mut V,
+    filter: &mut WithSpan<FilterBlock<'a>>,
+) {
+    v.visit_filter_mut(&mut filter.filters);
+    v.visit_nodes_mut(&mut filter.nodes);
+}
+
+pub fn walk_with_values_mut<'a, V: VisitMut<'a> + ?Sized>(
+    v: &mut V,
+    with: &mut WithSpan<WithValues<'a>>,
+) {
+    for (_, value) in &mut with.values {
+        v.visit_expr_mut(value);
+    }
+    v.visit_nodes_mut(&mut with.nodes);
+}
+
+pub fn walk_expr_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, expr: &mut WithSpan<Box<Expr<'a>>>) {
+    match &mut ***expr {
+        Expr::BoolLit(_)
+        | Expr::NumLit(..)
+        | Expr::StrLit(_)
+        | Expr::CharLit(_)
+        | Expr::Var(_)
+        | Expr::Path(_)
+        | Expr::RustMacro(..)
+        | Expr::FilterSource
+        | Expr::IsDefined(_)
+        | Expr::IsNotDefined(_)
+        | Expr::ArgumentPlaceholder => {}
+        Expr::Array(exprs) | Expr::Tuple(exprs) | Expr::Concat(exprs) => {
+            for expr in exprs {
+                v.visit_expr_mut(expr);
+            }
+        }
+        Expr::ArrayRepeat(lhs, rhs) | Expr::Index(lhs, rhs) => {
+            v.visit_expr_mut(lhs);
+            v.visit_expr_mut(rhs);
+        }
+        Expr::AssociatedItem(expr, _)
+        | Expr::As(expr, _)
+        | Expr::NamedArgument(_, expr)
+        | Expr::Unary(_, expr)
+        | Expr::Group(expr)
+        | Expr::Try(expr) => v.visit_expr_mut(expr),
+        Expr::Filter(filter) => v.visit_filter_mut(filter),
+        Expr::Test(test) => v.visit_test_mut(test),
+        Expr::BinOp(BinOp { lhs, rhs, .. }) => {
+            v.visit_expr_mut(lhs);
+            v.visit_expr_mut(rhs);
+        }
+        Expr::Range(Range { lhs, rhs, .. }) => {
+            for expr in [lhs, rhs].into_iter().flatten() {
+                v.visit_expr_mut(expr);
+            }
+        }
+        Expr::Conditional(Conditional {
+            cond,
+            then,
+            otherwise,
+        }) => {
+            v.visit_expr_mut(cond);
+            v.visit_expr_mut(then);
+            if let Some(otherwise) = otherwise {
+                v.visit_expr_mut(otherwise);
+            }
+        }
+        Expr::Call(CallExpr { path, args, .. }) => {
+            v.visit_expr_mut(path);
+            for arg in args {
+                v.visit_expr_mut(arg);
+            }
+        }
+        Expr::Struct(ExprStruct { path, fields, base }) => {
+            v.visit_expr_mut(path);
+            for value in fields.iter_mut().filter_map(|field| field.value.as_mut()) {
+                v.visit_expr_mut(value);
+            }
+            if let Some(base) = base {
+                v.visit_expr_mut(base);
+            }
+        }
+        Expr::LetCond(test) => v.visit_cond_test_mut(test),
+    }
+}
+
+pub fn walk_filter_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, filter: &mut Filter<'a>) {
+    for arg in &mut filter.arguments {
+        v.visit_expr_mut(arg);
+    }
+}
+
+pub fn walk_test_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, test: &mut Test<'a>) {
+    for arg in &mut test.arguments {
+        v.visit_expr_mut(arg);
+    }
+}
+
+pub fn walk_target_mut<'a, V: VisitMut<'a> + ?Sized>(v: &mut V, target: &mut Target<'a>) {
+    match target {
+        Target::Name(_)
+        | Target::NumLit(..)
+        | Target::StrLit(_)
+        | Target::CharLit(_)
+        | Target::BoolLit(_)
+        | Target::Path(_)
+        | Target::Placeholder(_)
+        | Target::Rest(_) => {}
+        Target::Tuple(tuple) => {
+            for target in &mut tuple.1 {
+                v.visit_target_mut(target);
+            }
+        }
+        Target::Struct(s) => {
+            for field in &mut s.1 {
+                v.visit_target_mut(&mut field.dest);
+            }
+        }
+        Target::Array(targets) | Target::OrChain(targets) => {
+            for target in &mut **targets {
+                v.visit_target_mut(target);
+            }
+        }
+    }
+}
@@ -52,7 +52,10 @@ fn test_common<'a, T: Template + 'a>(hello: fn(Result<Option<&'a str>, CustomErr
 
     let tmpl = hello(Err(CustomError));
     let mut cursor = Cursor::new(&mut buffer);
-    let err = match tmpl.render_into(&mut cursor) {
+    let err = match tmpl
+        .render_into(&mut cursor)
+        .map_err(askama::Error::without_location)
+    {
         Err(askama::Error::Custom(err)) => err,
         err => panic!("Expected `Err(Custom(_))`, got {err:#?}"),
     };
@@ -64,5 +64,9 @@ fn test_common<'a, T: Template + 'a>(hello: fn(Result<Option<&'a str>, fmt::Erro
 
     let tmpl = hello(Err(fmt::Error));
     let mut cursor = String::new();
-    assert_matches!(tmpl.render_into(&mut cursor), Err(some_name::Error::Fmt));
+    assert_matches!(
+        tmpl.render_into(&mut cursor)
+            .map_err(some_name::Error::without_location),
+        Err(some_name::Error::Fmt)
+    );
 }
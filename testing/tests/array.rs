@@ -53,7 +53,11 @@ fn test_array_repeat_in_for() {
 #[test]
 fn test_array_repeat_in_assignment() {
     #[derive(Template)]
-    #[template(source = r#"{%- let my_arr = [""; 0] -%}"#, ext = "txt")]
+    #[template(
+        source = r#"{%- let my_arr = [""; 0] -%}"#,
+        ext = "txt",
+        allow(unused_variables)
+    )]
     struct ArrayRepeatInAssignment;
 
     let t = ArrayRepeatInAssignment;
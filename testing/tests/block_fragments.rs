@@ -163,3 +163,55 @@ fn test_variable_inheritance_in_block() {
 
     assert_eq!(Y.render().unwrap(), "--> 42\n42");
 }
+
+/// Tests rendering blocks selected by their name at runtime, including inherited blocks.
+#[test]
+fn test_render_block_by_name() {
+    use askama::BlockTemplate;
+
+    #[derive(Template)]
+    #[template(path = "fragment-super.html", render_blocks = true)]
+    struct RenderBlocks<'a> {
+        name: &'a str,
+    }
+
+    let tmpl = RenderBlocks { name: "world" };
+    assert_eq!(RenderBlocks::BLOCK_NAMES, ["body", "other_body"]);
+    assert_eq!(
+        tmpl.render_block("body").unwrap(),
+        "\n<p>Hello world!</p>\n\n<p>Parent body content</p>\n\n"
+    );
+    assert_eq!(
+        tmpl.render_block("other_body").unwrap(),
+        "\n<p>Don't render me.</p>\n\n"
+    );
+    assert!(matches!(
+        tmpl.render_block("missing"),
+        Err(askama::Error::BlockMissing)
+    ));
+
+    let mut bytes = Vec::new();
+    tmpl.write_block("body", &mut bytes).unwrap();
+    assert_eq!(bytes, tmpl.render_block("body").unwrap().as_bytes());
+    let err = tmpl.write_block("missing", &mut bytes).unwrap_err();
+    assert_eq!(err.to_string(), "block not found in template");
+
+    // The full template is still rendered as usual.
+    assert!(tmpl.render().unwrap().contains("<p>Hello world!</p>"));
+}
+
+/// Tests that a template without blocks has no block names.
+#[test]
+fn test_render_block_without_blocks() {
+    use askama::BlockTemplate;
+
+    #[derive(Template)]
+    #[template(ext = "txt", source = "no blocks", render_blocks = true)]
+    struct NoBlocks;
+
+    assert!(NoBlocks::BLOCK_NAMES.is_empty());
+    assert!(matches!(
+        NoBlocks.render_block("body"),
+        Err(askama::Error::BlockMissing)
+    ));
+}
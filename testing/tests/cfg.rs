@@ -0,0 +1,101 @@
+use askama::Template;
+
+#[test]
+fn test_cfg() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- if cfg(test) -%}
+test
+{%- endif -%}
+{%- if cfg(not(test)) -%}
+{{ self.does_not_exist }}
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct Cfg;
+
+    assert_eq!(Cfg.render().unwrap(), "test");
+}
+
+#[test]
+fn test_cfg_else() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- if cfg(feature = "nightly-spans") -%}
+nightly
+{%- else -%}
+stable
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct CfgElse;
+
+    let expected = if cfg!(feature = "nightly-spans") {
+        "nightly"
+    } else {
+        "stable"
+    };
+    assert_eq!(CfgElse.render().unwrap(), expected);
+}
+
+#[test]
+fn test_cfg_combinators() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- if cfg(all(test, not(any(unix, windows, target_os = "none")))) -%}
+{{ self.does_not_exist() }}
+{%- elif cfg(any(test, feature = "does-not-exist")) -%}
+any
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct CfgCombinators;
+
+    assert_eq!(CfgCombinators.render().unwrap(), "any");
+}
+
+#[test]
+fn test_cfg_mixed_with_runtime_conditions() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- if x == 0 -%}
+zero
+{%- elif cfg(not(test)) -%}
+{{ missing }}
+{%- elif x == 1 -%}
+one
+{%- elif cfg(test) -%}
+test
+{%- else -%}
+unreachable
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct Mixed {
+        x: u32,
+    }
+
+    assert_eq!(Mixed { x: 0 }.render().unwrap(), "zero");
+    assert_eq!(Mixed { x: 1 }.render().unwrap(), "one");
+    assert_eq!(Mixed { x: 2 }.render().unwrap(), "test");
+}
+
+#[test]
+fn test_cfg_shadowed() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- let cfg = self.cfg -%}
+{%- if cfg(true) -%}
+yes
+{%- else -%}
+no
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct Shadowed {
+        cfg: fn(bool) -> bool,
+    }
+
+    // A variable named `cfg` is called like any other function.
+    assert_eq!(Shadowed { cfg: |b| !b }.render().unwrap(), "no");
+}
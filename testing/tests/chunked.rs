@@ -0,0 +1,212 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use askama::{DynTemplate, Error, RenderChunks, Template};
+
+fn chunks_of(tmpl: &dyn DynTemplate, chunk_size: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    tmpl.dyn_render_chunked(chunk_size, &mut |chunk| {
+        assert!(!chunk.is_empty());
+        assert!(chunk.len() <= chunk_size);
+        chunks.push(String::from_utf8(chunk.to_vec()).unwrap());
+        Ok(())
+    })
+    .unwrap();
+    chunks
+}
+
+#[test]
+fn test_chunked_simple() {
+    #[derive(Template)]
+    #[template(source = "Hello, {{ name }}!", ext = "html")]
+    struct Hello<'a> {
+        name: &'a str,
+    }
+
+    let t = Hello { name: "<world>" };
+    let mut chunks = vec![];
+    t.render_chunked(4, &mut |chunk| {
+        chunks.push(chunk.to_vec());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(chunks.concat(), t.render().unwrap().as_bytes());
+    assert_eq!(
+        chunks,
+        [&b"Hell"[..], b"o, &", b"#60;", b"worl", b"d&#6", b"2;!"],
+    );
+}
+
+#[test]
+fn test_chunked_extends_include() {
+    #[derive(Template)]
+    #[template(path = "include-extends.html")]
+    struct IncludeExtendsTemplate<'a> {
+        name: &'a str,
+    }
+
+    let t = IncludeExtendsTemplate { name: "Alice" };
+    let expected = t.render().unwrap();
+    for chunk_size in [1, 3, 7, 64, 1000] {
+        assert_eq!(chunks_of(&t, chunk_size).concat(), expected);
+    }
+}
+
+#[test]
+fn test_chunked_callback_error_aborts() {
+    #[derive(Template)]
+    #[template(source = "{% for i in 0..100 %}{{ i }},{% endfor %}", ext = "txt")]
+    struct Counter;
+
+    let mut calls = 0;
+    let result = Counter.render_chunked(8, &mut |_| {
+        calls += 1;
+        if calls == 2 {
+            Err(Error::custom("client went away"))
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(result.unwrap_err().to_string(), "client went away");
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn test_chunked_template_error() {
+    #[derive(Template)]
+    #[template(source = r#"abc{{ askama::get_value::<u32>("name")? }}"#, ext = "txt")]
+    struct Missing;
+
+    let mut chunks = vec![];
+    let result = Missing.render_chunked(2, &mut |chunk| {
+        chunks.push(chunk.to_vec());
+        Ok(())
+    });
+    assert!(matches!(result, Err(Error::ValueMissing)));
+    assert_eq!(chunks, [b"ab"]);
+}
+
+#[derive(Template)]
+#[template(path = "include-extends.html")]
+struct OwnedIncludeExtends {
+    name: String,
+}
+
+#[test]
+fn test_chunks_iterator() {
+    let expected = OwnedIncludeExtends {
+        name: "Alice".into(),
+    }
+    .render()
+    .unwrap();
+    for chunk_size in [1, 3, 64, 1000] {
+        let t = OwnedIncludeExtends {
+            name: "Alice".into(),
+        };
+        let chunks = t.render_chunks(chunk_size).collect::<Result<Vec<_>, _>>();
+        let chunks = chunks.unwrap();
+        assert!(
+            chunks
+                .iter()
+                .all(|c| !c.is_empty() && c.len() <= chunk_size)
+        );
+        assert_eq!(chunks.concat(), expected.as_bytes());
+
+        let t: &dyn DynTemplate = &t;
+        let chunks = RenderChunks::new(t, chunk_size).collect::<Result<Vec<_>, _>>();
+        assert_eq!(chunks.unwrap().concat(), expected.as_bytes());
+    }
+}
+
+#[test]
+fn test_chunks_iterator_is_lazy() {
+    #[derive(Template)]
+    #[template(source = "abcdef{{ self.finish() }}", ext = "txt")]
+    struct Lazy {
+        finished: Arc<AtomicBool>,
+    }
+
+    impl Lazy {
+        fn finish(&self) -> &'static str {
+            self.finished.store(true, Ordering::SeqCst);
+            "!"
+        }
+    }
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let tmpl = Lazy {
+        finished: Arc::clone(&finished),
+    };
+    let mut chunks = tmpl.render_chunks(2);
+    // The rendering stops as soon as the requested chunk is complete.
+    assert_eq!(chunks.next().unwrap().unwrap(), b"ab");
+    assert!(!finished.load(Ordering::SeqCst));
+    let rest = chunks.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rest, [&b"cd"[..], b"ef", b"!"]);
+    assert!(finished.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_chunks_iterator_error() {
+    #[derive(askama::Values)]
+    struct Name {
+        name: u32,
+    }
+
+    #[derive(Template)]
+    #[template(source = r#"abc{{ askama::get_value(Name::NAME)? }}"#, ext = "txt")]
+    struct Missing;
+
+    let mut chunks = Missing.render_chunks(2);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"ab");
+    // The output up to the error is returned before the error.
+    assert_eq!(chunks.next().unwrap().unwrap(), b"c");
+    assert!(matches!(chunks.next().unwrap(), Err(Error::ValueMissing)));
+    assert!(chunks.next().is_none());
+
+    let chunks = Missing.render_chunks_with_values(2, &Name { name: 7 });
+    assert_eq!(
+        chunks.collect::<Result<Vec<_>, _>>().unwrap(),
+        [&b"ab"[..], b"c7"]
+    );
+}
+
+#[test]
+#[should_panic = "boom"]
+fn test_chunks_iterator_panic() {
+    #[derive(Template)]
+    #[template(source = "abc{{ self.boom() }}", ext = "txt")]
+    struct Panics;
+
+    impl Panics {
+        fn boom(&self) -> &'static str {
+            panic!("boom")
+        }
+    }
+
+    for chunk in Panics.render_chunks(2) {
+        chunk.unwrap();
+    }
+}
+
+#[test]
+fn test_chunks_iterator_same_thread() {
+    std::thread_local! {
+        static GREETING: std::cell::Cell<&'static str> = const { std::cell::Cell::new("") };
+    }
+
+    #[derive(Template)]
+    #[template(source = "{{ self.greeting() }}, world!", ext = "txt")]
+    struct Greeting;
+
+    impl Greeting {
+        fn greeting(&self) -> &'static str {
+            GREETING.get()
+        }
+    }
+
+    // The template is rendered by the caller, so it can use thread-locals.
+    GREETING.set("Hello");
+    let chunks = Greeting.render_chunks(5).collect::<Result<Vec<_>, _>>();
+    assert_eq!(chunks.unwrap().concat(), b"Hello, world!");
+}
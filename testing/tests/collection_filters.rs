@@ -0,0 +1,238 @@
+use askama::Template;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Address {
+    city: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct User {
+    name: &'static str,
+    age: u32,
+    is_admin: bool,
+    address: Address,
+    score: f32,
+}
+
+fn users() -> Vec<User> {
+    [
+        ("Ferris", 12, true, "Paris", 1.5),
+        ("Corro", 7, false, "Berlin", 2.0),
+        ("Rusty", 12, false, "Paris", 0.25),
+        ("Bors", 30, true, "Amsterdam", 4.0),
+    ]
+    .into_iter()
+    .map(|(name, age, is_admin, city, score)| User {
+        name,
+        age,
+        is_admin,
+        address: Address { city },
+        score,
+    })
+    .collect()
+}
+
+#[test]
+fn test_sort() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {{- numbers|sort|join(",") }}
+            {{~ numbers|sort(true)|join(",") }}
+            {%~ for user in users|sort(attribute = "age") %}{{ user.name }} {% endfor %}
+            {%~ for user in users|sort(reverse = true, attribute = "address.city") -%}
+                {{ user.name }} {% endfor -%}
+        "#,
+        ext = "txt"
+    )]
+    struct Sort {
+        numbers: [i32; 4],
+        users: Vec<User>,
+    }
+
+    let tmpl = Sort {
+        numbers: [3, -1, 4, 1],
+        users: users(),
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "-1,1,3,4\n4,3,1,-1\nCorro Ferris Rusty Bors \nFerris Rusty Corro Bors "
+    );
+}
+
+#[test]
+fn test_groupby() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- for group in users|groupby("address.city") -%}
+                {{ group.grouper }}:
+                {%- for user in group.list %} {{ user.name }}{% endfor %};
+            {%~ endfor -%}
+            {%- for group in users|groupby("address") -%}
+                {{ group.list.len() }}
+            {%- endfor -%}
+        "#,
+        ext = "txt"
+    )]
+    struct GroupBy {
+        users: Vec<User>,
+    }
+
+    assert_eq!(
+        GroupBy { users: users() }.render().unwrap(),
+        "Amsterdam: Bors;\nBerlin: Corro;\nParis: Ferris Rusty;\n112"
+    );
+}
+
+#[test]
+fn test_map_selectattr() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {{- users|map(attribute = "name")|join(", ") }}
+            {{~ users|selectattr("is_admin")|map(attribute = "name")|join(", ") }}
+            {{~ users|selectattr("age", "even")|map(attribute = "name")|join(", ") }}
+            {{~ users|selectattr("name", "in", friends)|map(attribute = "name")|join(", ") }}
+            {{~ users|selectattr("age", "divisibleby", 3)|map(attribute = "address.city")|join(", ") }}
+            {%- for (name, age) in pairs|selectattr("1", "odd") %} {{ name }}={{ age }}{% endfor -%}
+        "#,
+        ext = "txt"
+    )]
+    struct MapSelect<'a> {
+        users: Vec<User>,
+        friends: &'a [&'a str],
+        pairs: Vec<(&'a str, u32)>,
+    }
+
+    let tmpl = MapSelect {
+        users: users(),
+        friends: &["Bors", "Corro"],
+        pairs: vec![("a", 1), ("b", 2), ("c", 3)],
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "Ferris, Corro, Rusty, Bors\n\
+         Ferris, Bors\n\
+         Ferris, Rusty, Bors\n\
+         Corro, Bors\n\
+         Paris, Paris, Amsterdam a=1 c=3"
+    );
+}
+
+#[test]
+fn test_batch_slice() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- for row in numbers|batch(3) %}{{ row|join(",") }};{% endfor %}
+            {%~ for row in numbers|batch(3, 0) %}{{ row|join(",") }};{% endfor %}
+            {%~ for column in numbers|slice(3) %}{{ column|join(",") }};{% endfor %}
+            {%~ for column in numbers|slice(slices = 3, fill_with = 0) -%}
+                {{ column|join(",") }};
+            {%- endfor %}
+            {%~ for column in users|map(attribute = "name")|slice(2) -%}
+                {{ column|join(",") }};
+            {%- endfor -%}
+        "#,
+        ext = "txt"
+    )]
+    struct BatchSlice {
+        numbers: Vec<u32>,
+        users: Vec<User>,
+    }
+
+    let tmpl = BatchSlice {
+        numbers: (1..=7).collect(),
+        users: users(),
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "1,2,3;4,5,6;7;\n\
+         1,2,3;4,5,6;7,0,0;\n\
+         1,2,3;4,5;6,7;\n\
+         1,2,3;4,5,0;6,7,0;\n\
+         Ferris,Corro;Rusty,Bors;"
+    );
+}
+
+#[test]
+fn test_batch_zero() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for row in numbers|batch(size) %}{% endfor %}",
+        ext = "txt"
+    )]
+    struct BatchZero {
+        numbers: Vec<u32>,
+        size: usize,
+    }
+
+    let tmpl = BatchZero {
+        numbers: vec![1, 2, 3],
+        size: 0,
+    };
+    assert_eq!(
+        tmpl.render().unwrap_err().to_string(),
+        "the `linecount` of a batch must not be zero"
+    );
+}
+
+#[test]
+fn test_aggregates() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- if let Some(user) = users|first %}{{ user.name }}{% endif %}
+            {%~ if let Some(user) = users|last %}{{ user.name }}{% endif %}
+            {{~ users|min(attribute = "age")|map(attribute = "name")|join("") }}
+            {{~ users|max(attribute = "age")|map(attribute = "name")|join("") }}
+            {{~ numbers|min|assigned_or(0) }} {{ numbers|max|assigned_or(0) }}
+            {{~ empty|first|assigned_or(0) }} {{ empty|max|assigned_or(0) }}
+            {{~ numbers|sum }} {{ numbers|sum(start = 10) }} {{ empty|sum }}
+            {{~ users|sum(attribute = "age") }} {{ users|sum("score", 0.0) -}}
+        "#,
+        ext = "txt"
+    )]
+    struct Aggregates {
+        users: Vec<User>,
+        numbers: Vec<i64>,
+        empty: Vec<i64>,
+    }
+
+    let tmpl = Aggregates {
+        users: users(),
+        numbers: vec![4, -2, 7],
+        empty: vec![],
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "Ferris\nBors\nCorro\nBors\n-2 7\n0 0\n9 19 0\n61 7.75"
+    );
+}
+
+#[test]
+fn test_lazy() {
+    // The filters only take the items they need from an endless iterator.
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- if let Some(pair) = self.pairs()|selectattr("0", "even")|first -%}
+                {{ pair.1 }}
+            {%- endif %}
+            {%~ for numbers in (1..)|batch(2) -%}
+                {%- if loop.index > 2 %}{% break %}{% endif %}{{ numbers|join("+") }};
+            {%- endfor -%}
+        "#,
+        ext = "txt"
+    )]
+    struct Lazy;
+
+    impl Lazy {
+        fn pairs(&self) -> impl Iterator<Item = (u32, char)> {
+            (1..).zip('a'..)
+        }
+    }
+
+    assert_eq!(Lazy.render().unwrap(), "b\n1+2;3+4;");
+}
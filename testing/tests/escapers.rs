@@ -0,0 +1,116 @@
+use askama::Template;
+
+#[test]
+fn test_js_escaper() {
+    #[derive(Template)]
+    #[template(source = "const name = '{{ name }}', id = {{ id }};", ext = "js")]
+    struct Js<'a> {
+        name: &'a str,
+        id: u32,
+    }
+
+    assert_eq!(
+        Js {
+            name: "O'Reilly</script>",
+            id: 42
+        }
+        .render()
+        .unwrap(),
+        r"const name = 'O\u0027Reilly\u003C\/script\u003E', id = 42;"
+    );
+}
+
+#[test]
+fn test_json_escaper() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{"name": "{{ name }}", "admin": {{ admin }}}"#,
+        ext = "json"
+    )]
+    struct Json<'a> {
+        name: &'a str,
+        admin: bool,
+    }
+
+    assert_eq!(
+        Json {
+            name: "\"Ferris\"\n<crab>",
+            admin: true
+        }
+        .render()
+        .unwrap(),
+        r#"{"name": "\"Ferris\"\n\u003ccrab\u003e", "admin": true}"#
+    );
+}
+
+#[test]
+fn test_xml_escaper() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<item title="{{ title }}">{{ title }}</item>"#,
+        ext = "xml"
+    )]
+    struct Xml<'a> {
+        title: &'a str,
+    }
+
+    assert_eq!(
+        Xml {
+            title: "a < b\n& c"
+        }
+        .render()
+        .unwrap(),
+        r#"<item title="a &#60; b&#10;&#38; c">a &#60; b&#10;&#38; c</item>"#
+    );
+}
+
+#[test]
+fn test_css_escaper() {
+    #[derive(Template)]
+    #[template(source = r#".avatar { content: "{{ name }}"; }"#, ext = "css")]
+    struct Css<'a> {
+        name: &'a str,
+    }
+
+    assert_eq!(
+        Css { name: "a\"; } b" }.render().unwrap(),
+        r#".avatar { content: "a\22 \3b  \7d  b"; }"#
+    );
+}
+
+#[test]
+fn test_tex_escaper() {
+    #[derive(Template)]
+    #[template(source = r"\textbf{ {{- name -}} } pays {{ amount }}\,\%", ext = "tex")]
+    struct Tex<'a> {
+        name: &'a str,
+        amount: &'a str,
+    }
+
+    assert_eq!(
+        Tex {
+            name: "Smith & Sons_{1}",
+            amount: "$100 ~50%"
+        }
+        .render()
+        .unwrap(),
+        r"\textbf{Smith \& Sons\_\{1\}} pays \$100 \textasciitilde{}50\%\,\%"
+    );
+}
+
+#[test]
+fn test_escape_filter_with_other_escapers() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<a href="/search?q={{ query|e("url") }}" data-q='{{ query|e("json") }}'>"#,
+        ext = "html"
+    )]
+    struct Filter<'a> {
+        query: &'a str,
+    }
+
+    assert_eq!(
+        Filter { query: "a&b c/'d'" }.render().unwrap(),
+        r#"<a href="/search?q=a%26b%20c%2F%27d%27" data-q='a\u0026b c/\u0027d\u0027'>"#
+    );
+}
@@ -292,7 +292,8 @@ fn filter_nested_filter_blocks() {
             {%- endfor -%}
         ]
     {%~ endfilter %}{{ canary }}"#,
-        ext = "html"
+        ext = "html",
+        allow(unused_variables)
     )]
     struct NestedFilterBlocks2 {
         v: &'static str,
@@ -753,3 +753,33 @@ fn issue_671() {
     };
     assert_eq!(a.render().unwrap(), "ok");
 }
+
+#[test]
+fn filter_sanitize() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<div>{{ comment|sanitize }}</div>
+<div>{{ comment|sanitize(["em", "a"]) }}</div>
+<div>{{ comment|sanitize(attributes = attrs, tags = tags) }}</div>"#,
+        ext = "html"
+    )]
+    struct Comment<'a> {
+        comment: &'a str,
+        tags: Vec<String>,
+        attrs: &'a [&'a str],
+    }
+
+    let tmpl = Comment {
+        comment: "<p class=x>Look <em>at</em> <a href='javascript:alert(1)' title=me>this</a>\
+                  <script>alert(2)</script> &amp; <img src=https://askama.rs/logo.svg>!",
+        tags: vec!["a".into(), "img".into()],
+        attrs: &["class", "src"],
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "<div><p>Look <em>at</em> <a href=\"#askama-unsafe-url\" title=\"me\">this</a> &#38; \
+         <img src=\"https://askama.rs/logo.svg\">!</p></div>\n\
+         <div>Look <em>at</em> <a href=\"#askama-unsafe-url\" title=\"me\">this</a> &#38; !</div>\n\
+         <div>Look at <a>this</a> &#38; <img src=\"https://askama.rs/logo.svg\">!</div>"
+    );
+}
@@ -0,0 +1,191 @@
+use askama::Template;
+
+#[test]
+fn test_script() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script>
+let name = {{ name }};
+let greeting = 'Hello, {{ name }}!';
+let count = {{ count }} / 2;
+let flag = {{ flag }};
+let tmpl = `${ {{ name }} }: {{ name }}`;
+// {{ name }}
+</script>
+<p title="{{ name }}">{{ name }}</p>"#,
+        ext = "html"
+    )]
+    struct Script<'a> {
+        name: &'a str,
+        count: u32,
+        flag: bool,
+    }
+
+    let tmpl = Script {
+        name: "</script>'\"&",
+        count: 42,
+        flag: true,
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r#"<script>
+let name = "\u003C\/script\u003E\u0027\u0022\u0026";
+let greeting = 'Hello, \u003C\/script\u003E\u0027\u0022\u0026!';
+let count = 42 / 2;
+let flag = true;
+let tmpl = `${ "\u003C\/script\u003E\u0027\u0022\u0026" }: \u003C\/script\u003E\u0027\u0022\u0026`;
+// \u003C\/script\u003E\u0027\u0022\u0026
+</script>
+<p title="&#60;/script&#62;&#39;&#34;&#38;">&#60;/script&#62;&#39;&#34;&#38;</p>"#
+    );
+}
+
+#[test]
+fn test_script_json() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script>const data = {{ data|json }};</script>
+<script type="application/json">{"items": {{ data|json }}, "name": {{ name }}}</script>"#,
+        ext = "html"
+    )]
+    struct Json<'a> {
+        data: &'a [&'a str],
+        name: &'a str,
+    }
+
+    let tmpl = Json {
+        data: &["a", "</script>"],
+        name: "b",
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r#"<script>const data = ["a","\u003c/script\u003e"];</script>
+<script type="application/json">{"items": ["a","\u003c/script\u003e"], "name": "b"}</script>"#
+    );
+}
+
+#[test]
+fn test_non_js_script() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script type="text/template"><b>{{ name }}</b></script>"#,
+        ext = "html"
+    )]
+    struct TextTemplate<'a> {
+        name: &'a str,
+    }
+
+    assert_eq!(
+        TextTemplate { name: "<i>" }.render().unwrap(),
+        r#"<script type="text/template"><b>&#60;i&#62;</b></script>"#
+    );
+}
+
+#[test]
+fn test_event_handler() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<button onclick="greet({{ name }}, {{ id }})" onmouseover='say("{{ name }}")'>"#,
+        ext = "html"
+    )]
+    struct EventHandler<'a> {
+        name: &'a str,
+        id: i32,
+    }
+
+    let tmpl = EventHandler {
+        name: "O'Reilly \"Bob\"",
+        id: -1,
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r#"<button onclick="greet(&#34;O\u0027Reilly \u0022Bob\u0022&#34;, -1)" onmouseover='say("O\u0027Reilly \u0022Bob\u0022")'>"#
+    );
+}
+
+#[test]
+fn test_style() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<style>p { color: {{ color }}; }</style><p style="margin: {{ margin }}">"#,
+        ext = "html"
+    )]
+    struct Style<'a> {
+        color: &'a str,
+        margin: &'a str,
+    }
+
+    let tmpl = Style {
+        color: "red; } </style><script>",
+        margin: "1em",
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r#"<style>p { color: red\3b  \7d  \3c \2f style\3e \3c script\3e ; }</style><p style="margin: 1em">"#
+    );
+}
+
+#[test]
+fn test_url_attributes() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<a href="{{ url }}">x</a><a href="/search?q={{ url }}">y</a><img src='{{ img }}'>"#,
+        ext = "html"
+    )]
+    struct Urls<'a> {
+        url: &'a str,
+        img: &'a str,
+    }
+
+    let tmpl = Urls {
+        url: "javascript:alert(1)",
+        img: "https://example.com/a.png?a=1&b=2",
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r##"<a href="#askama-unsafe-url">x</a><a href="/search?q=javascript:alert(1)">y</a><img src='https://example.com/a.png?a=1&#38;b=2'>"##
+    );
+}
+
+#[test]
+fn test_branches() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<input {% if checked %}checked{% endif %} value="{{ value }}">
+{%- match kind -%}
+    {%- when 0 -%}<script>
+    {%- when _ -%}<script type="module">
+{%- endmatch -%}
+let x = {{ value }};</script>"#,
+        ext = "html"
+    )]
+    struct Branches<'a> {
+        checked: bool,
+        value: &'a str,
+        kind: u8,
+    }
+
+    let tmpl = Branches {
+        checked: true,
+        value: "<b>",
+        kind: 1,
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        r#"<input checked value="&#60;b&#62;"><script type="module">let x = "\u003Cb\u003E";</script>"#
+    );
+}
+
+#[test]
+fn test_text_escaper_is_unchanged() {
+    #[derive(Template)]
+    #[template(source = "<script>let x = {{ x }};</script>", ext = "txt")]
+    struct Text<'a> {
+        x: &'a str,
+    }
+
+    assert_eq!(
+        Text { x: "'a'" }.render().unwrap(),
+        "<script>let x = 'a';</script>"
+    );
+}
@@ -346,7 +346,7 @@ fn test_let_block() {
 #[test]
 fn test_named_end() {
     #[derive(Template)]
-    #[template(path = "named-end.html")]
+    #[template(path = "named-end.html", allow(unused_macros, unused_blocks))]
     struct NamedBlocks<'a> {
         title: &'a str,
     }
@@ -0,0 +1,95 @@
+use askama::Template;
+
+#[test]
+fn test_inline_if() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ "neg" if n < 0 else "zero" if n == 0 else "pos" }}
+{{ n if n is even else 0 }} {{ (n if n > 0 else -n) + 1 }}"#,
+        ext = "txt"
+    )]
+    struct InlineIf {
+        n: i32,
+    }
+
+    assert_eq!(InlineIf { n: -3 }.render().unwrap(), "neg\n0 4");
+    assert_eq!(InlineIf { n: 0 }.render().unwrap(), "zero\n0 1");
+    assert_eq!(InlineIf { n: 4 }.render().unwrap(), "pos\n4 5");
+}
+
+#[test]
+fn test_inline_if_without_else() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- for item in items -%}
+<li class="{{ "active" if *item == current }}">{{ item }}{{ " (new)" if item.len() < 4 }}</li>
+{% endfor -%}"#,
+        ext = "html"
+    )]
+    struct Menu<'a> {
+        items: &'a [&'a str],
+        current: &'a str,
+    }
+
+    let menu = Menu {
+        items: &["home", "faq", "about"],
+        current: "about",
+    };
+    assert_eq!(
+        menu.render().unwrap(),
+        "<li class=\"\">home</li>\n\
+        <li class=\"\">faq (new)</li>\n\
+        <li class=\"active\">about</li>\n"
+    );
+}
+
+#[test]
+fn test_inline_if_escaping() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ "<b>" if bold else "<i>" }} {{ "<b>"|safe if bold else "<i>"|safe }} {{ "<u>"|safe if bold }}"#,
+        ext = "html"
+    )]
+    struct Escaping {
+        bold: bool,
+    }
+
+    assert_eq!(
+        Escaping { bold: true }.render().unwrap(),
+        "&#60;b&#62; <b> <u>"
+    );
+    assert_eq!(
+        Escaping { bold: false }.render().unwrap(),
+        "&#60;i&#62; <i> "
+    );
+}
+
+#[test]
+fn test_inline_if_in_expressions() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- let label = name if !name.is_empty() else "anonymous" -%}
+{{ label|upper }} {{ label|truncate(3 if short else 20) }} {{ [1, 2 if short else 3, 4].len() }}
+{%- for i in 0..5 if *i != skip %} {{ i }}{% endfor %}"#,
+        ext = "txt"
+    )]
+    struct Expressions<'a> {
+        name: &'a str,
+        short: bool,
+        skip: u32,
+    }
+
+    let tmpl = Expressions {
+        name: "",
+        short: true,
+        skip: 2,
+    };
+    assert_eq!(tmpl.render().unwrap(), "ANONYMOUS ano... 3 0 1 3 4");
+
+    let tmpl = Expressions {
+        name: "Ferris",
+        short: false,
+        skip: 0,
+    };
+    assert_eq!(tmpl.render().unwrap(), "FERRIS Ferris 3 1 2 3 4");
+}
@@ -0,0 +1,123 @@
+use askama::Template;
+
+mod template_tests {
+    #[askama::test_fn]
+    pub fn short(value: &str, _: &dyn askama::Values) -> askama::Result<bool> {
+        Ok(value.len() < 4)
+    }
+
+    #[askama::test_fn]
+    pub fn between(
+        value: usize,
+        _: &dyn askama::Values,
+        min: usize,
+        #[optional(usize::MAX)] max: usize,
+    ) -> askama::Result<bool> {
+        Ok((min..=max).contains(&value))
+    }
+}
+
+mod custom {
+    #[askama::test_fn]
+    pub fn shouting(value: &str, _: &dyn askama::Values) -> askama::Result<bool> {
+        Ok(value.chars().all(|c| !c.is_lowercase()))
+    }
+}
+
+#[test]
+fn test_builtin_tests() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+{%- for i in numbers -%}
+    {{ i }}:{% if i is even %}e{% endif %}{% if i is odd %}o{% endif %}
+    {%- if i is divisibleby(3) %}3{% endif %}{% if *i is not in(1..3) %}!{% endif %}
+{% endfor -%}
+{{ user is none }} {{ user is some }} {{ numbers is empty }} {{ "" is empty }}
+{{ name is startingwith("Fer") }} {{ name is not startingwith("fer") }}
+{{ name is in(["Ferris", "Corro"]) }} {{ "rr" is in(name) }} {{ numbers.len() is divisibleby(size) }}"#,
+        ext = "txt"
+    )]
+    struct Builtin<'a> {
+        numbers: Vec<u32>,
+        user: Option<&'a str>,
+        name: &'a str,
+        size: usize,
+    }
+
+    let tmpl = Builtin {
+        numbers: vec![1, 2, 3, 6],
+        user: None,
+        name: "Ferris",
+        size: 2,
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "1:o\n2:e\n3:o3!\n6:e3!\ntrue false false true\ntrue true\ntrue true true"
+    );
+}
+
+#[test]
+fn test_tests_in_conditions() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+{%- if x is even && x is not divisibleby(4) || y is some -%}
+    yes
+{%- elif !(x is odd) -%}
+    even
+{%- else -%}
+    no
+{%- endif -%}"#,
+        ext = "txt"
+    )]
+    struct Conditions {
+        x: i32,
+        y: Option<()>,
+    }
+
+    assert_eq!(Conditions { x: 6, y: None }.render().unwrap(), "yes");
+    assert_eq!(Conditions { x: 8, y: None }.render().unwrap(), "even");
+    assert_eq!(Conditions { x: 7, y: None }.render().unwrap(), "no");
+    assert_eq!(Conditions { x: 7, y: Some(()) }.render().unwrap(), "yes");
+}
+
+#[test]
+fn test_custom_tests() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+{%- for word in words -%}
+    {{ word }}:{{ word is short }},{{ word is not custom::shouting }}
+    {%- if word.len() is between(3, 4) %},3-4{% endif %}
+    {%- if word.len() is between(min = 5) %},5+{% endif %}
+{% endfor -%}"#,
+        ext = "txt"
+    )]
+    struct Custom<'a> {
+        words: &'a [&'a str],
+    }
+
+    assert_eq!(
+        Custom {
+            words: &["ABC", "Ferris", "yeah"]
+        }
+        .render()
+        .unwrap(),
+        "ABC:true,false,3-4\nFerris:false,true,5+\nyeah:false,true,3-4\n"
+    );
+}
+
+#[test]
+fn test_is_defined_still_works() {
+    #[derive(Template)]
+    #[template(
+        source = "{% if x is defined %}{{ x is even }}{% endif %}{% if y is not defined %}!{% endif %}",
+        ext = "txt"
+    )]
+    struct Defined {
+        x: u8,
+    }
+
+    assert_eq!(Defined { x: 2 }.render().unwrap(), "true!");
+}
@@ -199,7 +199,8 @@ fn test_trailing_comma() {
 {%- call button(label="hi", ) -%}{%- endcall -%}
 {%- call button(label="hi" ) -%}{%- endcall -%}
 "#,
-        ext = "html"
+        ext = "html",
+        allow(unused_macros)
     )]
     struct TrailingComma;
 
@@ -390,7 +391,8 @@ fn test_default_value5() {
 {%- call thrice(1) -%}{%- endcall -%}
 {%- call thrice(1, 2) -%}{%- endcall -%}
 ",
-        ext = "html"
+        ext = "html",
+        allow(unused_variables)
     )]
     struct MacroDefaultValue5;
 
@@ -556,7 +558,8 @@ fn test_expr_macro_call_imported_nested2() {
 {% let child1 = "Test: Macros scoped by file" %}
 {{- otherscope::parent() -}}
 "#,
-        ext = "html"
+        ext = "html",
+        allow(unused_variables)
     )]
     struct ExprMacroCall;
 
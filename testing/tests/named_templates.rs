@@ -0,0 +1,51 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "email.html")]
+#[template(name = "text", path = "email.txt")]
+struct Email<'a> {
+    name: &'a str,
+    message: &'a str,
+}
+
+#[test]
+fn test_named_template() {
+    let email = Email {
+        name: "<Ferris>",
+        message: "Your order has shipped.",
+    };
+    assert_eq!(
+        email.render().unwrap(),
+        "<p>Hello, &#60;Ferris&#62;!</p>\n<p>Your order has shipped.</p>"
+    );
+    assert_eq!(
+        email.as_text().render().unwrap(),
+        "Hello, <Ferris>!\n\nYour order has shipped."
+    );
+    assert_eq!(
+        email.as_text().to_string(),
+        email.as_text().render().unwrap()
+    );
+}
+
+#[test]
+fn test_named_template_sources() {
+    #[derive(Template)]
+    #[template(ext = "txt", source = "{{ a }}+{{ b }}")]
+    #[template(name = "sum", source = "{{ a + b }}", ext = "md")]
+    #[template(
+        name = "html",
+        source = "<b>{{ a }}</b>",
+        ext = "html",
+        escape = "none"
+    )]
+    struct Numbers {
+        a: u32,
+        b: u32,
+    }
+
+    let numbers = Numbers { a: 1, b: 2 };
+    assert_eq!(numbers.render().unwrap(), "1+2");
+    assert_eq!(numbers.as_sum().render().unwrap(), "3");
+    assert_eq!(numbers.as_html().render().unwrap(), "<b>1</b>");
+}
@@ -6,7 +6,7 @@ fn cross_file_paths() {
     macro_rules! define_template {
         () => {
             #[derive(askama::Template)]
-            #[template(path = "base-decl.txt")]
+            #[template(path = "base-decl.txt", allow(unused_variables))]
             struct Empty {}
         };
     }
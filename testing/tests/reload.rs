@@ -0,0 +1,72 @@
+// The reloading is tested in a copy of `tests/reload`, so the templates can be modified.
+
+use std::fs::{copy, create_dir_all, read_dir, remove_dir_all, write};
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_reload() {
+    if !cfg!(unix) {
+        return;
+    }
+    let Ok(cargo_home) = std::env::var("CARGO_MANIFEST_DIR") else {
+        panic!(">> cannot get `CARGO_MANIFEST_DIR` env variable");
+    };
+    let cargo_home_path = Path::new(&cargo_home).parent().unwrap();
+    let test_dir = cargo_home_path.join("target/tests/reload");
+
+    // We don't check whether it succeeds or not.
+    let _ = remove_dir_all(&test_dir);
+    create_dir_all(test_dir.join("templates")).expect("failed to create test dir");
+
+    let reload_folder = cargo_home_path.join("testing/tests/reload");
+    for entry in read_dir(reload_folder.join("templates")).unwrap() {
+        let entry = entry.unwrap();
+        copy(
+            entry.path(),
+            test_dir.join("templates").join(entry.file_name()),
+        )
+        .unwrap();
+    }
+    copy(reload_folder.join("program.rs"), test_dir.join("main.rs")).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(cargo_home_path.join("target"), test_dir.join("target")).unwrap();
+
+    write(
+        test_dir.join("Cargo.toml"),
+        format!(
+            r#"
+[package]
+name = "askama_reload_test"
+version = "0.0.1"
+edition = "2024"
+
+[workspace]
+
+[dependencies]
+askama = {{ path = {:?}, features = ["reload"] }}
+askama_interpreter = {{ path = {:?} }}
+serde = {{ version = "1.0", features = ["derive"] }}
+
+[[bin]]
+name = "main"
+path = "main.rs"
+"#,
+            cargo_home_path.join("askama").display(),
+            cargo_home_path.join("askama_interpreter").display(),
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO"))
+        .args(["run", "--bin", "main", "--color", "never"])
+        .current_dir(&test_dir)
+        .output()
+        .expect("failed to execute process");
+    if !output.status.success() {
+        panic!(
+            "Failed to run `tests/reload`: {}",
+            String::from_utf8_lossy(&output.stderr),
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Compiled and run by `tests/reload.rs` in a copy of this folder, so it can modify its templates.
+
+use std::any::Any;
+use std::fs::{File, write};
+use std::time::Duration;
+
+use askama::Template;
+use serde::{Serialize, Serializer};
+
+#[derive(Template, Serialize)]
+#[template(path = "reload.html", reload = true)]
+struct ReloadTemplate<'a> {
+    name: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "reload.html", reload = true)]
+struct UnserializableTemplate {
+    name: Unserializable,
+    items: Vec<&'static str>,
+}
+
+struct Unserializable;
+
+impl std::fmt::Display for Unserializable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("unserializable")
+    }
+}
+
+impl Serialize for Unserializable {
+    fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("not serializable"))
+    }
+}
+
+fn main() {
+    assert!(askama_interpreter::reload::install());
+    assert!(!askama_interpreter::reload::install());
+
+    let t = ReloadTemplate {
+        name: "<world>",
+        items: vec!["a", "b&c"],
+    };
+    let u = UnserializableTemplate {
+        name: Unserializable,
+        items: vec![],
+    };
+    let expected = "Hello, &#60;world&#62;!\n- a\n- b&#38;c";
+    // As long as the files are unchanged, the compiled template is used.
+    let footer: (&str, &dyn Any) = ("footer", &"<footer>");
+    assert_eq!(t.render().unwrap(), expected);
+    assert_eq!(
+        t.render_with_values(&footer).unwrap(),
+        format!("{expected}\n&#60;footer&#62;"),
+    );
+    assert_eq!(u.render().unwrap(), "Hello, unserializable!");
+
+    // Touch the included file, so the interpreter renders the template from now on.
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/reload-item.html");
+    let file = File::options().write(true).open(path).unwrap();
+    let modified = file.metadata().unwrap().modified().unwrap();
+    file.set_modified(modified + Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(t.render().unwrap(), expected);
+    // The runtime values are passed on to the interpreter.
+    assert_eq!(
+        t.render_with_values(&footer).unwrap(),
+        format!("{expected}\n&#60;footer&#62;"),
+    );
+    // The interpreter needs the serialized fields.
+    assert!(u.render().is_err());
+
+    // Edits show up without rebuilding the program.
+    write(path, "Bye, {{ name|upper }}!").unwrap();
+    assert_eq!(
+        t.render().unwrap(),
+        "Bye, &#60;WORLD&#62;!\n- a\n- b&#38;c"
+    );
+}
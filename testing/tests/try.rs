@@ -231,3 +231,84 @@ fn error_conversion_from_wrapped_in_io() {
     };
     assert!(err.is::<CustomError>());
 }
+
+#[derive(Template)]
+#[template(path = "error-location.html")]
+struct LocationTemplate<'a> {
+    number: &'a str,
+}
+
+#[test]
+fn error_location() {
+    let err = LocationTemplate { number: "x" }.render().unwrap_err();
+    assert_matches!(err, askama::Error::Located(..));
+    assert_matches!(err.inner(), askama::Error::Custom(_));
+    let location = err.location().unwrap();
+    assert_eq!(location.file(), "error-location.html");
+    assert_eq!((location.line(), location.column()), (3, 37));
+    assert_eq!(location.to_string(), "error-location.html:3:37");
+    assert_eq!(err.to_string(), "invalid digit found in string");
+
+    // Another error of the same variant has no location.
+    assert_eq!(askama::Error::custom("other").location(), None);
+
+    // The location is only recorded for templates loaded from a file.
+    #[derive(Template)]
+    #[template(source = "{{ value()? }}", ext = "txt")]
+    struct SourceTemplate;
+
+    impl SourceTemplate {
+        fn value(&self) -> Result<&'static str, &'static str> {
+            Err("source")
+        }
+    }
+
+    let err = SourceTemplate.render().unwrap_err();
+    assert_matches!(err, askama::Error::Custom(_));
+    assert_eq!(err.location(), None);
+}
+
+#[test]
+fn error_location_nested() {
+    #[derive(Template)]
+    #[template(path = "error-location-outer.html")]
+    struct OuterTemplate {
+        nested: bool,
+    }
+
+    impl OuterTemplate {
+        fn inner(&self) -> askama::Result<&'static str> {
+            match self.nested {
+                true => Err(LocationTemplate { number: "x" }.render().unwrap_err()),
+                false => Err(askama::Error::custom("outer")),
+            }
+        }
+    }
+
+    let err = OuterTemplate { nested: false }.render().unwrap_err();
+    assert_eq!(
+        err.location().unwrap().to_string(),
+        "error-location-outer.html:2:11"
+    );
+    assert_eq!(err.to_string(), "outer");
+
+    // The location where the error was raised first is kept.
+    let err = OuterTemplate { nested: true }.render().unwrap_err();
+    assert_eq!(
+        err.location().unwrap().to_string(),
+        "error-location.html:3:37"
+    );
+    assert_matches!(err.without_location(), askama::Error::Custom(_));
+}
+
+#[test]
+fn error_location_render_chunks() {
+    let result = LocationTemplate { number: "x" }
+        .render_chunks(8)
+        .collect::<Result<Vec<_>, _>>();
+    let err = result.unwrap_err();
+    assert_eq!(
+        err.location().unwrap().to_string(),
+        "error-location.html:3:37"
+    );
+}
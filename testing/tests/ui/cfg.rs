@@ -0,0 +1,27 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% if cfg(test, unix) %}{% endif %}")]
+struct TwoPredicates;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% if cfg(feature = 1) %}{% endif %}")]
+struct NotAString;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% if cfg(test && unix) %}{% endif %}")]
+struct InvalidPredicate;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% if cfg(not(test, unix)) %}{% endif %}")]
+struct InvalidNot;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% if !cfg(test) %}{% endif %}")]
+struct NotWholeCondition;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ cfg(test) }}")]
+struct OutsideOfIf;
+
+fn main() {}
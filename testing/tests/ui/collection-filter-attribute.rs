@@ -0,0 +1,40 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ users|map(attribute = name)|join(",") }}"#,
+    ext = "txt",
+)]
+struct NotALiteral<'a> {
+    users: &'a [(&'a str, u32)],
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ users|sort(attribute = "address-city")|join(",") }}"#,
+    ext = "txt",
+)]
+struct InvalidField<'a> {
+    users: &'a [(&'a str, u32)],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ users|selectattr("1", value = 3)|join(",") }}"#,
+    ext = "txt",
+)]
+struct ValueWithoutTest<'a> {
+    users: &'a [(&'a str, u32)],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ users|groupby|join(",") }}"#,
+    ext = "txt",
+)]
+struct MissingAttribute<'a> {
+    users: &'a [(&'a str, u32)],
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+#[derive(askama::Values)]
+struct Tuple(u32);
+
+#[derive(askama::Values)]
+enum Enum {
+    A { a: u32 },
+}
+
+#[derive(askama::Values)]
+struct Duplicated {
+    a: u32,
+    #[values(rename = "a")]
+    b: u32,
+}
+
+#[derive(askama::Values)]
+struct Unknown {
+    #[values(default)]
+    a: u32,
+}
+
+#[derive(askama::Values)]
+struct NotAString {
+    #[values(rename = a)]
+    a: u32,
+}
+
+#[derive(askama::Template)]
+#[template(ext = "txt", source = r#"{{ "a"|value }}"#)]
+struct UntypedKey;
+
+fn main() {
+}
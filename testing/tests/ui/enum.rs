@@ -51,4 +51,10 @@ enum MissingBlockName {
     D,
 }
 
+#[derive(Template)]
+#[template(ext = "txt", source = "{% block a %}{% endblock %}", render_blocks = true)]
+enum RenderBlocks {
+    A,
+}
+
 fn main() {}
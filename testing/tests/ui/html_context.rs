@@ -0,0 +1,46 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "<div {{ attrs }}></div>", ext = "html")]
+struct InTag<'a> {
+    attrs: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "<{{ tag }}>", ext = "html")]
+struct TagName<'a> {
+    tag: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "<input value={{ value }}>", ext = "html")]
+struct UnquotedAttr<'a> {
+    value: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<p {% if script %}onclick="{% else %}title="{% endif %}{{ value }}">"#,
+    ext = "html"
+)]
+struct Ambiguous<'a> {
+    script: bool,
+    value: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<div class="{% for class in classes %}{{ class }}"{% endfor %}>"#,
+    ext = "html"
+)]
+struct UnclosedInLoop<'a> {
+    classes: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(source = "<div {{ attrs|safe }}></div><input value={{ 5|safe }}>", ext = "html")]
+struct SafeIsAccepted<'a> {
+    attrs: &'a str,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{{ a if }}", ext = "txt")]
+struct MissingCondition {
+    a: u32,
+}
+
+#[derive(Template)]
+#[template(source = "{{ a if a > 0 else }}", ext = "txt")]
+struct MissingElse {
+    a: u32,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ a if a > 0 else "none" }}"#, ext = "txt")]
+struct MismatchedTypes {
+    a: u32,
+}
+
+fn main() {}
@@ -31,7 +31,7 @@ struct D;
 #[derive(Template)]
 #[template(
     ext = "html",
-    source = r#"{% if x is blue %}{% endif %}"#,
+    source = r#"{% if x is defined(y) %}{% endif %}"#,
 )]
 struct E;
 
@@ -0,0 +1,39 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is even(2) }}")]
+struct TooManyArguments {
+    x: u32,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is divisibleby }}")]
+struct MissingArgument {
+    x: u32,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is divisibleby(num = 2) }}")]
+struct NamedArgument {
+    x: u32,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is not }}")]
+struct MissingTest {
+    x: u32,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is even }}")]
+struct NotAnInteger {
+    x: &'static str,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{{ x is blue }}")]
+struct UnknownTest {
+    x: u32,
+}
+
+fn main() {}
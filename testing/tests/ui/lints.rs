@@ -0,0 +1,49 @@
+#![deny(deprecated)]
+
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = "{% let a = 1 %}{% let (b, _c) = (2, 3) %}{% let d = 4 %}{{ d }}",
+)]
+struct UnusedVariables;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = "{% macro used() %}{% endmacro %}{% macro unused() %}{% endmacro %}{% call used() %}{% endcall %}",
+)]
+struct UnusedMacros;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = r#"{% import "macro.html" as used %}{% import "macro.html" as unused %}{% call used::thrice("a") %}{% endcall %}"#,
+)]
+struct UnusedImports;
+
+#[derive(Template)]
+#[template(
+    ext = "html",
+    source = r#"{% extends "base.html" %}{% block content %}{% endblock %}{% block sidebar %}{% endblock %}"#,
+)]
+struct UnusedBlocks {
+    title: String,
+}
+
+#[derive(Template)]
+#[template(
+    ext = "html",
+    source = r#"{% extends "base.html" %}{% let a = 1 %}{% macro m() %}{% endmacro %}{% block sidebar %}{% endblock %}"#,
+    allow(unused_variables, unused_macros, unused_blocks),
+)]
+struct Allowed {
+    title: String,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "", allow(unused_functions))]
+struct UnknownLint;
+
+fn main() {}
@@ -0,0 +1,14 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% if a + %}\n{{ b( }}\n{% else %}\n{% for in x %}{% endfor %}\n{% endif %}",
+    ext = "txt"
+)]
+struct Blocks;
+
+#[derive(Template)]
+#[template(source = "{{ a }} {{ b + }} {{ c }} {% include %}", ext = "txt")]
+struct Exprs;
+
+fn main() {}
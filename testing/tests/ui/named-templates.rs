@@ -0,0 +1,29 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "")]
+#[template(name = "not an ident", ext = "txt", source = "")]
+struct BadName;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "")]
+#[template(name = "text", ext = "txt", source = "", blocks = ["a"])]
+struct Unsupported;
+
+#[derive(Template)]
+#[template(name = "text", ext = "txt", source = "")]
+struct OnlyNamed;
+
+#[derive(Template)]
+#[template(ext = "txt", source = "")]
+enum Enum {
+    #[template(name = "text", source = "")]
+    A,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = "{% block text %}{% endblock %}", blocks = ["text"])]
+#[template(name = "text", ext = "txt", source = "")]
+struct ClashesWithBlock;
+
+fn main() {}
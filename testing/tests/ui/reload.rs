@@ -0,0 +1,36 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{{ a }}", ext = "txt", reload = true)]
+struct Source {
+    a: u32,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", reload = true)]
+enum Enum {
+    #[template(source = "{{ a }}")]
+    A { a: u32 },
+}
+
+#[derive(Template)]
+#[template(path = "hello.html", reload = true, reload = false)]
+struct Twice;
+
+#[derive(Template)]
+#[template(path = "hello.html", reload = true)]
+struct NotSerialize<'a> {
+    name: &'a str,
+    user: User,
+}
+
+struct User;
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("user")
+    }
+}
+
+fn main() {
+}
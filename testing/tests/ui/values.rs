@@ -42,4 +42,28 @@ struct E;
 )]
 struct F;
 
+#[derive(Template)]
+#[template(
+    ext = "html",
+    source = r#"{% if let Ok(x) = "nmae"|value::<&str> %}{% endif %}"#,
+    values(name: &str),
+)]
+struct G;
+
+#[derive(Template)]
+#[template(
+    ext = "html",
+    source = r#"{% if let Ok(x) = askama::get_value::<u8>("age") %}{% endif %}"#,
+    values(name: &str, age: u32),
+)]
+struct H;
+
+#[derive(Template)]
+#[template(ext = "html", source = "", values(age: u32, age: u8))]
+struct I;
+
+#[derive(Template)]
+#[template(ext = "html", source = "", values(age = u32))]
+struct J;
+
 fn main() {}
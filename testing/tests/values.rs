@@ -156,3 +156,150 @@ fn test_value_in_wordcount() {
     );
     assert_eq!(Parent.render_with_values(&values).unwrap(), "10");
 }
+
+#[test]
+fn test_derive_values() {
+    #[derive(askama::Values)]
+    struct RequestValues {
+        user: &'static str,
+        #[values(rename = "csrf-token")]
+        csrf_token: String,
+        #[values(skip)]
+        #[allow(dead_code)]
+        secret: u32,
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- if let Ok(user) = RequestValues::USER|value -%}
+                {{ user }}
+            {%- endif %} {{ askama::get_value(RequestValues::CSRF_TOKEN)? }}
+            {%- if let Ok(secret) = "secret"|value::<u32> %} {{ secret }}{% endif -%}
+        "#,
+        ext = "txt"
+    )]
+    struct V;
+
+    let values = RequestValues {
+        user: "Ferris",
+        csrf_token: "xyz".to_owned(),
+        secret: 42,
+    };
+    assert_eq!(V.render_with_values(&values).unwrap(), "Ferris xyz");
+    assert_eq!(RequestValues::USER.get(&values).unwrap(), &"Ferris");
+    assert_eq!(RequestValues::CSRF_TOKEN.name(), "csrf-token");
+    assert_eq!(
+        askama::get_value::<String>(&values, "csrf-token").unwrap(),
+        "xyz"
+    );
+    assert!(askama::get_value::<u32>(&values, "secret").is_err());
+}
+
+#[test]
+fn test_declared_values() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {{- askama::get_value("name")? }} is {{ askama::get_value("age")? -}}
+            {%- if let Ok(title) = "title"|value::<&str> %} ({{ title }}){% endif -%}
+        "#,
+        ext = "txt",
+        values(name: &str, age: u32, title: Option<&str>)
+    )]
+    struct V;
+
+    let mut values: HashMap<String, Box<dyn Any>> = HashMap::default();
+    values.insert("name".to_string(), Box::new("Ferris"));
+    values.insert("age".to_string(), Box::new(12u32));
+    assert_eq!(V.render_with_values(&values).unwrap(), "Ferris is 12");
+    values.insert("title".to_string(), Box::new("crab"));
+    assert_eq!(
+        V.render_with_values(&values).unwrap(),
+        "Ferris is 12 (crab)"
+    );
+
+    // The values are validated before anything is rendered.
+    values.insert("title".to_string(), Box::new(1u8));
+    assert!(matches!(
+        V.render_with_values(&values),
+        Err(askama::Error::ValueType)
+    ));
+    values.remove("title");
+    values.remove("age");
+    assert!(matches!(
+        V.render_with_values(&values),
+        Err(askama::Error::ValueMissing)
+    ));
+    assert!(matches!(V.render(), Err(askama::Error::ValueMissing)));
+}
+
+#[test]
+fn test_declared_values_alias() {
+    // The optional value is recognized by its type, not by its spelling.
+    type Title = Option<&'static str>;
+
+    #[derive(Template)]
+    #[template(
+        source = r#"{% if let Ok(title) = askama::get_value("title") %}{{ title }}{% endif %}"#,
+        ext = "txt",
+        values(title: Title)
+    )]
+    struct V;
+
+    let mut values: HashMap<String, Box<dyn Any>> = HashMap::default();
+    assert_eq!(V.render_with_values(&values).unwrap(), "");
+    values.insert("title".to_string(), Box::new("crab"));
+    assert_eq!(V.render_with_values(&values).unwrap(), "crab");
+}
+
+#[test]
+fn test_layered_values() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {{- askama::get_value::<&str>("user")? }} {{ askama::get_value::<&str>("theme")? -}}
+        "#,
+        ext = "txt"
+    )]
+    struct V;
+
+    let mut app: HashMap<String, Box<dyn Any>> = HashMap::default();
+    app.insert("theme".to_string(), Box::new("light"));
+    app.insert("user".to_string(), Box::new("nobody"));
+    let request: (&str, &dyn Any) = ("user", &"Ferris");
+    let values = askama::Layered::new(request, &app);
+    assert_eq!(V.render_with_values(&values).unwrap(), "Ferris light");
+
+    let overrides: (&str, &dyn Any) = ("theme", &"dark");
+    let values = askama::Layered::new(overrides, values);
+    assert_eq!(V.render_with_values(&values).unwrap(), "Ferris dark");
+}
+
+#[test]
+fn test_with_values_block() {
+    #[derive(Template)]
+    #[template(
+        source = r#"
+            {%- macro theme() -%}
+                [{{ askama::get_value::<&str>("theme")? }}]
+            {%- endmacro -%}
+
+            {% include "values-include.txt" %} {% with_values "theme" = "dark", "count" = self.count %}
+                {%- include "values-include.txt" %} {% call theme() %}{% endcall %}
+                {%- with_values "theme" = "blue" %} {% call theme() %}{% endcall %}{% endwith_values %}
+                {%- if let Ok(count) = "count"|value::<u32> %} {{ count }}{% endif -%}
+            {% endwith_values %} {% call theme() %}{% endcall -%}
+        "#,
+        ext = "txt"
+    )]
+    struct V {
+        count: u32,
+    }
+
+    let values: (&str, &dyn Any) = ("theme", &"light");
+    assert_eq!(
+        V { count: 3 }.render_with_values(&values).unwrap(),
+        "light dark [dark] [blue] 3 [light]"
+    );
+}
@@ -5,7 +5,7 @@ use askama::Template;
 #[test]
 fn test_extra_whitespace() {
     #[derive(askama::Template, Default)]
-    #[template(path = "allow-whitespaces.html")]
+    #[template(path = "allow-whitespaces.html", allow(unused_variables))]
     struct AllowWhitespaces {
         tuple: (u64, u64, u64, u64),
         string: &'static str,
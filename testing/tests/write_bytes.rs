@@ -0,0 +1,143 @@
+use std::io;
+
+use askama::{ByteSink, Error, Template};
+use assert_matches::assert_matches;
+
+#[derive(Template)]
+#[template(path = "include-extends.html")]
+struct IncludeExtendsTemplate<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_write_bytes_vec() {
+    let t = IncludeExtendsTemplate { name: "Alice" };
+    let mut buf = b"prefix:".to_vec();
+    t.write_bytes(&mut buf).unwrap();
+    assert_eq!(buf, format!("prefix:{}", t.render().unwrap()).as_bytes());
+}
+
+struct Full(usize);
+
+impl io::Write for Full {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0 == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        let len = buf.len().min(self.0);
+        self.0 -= len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_bytes_io_error() {
+    let t = IncludeExtendsTemplate { name: "Alice" };
+    let sink: &mut dyn io::Write = &mut Full(10);
+    assert_matches!(
+        t.write_bytes(sink),
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::WriteZero
+    );
+
+    let err = t.write_into(&mut Full(10)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn test_write_into_keeps_template_error() {
+    #[derive(Template)]
+    #[template(source = r#"{{ askama::get_value::<u32>("name")? }}"#, ext = "txt")]
+    struct Missing;
+
+    let err = Missing.write_into(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.to_string(), Error::ValueMissing.to_string());
+}
+
+#[test]
+fn test_write_bytes_direct() {
+    // Every literal and value is written as is, without going through a `fmt::Write`.
+    #[derive(Default)]
+    struct Chunks(Vec<Vec<u8>>);
+
+    impl ByteSink for Chunks {
+        fn write_bytes(&mut self, bytes: &[u8]) -> askama::Result<()> {
+            self.0.push(bytes.to_vec());
+            Ok(())
+        }
+    }
+
+    #[derive(Template)]
+    #[template(source = "a{{ n }}b{{ s }}{{ c }}", ext = "txt")]
+    struct Direct {
+        n: u32,
+        s: &'static str,
+        c: char,
+    }
+
+    let mut sink = Chunks::default();
+    Direct {
+        n: 42,
+        s: "é",
+        c: 'ü',
+    }
+    .write_bytes(&mut sink)
+    .unwrap();
+    assert_eq!(
+        sink.0,
+        [&b"a"[..], b"42", b"b", "é".as_bytes(), "ü".as_bytes()]
+    );
+}
+
+#[test]
+fn test_write_bytes_nested() {
+    #[derive(Template)]
+    #[template(
+        source = "{% filter upper %}{{ name }}{% endfilter %} {{ inner }} {{ 1.5 }}",
+        ext = "html"
+    )]
+    struct Outer<'a> {
+        name: &'a str,
+        inner: IncludeExtendsTemplate<'a>,
+    }
+
+    #[derive(Template)]
+    enum Enum<'a> {
+        #[template(source = "[{{ self.0 }}]", ext = "html")]
+        Escaped(&'a str),
+        #[template(source = "{{ outer }}", ext = "html")]
+        Nested { outer: Outer<'a> },
+    }
+
+    let outer = Outer {
+        name: "Bob",
+        inner: IncludeExtendsTemplate { name: "Alice" },
+    };
+    let mut buf = Vec::new();
+    outer.write_bytes(&mut buf).unwrap();
+    assert_eq!(buf, outer.render().unwrap().as_bytes());
+
+    for t in [Enum::Escaped("<&>"), Enum::Nested { outer }] {
+        let mut buf = Vec::new();
+        t.write_bytes(&mut buf).unwrap();
+        assert_eq!(buf, t.render().unwrap().as_bytes());
+    }
+}
+
+#[test]
+fn test_write_bytes_filter_block_io_error() {
+    #[derive(Template)]
+    #[template(source = "{% filter upper %}{{ name }}{% endfilter %}", ext = "txt")]
+    struct Filtered<'a> {
+        name: &'a str,
+    }
+
+    let sink: &mut dyn io::Write = &mut Full(2);
+    assert_matches!(
+        Filtered { name: "Alice" }.write_bytes(sink),
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::WriteZero
+    );
+}